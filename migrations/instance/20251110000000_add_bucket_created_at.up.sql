@@ -0,0 +1 @@
+ALTER TABLE buckets ADD COLUMN created_at DATETIME NOT NULL DEFAULT '1970-01-01 00:00:00';
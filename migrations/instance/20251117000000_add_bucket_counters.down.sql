@@ -0,0 +1,2 @@
+ALTER TABLE buckets DROP COLUMN total_bytes;
+ALTER TABLE buckets DROP COLUMN object_count;
@@ -0,0 +1,2 @@
+ALTER TABLE buckets ADD COLUMN object_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE buckets ADD COLUMN total_bytes INTEGER NOT NULL DEFAULT 0;
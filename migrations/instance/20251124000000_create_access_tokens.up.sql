@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS access_tokens (
+    uuid TEXT PRIMARY KEY UNIQUE NOT NULL,
+    access_key_id TEXT UNIQUE NOT NULL,
+    secret_access_key TEXT NOT NULL,
+
+    created_at DATETIME NOT NULL
+);
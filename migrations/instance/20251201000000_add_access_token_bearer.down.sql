@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS access_tokens_token_hash;
+
+ALTER TABLE access_tokens DROP COLUMN expires_at;
+ALTER TABLE access_tokens DROP COLUMN description;
+ALTER TABLE access_tokens DROP COLUMN token_hash;
@@ -0,0 +1,5 @@
+ALTER TABLE access_tokens ADD COLUMN token_hash TEXT NOT NULL DEFAULT '';
+ALTER TABLE access_tokens ADD COLUMN description TEXT NOT NULL DEFAULT '';
+ALTER TABLE access_tokens ADD COLUMN expires_at DATETIME;
+
+CREATE INDEX IF NOT EXISTS access_tokens_token_hash ON access_tokens (token_hash);
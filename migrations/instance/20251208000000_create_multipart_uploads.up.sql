@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS multipart_uploads (
+    upload_id TEXT PRIMARY KEY UNIQUE NOT NULL,
+    bucket_uuid TEXT NOT NULL REFERENCES buckets (uuid) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    content_type TEXT,
+
+    initiated_at DATETIME NOT NULL
+);
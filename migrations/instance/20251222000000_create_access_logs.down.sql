@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS access_logs_bucket_uuid;
+DROP TABLE IF EXISTS access_logs;
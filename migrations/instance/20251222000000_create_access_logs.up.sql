@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS access_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bucket_uuid TEXT NOT NULL REFERENCES buckets (uuid) ON DELETE CASCADE,
+    object_key TEXT,
+    method TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    requester_ip TEXT NOT NULL,
+    bytes_transferred INTEGER NOT NULL,
+    request_id TEXT,
+
+    timestamp DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS access_logs_bucket_uuid ON access_logs (bucket_uuid, id);
@@ -0,0 +1 @@
+ALTER TABLE buckets ADD COLUMN max_object_size INTEGER;
@@ -0,0 +1 @@
+ALTER TABLE buckets ADD COLUMN versioning_enabled BOOLEAN NOT NULL DEFAULT FALSE;
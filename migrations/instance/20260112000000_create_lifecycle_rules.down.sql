@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS lifecycle_rules_bucket_uuid;
+DROP TABLE IF EXISTS lifecycle_rules;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS lifecycle_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bucket_uuid TEXT NOT NULL REFERENCES buckets (uuid) ON DELETE CASCADE,
+    prefix TEXT NOT NULL DEFAULT '',
+    expiration_days INTEGER,
+    abort_incomplete_multipart_days INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS lifecycle_rules_bucket_uuid ON lifecycle_rules (bucket_uuid, id);
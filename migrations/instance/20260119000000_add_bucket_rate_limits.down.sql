@@ -0,0 +1,2 @@
+ALTER TABLE buckets DROP COLUMN rate_limit_burst_size;
+ALTER TABLE buckets DROP COLUMN rate_limit_period_ms;
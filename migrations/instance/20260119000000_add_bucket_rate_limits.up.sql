@@ -0,0 +1,2 @@
+ALTER TABLE buckets ADD COLUMN rate_limit_period_ms INTEGER;
+ALTER TABLE buckets ADD COLUMN rate_limit_burst_size INTEGER;
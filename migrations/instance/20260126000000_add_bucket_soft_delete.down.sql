@@ -0,0 +1 @@
+ALTER TABLE buckets DROP COLUMN soft_delete_enabled;
@@ -0,0 +1 @@
+ALTER TABLE buckets ADD COLUMN soft_delete_enabled BOOLEAN NOT NULL DEFAULT FALSE;
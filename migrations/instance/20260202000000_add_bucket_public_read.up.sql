@@ -0,0 +1 @@
+ALTER TABLE buckets ADD COLUMN public_read BOOLEAN NOT NULL DEFAULT FALSE;
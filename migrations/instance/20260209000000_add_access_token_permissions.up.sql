@@ -0,0 +1 @@
+ALTER TABLE access_tokens ADD COLUMN permissions TEXT NOT NULL DEFAULT '["read","write","admin"]';
@@ -0,0 +1 @@
+ALTER TABLE multipart_parts DROP COLUMN last_modified;
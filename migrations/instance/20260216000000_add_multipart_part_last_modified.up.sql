@@ -0,0 +1,6 @@
+ALTER TABLE multipart_parts ADD COLUMN last_modified DATETIME;
+
+UPDATE multipart_parts SET last_modified = (
+    SELECT initiated_at FROM multipart_uploads
+    WHERE multipart_uploads.upload_id = multipart_parts.upload_id
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS bucket_tags (
+    bucket_uuid TEXT NOT NULL REFERENCES buckets (uuid) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (bucket_uuid, key)
+);
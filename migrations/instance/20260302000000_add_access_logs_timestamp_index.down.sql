@@ -0,0 +1 @@
+DROP INDEX IF EXISTS access_logs_bucket_uuid_timestamp;
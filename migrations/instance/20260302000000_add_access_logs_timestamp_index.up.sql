@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS access_logs_bucket_uuid_timestamp ON access_logs (bucket_uuid, timestamp);
@@ -0,0 +1,5 @@
+ALTER TABLE buckets ADD COLUMN versioning_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE buckets SET versioning_enabled = TRUE WHERE versioning_status = 'enabled';
+
+ALTER TABLE buckets DROP COLUMN versioning_status;
@@ -0,0 +1,5 @@
+ALTER TABLE buckets ADD COLUMN versioning_status TEXT NOT NULL DEFAULT 'disabled';
+
+UPDATE buckets SET versioning_status = 'enabled' WHERE versioning_enabled;
+
+ALTER TABLE buckets DROP COLUMN versioning_enabled;
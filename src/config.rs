@@ -1,15 +1,17 @@
 use std::{
     collections::{BTreeSet, HashSet},
-    net::Ipv4Addr,
+    net::{IpAddr, Ipv4Addr},
     path::PathBuf,
     time::Duration,
 };
 
 use axum::http::{HeaderName, Method};
-use serde::Deserialize;
-use url::Origin;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{DeserializeAs, DisplayFromStr, DurationSeconds, SerializeAs, serde_as};
+use url::{Origin, Url};
 
-pub use crate::models::CachePolicy;
+use crate::storage::Storage;
+pub use crate::{models::CachePolicy, storage::PathStrategy};
 
 #[derive(Debug, Default)]
 pub struct Config {
@@ -20,21 +22,140 @@ pub struct Config {
     pub cache_control: CacheControlConfig,
     pub access_control: AccessControlConfig,
     pub ip_filter: Option<IpFilterConfig>,
-    pub content_types: Option<ContentTypesConfig>,
-    pub rate_limiting: Option<RateLimitingConfig>,
+    pub content_types: ContentTypesConfig,
+    pub rate_limiting: RateLimitingConfig,
+    pub lifecycle: LifecycleConfig,
+    pub database: DatabaseConfig,
+    pub storage: StorageConfig,
+    pub cache: CacheConfig,
+    /// Key for signing presigned URLs, generated and stored in the data
+    /// directory on first startup if not set
+    pub presigned_secret: Option<String>,
+    /// Where object contents are stored, defaulting to files in the data
+    /// directory
+    pub storage_backend: Option<Storage>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+/// Builder for [`Config`], any fields which are not set fall back to their
+/// defaults
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    pub fn data_directory(mut self, data_directory: impl Into<PathBuf>) -> Self {
+        self.config.data_directory = data_directory.into();
+        self
+    }
+
+    pub fn http(mut self, http: HttpConfig) -> Self {
+        self.config.http = http;
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config.tls = Some(tls);
+        self
+    }
+
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.config.cors = Some(cors);
+        self
+    }
+
+    pub fn cache_control(mut self, cache_control: CacheControlConfig) -> Self {
+        self.config.cache_control = cache_control;
+        self
+    }
+
+    pub fn access_control(mut self, access_control: AccessControlConfig) -> Self {
+        self.config.access_control = access_control;
+        self
+    }
+
+    pub fn ip_filter(mut self, ip_filter: IpFilterConfig) -> Self {
+        self.config.ip_filter = Some(ip_filter);
+        self
+    }
+
+    pub fn content_types(mut self, content_types: ContentTypesConfig) -> Self {
+        self.config.content_types = content_types;
+        self
+    }
+
+    pub fn rate_limiting(mut self, rate_limiting: RateLimitingConfig) -> Self {
+        self.config.rate_limiting = rate_limiting;
+        self
+    }
+
+    pub fn lifecycle(mut self, lifecycle: LifecycleConfig) -> Self {
+        self.config.lifecycle = lifecycle;
+        self
+    }
+
+    pub fn database(mut self, database: DatabaseConfig) -> Self {
+        self.config.database = database;
+        self
+    }
+
+    pub fn storage(mut self, storage: StorageConfig) -> Self {
+        self.config.storage = storage;
+        self
+    }
+
+    pub fn cache(mut self, cache: CacheConfig) -> Self {
+        self.config.cache = cache;
+        self
+    }
+
+    pub fn presigned_secret(mut self, presigned_secret: impl Into<String>) -> Self {
+        self.config.presigned_secret = Some(presigned_secret.into());
+        self
+    }
+
+    pub fn storage_backend(mut self, storage_backend: Storage) -> Self {
+        self.config.storage_backend = Some(storage_backend);
+        self
+    }
+
+    pub fn build(self) -> Config {
+        self.config
+    }
 }
 
 #[derive(Debug)]
 pub struct HttpConfig {
-    pub host: Ipv4Addr,
+    pub host: IpAddr,
     pub port: u16,
+    /// Largest request body accepted by any route, or `None` for no limit
+    pub max_body_bytes: Option<u64>,
+    /// Domain under which buckets are also addressable virtual-hosted-style,
+    /// as `{bucket}.{domain}`, alongside path-style URLs
+    pub virtual_host_domain: Option<String>,
+    /// Seconds a connection may go without sending or receiving anything
+    /// before it is closed, or `None` to keep idle connections open
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds of silence before TCP keepalive probes are sent, and between
+    /// probes, or `None` to leave keepalive off
+    pub tcp_keepalive_secs: Option<u64>,
 }
 
 impl HttpConfig {
+    /// Request bodies are limited to 5 GiB by default, the same as the
+    /// largest single PUT accepted by S3
+    pub const DEFAULT_MAX_BODY_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
     pub fn random_port() -> Self {
         Self {
-            host: Ipv4Addr::UNSPECIFIED,
             port: 0,
+            ..Default::default()
         }
     }
 }
@@ -42,8 +163,12 @@ impl HttpConfig {
 impl Default for HttpConfig {
     fn default() -> Self {
         Self {
-            host: Ipv4Addr::UNSPECIFIED,
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             port: 2048,
+            max_body_bytes: Some(Self::DEFAULT_MAX_BODY_BYTES),
+            virtual_host_domain: None,
+            idle_timeout_secs: None,
+            tcp_keepalive_secs: None,
         }
     }
 }
@@ -52,6 +177,32 @@ impl Default for HttpConfig {
 pub struct TlsConfig {
     pub tls_versions: BTreeSet<TlsVersion>,
     pub keys: TlsKeyConfig,
+    /// `max-age` of the `Strict-Transport-Security` header sent with every
+    /// response, or `None` to not send it
+    pub hsts_max_age: Option<u64>,
+    /// Whether to also accept plain HTTP on `redirect_port`, redirecting
+    /// every request there to HTTPS
+    pub tls_redirect: bool,
+    pub redirect_port: u16,
+}
+
+impl TlsConfig {
+    /// Browsers remember to use HTTPS for two years by default, the minimum
+    /// for inclusion in preload lists
+    pub const DEFAULT_HSTS_MAX_AGE: u64 = 63_072_000;
+    pub const DEFAULT_REDIRECT_PORT: u16 = 80;
+
+    /// Config for serving HTTPS with the given keys, which sends HSTS headers
+    /// but doesn't redirect plain HTTP
+    pub fn new(tls_versions: BTreeSet<TlsVersion>, keys: TlsKeyConfig) -> Self {
+        Self {
+            tls_versions,
+            keys,
+            hsts_max_age: Some(Self::DEFAULT_HSTS_MAX_AGE),
+            tls_redirect: false,
+            redirect_port: Self::DEFAULT_REDIRECT_PORT,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, strum::EnumString)]
@@ -76,14 +227,59 @@ pub enum TlsKeyConfig {
     },
 }
 
-#[derive(Debug)]
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CorsConfig {
+    #[serde_as(as = "HashSet<OriginAsString>")]
     pub allow_origins: HashSet<Origin>,
+    #[serde_as(as = "HashSet<DisplayFromStr>")]
     pub allow_methods: HashSet<Method>,
+    #[serde_as(as = "HashSet<DisplayFromStr>")]
     pub allow_headers: HashSet<HeaderName>,
     pub allow_credentials: bool,
+    /// Response headers which scripts on other origins may read
+    #[serde_as(as = "HashSet<DisplayFromStr>")]
+    #[serde(default)]
+    pub expose_headers: HashSet<HeaderName>,
+    /// How long browsers may cache the response to a preflight request
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
+    #[serde(default)]
+    pub max_age: Option<Duration>,
 }
 
+/// (De)serializes a [`Origin`] using its ASCII serialization (e.g.
+/// `https://cdn.example.com`), since it doesn't implement `Display`/`FromStr`
+struct OriginAsString;
+
+impl SerializeAs<Origin> for OriginAsString {
+    fn serialize_as<S: Serializer>(source: &Origin, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&source.ascii_serialization())
+    }
+}
+
+impl<'de> DeserializeAs<'de, Origin> for OriginAsString {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Origin, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        let origin = value
+            .parse::<Url>()
+            .map_err(|e| serde::de::Error::custom(format!("invalid origin '{}': {}", value, e)))?
+            .origin();
+
+        if !origin.is_tuple() {
+            return Err(serde::de::Error::custom(format!(
+                "invalid origin '{}': opaque origins are not supported",
+                value
+            )));
+        }
+
+        Ok(origin)
+    }
+}
+
+/// Max age in seconds of objects with the immutable cache policy
+const IMMUTABLE_MAX_AGE: u64 = 31_536_000;
+
 #[derive(Debug)]
 pub struct CacheControlConfig {
     pub default_policy: CachePolicy,
@@ -99,6 +295,18 @@ impl Default for CacheControlConfig {
     }
 }
 
+impl CacheControlConfig {
+    /// Value of the `Cache-Control` header for an object with the given policy,
+    /// falling back to the default policy if it has none
+    pub fn header_value(&self, policy: Option<CachePolicy>) -> String {
+        match policy.unwrap_or(self.default_policy) {
+            CachePolicy::Cache => format!("max-age={}", self.default_max_age),
+            CachePolicy::NoCache => "no-cache".to_owned(),
+            CachePolicy::Immutable => format!("immutable, max-age={}", IMMUTABLE_MAX_AGE),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AccessControlConfig {
     pub enable_access_tokens: bool,
@@ -120,14 +328,203 @@ pub enum IpFilterConfig {
     Blacklist(BTreeSet<cidr::IpCidr>),
 }
 
+impl IpFilterConfig {
+    /// Checks whether the given address passes the filter. IPv4 addresses
+    /// mapped into IPv6 are matched as IPv4.
+    pub fn allows(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        let matches = |cidr: &cidr::IpCidr| cidr.contains(&addr);
+
+        match self {
+            IpFilterConfig::Whitelist(cidrs) => cidrs.iter().any(matches),
+            IpFilterConfig::Blacklist(cidrs) => !cidrs.iter().any(matches),
+        }
+    }
+}
+
+/// Restricts the content types objects can be stored with
+#[derive(Debug, Default)]
+pub struct ContentTypesConfig {
+    /// Applies to objects whose keys don't match any of the rules
+    pub filter: Option<ContentTypeFilter>,
+    /// Content types required of objects whose keys match a pattern, checked
+    /// in order before the filter
+    pub rules: Vec<ContentTypeRule>,
+}
+
+impl ContentTypesConfig {
+    pub fn is_empty(&self) -> bool {
+        self.filter.is_none() && self.rules.is_empty()
+    }
+
+    /// Checks whether an object stored under `key` may have the given content
+    /// type. The first rule whose pattern matches the key decides, and the
+    /// filter is only used when no rule matches.
+    pub fn check(&self, key: &str, content_type: &mime::Mime) -> Result<(), ContentTypeError> {
+        match self.rules.iter().find(|rule| rule.matches_key(key)) {
+            Some(rule) if !rule.allows(content_type) => Err(ContentTypeError::Rule {
+                key_pattern: rule.key_pattern.to_string(),
+                required: rule.content_type.to_string(),
+                content_type: content_type.to_string(),
+            }),
+            Some(_) => Ok(()),
+            None => match &self.filter {
+                Some(filter) if !filter.allows(content_type) => {
+                    Err(ContentTypeError::Filtered(content_type.to_string()))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// Reasons an object's content type isn't allowed
+#[derive(Debug, thiserror::Error)]
+pub enum ContentTypeError {
+    #[error(
+        "Objects matching `{key_pattern}` must have content type `{required}`, not `{content_type}`"
+    )]
+    Rule {
+        key_pattern: String,
+        required: String,
+        content_type: String,
+    },
+    #[error("Objects with content type `{0}` are not allowed")]
+    Filtered(String),
+}
+
 #[derive(Debug)]
-pub enum ContentTypesConfig {
+pub enum ContentTypeFilter {
     Whitelist(BTreeSet<mime::Mime>),
     Blacklist(BTreeSet<mime::Mime>),
 }
 
+impl ContentTypeFilter {
+    /// Checks whether the given content type passes the filter. Patterns may
+    /// use wildcards such as `image/*` or `*/*`.
+    pub fn allows(&self, content_type: &mime::Mime) -> bool {
+        let matches = |pattern: &mime::Mime| mime_matches(pattern, content_type);
+
+        match self {
+            ContentTypeFilter::Whitelist(patterns) => patterns.iter().any(matches),
+            ContentTypeFilter::Blacklist(patterns) => !patterns.iter().any(matches),
+        }
+    }
+}
+
+/// Requires objects whose keys match `key_pattern` to have a content type
+/// matching `content_type`
+#[derive(Debug, Clone)]
+pub struct ContentTypeRule {
+    /// Glob pattern, where `*` and `?` don't match across a `/` and `**`
+    /// matches any number of path segments
+    pub key_pattern: glob::Pattern,
+    /// May use wildcards such as `image/*`
+    pub content_type: mime::Mime,
+}
+
+impl ContentTypeRule {
+    pub fn matches_key(&self, key: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+
+        self.key_pattern.matches_with(key, options)
+    }
+
+    pub fn allows(&self, content_type: &mime::Mime) -> bool {
+        mime_matches(&self.content_type, content_type)
+    }
+}
+
+fn mime_matches(pattern: &mime::Mime, content_type: &mime::Mime) -> bool {
+    (pattern.type_() == mime::STAR || pattern.type_() == content_type.type_())
+        && (pattern.subtype() == mime::STAR || pattern.subtype() == content_type.subtype())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RateLimitingConfig {
+    pub enable_rate_limiting: bool,
     pub default_period: Duration,
     pub default_burst_size: u32,
 }
+
+impl Default for RateLimitingConfig {
+    fn default() -> Self {
+        Self {
+            enable_rate_limiting: true,
+            default_period: Duration::from_secs(60),
+            default_burst_size: 100,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LifecycleConfig {
+    /// How often bucket lifecycle rules are applied
+    pub interval: Duration,
+    /// How long soft deleted objects are kept before they are purged
+    pub soft_delete_retention_days: u32,
+    /// How long multipart uploads can be in progress before they are
+    /// considered stale and aborted
+    pub multipart_upload_ttl: Duration,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60 * 60),
+            soft_delete_retention_days: 30,
+            multipart_upload_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// Sizing of the database connection pool
+#[derive(Debug)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+    /// Connections which are kept open even while idle
+    pub min_connections: u32,
+    /// How long a connection can be idle before it is closed, or `None` to
+    /// keep idle connections open
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+        }
+    }
+}
+
+/// Options for the local storage backend, which is used unless another
+/// backend is given
+#[derive(Debug, Default)]
+pub struct StorageConfig {
+    pub path_strategy: PathStrategy,
+}
+
+/// Caching of object contents as they are served
+#[derive(Debug)]
+pub struct CacheConfig {
+    /// Whether concurrent downloads of the same contents share a single read
+    /// of storage
+    pub coalesce_concurrent_reads: bool,
+    /// How long a download waits for others of the same contents to join it
+    /// before it starts reading
+    pub coalesce_window: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            coalesce_concurrent_reads: false,
+            coalesce_window: Duration::from_millis(10),
+        }
+    }
+}
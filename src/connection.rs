@@ -0,0 +1,140 @@
+//! Per-connection socket settings, applied to every accepted TCP connection
+//! before it is handed to hyper (or to rustls first when serving HTTPS)
+
+use std::{
+    future::Ready,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum_server::accept::Accept;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    time::{Instant, Sleep},
+};
+
+use crate::config::HttpConfig;
+
+/// Enables TCP keepalive on accepted connections and closes them once they
+/// have been idle for too long
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionAcceptor {
+    idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+}
+
+impl ConnectionAcceptor {
+    pub fn new(http: &HttpConfig) -> Self {
+        Self {
+            idle_timeout: http.idle_timeout_secs.map(Duration::from_secs),
+            tcp_keepalive: http.tcp_keepalive_secs.map(Duration::from_secs),
+        }
+    }
+
+    fn set_keepalive(&self, stream: &TcpStream) -> io::Result<()> {
+        let Some(keepalive) = self.tcp_keepalive else {
+            return Ok(());
+        };
+
+        // Probes start after the connection has been quiet for `keepalive`,
+        // and are repeated at the same interval where the platform allows
+        let params = TcpKeepalive::new().with_time(keepalive);
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        let params = params.with_interval(keepalive);
+
+        SockRef::from(stream).set_tcp_keepalive(&params)
+    }
+}
+
+impl<S> Accept<TcpStream, S> for ConnectionAcceptor {
+    type Stream = IdleTimeout<TcpStream>;
+    type Service = S;
+    type Future = Ready<io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        std::future::ready(
+            self.set_keepalive(&stream)
+                .map(|_| (IdleTimeout::new(stream, self.idle_timeout), service)),
+        )
+    }
+}
+
+/// A stream which reports end of file once nothing has been read or written
+/// for `timeout`, so hyper closes the connection as if the client had hung up
+pub struct IdleTimeout<S> {
+    inner: S,
+    timeout: Option<Duration>,
+    deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> IdleTimeout<S> {
+    fn new(inner: S, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout,
+            deadline: timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout))),
+        }
+    }
+
+    /// Pushes the deadline back after activity on the connection
+    fn reset(&mut self) {
+        if let (Some(deadline), Some(timeout)) = (&mut self.deadline, self.timeout) {
+            deadline.as_mut().reset(Instant::now() + timeout);
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for IdleTimeout<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match Pin::new(&mut self.inner).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                self.reset();
+                Poll::Ready(result)
+            }
+            // Leaving the buffer empty signals end of file
+            Poll::Pending => {
+                let idle = self
+                    .deadline
+                    .as_mut()
+                    .is_some_and(|deadline| deadline.as_mut().poll(cx).is_ready());
+
+                if idle {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for IdleTimeout<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if result.is_ready() {
+            self.reset();
+        }
+
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
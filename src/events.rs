@@ -0,0 +1,83 @@
+//! Notifications of changes to buckets, streamed to clients as server-sent
+//! events.
+//!
+//! Handlers and background tasks publish events through the server's
+//! [`BucketEvents`]. Every bucket gets its own broadcast channel, created when
+//! the first client subscribes.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::models::object::Object;
+
+/// Events buffered per subscriber before the oldest are dropped for clients
+/// that can't keep up
+const CHANNEL_CAPACITY: usize = 256;
+
+/// A change to a bucket or its objects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, strum::IntoStaticStr)]
+#[serde(tag = "type")]
+pub enum BucketEvent {
+    ObjectCreated {
+        key: String,
+        etag: String,
+        size: u64,
+    },
+    ObjectDeleted {
+        key: String,
+    },
+    BucketSettingsChanged,
+}
+
+impl BucketEvent {
+    pub fn object_created(object: &Object) -> Self {
+        Self::ObjectCreated {
+            key: object.path().to_owned(),
+            etag: object.etag().to_owned(),
+            size: object.size(),
+        }
+    }
+
+    /// The event's name, sent as the `event` field of a server-sent event
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Broadcast channels of every bucket which has subscribers
+#[derive(Debug, Clone, Default)]
+pub struct BucketEvents {
+    senders: Arc<Mutex<HashMap<Uuid, broadcast::Sender<BucketEvent>>>>,
+}
+
+impl BucketEvents {
+    pub fn subscribe(&self, bucket: Uuid) -> broadcast::Receiver<BucketEvent> {
+        self.senders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(bucket)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Sends an event to the bucket's subscribers, dropping its channel once
+    /// they have all gone away
+    pub fn publish(&self, bucket: Uuid, event: BucketEvent) {
+        let mut senders = self
+            .senders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(sender) = senders.get(&bucket)
+            && sender.send(event).is_err()
+        {
+            senders.remove(&bucket);
+        }
+    }
+}
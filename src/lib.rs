@@ -1,35 +1,130 @@
-use std::{net::SocketAddr, path::Path, sync::Arc};
+#![deny(clippy::unwrap_used)]
 
-use crate::{config::Config, routes::create_router};
+use std::{io::Write, net::SocketAddr, path::Path, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{
+    config::{Config, DatabaseConfig},
+    connection::ConnectionAcceptor,
+    events::BucketEvents,
+    middleware::{
+        access_log::log_access,
+        auth::authenticate,
+        compression::compress_responses,
+        https::{redirect_to_https, set_hsts},
+        ip_filter::filter_ips,
+        log_errors::log_server_errors,
+        metrics::track_requests,
+        presign::verify_presigned_url,
+        rate_limit::{RateLimiter, rate_limit},
+        request_id::{RequestId, assign_request_id},
+        virtual_host::route_virtual_hosts,
+    },
+    routes::{create_health_router, create_metrics_router, create_router},
+    storage::{CoalescingBackend, LocalFsBackend, Storage},
+    tls::TlsError,
+};
 use axum::{
     Json, Router, ServiceExt,
     extract::{FromRef, Request},
-    http::{HeaderValue, StatusCode, Uri},
+    http::{HeaderValue, StatusCode, Uri, header::InvalidHeaderValue},
 };
+use axum_server::tls_rustls::RustlsConfig;
+use futures::FutureExt;
+use metrics_exporter_prometheus::{BuildError, PrometheusHandle};
 use serde_json::{Value, json};
-use sqlx::migrate::MigrateDatabase;
-use tower_http::{cors::CorsLayer, normalize_path::NormalizePath, trace::TraceLayer};
+use sqlx::{
+    migrate::{MigrateDatabase, Migrator},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+};
+use tower_http::{
+    cors::CorsLayer, limit::RequestBodyLimitLayer, normalize_path::NormalizePath, trace::TraceLayer,
+};
 
 use tokio::task::JoinHandle;
 
 pub mod config;
+mod connection;
+mod events;
+mod metrics;
+mod middleware;
 mod models;
 mod routes;
+pub mod storage;
+mod tasks;
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
+mod tls;
+
+/// Migrations for the instance database, embedded at compile time
+static MIGRATOR: Migrator = sqlx::migrate!("migrations/instance");
+
+/// How long a connection waits for the database to be unlocked before giving
+/// up on a query
+const DATABASE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Clone, FromRef)]
 struct AppState {
     db: sqlx::SqlitePool,
     config: Arc<Config>,
+    metrics: PrometheusHandle,
+    storage: Storage,
+    /// Handle for reloading the TLS certificate of the running server
+    tls: Option<RustlsConfig>,
+    events: BucketEvents,
+}
+
+/// Errors that can occur while starting the server
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    #[error("Failed to create data directory: {0}")]
+    DataDirectory(#[source] std::io::Error),
+    #[error("Failed to load presigned URL secret: {0}")]
+    PresignedSecret(#[source] std::io::Error),
+    #[error("Failed to open storage: {0}")]
+    Storage(#[source] std::io::Error),
+    #[error("Failed to initialize database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Invalid CORS origin: {0}")]
+    CorsOrigin(#[from] InvalidHeaderValue),
+    #[error("Failed to bind listener: {0}")]
+    Bind(#[source] std::io::Error),
+    #[error("Failed to load TLS configuration: {0}")]
+    Tls(#[from] TlsError),
+    #[error("Failed to install metrics recorder: {0}")]
+    Metrics(#[from] BuildError),
 }
 
-pub async fn create_server(config: Config) -> (SocketAddr, JoinHandle<()>) {
+pub async fn create_server(
+    mut config: Config,
+) -> Result<(SocketAddr, JoinHandle<std::io::Result<()>>), ServerError> {
     /* Initialize State */
 
-    init_data_directory(&config.data_directory).expect("Failed to create data directory");
+    init_data_directory(&config.data_directory).map_err(ServerError::DataDirectory)?;
 
-    let db = init_main_db(&config.data_directory)
-        .await
-        .expect("Failed to initialize DB");
+    if config.presigned_secret.is_none() {
+        config.presigned_secret = Some(
+            init_presigned_secret(&config.data_directory).map_err(ServerError::PresignedSecret)?,
+        );
+    }
+
+    let db = init_main_db(&config.data_directory, &config.database).await?;
+
+    let metrics = metrics::install_recorder()?;
+
+    let storage: Storage = match &config.storage_backend {
+        Some(storage) => storage.clone(),
+        None => Arc::new(
+            LocalFsBackend::open(&config.data_directory, config.storage.path_strategy)
+                .map_err(ServerError::Storage)?,
+        ),
+    };
+    let storage: Storage = match config.cache.coalesce_concurrent_reads {
+        true => Arc::new(CoalescingBackend::new(
+            storage,
+            config.cache.coalesce_window,
+        )),
+        false => storage,
+    };
 
     /* CORS Support */
 
@@ -38,55 +133,214 @@ pub async fn create_server(config: Config) -> (SocketAddr, JoinHandle<()>) {
             let origins = cors
                 .allow_origins
                 .iter()
-                .map(|o| o.ascii_serialization().parse().unwrap())
-                .collect::<Vec<HeaderValue>>();
+                .map(|o| HeaderValue::from_str(&o.ascii_serialization()))
+                .collect::<Result<Vec<_>, _>>()?;
 
-            CorsLayer::new()
+            let layer = CorsLayer::new()
                 .allow_methods(cors.allow_methods.clone().into_iter().collect::<Vec<_>>())
                 .allow_headers(cors.allow_headers.clone().into_iter().collect::<Vec<_>>())
                 .allow_credentials(cors.allow_credentials)
-                .allow_origin(origins)
+                .expose_headers(cors.expose_headers.clone().into_iter().collect::<Vec<_>>())
+                .allow_origin(origins);
+
+            match cors.max_age {
+                Some(max_age) => layer.max_age(max_age),
+                None => layer,
+            }
         }
         None => CorsLayer::new(),
     };
 
+    /* TLS Support */
+
+    let tls = match &config.tls {
+        Some(tls) => Some(RustlsConfig::from_config(Arc::new(
+            tls::load_server_config(tls)?,
+        ))),
+        None => None,
+    };
+
     /* Initialize Application */
 
     let (host, port) = (config.http.host, config.http.port);
     let state = AppState {
         db,
         config: Arc::new(config),
+        metrics,
+        storage,
+        tls: tls.clone(),
+        events: BucketEvents::default(),
     };
 
+    let background_tasks = tasks::run(state.clone());
+
+    let mut router = Router::new()
+        .fallback(fallback)
+        .merge(create_router(state.clone()))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            authenticate,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.config.clone(),
+            verify_presigned_url,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.db.clone(),
+            log_access,
+        ))
+        // Health checks and metrics are merged after authentication so probes
+        // and scrapers never need an access token
+        .merge(create_health_router())
+        .merge(create_metrics_router())
+        .layer(axum::middleware::from_fn(track_requests))
+        // Compression sits outside of metrics so that byte counts reflect
+        // object sizes rather than what went over the wire
+        .layer(compress_responses())
+        .layer(cors);
+
+    // Bodies over the limit are rejected before reaching any handler when
+    // their length is declared up front, or cut off while being streamed
+    if let Some(max_body_bytes) = state.config.http.max_body_bytes {
+        router = router.layer(RequestBodyLimitLayer::new(
+            usize::try_from(max_body_bytes).unwrap_or(usize::MAX),
+        ));
+    }
+
+    if state.config.rate_limiting.enable_rate_limiting {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            (
+                Arc::new(RateLimiter::new(&state.config.rate_limiting)),
+                state.db.clone(),
+            ),
+            rate_limit,
+        ));
+    }
+
+    if state.config.ip_filter.is_some() {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            state.config.clone(),
+            filter_ips,
+        ));
+    }
+
+    let config = state.config.clone();
     let app = NormalizePath::trim_trailing_slash(
-        Router::new()
-            .fallback(fallback)
-            .merge(create_router(state.clone()))
-            .layer(cors)
-            .layer(TraceLayer::new_for_http())
+        router
+            .layer(axum::middleware::from_fn(log_server_errors))
+            .layer(TraceLayer::new_for_http().make_span_with(|req: &Request| {
+                let request_id = req.extensions().get::<RequestId>().map(|id| &*id.0);
+
+                tracing::debug_span!(
+                    "request",
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    request_id,
+                )
+            }))
+            .layer(axum::middleware::from_fn(assign_request_id))
             .with_state(state),
     );
 
+    // The outer router records the request URI as `OriginalUri` before it is
+    // normalized, since signatures cover the path exactly as it was sent
+    let mut app = Router::new().fallback_service(app);
+    if config.http.virtual_host_domain.is_some() {
+        app = app.layer(axum::middleware::from_fn_with_state(
+            config.clone(),
+            route_virtual_hosts,
+        ));
+    }
+    if let Some(hsts_max_age) = config.tls.as_ref().and_then(|tls| tls.hsts_max_age) {
+        app = app.layer(axum::middleware::from_fn_with_state(hsts_max_age, set_hsts));
+    }
+
     /* Serve our app with hyper */
 
     let addr = SocketAddr::from((host, port));
     let listener = tokio::net::TcpListener::bind(addr)
         .await
-        .expect("failed to bind");
+        .map_err(ServerError::Bind)?;
+    let local_addr = listener.local_addr().map_err(ServerError::Bind)?;
+
+    tracing::info!(
+        "Listening on: {}",
+        ServerAddress {
+            addr: local_addr,
+            has_tls: tls.is_some(),
+        }
+    );
 
-    tracing::info!("Listening on: http://{}", addr);
+    // Plain HTTP is only accepted on a separate port, where every request is
+    // redirected to the HTTPS listener
+    let redirect_server = match config.tls.as_ref().filter(|tls| tls.tls_redirect) {
+        Some(tls_config) => {
+            let listener =
+                tokio::net::TcpListener::bind(SocketAddr::from((host, tls_config.redirect_port)))
+                    .await
+                    .map_err(ServerError::Bind)?;
 
-    (
-        listener.local_addr().unwrap(),
-        tokio::spawn(async {
-            axum::serve(
-                listener,
-                ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app),
-            )
-            .await
-            .unwrap()
-        }),
-    )
+            tracing::info!(
+                "Redirecting to HTTPS from: {}",
+                ServerAddress {
+                    addr: listener.local_addr().map_err(ServerError::Bind)?,
+                    has_tls: false,
+                }
+            );
+
+            let redirect = Router::new()
+                .fallback(redirect_to_https)
+                .with_state(local_addr.port());
+
+            axum::serve(listener, redirect).into_future().boxed()
+        }
+        None => std::future::pending().boxed(),
+    };
+
+    let make_service =
+        ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app);
+
+    // axum-server is used for plain HTTP as well, since its acceptors can wrap
+    // each connection to apply keepalive and idle timeouts
+    let acceptor = ConnectionAcceptor::new(&config.http);
+    let listener = listener.into_std().map_err(ServerError::Bind)?;
+    let server = match tls {
+        Some(tls) => axum_server::from_tcp_rustls(listener, tls)
+            .map_err(ServerError::Bind)?
+            .map(|tls| tls.acceptor(acceptor))
+            .serve(make_service)
+            .boxed(),
+        None => axum_server::from_tcp(listener)
+            .map_err(ServerError::Bind)?
+            .acceptor(acceptor)
+            .serve(make_service)
+            .boxed(),
+    };
+
+    // Background tasks are tied to the server task so they stop with it
+    let handle = tokio::spawn(async {
+        tokio::select! {
+            r = server => r,
+            r = redirect_server => r,
+            _ = background_tasks => Ok(()),
+        }
+    });
+
+    Ok((local_addr, handle))
+}
+
+/// The address a server is reachable at, formatted as a URL for logging
+struct ServerAddress {
+    addr: SocketAddr,
+    has_tls: bool,
+}
+
+impl std::fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = if self.has_tls { "https" } else { "http" };
+
+        write!(f, "{}://{}", scheme, self.addr)
+    }
 }
 
 fn init_data_directory(path: impl AsRef<Path>) -> std::io::Result<()> {
@@ -97,11 +351,42 @@ fn init_data_directory(path: impl AsRef<Path>) -> std::io::Result<()> {
     Ok(())
 }
 
-async fn init_main_db(data_directory: impl AsRef<Path>) -> sqlx::Result<sqlx::SqlitePool> {
-    let database_url = format!(
+/// Loads the secret used to sign presigned URLs from the data directory,
+/// generating one on first startup so URLs stay valid across restarts
+fn init_presigned_secret(data_directory: impl AsRef<Path>) -> std::io::Result<String> {
+    let path = data_directory.as_ref().join("presigned_secret");
+
+    match std::fs::read_to_string(&path) {
+        Ok(secret) => Ok(secret.trim().to_owned()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let secret = hex::encode(rand::random::<[u8; 32]>());
+
+            // Anyone who can read the secret can presign URLs, so only the
+            // server's user may read it
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options.open(&path)?.write_all(secret.as_bytes())?;
+
+            Ok(secret)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn database_url(data_directory: impl AsRef<Path>) -> String {
+    format!(
         "sqlite://{}",
         data_directory.as_ref().join("database.sqlite3").display()
-    );
+    )
+}
+
+async fn init_main_db(
+    data_directory: impl AsRef<Path>,
+    database: &DatabaseConfig,
+) -> sqlx::Result<sqlx::SqlitePool> {
+    let database_url = database_url(data_directory);
 
     if !sqlx::Sqlite::database_exists(&database_url)
         .await
@@ -113,9 +398,21 @@ async fn init_main_db(data_directory: impl AsRef<Path>) -> sqlx::Result<sqlx::Sq
         tracing::debug!("Database already exists at {}", database_url);
     }
 
-    let db = sqlx::SqlitePool::connect(&database_url).await?;
+    // WAL lets reads continue while a write is in progress, and writers wait
+    // for each other rather than failing with `SQLITE_BUSY`
+    let options = SqliteConnectOptions::from_str(&database_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(DATABASE_BUSY_TIMEOUT);
+
+    let db = SqlitePoolOptions::new()
+        .max_connections(database.max_connections)
+        .min_connections(database.min_connections)
+        .idle_timeout(database.idle_timeout)
+        .connect_with(options)
+        .await?;
 
-    sqlx::migrate!("migrations/instance").run(&db).await?;
+    MIGRATOR.run(&db).await?;
 
     tracing::debug!("Initialized database!");
 
@@ -1,11 +1,18 @@
 use std::collections::BTreeSet;
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use clap::error::ErrorKind;
 use clap::{CommandFactory, Parser};
+use mime::Mime;
 use objection::{
-    config::{CachePolicy, Config, CorsConfig, HttpConfig, TlsConfig, TlsKeyConfig, TlsVersion},
+    config::{
+        AccessControlConfig, CacheConfig, CacheControlConfig, CachePolicy, Config,
+        ContentTypeFilter, ContentTypeRule, ContentTypesConfig, CorsConfig, DatabaseConfig,
+        HttpConfig, IpFilterConfig, LifecycleConfig, PathStrategy, RateLimitingConfig,
+        StorageConfig, TlsConfig, TlsKeyConfig, TlsVersion,
+    },
     create_server,
 };
 use serde::Deserialize;
@@ -15,6 +22,27 @@ use url::Url;
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     config_path: Option<PathBuf>,
+    /// Format of the configuration file, detected from its extension if
+    /// omitted
+    #[arg(long, value_enum)]
+    format: Option<ConfigFormat>,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detects the format of a configuration file from its extension,
+    /// defaulting to TOML
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
 }
 
 #[tokio::main]
@@ -27,23 +55,32 @@ async fn main() -> std::io::Result<()> {
 
     let args = Args::parse();
 
-    let config = args.config_path.map(parse_and_validate).unwrap_or_default();
+    let mut file = args
+        .config_path
+        .map(|path| parse_file(&path, args.format))
+        .unwrap_or_default();
+    env_overrides(&mut file);
+    let config = validate_file(file);
 
     tracing::debug!("using config: {:#?}", config);
 
-    let (_, handle) = create_server(config).await;
+    let (_, handle) = match create_server(config).await {
+        Ok(server) => server,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     tokio::select! {
-        r = handle => r.expect("failed to serve"),
+        r = handle => r.expect("server task panicked")?,
         _ =  tokio::signal::ctrl_c() => {}
     }
 
     Ok(())
 }
 
-pub fn parse_and_validate(path: impl AsRef<Path>) -> Config {
-    use clap::error::ErrorKind;
-
+pub fn parse_file(path: impl AsRef<Path>, format: Option<ConfigFormat>) -> ConfigFile {
     let mut cmd = Args::command();
 
     let contents = match std::fs::read_to_string(path.as_ref()) {
@@ -59,7 +96,14 @@ pub fn parse_and_validate(path: impl AsRef<Path>) -> Config {
             )
             .exit(),
     };
-    let file = match toml::from_str::<ConfigFile>(&contents) {
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(path.as_ref()));
+    let file = match format {
+        ConfigFormat::Toml => toml::from_str::<ConfigFile>(&contents).map_err(|e| e.to_string()),
+        ConfigFormat::Json => {
+            serde_json::from_str::<ConfigFile>(&contents).map_err(|e| e.to_string())
+        }
+    };
+    match file {
         Ok(value) => value,
         Err(e) => cmd
             .error(
@@ -71,22 +115,114 @@ pub fn parse_and_validate(path: impl AsRef<Path>) -> Config {
                 ),
             )
             .exit(),
+    }
+}
+
+/// Overrides the configuration file with any `OBJECTION_*` environment
+/// variables which are set, so the server can be configured without a
+/// configuration file. Environment variables take priority over the
+/// configuration file, which takes priority over the defaults.
+fn env_overrides(file: &mut ConfigFile) {
+    let mut cmd = Args::command();
+
+    let var = |name: &str| std::env::var(name).ok();
+    let mut invalid = |name: &str, value: &str| -> ! {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            format!("Invalid value '{}' for {}", value, name),
+        )
+        .exit()
+    };
+
+    if let Some(data_directory) = var("OBJECTION_DATA_DIRECTORY") {
+        file.data_directory = Some(data_directory);
+    }
+    if let Some(host) = var("OBJECTION_HTTP_HOST") {
+        file.http.get_or_insert_default().host = Some(
+            host.parse()
+                .unwrap_or_else(|_| invalid("OBJECTION_HTTP_HOST", &host)),
+        );
+    }
+    if let Some(port) = var("OBJECTION_HTTP_PORT") {
+        file.http.get_or_insert_default().port = Some(
+            port.parse()
+                .unwrap_or_else(|_| invalid("OBJECTION_HTTP_PORT", &port)),
+        );
+    }
+
+    let tls_keys = match (
+        var("OBJECTION_TLS_PRIVATE_KEY"),
+        var("OBJECTION_TLS_PUBLIC_KEY"),
+        var("OBJECTION_TLS_PRIVATE_KEY_FILE"),
+        var("OBJECTION_TLS_PUBLIC_KEY_FILE"),
+    ) {
+        (None, None, None, None) => None,
+        keys @ ((Some(_), Some(_), None, None) | (None, None, Some(_), Some(_))) => Some(keys),
+        _ => cmd
+            .error(
+                ErrorKind::ValueValidation,
+                "Invalid TLS environment variables. Must set either OBJECTION_TLS_PRIVATE_KEY and OBJECTION_TLS_PUBLIC_KEY or OBJECTION_TLS_PRIVATE_KEY_FILE and OBJECTION_TLS_PUBLIC_KEY_FILE",
+            )
+            .exit(),
     };
+    if let Some((private_key, public_key, private_key_file, public_key_file)) = tls_keys {
+        // Keys from the environment replace both kinds of keys in the file
+        let tls = file.tls.get_or_insert_default();
+        tls.private_key = private_key;
+        tls.public_key = public_key;
+        tls.private_key_file = private_key_file.map(PathBuf::from);
+        tls.public_key_file = public_key_file.map(PathBuf::from);
+    }
 
-    validate_file(file)
+    if let Some(enable) = var("OBJECTION_ENABLE_ACCESS_TOKENS") {
+        file.access_control
+            .get_or_insert_default()
+            .enable_access_tokens = Some(
+            enable
+                .parse()
+                .unwrap_or_else(|_| invalid("OBJECTION_ENABLE_ACCESS_TOKENS", &enable)),
+        );
+    }
+    if let Some(enable) = var("OBJECTION_ENABLE_RATE_LIMITING") {
+        file.rate_limiting
+            .get_or_insert_default()
+            .enable_rate_limiting = Some(
+            enable
+                .parse()
+                .unwrap_or_else(|_| invalid("OBJECTION_ENABLE_RATE_LIMITING", &enable)),
+        );
+    }
+    if let Some(presigned_secret) = var("OBJECTION_PRESIGNED_SECRET") {
+        file.presigned_secret = Some(presigned_secret);
+    }
 }
 
 fn validate_file(file: ConfigFile) -> Config {
     let mut cmd = Args::command();
 
-    let data_directory =
-        Path::new(&file.data_directory.expect("missing data directory")).to_path_buf();
+    let data_directory = match file.data_directory {
+        Some(data_directory) => PathBuf::from(data_directory),
+        None => cmd
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "No data directory is configured. Set `data-directory` in the configuration file or OBJECTION_DATA_DIRECTORY",
+            )
+            .exit(),
+    };
 
     let http = file
         .http
         .map(|http| HttpConfig {
             host: http.host.unwrap_or_else(|| HttpConfig::default().host),
             port: http.port.unwrap_or_else(|| HttpConfig::default().port),
+            max_body_bytes: match http.max_body_bytes {
+                Some(0) => None,
+                Some(max_body_bytes) => Some(max_body_bytes),
+                None => HttpConfig::default().max_body_bytes,
+            },
+            virtual_host_domain: http.virtual_host_domain,
+            idle_timeout_secs: http.idle_timeout_secs,
+            tcp_keepalive_secs: http.tcp_keepalive_secs,
         })
         .unwrap_or_default();
 
@@ -126,8 +262,14 @@ fn validate_file(file: ConfigFile) -> Config {
             };
 
             TlsConfig {
-                tls_versions,
-                keys,
+                hsts_max_age: match tls.hsts_max_age {
+                    Some(0) => None,
+                    Some(hsts_max_age) => Some(hsts_max_age),
+                    None => Some(TlsConfig::DEFAULT_HSTS_MAX_AGE),
+                },
+                tls_redirect: tls.tls_redirect.unwrap_or(false),
+                redirect_port: tls.redirect_port.unwrap_or(TlsConfig::DEFAULT_REDIRECT_PORT),
+                ..TlsConfig::new(tls_versions, keys)
             }
         });
 
@@ -157,6 +299,14 @@ fn validate_file(file: ConfigFile) -> Config {
                 methods
                     .into_iter()
                     .map(|m| {
+                        if m == "*" {
+                            cmd.error(
+                                ErrorKind::ValueValidation,
+                                "CORS methods must be listed explicitly, the '*' wildcard is not supported",
+                            )
+                            .exit()
+                        }
+
                         m.parse().unwrap_or_else(|_| {
                             cmd.error(
                                 ErrorKind::ValueValidation,
@@ -186,27 +336,345 @@ fn validate_file(file: ConfigFile) -> Config {
             })
             .unwrap_or_default(),
         allow_credentials: cors.allow_credentials.unwrap_or_default(),
+        expose_headers: cors
+            .expose_headers
+            .map(|headers| {
+                headers
+                    .into_iter()
+                    .map(|h| {
+                        h.parse().unwrap_or_else(|_| {
+                            cmd.error(
+                                ErrorKind::ValueValidation,
+                                format!("Invalid CORS exposed HTTP header '{}'", h),
+                            )
+                            .exit()
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        max_age: cors.max_age.map(Duration::from_secs),
     });
 
+    // Browsers refuse credentialed requests when any origin is allowed, which
+    // is what an empty list of origins means
+    if let Some(cors) = &cors
+        && cors.allow_credentials
+        && cors.allow_origins.is_empty()
+    {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            "CORS `allow-credentials` requires `allow-origins` to list the allowed origins explicitly",
+        )
+        .exit()
+    }
+
     let cache_control = file
         .cache_control
-        .map(|_| todo!("Validate cache control config"))
+        .map(|cache_control| {
+            let defaults = CacheControlConfig::default();
+
+            let default_policy = cache_control
+                .default_policy
+                .map(|p| {
+                    p.parse::<CachePolicy>().unwrap_or_else(|_| {
+                        cmd.error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid cache policy '{}'", p),
+                        )
+                        .exit()
+                    })
+                })
+                .unwrap_or(defaults.default_policy);
+
+            // Max ages are converted to milliseconds internally
+            let default_max_age = match cache_control.default_max_age {
+                Some(max_age) if max_age > u64::MAX / 1000 => cmd
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("Cache max age '{}' is too large", max_age),
+                    )
+                    .exit(),
+                Some(max_age) => max_age,
+                None => defaults.default_max_age,
+            };
+
+            CacheControlConfig {
+                default_policy,
+                default_max_age,
+            }
+        })
         .unwrap_or_default();
     let access_control = file
         .access_control
-        .map(|_| todo!("Validate access control config"))
-        .unwrap_or_default();
-    let ip_filter = file
-        .ip_filter
-        .map(|_| todo!("Validate ip filter config"))
-        .unwrap_or_default();
-    let content_types = file
-        .content_types
-        .map(|_| todo!("Validate content type filter config"))
+        .map(|access_control| {
+            let defaults = AccessControlConfig::default();
+
+            let enable_access_tokens = access_control
+                .enable_access_tokens
+                .unwrap_or(defaults.enable_access_tokens);
+            let enable_local_host_auth_bypass = access_control
+                .enable_local_host_auth_bypass
+                .unwrap_or(defaults.enable_local_host_auth_bypass);
+
+            if enable_local_host_auth_bypass && !enable_access_tokens {
+                tracing::warn!(
+                    "Local host auth bypass has no effect when access tokens are disabled"
+                );
+            }
+
+            AccessControlConfig {
+                enable_access_tokens,
+                enable_local_host_auth_bypass,
+            }
+        })
         .unwrap_or_default();
+    let ip_filter = file.ip_filter.map(|ip_filter| {
+        let mut parse_cidrs = |cidrs: BTreeSet<String>| {
+            cidrs
+                .into_iter()
+                .map(|c| {
+                    c.parse::<cidr::IpCidr>().unwrap_or_else(|_| {
+                        cmd.error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid IP address or CIDR '{}'", c),
+                        )
+                        .exit()
+                    })
+                })
+                .collect()
+        };
+
+        match (ip_filter.whitelist, ip_filter.blacklist) {
+            (Some(whitelist), None) => IpFilterConfig::Whitelist(parse_cidrs(whitelist)),
+            (None, Some(blacklist)) => IpFilterConfig::Blacklist(parse_cidrs(blacklist)),
+            _ => cmd
+                .error(
+                    ErrorKind::ValueValidation,
+                    "Invalid IP filter configuration. Must specify either 'whitelist' or 'blacklist', but not both",
+                )
+                .exit(),
+        }
+    });
+    let content_types = match file.content_types {
+        Some(content_types) => {
+            let mut parse_mime = |m: String| {
+                m.parse::<Mime>().unwrap_or_else(|_| {
+                    cmd.error(
+                        ErrorKind::ValueValidation,
+                        format!("Invalid content type '{}'", m),
+                    )
+                    .exit()
+                })
+            };
+
+            let mut rules = Vec::new();
+            for rule in content_types.rules.unwrap_or_default() {
+                if rule.key_pattern.is_empty() {
+                    cmd.error(
+                        ErrorKind::ValueValidation,
+                        "Invalid content type rule. 'key-pattern' must not be empty",
+                    )
+                    .exit()
+                }
+
+                let key_pattern = match glob::Pattern::new(&rule.key_pattern) {
+                    Ok(key_pattern) => key_pattern,
+                    Err(e) => cmd
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid key pattern '{}': {}", rule.key_pattern, e),
+                        )
+                        .exit(),
+                };
+
+                rules.push(ContentTypeRule {
+                    key_pattern,
+                    content_type: parse_mime(rule.content_type),
+                });
+            }
+
+            let filter = match (content_types.whitelist, content_types.blacklist) {
+                (Some(whitelist), None) => Some(ContentTypeFilter::Whitelist(
+                    whitelist.into_iter().map(&mut parse_mime).collect(),
+                )),
+                (None, Some(blacklist)) => Some(ContentTypeFilter::Blacklist(
+                    blacklist.into_iter().map(&mut parse_mime).collect(),
+                )),
+                (None, None) if !rules.is_empty() => None,
+                _ => cmd
+                    .error(
+                        ErrorKind::ValueValidation,
+                        "Invalid content types configuration. Must specify either 'whitelist' or 'blacklist', but not both",
+                    )
+                    .exit(),
+            };
+
+            ContentTypesConfig { filter, rules }
+        }
+        None => ContentTypesConfig::default(),
+    };
     let rate_limiting = file
         .rate_limiting
-        .map(|_| todo!("Validate rate limiting config"))
+        .map(|rate_limiting| {
+            let defaults = RateLimitingConfig::default();
+
+            let default_period = rate_limiting
+                .default_period
+                .map(|p| match humantime::parse_duration(&p) {
+                    Ok(period) if !period.is_zero() => period,
+                    _ => cmd
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid rate limiting period '{}'", p),
+                        )
+                        .exit(),
+                })
+                .unwrap_or(defaults.default_period);
+
+            let default_burst_size = match rate_limiting.default_burst_size {
+                Some(0) => cmd
+                    .error(
+                        ErrorKind::ValueValidation,
+                        "Rate limiting burst size must be at least 1",
+                    )
+                    .exit(),
+                Some(burst_size) => burst_size,
+                None => defaults.default_burst_size,
+            };
+
+            RateLimitingConfig {
+                enable_rate_limiting: rate_limiting
+                    .enable_rate_limiting
+                    .unwrap_or(defaults.enable_rate_limiting),
+                default_period,
+                default_burst_size,
+            }
+        })
+        .unwrap_or_default();
+    let lifecycle = file
+        .lifecycle
+        .map(|lifecycle| {
+            let defaults = LifecycleConfig::default();
+
+            let interval = lifecycle
+                .interval
+                .map(|i| match humantime::parse_duration(&i) {
+                    Ok(interval) if !interval.is_zero() => interval,
+                    _ => cmd
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid lifecycle interval '{}'", i),
+                        )
+                        .exit(),
+                })
+                .unwrap_or(defaults.interval);
+
+            let multipart_upload_ttl = lifecycle
+                .multipart_upload_ttl
+                .map(|ttl| match humantime::parse_duration(&ttl) {
+                    Ok(ttl) if !ttl.is_zero() => ttl,
+                    _ => cmd
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid multipart upload TTL '{}'", ttl),
+                        )
+                        .exit(),
+                })
+                .unwrap_or(defaults.multipart_upload_ttl);
+
+            LifecycleConfig {
+                interval,
+                soft_delete_retention_days: lifecycle
+                    .soft_delete_retention_days
+                    .unwrap_or(defaults.soft_delete_retention_days),
+                multipart_upload_ttl,
+            }
+        })
+        .unwrap_or_default();
+    let database = file
+        .database
+        .map(|database| {
+            let defaults = DatabaseConfig::default();
+
+            let max_connections = database.max_connections.unwrap_or(defaults.max_connections);
+            let min_connections = database.min_connections.unwrap_or(defaults.min_connections);
+            if max_connections == 0 || min_connections > max_connections {
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    "Database max-connections must be at least 1 and no less than min-connections",
+                )
+                .exit();
+            }
+
+            // A zero timeout keeps idle connections open forever
+            let idle_timeout = database
+                .idle_timeout
+                .map(|t| match humantime::parse_duration(&t) {
+                    Ok(timeout) => Some(timeout).filter(|timeout| !timeout.is_zero()),
+                    Err(_) => cmd
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid database idle timeout '{}'", t),
+                        )
+                        .exit(),
+                })
+                .unwrap_or(defaults.idle_timeout);
+
+            DatabaseConfig {
+                max_connections,
+                min_connections,
+                idle_timeout,
+            }
+        })
+        .unwrap_or_default();
+
+    let storage = file
+        .storage
+        .map(|storage| StorageConfig {
+            path_strategy: storage
+                .path_strategy
+                .map(|s| {
+                    s.parse::<PathStrategy>().unwrap_or_else(|_| {
+                        cmd.error(
+                            ErrorKind::ValueValidation,
+                            format!(
+                                "Invalid storage path strategy '{}'. Must be one of 'flat', 'sharded' or 'content-addressed'",
+                                s
+                            ),
+                        )
+                        .exit()
+                    })
+                })
+                .unwrap_or_default(),
+        })
+        .unwrap_or_default();
+
+    let cache = file
+        .cache
+        .map(|cache| {
+            let defaults = CacheConfig::default();
+
+            let coalesce_window = cache
+                .coalesce_window
+                .map(|w| {
+                    humantime::parse_duration(&w).unwrap_or_else(|_| {
+                        cmd.error(
+                            ErrorKind::ValueValidation,
+                            format!("Invalid read coalescing window '{}'", w),
+                        )
+                        .exit()
+                    })
+                })
+                .unwrap_or(defaults.coalesce_window);
+
+            CacheConfig {
+                coalesce_concurrent_reads: cache
+                    .coalesce_concurrent_reads
+                    .unwrap_or(defaults.coalesce_concurrent_reads),
+                coalesce_window,
+            }
+        })
         .unwrap_or_default();
 
     Config {
@@ -219,10 +687,16 @@ fn validate_file(file: ConfigFile) -> Config {
         ip_filter,
         content_types,
         rate_limiting,
+        lifecycle,
+        database,
+        storage,
+        cache,
+        presigned_secret: file.presigned_secret,
+        storage_backend: None,
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ConfigFile {
     data_directory: Option<String>,
@@ -234,16 +708,25 @@ pub struct ConfigFile {
     ip_filter: Option<PartialIpFilterConfig>,
     content_types: Option<PartialContentTypesConfig>,
     rate_limiting: Option<PartialRateLimitingConfig>,
+    lifecycle: Option<PartialLifecycleConfig>,
+    database: Option<PartialDatabaseConfig>,
+    storage: Option<PartialStorageConfig>,
+    cache: Option<PartialCacheConfig>,
+    presigned_secret: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartialHttpConfig {
-    host: Option<Ipv4Addr>,
+    host: Option<IpAddr>,
     port: Option<u16>,
+    max_body_bytes: Option<u64>,
+    virtual_host_domain: Option<String>,
+    idle_timeout_secs: Option<u64>,
+    tcp_keepalive_secs: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartialTlsConfig {
     tls_versions: Option<BTreeSet<String>>,
@@ -251,6 +734,9 @@ pub struct PartialTlsConfig {
     public_key: Option<String>,
     private_key_file: Option<PathBuf>,
     public_key_file: Option<PathBuf>,
+    hsts_max_age: Option<u64>,
+    tls_redirect: Option<bool>,
+    redirect_port: Option<u16>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -260,19 +746,22 @@ pub struct PartialCorsConfig {
     allow_methods: Option<BTreeSet<String>>,
     allow_headers: Option<BTreeSet<String>>,
     allow_credentials: Option<bool>,
+    expose_headers: Option<BTreeSet<String>>,
+    max_age: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartialCacheControlConfig {
-    default_policy: Option<CachePolicy>,
+    default_policy: Option<String>,
     default_max_age: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PartialAccessControlConfig {
     enable_access_tokens: Option<bool>,
+    #[serde(alias = "enable-localhost-auth-bypass")]
     enable_local_host_auth_bypass: Option<bool>,
 }
 
@@ -288,11 +777,49 @@ pub struct PartialIpFilterConfig {
 pub struct PartialContentTypesConfig {
     whitelist: Option<BTreeSet<String>>,
     blacklist: Option<BTreeSet<String>>,
+    rules: Option<Vec<PartialContentTypeRule>>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
+pub struct PartialContentTypeRule {
+    key_pattern: String,
+    content_type: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct PartialRateLimitingConfig {
+    enable_rate_limiting: Option<bool>,
     default_period: Option<String>,
     default_burst_size: Option<u32>,
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartialLifecycleConfig {
+    interval: Option<String>,
+    soft_delete_retention_days: Option<u32>,
+    multipart_upload_ttl: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartialDatabaseConfig {
+    max_connections: Option<u32>,
+    min_connections: Option<u32>,
+    idle_timeout: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartialStorageConfig {
+    path_strategy: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PartialCacheConfig {
+    coalesce_concurrent_reads: Option<bool>,
+    coalesce_window: Option<String>,
+}
@@ -0,0 +1,40 @@
+//! Prometheus metrics, recorded with the `metrics` crate and rendered by the
+//! `GET /metrics` endpoint
+
+use std::sync::Mutex;
+
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+
+pub const HTTP_REQUESTS_TOTAL: &str = "objection_http_requests_total";
+pub const REQUEST_DURATION_SECONDS: &str = "objection_request_duration_seconds";
+pub const BYTES_UPLOADED_TOTAL: &str = "objection_bytes_uploaded_total";
+pub const BYTES_DOWNLOADED_TOTAL: &str = "objection_bytes_downloaded_total";
+pub const OBJECTS_TOTAL: &str = "objection_objects_total";
+
+/// Histogram buckets for request durations, in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Handle to the global recorder, which can only be installed once per
+/// process but is shared by every server started in it
+static HANDLE: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+
+/// Installs the global Prometheus recorder if it isn't already installed,
+/// returning a handle for rendering it
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    let mut handle = HANDLE.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(handle) = &*handle {
+        return Ok(handle.clone());
+    }
+
+    let recorder = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(REQUEST_DURATION_SECONDS.to_owned()),
+            DURATION_BUCKETS,
+        )?
+        .install_recorder()?;
+
+    Ok(handle.insert(recorder).clone())
+}
@@ -0,0 +1,112 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, Method, header},
+    middleware::Next,
+    response::Response,
+};
+use percent_encoding::percent_decode_str;
+
+use super::request_id::REQUEST_ID;
+use crate::models::{
+    access_log::{AccessLog, NewAccessLog},
+    bucket::{Bucket, BucketError},
+};
+
+/// Records requests to buckets which have access logging enabled.
+///
+/// The log entry is written in the background once the response has been
+/// produced, so logging never delays or fails a request.
+pub async fn log_access(
+    State(db): State<sqlx::SqlitePool>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some((name, object_key)) = addressed_object(req.uri().path()) else {
+        return next.run(req).await;
+    };
+
+    let method = req.method().clone();
+    let request_bytes = content_length(req.headers());
+    let request_id = REQUEST_ID.try_with(|id| id.0.clone()).ok();
+
+    let response = next.run(req).await;
+
+    let bytes_transferred = match method {
+        Method::PUT | Method::POST => request_bytes,
+        Method::HEAD => 0,
+        _ => content_length(response.headers()),
+    };
+
+    let status_code = response.status().as_u16();
+
+    tokio::spawn(async move {
+        let bucket = match Bucket::find_by_name(&db, &name).await {
+            Ok(bucket) => bucket,
+            Err(BucketError::NotFound(_)) => return,
+            Err(e) => {
+                tracing::error!("Failed to look up bucket for access log: {}", e);
+                return;
+            }
+        };
+
+        if !bucket.settings().access_logging {
+            return;
+        }
+
+        let log = NewAccessLog {
+            bucket_uuid: bucket.uuid(),
+            object_key,
+            method: method.to_string(),
+            status_code,
+            requester_ip: addr.ip().to_canonical().to_string(),
+            bytes_transferred,
+            request_id,
+        };
+        if let Err(e) = AccessLog::insert(&db, log).await {
+            tracing::error!("Failed to write access log: {}", e);
+        }
+    });
+
+    response
+}
+
+pub(super) fn content_length(headers: &HeaderMap) -> u64 {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+/// The bucket name and object key addressed by a request path, for both the
+/// JSON API and path-style S3 requests. Other API routes aren't logged.
+pub(super) fn addressed_object(path: &str) -> Option<(String, Option<String>)> {
+    let path = path.strip_prefix('/')?;
+
+    let (name, key) = match path.strip_prefix("api/buckets/") {
+        Some(path) => {
+            let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+
+            match rest {
+                "" | "objects" => (name, None),
+                rest => (name, Some(rest.strip_prefix("objects/")?)),
+            }
+        }
+        None if path == "api" || path.starts_with("api/") => return None,
+        None => match path.split_once('/') {
+            Some((name, key)) => (name, Some(key)),
+            None => (path, None),
+        },
+    };
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let decode = |value: &str| percent_decode_str(value).decode_utf8_lossy().into_owned();
+
+    Some((decode(name), key.map(decode)))
+}
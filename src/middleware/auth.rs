@@ -0,0 +1,189 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, OriginalUri, Request, State},
+    http::{Method, StatusCode, Uri, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::{access_log::addressed_object, presign::Presigned, sigv4};
+use crate::{
+    AppState,
+    models::{
+        access_token::{AccessToken, AccessTokenPermission},
+        bucket::{Bucket, BucketError},
+        object::Object,
+    },
+    routes::{api::ApiError, xml::S3Error},
+};
+
+/// Rejects requests which aren't authenticated by a valid access token, unless
+/// access tokens are disabled in the config.
+///
+/// Requests can be authenticated with either an `Authorization: Bearer` token
+/// or an AWS Signature Version 4 signature, and the token must have the
+/// permission the request needs. The [`AccessToken`] used is added to the
+/// request extensions. Objects which are publicly readable can be read
+/// without one.
+///
+/// Permissions are checked against the path the request is routed by, which
+/// for virtual-hosted-style requests includes the bucket. Only signatures
+/// are checked against the path as it was sent.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    OriginalUri(original_uri): OriginalUri,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let access_control = &state.config.access_control;
+    let uri = req.uri().clone();
+
+    if !access_control.enable_access_tokens {
+        return next.run(req).await;
+    }
+
+    if req.extensions().get::<Presigned>().is_some() {
+        return next.run(req).await;
+    }
+
+    if access_control.enable_local_host_auth_bypass && addr.ip().to_canonical().is_loopback() {
+        return next.run(req).await;
+    }
+
+    if matches!(*req.method(), Method::GET | Method::HEAD) && is_public_read(&state.db, &uri).await
+    {
+        return next.run(req).await;
+    }
+
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    let signed =
+        authorization.is_some_and(|authorization| authorization.starts_with(sigv4::ALGORITHM));
+    let token = match authorization {
+        Some(_) if signed => sigv4::verify(&state.db, req.method(), &original_uri, req.headers())
+            .await
+            .map_err(IntoResponse::into_response),
+        Some(authorization) if authorization.starts_with("Bearer ") => {
+            verify_bearer(&state.db, &authorization["Bearer ".len()..])
+                .await
+                .map_err(IntoResponse::into_response)
+        }
+        _ if uri.path().starts_with("/api") => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Requests must be authenticated with an access token",
+        )
+        .into_response()),
+        _ => Err(S3Error::new(
+            StatusCode::FORBIDDEN,
+            "AccessDenied",
+            "Anonymous access is disabled, requests must be signed",
+        )
+        .into_response()),
+    };
+
+    let token = match token {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+
+    let permission = required_permission(req.method(), &uri);
+    if !token.permits(permission) {
+        let message = format!(
+            "The access token does not have the `{}` permission",
+            permission
+        );
+
+        return match uri.path().starts_with("/api") {
+            true => ApiError::new(StatusCode::FORBIDDEN, "FORBIDDEN", message).into_response(),
+            false => S3Error::new(StatusCode::FORBIDDEN, "AccessDenied", message).into_response(),
+        };
+    }
+
+    req.extensions_mut().insert(token);
+
+    // The signature only covers the body through its hash, so the body has
+    // to be checked against it as it is read
+    if signed {
+        let (parts, body) = req.into_parts();
+        let body = sigv4::verify_payload(&parts.headers, body);
+        req = Request::from_parts(parts, body);
+    }
+
+    next.run(req).await
+}
+
+/// The permission an access token needs for a request. Reads only need
+/// `read`, changes to objects need `write`, and anything else, like managing
+/// buckets, access tokens or the server itself, needs `admin`.
+fn required_permission(method: &Method, uri: &Uri) -> AccessTokenPermission {
+    if uri.path().starts_with("/api/access-tokens") || uri.path().starts_with("/api/admin") {
+        return AccessTokenPermission::Admin;
+    }
+
+    if matches!(*method, Method::GET | Method::HEAD) {
+        return AccessTokenPermission::Read;
+    }
+
+    // Batch deletes are sent to the bucket rather than an object
+    let is_batch_delete = match *method {
+        Method::POST => uri.query().is_some_and(|query| {
+            query
+                .split('&')
+                .any(|param| param.split('=').next() == Some("delete"))
+        }),
+        Method::DELETE => uri.path().trim_end_matches('/').ends_with("/objects"),
+        _ => false,
+    };
+
+    match addressed_object(uri.path()) {
+        Some((_, Some(_))) => AccessTokenPermission::Write,
+        Some((_, None)) if is_batch_delete => AccessTokenPermission::Write,
+        _ => AccessTokenPermission::Admin,
+    }
+}
+
+/// Whether a request addresses an object anyone may read, either because its
+/// bucket is public or because it was uploaded with the `public-read` ACL
+async fn is_public_read(db: &sqlx::SqlitePool, uri: &Uri) -> bool {
+    let Some((name, Some(key))) = addressed_object(uri.path()) else {
+        return false;
+    };
+
+    let bucket = match Bucket::find_by_name(db, &name).await {
+        Ok(bucket) => bucket,
+        Err(BucketError::NotFound(_)) => return false,
+        Err(e) => {
+            tracing::error!("Failed to look up bucket for authentication: {}", e);
+            return false;
+        }
+    };
+
+    if bucket.settings().public_read {
+        return true;
+    }
+
+    match Object::find_by_path(db, &bucket, &key).await {
+        Ok(object) => object.is_some_and(|object| object.public_read()),
+        Err(e) => {
+            tracing::error!("Failed to look up object for authentication: {}", e);
+            false
+        }
+    }
+}
+
+async fn verify_bearer(db: &sqlx::SqlitePool, token: &str) -> Result<AccessToken, ApiError> {
+    match AccessToken::find_by_token(db, token.trim()).await? {
+        Some(token) if !token.is_expired() => Ok(token),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "The access token is invalid or has expired",
+        )),
+    }
+}
@@ -0,0 +1,32 @@
+//! Response compression for clients which send `Accept-Encoding`
+
+use tower_http::compression::{
+    CompressionLayer, DefaultPredicate, Predicate, predicate::NotForContentType,
+};
+
+/// Compresses responses with gzip or brotli, skipping content types which
+/// are already compressed and wouldn't get any smaller.
+///
+/// Responses which already have a `Content-Encoding`, such as objects that
+/// were uploaded gzipped, or a `Content-Range` are always passed through
+/// as-is.
+pub fn compress_responses() -> CompressionLayer<impl Predicate> {
+    // `DefaultPredicate` already excludes tiny bodies, images, gRPC and event
+    // streams
+    let predicate = DefaultPredicate::new()
+        .and(NotForContentType::const_new("video/"))
+        .and(NotForContentType::const_new("audio/"))
+        .and(NotForContentType::const_new("font/woff"))
+        .and(NotForContentType::const_new("application/gzip"))
+        .and(NotForContentType::const_new("application/x-gzip"))
+        .and(NotForContentType::const_new("application/zip"))
+        .and(NotForContentType::const_new("application/zstd"))
+        .and(NotForContentType::const_new("application/x-7z-compressed"))
+        .and(NotForContentType::const_new("application/x-bzip2"))
+        .and(NotForContentType::const_new("application/x-xz"));
+
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(predicate)
+}
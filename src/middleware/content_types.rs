@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, RawPathParams, Request, State},
+    http::{Method, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use mime::Mime;
+
+use crate::{
+    config::Config,
+    models::object::normalize_key,
+    routes::{api::ApiError, xml::S3Error},
+};
+
+/// Query parameters selecting something other than an object's contents.
+/// `uploads` isn't one of them, since initiating an upload gives the content
+/// type of the object it will create.
+const SUB_RESOURCES: [&str; 3] = ["tagging", "partNumber", "uploadId"];
+
+/// Rejects `PUT` and `POST` requests whose `Content-Type` isn't allowed for
+/// the object's key by the content types configuration. Requests without a
+/// content type are treated as `application/octet-stream`.
+///
+/// Requests for an object's sub-resources are exempt, since their bodies are
+/// tags or multipart upload parts rather than object contents. The content type
+/// of a multipart upload is filtered when the upload is initiated instead.
+/// Copies are checked by their handlers, since the content type may come from
+/// the source object.
+///
+/// Content types which can't be parsed are left for the handler to reject.
+pub async fn filter_content_types(
+    State(config): State<Arc<Config>>,
+    OriginalUri(original_uri): OriginalUri,
+    params: RawPathParams,
+    req: Request,
+    next: Next,
+) -> Response {
+    if config.content_types.is_empty() {
+        return next.run(req).await;
+    }
+
+    if req.method() != Method::PUT && req.method() != Method::POST {
+        return next.run(req).await;
+    }
+
+    let sub_resource = req.uri().query().is_some_and(|query| {
+        query.split('&').any(|param| {
+            let name = param.split_once('=').map_or(param, |(name, _)| name);
+            SUB_RESOURCES.contains(&name)
+        })
+    });
+    if sub_resource || req.headers().contains_key("x-amz-copy-source") {
+        return next.run(req).await;
+    }
+
+    let content_type = match req.headers().get(header::CONTENT_TYPE) {
+        Some(value) => match value.to_str().ok().and_then(|v| v.parse::<Mime>().ok()) {
+            Some(content_type) => content_type,
+            None => return next.run(req).await,
+        },
+        None => mime::APPLICATION_OCTET_STREAM,
+    };
+
+    // Keys which aren't valid are left for the handler to reject
+    let Some(key) = params
+        .iter()
+        .find(|(name, _)| *name == "key")
+        .and_then(|(_, key)| normalize_key(key).ok())
+    else {
+        return next.run(req).await;
+    };
+
+    // S3 clients expect XML errors. The API router is nested, so only the
+    // original URI still has its `/api` prefix.
+    match config.content_types.check(&key, &content_type) {
+        Ok(()) => next.run(req).await,
+        Err(e) if original_uri.path().starts_with("/api") => ApiError::from(e).into_response(),
+        Err(e) => S3Error::from(e).into_response(),
+    }
+}
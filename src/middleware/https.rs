@@ -0,0 +1,60 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, Uri, header, uri::Authority},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Redirects a plain HTTP request to the same URL over HTTPS, served on
+/// `https_port`. Used as the only handler of the plain HTTP listener started
+/// when `tls_redirect` is enabled.
+pub async fn redirect_to_https(State(https_port): State<u16>, req: Request) -> Response {
+    let Some(authority) = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing or invalid `Host` header").into_response();
+    };
+
+    let authority = match https_port {
+        443 => authority.host().to_owned(),
+        port => format!("{}:{}", authority.host(), port),
+    };
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+
+    let location = Uri::builder()
+        .scheme("https")
+        .authority(authority)
+        .path_and_query(path_and_query)
+        .build()
+        .ok()
+        .and_then(|uri| HeaderValue::try_from(uri.to_string()).ok());
+
+    match location {
+        Some(location) => (
+            StatusCode::MOVED_PERMANENTLY,
+            [(header::LOCATION, location)],
+        )
+            .into_response(),
+        None => (StatusCode::BAD_REQUEST, "Invalid request URL").into_response(),
+    }
+}
+
+/// Sets `Strict-Transport-Security` on every response, so browsers only ever
+/// use HTTPS for this host after their first visit
+pub async fn set_hsts(State(max_age): State<u64>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+
+    response.headers_mut().insert(
+        header::STRICT_TRANSPORT_SECURITY,
+        HeaderValue::from_str(&format!("max-age={}; includeSubDomains", max_age))
+            .expect("HSTS header is always valid"),
+    );
+
+    response
+}
@@ -0,0 +1,32 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{config::Config, routes::api::ApiError};
+
+/// Rejects requests from client addresses which don't pass the configured IP
+/// filter
+pub async fn filter_ips(
+    State(config): State<Arc<Config>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let Some(ip_filter) = &config.ip_filter
+        && !ip_filter.allows(addr.ip())
+    {
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            "FORBIDDEN",
+            format!("Requests from `{}` are not allowed", addr.ip()),
+        )
+        .into_response();
+    }
+
+    next.run(req).await
+}
@@ -0,0 +1,26 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+
+use super::request_id::RequestId;
+
+/// Emits an error event for every response with a 5xx status so that server
+/// failures are never silently returned to clients
+pub async fn log_server_errors(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+    let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+
+    let response = next.run(req).await;
+
+    let status = response.status();
+    if status.is_server_error() {
+        tracing::error!(
+            status = %status,
+            method = %method,
+            path = %path,
+            request_id = ?request_id,
+            "Internal server error"
+        );
+    }
+
+    response
+}
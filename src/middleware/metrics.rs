@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use axum::{
+    RequestExt,
+    extract::{MatchedPath, RawPathParams, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+
+use super::access_log::content_length;
+use crate::metrics::{
+    BYTES_DOWNLOADED_TOTAL, BYTES_UPLOADED_TOTAL, HTTP_REQUESTS_TOTAL, REQUEST_DURATION_SECONDS,
+};
+
+/// Records request counts and durations, along with the bytes transferred to
+/// and from each bucket.
+///
+/// Requests are labeled with their route rather than their path, so that
+/// object keys don't each create a new time series.
+pub async fn track_requests(mut req: Request, next: Next) -> Response {
+    let start = Instant::now();
+
+    let method = req.method().clone();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    // Only requests which address an object transfer its contents
+    let object_bucket = match req.extract_parts::<RawPathParams>().await {
+        Ok(params) => {
+            let bucket = params.iter().find(|(name, _)| *name == "name");
+            let has_key = params.iter().any(|(name, _)| name == "key");
+
+            bucket
+                .filter(|_| has_key)
+                .map(|(_, bucket)| bucket.to_owned())
+        }
+        Err(_) => None,
+    };
+    let request_bytes = content_length(req.headers());
+
+    let response = next.run(req).await;
+
+    let status = response.status();
+    let labels = [
+        ("method", method.to_string()),
+        ("status", status.as_u16().to_string()),
+        ("path", path.clone()),
+    ];
+    metrics::counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+    metrics::histogram!(
+        REQUEST_DURATION_SECONDS,
+        "method" => method.to_string(),
+        "path" => path,
+    )
+    .record(start.elapsed().as_secs_f64());
+
+    if let Some(bucket) = object_bucket
+        && status.is_success()
+    {
+        match method {
+            Method::PUT | Method::POST => {
+                metrics::counter!(BYTES_UPLOADED_TOTAL, "bucket" => bucket).increment(request_bytes)
+            }
+            Method::GET => metrics::counter!(BYTES_DOWNLOADED_TOTAL, "bucket" => bucket)
+                .increment(content_length(response.headers())),
+            _ => {}
+        }
+    }
+
+    response
+}
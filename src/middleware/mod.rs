@@ -0,0 +1,13 @@
+pub mod access_log;
+pub mod auth;
+pub mod compression;
+pub mod content_types;
+pub mod https;
+pub mod ip_filter;
+pub mod log_errors;
+pub mod metrics;
+pub mod presign;
+pub mod rate_limit;
+pub mod request_id;
+pub mod sigv4;
+pub mod virtual_host;
@@ -0,0 +1,267 @@
+//! Presigned URLs, which grant access to a single object for a limited time
+//! without credentials
+//!
+//! URLs are signed with an HMAC-SHA256 of the method, path and canonical query
+//! string (which includes the date and expiry), keyed on the server's
+//! presigned URL secret. They can only be used for plain object reads and
+//! writes, never for copies or sub-resources like `?tagging`.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use sha2::Sha256;
+
+use super::virtual_host::VirtualHostBucket;
+use crate::{config::Config, routes::xml::S3Error};
+
+/// Longest a presigned URL may be valid for, matching S3's limit of 7 days
+pub const MAX_EXPIRES_SECS: u64 = 604_800;
+
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Characters which are left as-is in presigned paths, besides `/`
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// Characters which are left as-is in canonical query parameters
+const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Query parameters added by [`presign`], which are the only ones a presigned
+/// request may have
+const PRESIGNED_PARAMS: [&str; 3] = ["X-Amz-Date", "X-Amz-Expires", "X-Amz-Signature"];
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Added to the extensions of requests with a valid presigned URL, which
+/// don't need to be authenticated with an access token
+#[derive(Debug, Clone, Copy)]
+pub struct Presigned;
+
+/// Builds the path and query of a presigned URL for the object at `key`,
+/// valid for `expires_in_secs` from `now`
+pub fn presign(
+    secret: &str,
+    method: &Method,
+    bucket: &str,
+    key: &str,
+    now: DateTime<Utc>,
+    expires_in_secs: u64,
+) -> String {
+    let path = utf8_percent_encode(&format!("/{}/{}", bucket, key), PATH_ENCODE_SET).to_string();
+    let query = format!(
+        "X-Amz-Date={}&X-Amz-Expires={}",
+        now.format(DATE_FORMAT),
+        expires_in_secs
+    );
+    let signature = hex::encode(
+        mac(secret, method, &path, &canonical_query(&query))
+            .finalize()
+            .into_bytes(),
+    );
+
+    format!("{}?{}&X-Amz-Signature={}", path, query, signature)
+}
+
+/// MAC over the parts of a request covered by a presigned URL signature
+fn mac(secret: &str, method: &Method, path: &str, canonical_query: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}", method, path, canonical_query).as_bytes());
+
+    mac
+}
+
+/// Every query parameter besides the signature, decoded then consistently
+/// re-encoded and sorted, so that any parameter added to or changed in a
+/// presigned URL invalidates its signature
+fn canonical_query(query: &str) -> String {
+    let mut params = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| param.split_once('=').unwrap_or((param, "")))
+        .filter(|(key, _)| *key != "X-Amz-Signature")
+        .map(|(key, value)| {
+            let encode = |part: &str| {
+                let part = percent_decode_str(part).decode_utf8_lossy();
+                utf8_percent_encode(&part, QUERY_ENCODE_SET).to_string()
+            };
+
+            (encode(key), encode(value))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The presigned query parameters of a request
+struct PresignedQuery {
+    date: String,
+    expires_in_secs: String,
+    signature: String,
+    canonical_query: String,
+    /// The first parameter which presigned URLs can't be used with
+    unsupported_param: Option<String>,
+}
+
+impl PresignedQuery {
+    /// Extracts the presigned parameters from a query string, returning
+    /// `None` if the request isn't presigned
+    fn parse(query: &str) -> Option<Self> {
+        let (mut date, mut expires_in_secs, mut signature) = (None, None, None);
+        let mut unsupported_param = None;
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode_str(value).decode_utf8_lossy().into_owned();
+
+            match key {
+                "X-Amz-Date" => date = Some(value),
+                "X-Amz-Expires" => expires_in_secs = Some(value),
+                "X-Amz-Signature" => signature = Some(value),
+                _ => {
+                    unsupported_param.get_or_insert_with(|| {
+                        percent_decode_str(key).decode_utf8_lossy().into_owned()
+                    });
+                }
+            }
+        }
+
+        Some(Self {
+            date: date.unwrap_or_default(),
+            expires_in_secs: expires_in_secs.unwrap_or_default(),
+            signature: signature?,
+            canonical_query: canonical_query(query),
+            unsupported_param,
+        })
+    }
+
+    fn verify(
+        &self,
+        secret: &str,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Result<(), S3Error> {
+        // Sub-resources like `?tagging` or `?uploadId` and copies would let a
+        // URL presigned for one object's contents do something else entirely
+        if let Some(param) = &self.unsupported_param {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "AccessDenied",
+                format!(
+                    "Presigned URLs may only include the {} query parameters, not `{}`",
+                    PRESIGNED_PARAMS
+                        .map(|param| format!("`{}`", param))
+                        .join(", "),
+                    param
+                ),
+            ));
+        }
+        if headers.contains_key("x-amz-copy-source") {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "AccessDenied",
+                "Presigned URLs can't be used to copy objects",
+            ));
+        }
+
+        let malformed = || {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationQueryParametersError",
+                "Presigned URLs must include `X-Amz-Date`, `X-Amz-Expires` and `X-Amz-Signature`",
+            )
+        };
+
+        let date = NaiveDateTime::parse_from_str(&self.date, DATE_FORMAT)
+            .map_err(|_| malformed())?
+            .and_utc();
+        let expires_in_secs = self
+            .expires_in_secs
+            .parse::<u64>()
+            .ok()
+            .filter(|expires| *expires <= MAX_EXPIRES_SECS)
+            .ok_or_else(malformed)?;
+        let signature = hex::decode(&self.signature).map_err(|_| malformed())?;
+
+        // A URL presigned for `GET` may also be used to fetch just the headers
+        let method = match *method {
+            Method::HEAD => &Method::GET,
+            ref method => method,
+        };
+
+        mac(secret, method, path, &self.canonical_query)
+            .verify_slice(&signature)
+            .map_err(|_| {
+                S3Error::new(
+                    StatusCode::FORBIDDEN,
+                    "SignatureDoesNotMatch",
+                    "The presigned URL signature does not match the request",
+                )
+            })?;
+
+        if Utc::now() > date + TimeDelta::seconds(expires_in_secs as i64) {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "RequestExpired",
+                "The presigned URL has expired",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates requests made with a presigned URL, marking them as
+/// [`Presigned`] so they skip authentication. Requests without a presigned
+/// signature are passed through untouched.
+pub async fn verify_presigned_url(
+    State(config): State<Arc<Config>>,
+    OriginalUri(uri): OriginalUri,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(query) = uri.query().and_then(PresignedQuery::parse) else {
+        return next.run(req).await;
+    };
+
+    let Some(secret) = config.presigned_secret.as_deref() else {
+        tracing::error!("Received a presigned request, but no presigned URL secret is configured");
+
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    // URLs are presigned path-style, so virtual-hosted-style requests are
+    // checked with their bucket in the path
+    let path = match req.extensions().get::<VirtualHostBucket>() {
+        Some(VirtualHostBucket(Some(bucket))) => format!("/{}{}", bucket, uri.path()),
+        _ => uri.path().to_owned(),
+    };
+
+    if let Err(e) = query.verify(secret, req.method(), &path, req.headers()) {
+        return e.into_response();
+    }
+
+    req.extensions_mut().insert(Presigned);
+    next.run(req).await
+}
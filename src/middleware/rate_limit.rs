@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::access_log::addressed_object;
+use crate::{
+    config::RateLimitingConfig,
+    models::bucket::{Bucket, BucketError},
+    routes::api::ApiError,
+};
+
+/// Past this many tracked clients, clients whose buckets have completely
+/// refilled are forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// In-memory token bucket rate limiter, keyed by client IP address.
+///
+/// Each client may make up to `burst_size` requests at once, and one more
+/// request becomes available every `period`. Requests to buckets which
+/// override these limits are counted separately for each of those buckets.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: Limit,
+    buckets: Mutex<HashMap<(IpAddr, Option<Uuid>), TokenBucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    period: Duration,
+    burst_size: u32,
+}
+
+impl Limit {
+    /// Number of tokens replenished between `since` and `now`
+    fn refilled(&self, since: Instant, now: Instant) -> f64 {
+        now.duration_since(since).as_secs_f64() / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// Tokens available, which may be fractional while refilling
+    tokens: f64,
+    last_refill: Instant,
+    limit: Limit,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitingConfig) -> Self {
+        Self {
+            limit: Limit {
+                period: config.default_period,
+                burst_size: config.default_burst_size,
+            },
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for a request from `addr`, or returns how long the client
+    /// has to wait until one is available. Requests to a bucket which
+    /// overrides the rate limit are limited by its settings instead.
+    async fn check(&self, addr: IpAddr, bucket: Option<&Bucket>) -> Result<(), Duration> {
+        let (bucket_uuid, limit) = match bucket {
+            Some(bucket) if bucket.settings().overrides_rate_limit() => {
+                let settings = bucket.settings();
+
+                (
+                    Some(bucket.uuid()),
+                    Limit {
+                        period: settings.rate_limit_period.unwrap_or(self.limit.period),
+                        burst_size: settings
+                            .rate_limit_burst_size
+                            .unwrap_or(self.limit.burst_size),
+                    },
+                )
+            }
+            _ => (None, self.limit),
+        };
+
+        let now = Instant::now();
+        let capacity = f64::from(limit.burst_size);
+
+        let mut buckets = self.buckets.lock().await;
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + bucket.limit.refilled(bucket.last_refill, now)
+                    < f64::from(bucket.limit.burst_size)
+            });
+        }
+
+        let bucket = buckets.entry((addr, bucket_uuid)).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+            limit,
+        });
+
+        // The bucket's settings may have changed since it was last used
+        bucket.limit = limit;
+        bucket.tokens = (bucket.tokens + limit.refilled(bucket.last_refill, now)).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(limit.period.mul_f64(1.0 - bucket.tokens))
+        }
+    }
+}
+
+/// Rejects requests from clients which have exceeded their rate limit
+pub async fn rate_limit(
+    State((limiter, db)): State<(Arc<RateLimiter>, sqlx::SqlitePool)>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let bucket = match addressed_object(req.uri().path()) {
+        Some((name, _)) => match Bucket::find_by_name(&db, &name).await {
+            Ok(bucket) => Some(bucket),
+            Err(BucketError::NotFound(_)) => None,
+            Err(e) => {
+                tracing::error!("Failed to look up bucket for rate limiting: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    match limiter
+        .check(addr.ip().to_canonical(), bucket.as_ref())
+        .await
+    {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            // Round up so clients never retry before a token is available
+            let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            (
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "TOO_MANY_REQUESTS",
+                    format!("Rate limit exceeded, retry in {} seconds", retry_after),
+                ),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest `X-Request-Id` accepted from a client, longer IDs are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Identifies a request in logs and responses, added to the request
+/// extensions by [`assign_request_id`]
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    /// ID of the request being handled by the current task
+    pub static REQUEST_ID: RequestId;
+}
+
+/// Assigns every request an ID, taken from the `X-Request-Id` request header
+/// if the client sent one and generated otherwise. The ID is echoed in the
+/// `X-Request-Id` response header.
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let header_value = HeaderValue::try_from(&request_id);
+    if let Ok(value) = &header_value {
+        req.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+    }
+
+    let request_id = RequestId(request_id);
+    req.extensions_mut().insert(request_id.clone());
+
+    let mut response = REQUEST_ID.scope(request_id, next.run(req)).await;
+
+    if let Ok(value) = header_value {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
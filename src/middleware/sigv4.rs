@@ -0,0 +1,329 @@
+//! Request authentication with AWS Signature Version 4
+//!
+//! See <https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html>
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use sha2::{Digest, Sha256};
+
+use crate::{models::access_token::AccessToken, routes::xml::S3Error};
+
+pub const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// How far the request date may be from the server's clock
+const MAX_CLOCK_SKEW: TimeDelta = TimeDelta::minutes(15);
+
+/// Sent instead of the payload hash when the body isn't part of the signature
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Characters which are left as-is by AWS URI encoding
+const URI_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verifies a request signed with an `Authorization: AWS4-HMAC-SHA256 ...`
+/// header, returning the access token it was signed with. `uri` must be the
+/// URI exactly as sent by the client.
+///
+/// The payload hash is taken from `x-amz-content-sha256` as sent by the
+/// client. It is only checked against the body as the body is read, by
+/// wrapping it with [`verify_payload`].
+pub async fn verify(
+    db: &sqlx::SqlitePool,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Result<AccessToken, S3Error> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Authorization::parse)
+        .ok_or_else(|| {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "AuthorizationHeaderMalformed",
+                "The authorization header is not a valid AWS Signature Version 4 header",
+            )
+        })?;
+
+    let token = match AccessToken::find_by_access_key_id(db, authorization.access_key_id).await {
+        Ok(Some(token)) if !token.is_expired() => token,
+        Ok(_) => {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "InvalidAccessKeyId",
+                "The access key ID does not exist or has expired",
+            ));
+        }
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+
+            return Err(S3Error::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalError",
+                "An internal database error occurred",
+            ));
+        }
+    };
+
+    let request = SignedRequest {
+        method: method.as_str(),
+        path: uri.path(),
+        query: uri.query().unwrap_or_default(),
+        headers,
+        authority: uri.authority().map(|a| a.as_str()),
+    };
+    request.verify(&authorization, token.secret_access_key())?;
+
+    Ok(token)
+}
+
+/// The body of a signed request doesn't match the `x-amz-content-sha256`
+/// header it was signed with
+#[derive(Debug, thiserror::Error)]
+#[error("The body does not match the `x-amz-content-sha256` header")]
+pub struct ContentSha256Mismatch;
+
+/// Wraps the body of a signed request so that reading it fails with
+/// [`ContentSha256Mismatch`] at the end if it doesn't match the signed payload
+/// hash. Bodies whose hash isn't signed are returned as they are.
+pub fn verify_payload(headers: &HeaderMap, body: Body) -> Body {
+    let Some(expected) = headers
+        .get("x-amz-content-sha256")
+        .and_then(|value| hex::decode(value.as_bytes()).ok())
+        .and_then(|digest| <[u8; 32]>::try_from(digest).ok())
+    else {
+        return body;
+    };
+
+    // The hasher is taken once the body ends, ending the stream after the
+    // mismatch error
+    let stream = futures::stream::unfold(
+        (body.into_data_stream(), Some(Sha256::new())),
+        move |(mut stream, mut sha256)| async move {
+            let hasher = sha256.as_mut()?;
+
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    hasher.update(&chunk);
+                    Some((Ok(chunk), (stream, sha256)))
+                }
+                Some(Err(e)) => Some((Err(axum::BoxError::from(e)), (stream, None))),
+                None => match sha256.take()?.finalize().as_slice() == expected {
+                    true => None,
+                    false => Some((Err(ContentSha256Mismatch.into()), (stream, None))),
+                },
+            }
+        },
+    );
+
+    Body::from_stream(stream)
+}
+
+/// The parts of an `Authorization: AWS4-HMAC-SHA256 ...` header
+#[derive(Debug)]
+struct Authorization<'a> {
+    access_key_id: &'a str,
+    /// Credential scope in the form `{date}/{region}/{service}/aws4_request`
+    scope: &'a str,
+    date: &'a str,
+    service: &'a str,
+    signed_headers: Vec<&'a str>,
+    signature: &'a str,
+}
+
+impl<'a> Authorization<'a> {
+    fn parse(value: &'a str) -> Option<Self> {
+        let params = value.strip_prefix(ALGORITHM)?.trim_start();
+
+        let (mut credential, mut signed_headers, mut signature) = (None, None, None);
+        for param in params.split(',') {
+            let (key, value) = param.trim().split_once('=')?;
+
+            match key {
+                "Credential" => credential = Some(value),
+                "SignedHeaders" => signed_headers = Some(value),
+                "Signature" => signature = Some(value),
+                _ => {}
+            }
+        }
+
+        let (access_key_id, scope) = credential?.split_once('/')?;
+
+        let mut parts = scope.split('/');
+        let (date, _region, service, terminator) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if terminator != "aws4_request" || parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            access_key_id,
+            scope,
+            date,
+            service,
+            signed_headers: signed_headers?.split(';').collect(),
+            signature: signature?,
+        })
+    }
+}
+
+/// The parts of a request which are covered by its signature
+struct SignedRequest<'a> {
+    method: &'a str,
+    /// Path exactly as sent by the client, before any normalization
+    path: &'a str,
+    query: &'a str,
+    headers: &'a HeaderMap,
+    /// Used in place of the `Host` header over HTTP/2
+    authority: Option<&'a str>,
+}
+
+impl SignedRequest<'_> {
+    fn verify(&self, authorization: &Authorization, secret: &str) -> Result<(), S3Error> {
+        let invalid = |message: &str| {
+            S3Error::new(
+                StatusCode::FORBIDDEN,
+                "InvalidSignature",
+                message.to_owned(),
+            )
+        };
+
+        if authorization.service != "s3" {
+            return Err(invalid("The credential scope must be for the `s3` service"));
+        }
+
+        let timestamp = self
+            .header("x-amz-date")
+            .ok_or_else(|| invalid("The `x-amz-date` header is required"))?;
+        let date = NaiveDateTime::parse_from_str(&timestamp, "%Y%m%dT%H%M%SZ")
+            .map_err(|_| invalid("The `x-amz-date` header is not a valid timestamp"))?
+            .and_utc();
+
+        if !timestamp.starts_with(authorization.date) {
+            return Err(invalid(
+                "The credential scope date does not match `x-amz-date`",
+            ));
+        }
+
+        if (Utc::now() - date).abs() > MAX_CLOCK_SKEW {
+            return Err(S3Error::new(
+                StatusCode::FORBIDDEN,
+                "RequestTimeTooSkewed",
+                "The difference between the request time and the server's time is too large",
+            ));
+        }
+
+        let canonical_request = self.canonical_request(&authorization.signed_headers);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            timestamp,
+            authorization.scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signature = hex::decode(authorization.signature)
+            .map_err(|_| invalid("The signature is not valid hex"))?;
+
+        let mut mac = signing_key(secret, authorization.scope);
+        mac.update(string_to_sign.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| {
+            invalid("The request signature does not match the signature calculated by the server")
+        })
+    }
+
+    fn canonical_request(&self, signed_headers: &[&str]) -> String {
+        let path = if self.path.is_empty() { "/" } else { self.path };
+
+        let headers = signed_headers
+            .iter()
+            .map(|name| format!("{}:{}\n", name, self.header(name).unwrap_or_default()))
+            .collect::<String>();
+
+        let payload_hash = self
+            .header("x-amz-content-sha256")
+            .unwrap_or_else(|| UNSIGNED_PAYLOAD.to_owned());
+
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            self.method,
+            path,
+            canonical_query(self.query),
+            headers,
+            signed_headers.join(";"),
+            payload_hash
+        )
+    }
+
+    /// Value of a header in canonical form, with repeated headers joined by
+    /// commas and runs of whitespace collapsed
+    fn header(&self, name: &str) -> Option<String> {
+        let values = self
+            .headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+
+        match values.is_empty() {
+            true if name == "host" => self.authority.map(ToOwned::to_owned),
+            true => None,
+            false => Some(values.join(",")),
+        }
+    }
+}
+
+/// Sorts query parameters by name and value, and re-encodes them with AWS URI
+/// encoding
+fn canonical_query(query: &str) -> String {
+    let mut params = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+
+            (uri_encode(key), uri_encode(value))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+
+    params
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn uri_encode(value: &str) -> String {
+    let decoded = percent_decode_str(value).decode_utf8_lossy();
+
+    utf8_percent_encode(&decoded, URI_ENCODE_SET).to_string()
+}
+
+/// Derives the signing key for a credential scope, returned as a MAC ready to
+/// sign the string to sign
+fn signing_key(secret: &str, scope: &str) -> HmacSha256 {
+    let mut key = format!("AWS4{}", secret).into_bytes();
+
+    // The scope is `{date}/{region}/{service}/aws4_request`, and each part is
+    // chained into the key in that order
+    for part in scope.split('/') {
+        let mut mac = HmacSha256::new_from_slice(&key).expect("HMAC accepts keys of any length");
+        mac.update(part.as_bytes());
+        key = mac.finalize().into_bytes().to_vec();
+    }
+
+    HmacSha256::new_from_slice(&key).expect("HMAC accepts keys of any length")
+}
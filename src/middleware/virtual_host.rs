@@ -0,0 +1,93 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{FromRef, FromRequestParts, Request},
+    http::{Uri, header, request::Parts, uri::PathAndQuery},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config::Config;
+
+/// Bucket addressed by a virtual-hosted-style request, where the bucket is a
+/// subdomain of the configured virtual host domain such as
+/// `photos.objection.example.com`. Empty for path-style requests.
+#[derive(Debug, Clone)]
+pub struct VirtualHostBucket(pub Option<String>);
+
+impl<S> FromRequestParts<S> for VirtualHostBucket
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        let Some(domain) = &config.http.virtual_host_domain else {
+            return Ok(Self(None));
+        };
+
+        // HTTP/2 requests carry the host in the URI rather than a header
+        let host = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| parts.uri.authority().map(|authority| authority.as_str()));
+
+        Ok(Self(host.and_then(|host| bucket_from_host(host, domain))))
+    }
+}
+
+/// Top-level paths which aren't buckets. Hosts with these as their subdomain
+/// are routed path-style, since moving them into the path would reach these
+/// routes rather than a bucket.
+const RESERVED_SUBDOMAINS: &[&str] = &["api", "health", "ready", "metrics"];
+
+/// Extracts the bucket from a `Host` of the form `{bucket}.{domain}`, with or
+/// without a port
+fn bucket_from_host(host: &str, domain: &str) -> Option<String> {
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => host,
+    };
+
+    let bucket = host.to_ascii_lowercase();
+    let bucket = bucket
+        .strip_suffix(&domain.to_ascii_lowercase())?
+        .strip_suffix('.')?;
+
+    (!bucket.is_empty() && !bucket.contains('.') && !RESERVED_SUBDOMAINS.contains(&bucket))
+        .then(|| bucket.to_owned())
+}
+
+/// Routes virtual-hosted-style requests by moving the bucket from the host
+/// into the path, so they reach the same handlers as path-style requests.
+/// Requests for any other host are left alone and routed path-style.
+///
+/// This runs after `OriginalUri` is recorded, so signatures are still
+/// checked against the path as it was sent. Everything else, like
+/// authorization, must look at the rewritten path. The bucket is added to the
+/// request extensions.
+pub async fn route_virtual_hosts(
+    VirtualHostBucket(bucket): VirtualHostBucket,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(bucket) = bucket {
+        req.extensions_mut()
+            .insert(VirtualHostBucket(Some(bucket.clone())));
+
+        let path_and_query = req.uri().path_and_query().map_or("/", PathAndQuery::as_str);
+
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query =
+            PathAndQuery::try_from(format!("/{}{}", bucket, path_and_query)).ok();
+
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+
+    next.run(req).await
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+use uuid::Uuid;
+
+/// A request made to a bucket with access logging enabled
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLog {
+    pub id: i64,
+    pub bucket_uuid: Uuid,
+    pub object_key: Option<String>,
+    pub method: String,
+    pub status_code: u16,
+    pub requester_ip: String,
+    pub bytes_transferred: u64,
+    pub request_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// The details of a request to record in the access log
+#[derive(Debug)]
+pub struct NewAccessLog {
+    pub bucket_uuid: Uuid,
+    pub object_key: Option<String>,
+    pub method: String,
+    pub status_code: u16,
+    pub requester_ip: String,
+    pub bytes_transferred: u64,
+    pub request_id: Option<String>,
+}
+
+/// Conditions an access log must meet to be listed, where unset conditions
+/// match every log
+#[derive(Debug, Default)]
+pub struct AccessLogFilter {
+    /// Inclusive lower bound on the timestamp
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on the timestamp
+    pub to: Option<DateTime<Utc>>,
+    pub method: Option<String>,
+    pub status_code: Option<u16>,
+}
+
+/// Matches the logs of bucket `?1` which meet an [`AccessLogFilter`] bound to
+/// `?2` through `?5`
+const FILTER_CONDITION: &str = "bucket_uuid = ?1
+    AND (?2 IS NULL OR timestamp >= ?2)
+    AND (?3 IS NULL OR timestamp < ?3)
+    AND (?4 IS NULL OR method = ?4)
+    AND (?5 IS NULL OR status_code = ?5)";
+
+impl FromRow<'_, SqliteRow> for AccessLog {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            bucket_uuid: row.try_get("bucket_uuid")?,
+            object_key: row.try_get("object_key")?,
+            method: row.try_get("method")?,
+            status_code: row.try_get("status_code")?,
+            requester_ip: row.try_get("requester_ip")?,
+            bytes_transferred: row.try_get::<i64, _>("bytes_transferred")? as u64,
+            request_id: row.try_get("request_id")?,
+            timestamp: row.try_get("timestamp")?,
+        })
+    }
+}
+
+impl AccessLog {
+    pub async fn insert(db: &sqlx::SqlitePool, log: NewAccessLog) -> sqlx::Result<()> {
+        sqlx::query(
+            "INSERT INTO access_logs
+                (bucket_uuid, object_key, method, status_code, requester_ip, bytes_transferred, request_id, timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(log.bucket_uuid)
+        .bind(log.object_key)
+        .bind(log.method)
+        .bind(log.status_code)
+        .bind(log.requester_ip)
+        .bind(log.bytes_transferred as i64)
+        .bind(log.request_id)
+        .bind(Utc::now())
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the access logs of a bucket matching `filter`, oldest first
+    pub async fn find_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        filter: &AccessLogFilter,
+        offset: u64,
+        limit: u64,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as(&format!(
+            "SELECT * FROM access_logs WHERE {FILTER_CONDITION}
+            ORDER BY timestamp, id LIMIT ?6 OFFSET ?7;"
+        ))
+        .bind(bucket_uuid)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.method.as_deref())
+        .bind(filter.status_code)
+        .bind(limit as i64)
+        .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+        .fetch_all(db)
+        .await
+    }
+
+    /// Counts the access logs of a bucket matching `filter`
+    pub async fn count_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        filter: &AccessLogFilter,
+    ) -> sqlx::Result<u64> {
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM access_logs WHERE {FILTER_CONDITION};"
+        ))
+        .bind(bucket_uuid)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.method.as_deref())
+        .bind(filter.status_code)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count as u64)
+    }
+
+    /// Deletes the access logs of a bucket recorded before `cutoff`, returning
+    /// how many were deleted
+    pub async fn delete_before(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        cutoff: DateTime<Utc>,
+    ) -> sqlx::Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM access_logs WHERE bucket_uuid = ? AND timestamp < ?;")
+                .bind(bucket_uuid)
+                .bind(cutoff)
+                .execute(db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use rand::{Rng, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+/// What an access token may be used for. Each permission includes the ones
+/// before it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, strum::Display, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AccessTokenPermission {
+    /// Reading objects and listing buckets
+    Read,
+    /// Uploading and deleting objects
+    Write,
+    /// Managing buckets and access tokens
+    Admin,
+}
+
+impl AccessTokenPermission {
+    /// Every permission, which tokens are given unless they are scoped
+    pub const ALL: [Self; 3] = [Self::Read, Self::Write, Self::Admin];
+}
+
+/// A credential which can authenticate requests either as a bearer token or
+/// with AWS Signature Version 4 using its access key pair.
+///
+/// Only a hash of the bearer token is stored. The secret access key has to be
+/// stored as-is since verifying a signature requires recomputing it.
+#[derive(Debug, Clone, FromRow)]
+pub struct AccessToken {
+    uuid: Uuid,
+    description: Box<str>,
+    access_key_id: Box<str>,
+    secret_access_key: Box<str>,
+    permissions: Json<BTreeSet<AccessTokenPermission>>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl AccessToken {
+    /// Generates and stores a new access token. Returns the token along with
+    /// the plaintext bearer token, which can't be recovered later.
+    pub async fn new(
+        db: &sqlx::SqlitePool,
+        description: &str,
+        permissions: BTreeSet<AccessTokenPermission>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<(Self, String)> {
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let access_key_id = format!("OBJ{}", random_string(17)).to_uppercase();
+        let secret_access_key = random_string(40);
+
+        let access_token = sqlx::query_as(
+            "INSERT INTO access_tokens
+                (uuid, token_hash, description, access_key_id, secret_access_key, permissions, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *;",
+        )
+        .bind(Uuid::new_v4())
+        .bind(sha256::digest(&token))
+        .bind(description)
+        .bind(access_key_id)
+        .bind(secret_access_key)
+        .bind(Json(permissions))
+        .bind(Utc::now())
+        .bind(expires_at)
+        .fetch_one(db)
+        .await?;
+
+        Ok((access_token, token))
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    pub fn secret_access_key(&self) -> &str {
+        &self.secret_access_key
+    }
+
+    pub fn permissions(&self) -> &BTreeSet<AccessTokenPermission> {
+        &self.permissions
+    }
+
+    /// Whether this token can be used for requests which need `permission`
+    pub fn permits(&self, permission: AccessTokenPermission) -> bool {
+        self.permissions
+            .iter()
+            .any(|granted| *granted >= permission)
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at
+    }
+
+    /// Whether this token has an expiry time which has already passed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    pub async fn find_all(db: &sqlx::SqlitePool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM access_tokens ORDER BY created_at;")
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn find_by_uuid(db: &sqlx::SqlitePool, uuid: Uuid) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as("SELECT * FROM access_tokens WHERE uuid = ?;")
+            .bind(uuid)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Finds the access token for a plaintext bearer token
+    pub async fn find_by_token(db: &sqlx::SqlitePool, token: &str) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as("SELECT * FROM access_tokens WHERE token_hash = ?;")
+            .bind(sha256::digest(token))
+            .fetch_optional(db)
+            .await
+    }
+
+    pub async fn find_by_access_key_id(
+        db: &sqlx::SqlitePool,
+        access_key_id: &str,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as("SELECT * FROM access_tokens WHERE access_key_id = ?;")
+            .bind(access_key_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Changes the description and expiry time of this token
+    pub async fn update(
+        &mut self,
+        db: &sqlx::SqlitePool,
+        description: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<()> {
+        sqlx::query("UPDATE access_tokens SET description = ?, expires_at = ? WHERE uuid = ?;")
+            .bind(description)
+            .bind(expires_at)
+            .bind(self.uuid)
+            .execute(db)
+            .await?;
+
+        self.description = description.into();
+        self.expires_at = expires_at;
+
+        Ok(())
+    }
+
+    pub async fn delete(self, db: &sqlx::SqlitePool) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM access_tokens WHERE uuid = ?;")
+            .bind(self.uuid)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
@@ -1,22 +1,237 @@
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use std::{
+    collections::BTreeMap,
+    io::{self, Cursor},
+    path::Path,
+    time::Duration,
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_with::{DurationMilliSeconds, serde_as};
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+use tokio_util::io::{ReaderStream, StreamReader};
 use uuid::Uuid;
 
-use super::CachePolicy;
+use super::{
+    CachePolicy, begin_write,
+    multipart::MultipartUpload,
+    object::{
+        Object, ObjectAttributes, ObjectError, StoredContents, Upload, normalize_key,
+        remove_unreferenced,
+    },
+    tag::{self, TagError},
+};
+use crate::storage::{Storage, StorageBackend, staging_directory};
+
+/// Most tags which can be attached to a single bucket, matching S3
+pub const MAX_BUCKET_TAGS: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BucketError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Bucket `{0}` does not exist")]
+    NotFound(String),
+    #[error("Bucket `{0}` is not empty")]
+    NotEmpty(String),
+    #[error(transparent)]
+    InvalidName(#[from] BucketNameError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BucketNameError {
+    #[error("Bucket names must be between 3 and 63 characters long, got {0}")]
+    Length(usize),
+    #[error("Bucket names may only contain lowercase letters, numbers and hyphens, found `{0}`")]
+    InvalidCharacter(char),
+    #[error("Bucket names must begin and end with a letter or number")]
+    InvalidBoundary,
+    #[error("Bucket names must not contain consecutive hyphens")]
+    ConsecutiveHyphens,
+    #[error("Bucket names must not be formatted as an IP address")]
+    IpAddress,
+}
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Bucket {
     uuid: Uuid,
     name: Box<str>,
     #[serde(flatten)]
     #[sqlx(flatten)]
     settings: BucketSettings,
+    created_at: DateTime<Utc>,
+    object_count: i64,
+    total_bytes: i64,
+}
+
+/// Whether uploads and deletions keep the previous versions of objects in a
+/// bucket. Once enabled, versioning can only be suspended and never disabled
+/// again.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, strum::Display, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum VersioningStatus {
+    /// Versioning has never been enabled for the bucket
+    #[default]
+    Disabled,
+    Enabled,
+    /// New uploads create the null version, but every version which already
+    /// exists is kept
+    Suspended,
+}
+
+impl VersioningStatus {
+    pub fn is_enabled(self) -> bool {
+        self == Self::Enabled
+    }
+
+    /// Whether a bucket with this status may be changed to `status`
+    pub fn can_transition_to(self, status: VersioningStatus) -> bool {
+        match (self, status) {
+            (from, to) if from == to => true,
+            (_, Self::Enabled) => true,
+            (Self::Enabled, Self::Suspended) => true,
+            _ => false,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BucketSettings {
     pub default_cache_policy: Option<CachePolicy>,
     pub access_logging: bool,
+    /// Largest object in bytes which may be uploaded to the bucket
+    pub max_object_size: Option<u64>,
+    #[serde(default)]
+    pub versioning_status: VersioningStatus,
+    /// Overrides the global rate limiting period for requests to the bucket
+    #[serde_as(as = "Option<DurationMilliSeconds<u64>>")]
+    #[serde(default, rename = "rate_limit_period_ms")]
+    pub rate_limit_period: Option<Duration>,
+    /// Overrides the global rate limiting burst size for requests to the
+    /// bucket
+    #[serde(default)]
+    pub rate_limit_burst_size: Option<u32>,
+    /// Whether deleting an object only hides it until it is restored or
+    /// purged after the retention period
+    #[serde(default)]
+    pub soft_delete_enabled: bool,
+    /// Whether anyone can read the bucket's objects without an access token
+    #[serde(default)]
+    pub public_read: bool,
+}
+
+impl BucketSettings {
+    /// Checks that the settings can be applied, returning a description of the
+    /// first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .rate_limit_period
+            .is_some_and(|period| period.is_zero())
+        {
+            return Err("`rate_limit_period_ms` must be at least 1".into());
+        }
+
+        if self.rate_limit_burst_size == Some(0) {
+            return Err("`rate_limit_burst_size` must be at least 1".into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether requests to the bucket are rate limited separately from the
+    /// rest of the server
+    pub fn overrides_rate_limit(&self) -> bool {
+        self.rate_limit_period.is_some() || self.rate_limit_burst_size.is_some()
+    }
+}
+
+impl FromRow<'_, SqliteRow> for BucketSettings {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        let max_object_size: Option<i64> = row.try_get("max_object_size")?;
+        let rate_limit_period_ms: Option<i64> = row.try_get("rate_limit_period_ms")?;
+        let rate_limit_burst_size: Option<i64> = row.try_get("rate_limit_burst_size")?;
+
+        Ok(Self {
+            default_cache_policy: row.try_get("default_cache_policy")?,
+            access_logging: row.try_get("access_logging")?,
+            max_object_size: max_object_size.map(|size| size as u64),
+            versioning_status: row.try_get("versioning_status")?,
+            rate_limit_period: rate_limit_period_ms.map(|ms| Duration::from_millis(ms as u64)),
+            rate_limit_burst_size: rate_limit_burst_size.map(|size| size as u32),
+            soft_delete_enabled: row.try_get("soft_delete_enabled")?,
+            public_read: row.try_get("public_read")?,
+        })
+    }
+}
+
+/// A partial update to [`BucketSettings`] where only the fields which are
+/// present are modified
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BucketSettingsPatch {
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub default_cache_policy: Option<Option<CachePolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_logging: Option<bool>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub max_object_size: Option<Option<u64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub rate_limit_period_ms: Option<Option<u64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    pub rate_limit_burst_size: Option<Option<u32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_delete_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_read: Option<bool>,
+}
+
+impl BucketSettingsPatch {
+    pub fn apply(self, settings: &mut BucketSettings) {
+        if let Some(default_cache_policy) = self.default_cache_policy {
+            settings.default_cache_policy = default_cache_policy;
+        }
+        if let Some(access_logging) = self.access_logging {
+            settings.access_logging = access_logging;
+        }
+        if let Some(max_object_size) = self.max_object_size {
+            settings.max_object_size = max_object_size;
+        }
+        if let Some(rate_limit_period_ms) = self.rate_limit_period_ms {
+            settings.rate_limit_period = rate_limit_period_ms.map(Duration::from_millis);
+        }
+        if let Some(rate_limit_burst_size) = self.rate_limit_burst_size {
+            settings.rate_limit_burst_size = rate_limit_burst_size;
+        }
+        if let Some(soft_delete_enabled) = self.soft_delete_enabled {
+            settings.soft_delete_enabled = soft_delete_enabled;
+        }
+        if let Some(public_read) = self.public_read {
+            settings.public_read = public_read;
+        }
+    }
 }
 
 impl Bucket {
@@ -24,16 +239,58 @@ impl Bucket {
         db: &sqlx::SqlitePool,
         name: impl Into<String>,
         settings: BucketSettings,
-    ) -> sqlx::Result<Self> {
+    ) -> Result<Self, BucketError> {
         let name: String = name.into();
+        validate_bucket_name(&name)?;
 
-        let bucket: Bucket = sqlx::query_as("INSERT INTO buckets VALUES (?, ?, ?, ?);")
-            .bind(Uuid::new_v4())
-            .bind(name)
-            .bind(settings.default_cache_policy)
-            .bind(settings.access_logging)
-            .fetch_one(db)
-            .await?;
+        Self::insert(db, Uuid::new_v4(), name, settings).await
+    }
+
+    /// Recreates the record of a bucket whose contents are still stored under
+    /// `uuid`, for recovering from a lost database. Its name and settings are
+    /// lost along with the database, so it is named `recovered-{uuid}` with
+    /// the default settings.
+    pub async fn recreate(db: &sqlx::SqlitePool, uuid: Uuid) -> Result<Self, BucketError> {
+        Self::insert(
+            db,
+            uuid,
+            format!("recovered-{}", uuid),
+            BucketSettings::default(),
+        )
+        .await
+    }
+
+    async fn insert(
+        db: &sqlx::SqlitePool,
+        uuid: Uuid,
+        name: String,
+        settings: BucketSettings,
+    ) -> Result<Self, BucketError> {
+        let mut tx = begin_write(db).await?;
+
+        let bucket: Bucket = sqlx::query_as(
+            "INSERT INTO buckets (uuid, name, default_cache_policy, access_logging, max_object_size, versioning_status,
+                rate_limit_period_ms, rate_limit_burst_size, soft_delete_enabled, public_read,
+                created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *;",
+        )
+        .bind(uuid)
+        .bind(name)
+        .bind(settings.default_cache_policy)
+        .bind(settings.access_logging)
+        .bind(settings.max_object_size.map(|size| size as i64))
+        .bind(settings.versioning_status)
+        .bind(rate_limit_period_ms(&settings))
+        .bind(settings.rate_limit_burst_size)
+        .bind(settings.soft_delete_enabled)
+        .bind(settings.public_read)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Object::create_table(&mut *tx, bucket.uuid).await?;
+
+        tx.commit().await?;
 
         Ok(bucket)
     }
@@ -50,31 +307,619 @@ impl Bucket {
         &self.settings
     }
 
-    pub async fn find_all(db: &sqlx::SqlitePool) -> sqlx::Result<Vec<Self>> {
-        sqlx::query_as("SELECT * FROM buckets;").fetch_all(db).await
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Number of objects in this bucket, not counting previous versions or
+    /// deleted objects, maintained on every upload and deletion
+    pub fn object_count(&self) -> u64 {
+        self.object_count as u64
     }
 
-    pub async fn find_by_name(db: &sqlx::SqlitePool, name: &str) -> Result<Option<Self>, ()> {
-        todo!()
+    /// Total size in bytes of all objects in this bucket including their
+    /// previous versions, maintained on every upload and deletion
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes as u64
     }
 
-    pub async fn find_by_uuid(db: &sqlx::SqlitePool, uuid: Uuid) -> Result<Option<Self>, ()> {
-        todo!()
+    /// Finds every bucket, ordered by name
+    pub async fn find_all_unpaginated(db: &sqlx::SqlitePool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM buckets ORDER BY name;")
+            .fetch_all(db)
+            .await
     }
 
-    pub async fn update_settings(&mut self, db: &sqlx::SqlitePool, settings: BucketSettings) {
-        todo!()
+    /// Finds a single page of buckets ordered by name, where `page` starts at 1
+    pub async fn find_all(
+        db: &sqlx::SqlitePool,
+        offset: u64,
+        limit: u64,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM buckets ORDER BY name LIMIT ? OFFSET ?;")
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn count(db: &sqlx::SqlitePool) -> sqlx::Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM buckets;")
+            .fetch_one(db)
+            .await?;
+
+        Ok(count as u64)
+    }
+
+    pub async fn find_by_name(db: &sqlx::SqlitePool, name: &str) -> Result<Self, BucketError> {
+        sqlx::query_as("SELECT * FROM buckets WHERE name = ?;")
+            .bind(name)
+            .fetch_optional(db)
+            .await?
+            .ok_or_else(|| BucketError::NotFound(name.to_owned()))
+    }
+
+    pub async fn find_by_uuid(db: &sqlx::SqlitePool, uuid: Uuid) -> Result<Self, BucketError> {
+        sqlx::query_as("SELECT * FROM buckets WHERE uuid = ?;")
+            .bind(uuid)
+            .fetch_optional(db)
+            .await?
+            .ok_or_else(|| BucketError::NotFound(uuid.to_string()))
     }
 
-    pub async fn delete(self, db: &sqlx::SqlitePool) {}
+    pub async fn update_settings(
+        &mut self,
+        db: &sqlx::SqlitePool,
+        settings: BucketSettings,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "UPDATE buckets SET default_cache_policy = ?, access_logging = ?, max_object_size = ?,
+                versioning_status = ?, rate_limit_period_ms = ?, rate_limit_burst_size = ?,
+                soft_delete_enabled = ?, public_read = ?
+            WHERE uuid = ?;",
+        )
+        .bind(settings.default_cache_policy)
+        .bind(settings.access_logging)
+        .bind(settings.max_object_size.map(|size| size as i64))
+        .bind(settings.versioning_status)
+        .bind(rate_limit_period_ms(&settings))
+        .bind(settings.rate_limit_burst_size)
+        .bind(settings.soft_delete_enabled)
+        .bind(settings.public_read)
+        .bind(self.uuid)
+        .execute(db)
+        .await?;
+
+        self.settings = settings;
+
+        Ok(())
+    }
+
+    /// Lists the tags of this bucket by key
+    pub async fn get_tags(&self, db: &sqlx::SqlitePool) -> sqlx::Result<BTreeMap<String, String>> {
+        let tags: Vec<(String, String)> =
+            sqlx::query_as("SELECT key, value FROM bucket_tags WHERE bucket_uuid = ?;")
+                .bind(self.uuid)
+                .fetch_all(db)
+                .await?;
 
-    pub async fn export_backup(&self) -> BucketBackup {
-        todo!()
+        Ok(tags.into_iter().collect())
     }
 
-    pub async fn import_backup(&self, backup: BucketBackup) {
-        todo!()
+    /// Replaces all of the tags of this bucket
+    pub async fn set_tags(
+        &self,
+        db: &sqlx::SqlitePool,
+        tags: &BTreeMap<String, String>,
+    ) -> sqlx::Result<()> {
+        let mut tx = begin_write(db).await?;
+
+        sqlx::query("DELETE FROM bucket_tags WHERE bucket_uuid = ?;")
+            .bind(self.uuid)
+            .execute(&mut *tx)
+            .await?;
+
+        for (key, value) in tags {
+            sqlx::query("INSERT INTO bucket_tags (bucket_uuid, key, value) VALUES (?, ?, ?);")
+                .bind(self.uuid)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Removes all of the tags of this bucket
+    pub async fn delete_tags(&self, db: &sqlx::SqlitePool) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM bucket_tags WHERE bucket_uuid = ?;")
+            .bind(self.uuid)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adjusts the materialized object counters for a bucket, should be called
+    /// in the same transaction as the change to the objects table
+    pub async fn adjust_counters<'c, E>(
+        executor: E,
+        uuid: Uuid,
+        object_count: i64,
+        total_bytes: i64,
+    ) -> sqlx::Result<()>
+    where
+        E: sqlx::SqliteExecutor<'c>,
+    {
+        sqlx::query(
+            "UPDATE buckets SET object_count = object_count + ?, total_bytes = total_bytes + ?
+            WHERE uuid = ?;",
+        )
+        .bind(object_count)
+        .bind(total_bytes)
+        .bind(uuid)
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recomputes the materialized object counters of every bucket from its
+    /// objects table, correcting any drift
+    pub async fn reconcile_counters(db: &sqlx::SqlitePool) -> sqlx::Result<()> {
+        for bucket in Self::find_all_unpaginated(db).await? {
+            let table = Object::table_name(bucket.uuid);
+
+            sqlx::query(&format!(
+                "UPDATE buckets SET
+                    object_count = (
+                        SELECT COUNT(*) FROM {table}
+                        WHERE is_latest AND NOT is_delete_marker AND deleted_at IS NULL
+                    ),
+                    total_bytes = (SELECT COALESCE(SUM(size), 0) FROM {table})
+                WHERE uuid = ?;"
+            ))
+            .bind(bucket.uuid)
+            .execute(db)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the object at `path` in this bucket to the same path in
+    /// `destination`, returning it as stored there, or `None` if there was no
+    /// object to move. The contents are stored as they are, and the source is
+    /// deleted like [`Object::delete_current`]. In a versioned bucket that
+    /// leaves a delete marker behind, keeping the moved version and any older
+    /// ones along with their contents.
+    ///
+    /// Both buckets are changed in a single write transaction, which is held
+    /// while the contents are copied so the object can't change in between.
+    pub async fn transfer_object(
+        &self,
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        path: &str,
+        destination: &Bucket,
+    ) -> Result<Option<Object>, ObjectError> {
+        if self.uuid == destination.uuid {
+            return Ok(Object::find_by_path(db, self, path).await?);
+        }
+
+        let mut tx = begin_write(db).await?;
+
+        let Some(source) = Object::find_by_path(&mut *tx, self, path).await? else {
+            return Ok(None);
+        };
+
+        storage
+            .copy(self.uuid, destination.uuid, source.hash(), path)
+            .await?;
+
+        let moved = async {
+            let (object, replaced) = Object::insert_in(
+                &mut tx,
+                destination,
+                StoredContents {
+                    path,
+                    hash: source.hash(),
+                    etag: source.etag(),
+                    size: source.size(),
+                    attributes: source.attributes(),
+                },
+            )
+            .await?;
+            let (_, unreferenced) = Object::delete_current_in(&mut tx, self, path).await?;
+
+            Ok::<_, sqlx::Error>((object, replaced, unreferenced))
+        }
+        .await;
+
+        let (object, replaced, unreferenced) = match moved {
+            Ok(moved) => moved,
+            Err(e) => {
+                drop(tx);
+
+                // The copy may be the only reference to the contents in the
+                // destination now that the transaction was rolled back
+                remove_unreferenced(db, storage, destination.uuid, source.hash()).await?;
+
+                return Err(e.into());
+            }
+        };
+
+        tx.commit().await?;
+
+        if let Some(hash) = replaced {
+            remove_unreferenced(db, storage, destination.uuid, &hash).await?;
+        }
+        if let Some(hash) = unreferenced {
+            remove_unreferenced(db, storage, self.uuid, &hash).await?;
+        }
+
+        Ok(Some(object))
+    }
+
+    /// Deletes this bucket along with its objects table, all of its stored
+    /// objects and the parts of any multipart uploads in progress. Unless
+    /// `force` is set, buckets with any object versions are left alone.
+    pub async fn delete(
+        self,
+        db: &sqlx::SqlitePool,
+        data_directory: &Path,
+        storage: &dyn StorageBackend,
+        force: bool,
+    ) -> Result<(), BucketError> {
+        let mut tx = begin_write(db).await?;
+
+        // Checked within the write transaction so that nothing can be
+        // uploaded between the check and the bucket being deleted
+        if !force && Object::count_in_bucket(&mut *tx, self.uuid).await? > 0 {
+            return Err(BucketError::NotEmpty(self.name.into()));
+        }
+
+        // Uploads are removed along with the bucket, so their parts have to
+        // be found before then
+        let uploads: Vec<MultipartUpload> =
+            sqlx::query_as("SELECT * FROM multipart_uploads WHERE bucket_uuid = ?;")
+                .bind(self.uuid)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        sqlx::query("DELETE FROM buckets WHERE uuid = ?;")
+            .bind(self.uuid)
+            .execute(&mut *tx)
+            .await?;
+
+        Object::drop_table(&mut *tx, self.uuid).await?;
+
+        tx.commit().await?;
+
+        for upload in uploads {
+            upload.remove_parts(data_directory).await;
+        }
+
+        if let Err(e) = storage.delete_bucket(self.uuid).await {
+            tracing::error!(
+                "Failed to remove storage for deleted bucket {}: {}",
+                self.uuid,
+                e
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Streams a backup of this bucket and the latest version of each of its
+    /// objects as newline delimited JSON. The [`BucketBackup`] header comes
+    /// first, then each object's [`BackupRecord::Object`] followed by its
+    /// contents split into [`BackupRecord::Chunk`]s. Contents are only read
+    /// from storage as the stream is polled.
+    pub async fn export_backup(
+        &self,
+        db: &sqlx::SqlitePool,
+        storage: Storage,
+    ) -> Result<impl Stream<Item = io::Result<Vec<u8>>> + Send + 'static, ObjectError> {
+        let listing = Object::find_all_in_bucket(db, self, None, None, None, 0, u64::MAX).await?;
+
+        let header = backup_line(&BucketBackup {
+            version: BUCKET_BACKUP_VERSION,
+            bucket: self.clone(),
+        });
+
+        let bucket_uuid = self.uuid;
+        let objects = futures::stream::iter(listing.objects)
+            .then(move |object| {
+                let storage = storage.clone();
+
+                async move {
+                    let contents = storage.get(bucket_uuid, object.hash()).await?;
+                    let chunks =
+                        ReaderStream::with_capacity(contents, BACKUP_CHUNK_SIZE).map_ok(|chunk| {
+                            backup_line(&BackupRecord::Chunk(BASE64_STANDARD.encode(chunk)))
+                        });
+
+                    let record = backup_line(&BackupRecord::Object(ObjectBackupEntry {
+                        key: object.path().to_owned(),
+                        content_type: object.content_type().map(ToString::to_string),
+                        content_encoding: object.content_encoding().map(ToOwned::to_owned),
+                        metadata: object.metadata().clone(),
+                        public_read: object.public_read(),
+                        size: object.size(),
+                    }));
+
+                    Ok::<_, io::Error>(futures::stream::once(async { Ok(record) }).chain(chunks))
+                }
+            })
+            .try_flatten();
+
+        Ok(futures::stream::once(async { Ok(header) }).chain(objects))
+    }
+
+    /// Restores a backup streamed in the format written by
+    /// [`export_backup`](Self::export_backup) into the bucket called `name`,
+    /// creating it with the backed up settings if it doesn't exist. Objects
+    /// which already exist with the same contents are skipped.
+    pub async fn import_backup(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        data_directory: &Path,
+        name: &str,
+        backup: impl AsyncBufRead + Unpin,
+    ) -> Result<Self, BucketBackupError> {
+        let mut lines = BackupLines::new(backup);
+
+        let header = lines
+            .next::<BucketBackup>()
+            .await?
+            .ok_or_else(|| BucketBackupError::Malformed("the backup is empty".to_owned()))?;
+        if header.version != BUCKET_BACKUP_VERSION {
+            return Err(BucketBackupError::UnsupportedVersion(header.version));
+        }
+
+        let bucket = match Self::find_by_name(db, name).await {
+            Ok(bucket) => bucket,
+            Err(BucketError::NotFound(_)) => Self::new(db, name, header.bucket.settings).await?,
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(record) = lines.next::<BackupRecord>().await? {
+            let BackupRecord::Object(entry) = record else {
+                return Err(BucketBackupError::Malformed(
+                    "contents must follow the object they belong to".to_owned(),
+                ));
+            };
+            let invalid = || BucketBackupError::InvalidObject(entry.key.clone());
+
+            let key = normalize_key(&entry.key).map_err(|_| invalid())?;
+            let content_type = entry
+                .content_type
+                .as_deref()
+                .map(|content_type| content_type.parse().map_err(|_| invalid()))
+                .transpose()?;
+
+            let upload = match Upload::receive(
+                &staging_directory(data_directory),
+                lines.contents(entry.size),
+                bucket.settings.max_object_size,
+            )
+            .await
+            {
+                Err(ObjectError::Io(e)) if e.kind() == io::ErrorKind::InvalidData => {
+                    return Err(invalid());
+                }
+                result => result?,
+            };
+
+            if let Some(existing) = Object::find_by_path(db, &bucket, &key).await?
+                && existing.hash() == upload.sha256()
+            {
+                continue;
+            }
+
+            Object::new(
+                db,
+                storage,
+                &bucket,
+                &key,
+                ObjectAttributes {
+                    content_type,
+                    content_encoding: entry.content_encoding.map(Into::into),
+                    metadata: entry.metadata,
+                    public_read: entry.public_read,
+                    ..Default::default()
+                },
+                upload,
+            )
+            .await?;
+        }
+
+        Ok(Self::find_by_uuid(db, bucket.uuid).await?)
     }
 }
 
-pub struct BucketBackup {}
+/// The rate limiting period of a bucket as stored in the database
+fn rate_limit_period_ms(settings: &BucketSettings) -> Option<i64> {
+    settings
+        .rate_limit_period
+        .map(|period| period.as_millis().min(i64::MAX as u128) as i64)
+}
+
+/// Format version written to new backups
+pub const BUCKET_BACKUP_VERSION: u8 = 2;
+
+/// Bytes of object contents carried by each chunk of a backup
+const BACKUP_CHUNK_SIZE: usize = 48 * 1024;
+
+/// Longest line accepted when importing a backup, which is far longer than
+/// any chunk or object record written by an export
+const MAX_BACKUP_LINE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BucketBackupError {
+    #[error(transparent)]
+    Bucket(#[from] BucketError),
+    #[error(transparent)]
+    Object(#[from] ObjectError),
+    #[error("Backup format version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Object `{0}` in the backup is invalid")]
+    InvalidObject(String),
+    #[error("The backup is malformed: {0}")]
+    Malformed(String),
+}
+
+impl From<sqlx::Error> for BucketBackupError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Bucket(BucketError::Database(value))
+    }
+}
+
+/// The first line of a backup, describing the bucket which was backed up
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BucketBackup {
+    /// Format of the backup, incremented whenever it changes incompatibly
+    pub version: u8,
+    pub bucket: Bucket,
+}
+
+/// Every line of a backup after the [`BucketBackup`] header
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupRecord {
+    /// An object, whose contents follow as `size` bytes of chunks
+    Object(ObjectBackupEntry),
+    /// Base64 encoded part of the contents of the preceding object
+    Chunk(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ObjectBackupEntry {
+    pub key: String,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<Box<str>, Box<str>>,
+    #[serde(default)]
+    pub public_read: bool,
+    pub size: u64,
+}
+
+/// Serializes one line of a backup
+fn backup_line(value: &impl Serialize) -> Vec<u8> {
+    let mut line = serde_json::to_vec(value).expect("backups always serialize");
+    line.push(b'\n');
+    line
+}
+
+/// Reads a backup one line at a time
+struct BackupLines<R> {
+    reader: R,
+    line: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> BackupLines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+        }
+    }
+
+    /// Parses the next line which isn't blank, or returns `None` at the end
+    /// of the backup
+    async fn next<T: DeserializeOwned>(&mut self) -> Result<Option<T>, BucketBackupError> {
+        loop {
+            self.line.clear();
+            (&mut self.reader)
+                .take(MAX_BACKUP_LINE_BYTES)
+                .read_until(b'\n', &mut self.line)
+                .await
+                .map_err(ObjectError::from)?;
+
+            if self.line.is_empty() {
+                return Ok(None);
+            }
+            if !self.line.ends_with(b"\n") && self.line.len() as u64 == MAX_BACKUP_LINE_BYTES {
+                return Err(BucketBackupError::Malformed(format!(
+                    "lines must not be longer than {} bytes",
+                    MAX_BACKUP_LINE_BYTES
+                )));
+            }
+            if self.line.trim_ascii().is_empty() {
+                continue;
+            }
+
+            return serde_json::from_slice(&self.line)
+                .map(Some)
+                .map_err(|e| BucketBackupError::Malformed(e.to_string()));
+        }
+    }
+
+    /// Decodes `size` bytes of contents from the chunks on the following
+    /// lines. Chunks which are missing, invalid or too long fail with
+    /// [`io::ErrorKind::InvalidData`].
+    fn contents(&mut self, size: u64) -> impl AsyncRead + Unpin + '_ {
+        let chunks = futures::stream::try_unfold((self, size), |(lines, remaining)| async move {
+            if remaining == 0 {
+                return Ok(None);
+            }
+
+            let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+            let chunk = match lines.next::<BackupRecord>().await {
+                Ok(Some(BackupRecord::Chunk(chunk))) => BASE64_STANDARD
+                    .decode(chunk)
+                    .map_err(|e| invalid(e.to_string()))?,
+                Ok(_) => return Err(invalid("the contents end early".to_owned())),
+                Err(BucketBackupError::Object(ObjectError::Io(e))) => return Err(e),
+                Err(e) => return Err(invalid(e.to_string())),
+            };
+            let remaining = remaining
+                .checked_sub(chunk.len() as u64)
+                .ok_or_else(|| invalid("the contents are longer than the object".to_owned()))?;
+
+            Ok(Some((Cursor::new(chunk), (lines, remaining))))
+        });
+
+        StreamReader::new(Box::pin(chunks))
+    }
+}
+
+/// Validates a bucket name against the S3 naming rules: 3-63 characters of
+/// lowercase letters, numbers and hyphens, beginning and ending with a letter
+/// or number, with no consecutive hyphens and not formatted as an IP address
+/// Validates tags to attach to a bucket
+pub fn validate_bucket_tags(
+    tags: impl IntoIterator<Item = (String, String)>,
+) -> Result<BTreeMap<String, String>, TagError> {
+    tag::validate_tags(tags, MAX_BUCKET_TAGS)
+}
+
+pub fn validate_bucket_name(name: &str) -> Result<(), BucketNameError> {
+    if !(3..=63).contains(&name.len()) {
+        return Err(BucketNameError::Length(name.len()));
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-'))
+    {
+        return Err(BucketNameError::InvalidCharacter(c));
+    }
+
+    if name.starts_with('-') || name.ends_with('-') {
+        return Err(BucketNameError::InvalidBoundary);
+    }
+
+    if name.contains("--") {
+        return Err(BucketNameError::ConsecutiveHyphens);
+    }
+
+    if name.parse::<std::net::IpAddr>().is_ok() {
+        return Err(BucketNameError::IpAddress);
+    }
+
+    Ok(())
+}
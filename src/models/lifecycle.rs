@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::{
+    begin_write,
+    bucket::{Bucket, BucketError},
+    multipart::MultipartUpload,
+    object::{Object, ObjectError},
+};
+use crate::storage::StorageBackend;
+
+/// A rule which automatically removes objects and abandoned multipart uploads
+/// under a prefix once they reach a certain age
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LifecycleRule {
+    pub id: i64,
+    pub bucket_uuid: Uuid,
+    pub prefix: String,
+    /// Days after being created that objects are deleted
+    pub expiration_days: Option<u32>,
+    /// Days after being initiated that unfinished multipart uploads are
+    /// aborted
+    pub abort_incomplete_multipart_days: Option<u32>,
+}
+
+/// A lifecycle rule to add to a bucket
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewLifecycleRule {
+    #[serde(default)]
+    pub prefix: String,
+    pub expiration_days: Option<u32>,
+    pub abort_incomplete_multipart_days: Option<u32>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LifecycleError {
+    #[error(transparent)]
+    Bucket(#[from] BucketError),
+    #[error(transparent)]
+    Object(#[from] ObjectError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl LifecycleRule {
+    /// Replaces all of the lifecycle rules of a bucket
+    pub async fn replace_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        rules: Vec<NewLifecycleRule>,
+    ) -> sqlx::Result<Vec<Self>> {
+        let mut tx = begin_write(db).await?;
+
+        sqlx::query("DELETE FROM lifecycle_rules WHERE bucket_uuid = ?;")
+            .bind(bucket_uuid)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut inserted = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let rule = sqlx::query_as(
+                "INSERT INTO lifecycle_rules (bucket_uuid, prefix, expiration_days, abort_incomplete_multipart_days)
+                VALUES (?, ?, ?, ?) RETURNING *;",
+            )
+            .bind(bucket_uuid)
+            .bind(rule.prefix)
+            .bind(rule.expiration_days)
+            .bind(rule.abort_incomplete_multipart_days)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            inserted.push(rule);
+        }
+
+        tx.commit().await?;
+
+        Ok(inserted)
+    }
+
+    /// Removes all of the lifecycle rules of a bucket
+    pub async fn delete_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM lifecycle_rules WHERE bucket_uuid = ?;")
+            .bind(bucket_uuid)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Lists the lifecycle rules of a bucket in the order they were added
+    pub async fn find_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM lifecycle_rules WHERE bucket_uuid = ? ORDER BY id;")
+            .bind(bucket_uuid)
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn find_all(db: &sqlx::SqlitePool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM lifecycle_rules ORDER BY id;")
+            .fetch_all(db)
+            .await
+    }
+
+    /// Deletes every object version and aborts every multipart upload which
+    /// this rule has expired. Returns the keys of the deleted objects.
+    pub async fn apply(
+        &self,
+        db: &sqlx::SqlitePool,
+        data_directory: &Path,
+        storage: &dyn StorageBackend,
+    ) -> Result<Vec<String>, LifecycleError> {
+        let bucket = Bucket::find_by_uuid(db, self.bucket_uuid).await?;
+
+        let mut expired = Vec::new();
+        if let Some(days) = self.expiration_days {
+            let cutoff = Utc::now() - Duration::days(days.into());
+
+            for object in Object::find_created_before(db, &bucket, &self.prefix, cutoff).await? {
+                let key = object.path().to_owned();
+                object.delete(db, storage).await?;
+                expired.push(key);
+            }
+        }
+
+        if let Some(days) = self.abort_incomplete_multipart_days {
+            let cutoff = Utc::now() - Duration::days(days.into());
+
+            for upload in MultipartUpload::find_all_in_bucket(db, bucket.uuid()).await? {
+                if upload.key().starts_with(&self.prefix) && upload.initiated_at() < cutoff {
+                    upload.abort(db, data_directory).await?;
+                }
+            }
+        }
+
+        Ok(expired)
+    }
+}
@@ -1,10 +1,14 @@
 //! The backing database for Objection uses a single buckets table to store all the bucket definitions and an "objects" table for each bucket
 
-use bucket::Bucket;
 use serde::{Deserialize, Serialize};
 
+pub mod access_log;
+pub mod access_token;
 pub mod bucket;
+pub mod lifecycle;
+pub mod multipart;
 pub mod object;
+pub mod tag;
 
 #[derive(
     Debug,
@@ -24,4 +28,18 @@ pub mod object;
 pub enum CachePolicy {
     Cache,
     NoCache,
+    /// Cached for a year without revalidation, for assets whose names change
+    /// whenever their contents do
+    Immutable,
+}
+
+/// Begins a transaction which takes the database's write lock immediately.
+///
+/// A deferred transaction which reads before it writes fails with
+/// `SQLITE_BUSY` if another connection writes in between, rather than waiting
+/// for the lock like an immediate one does.
+async fn begin_write(
+    db: &sqlx::SqlitePool,
+) -> sqlx::Result<sqlx::Transaction<'static, sqlx::Sqlite>> {
+    db.begin_with("BEGIN IMMEDIATE").await
 }
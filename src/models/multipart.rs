@@ -0,0 +1,433 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use mime::Mime;
+use sha2::Sha256;
+use sqlx::{FromRow, Row, sqlite::SqliteRow};
+use tempfile::TempPath;
+use uuid::Uuid;
+
+use super::{
+    bucket::Bucket,
+    object::{Object, ObjectAttributes, ObjectError, StoredContents, write_atomic},
+};
+use crate::storage::{StorageBackend, staging_directory};
+
+/// Part numbers accepted by S3, which limits uploads to 10,000 parts
+pub const PART_NUMBERS: std::ops::RangeInclusive<u16> = 1..=10_000;
+
+/// A page of in-progress multipart uploads to a bucket
+#[derive(Debug, Clone, Default)]
+pub struct MultipartUploadListing {
+    pub uploads: Vec<MultipartUpload>,
+    /// Whether there are more uploads after this page
+    pub is_truncated: bool,
+}
+
+/// A page of the parts uploaded so far for a multipart upload
+#[derive(Debug, Clone, Default)]
+pub struct MultipartPartListing {
+    pub parts: Vec<MultipartPart>,
+    /// Whether there are more parts after this page
+    pub is_truncated: bool,
+}
+
+/// An in-progress multipart upload, which becomes an object once it is
+/// completed
+#[derive(Debug, Clone)]
+pub struct MultipartUpload {
+    upload_id: Uuid,
+    bucket_uuid: Uuid,
+    key: Box<str>,
+    content_type: Option<Mime>,
+    initiated_at: DateTime<Utc>,
+}
+
+impl FromRow<'_, SqliteRow> for MultipartUpload {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        let content_type = row
+            .try_get::<Option<String>, _>("content_type")?
+            .map(|content_type| content_type.parse::<Mime>())
+            .transpose()
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "content_type".into(),
+                source: Box::new(e),
+            })?;
+
+        Ok(Self {
+            upload_id: row.try_get("upload_id")?,
+            bucket_uuid: row.try_get("bucket_uuid")?,
+            key: row.try_get("key")?,
+            content_type,
+            initiated_at: row.try_get("initiated_at")?,
+        })
+    }
+}
+
+impl MultipartUpload {
+    /// Starts a new multipart upload for `key` in the given bucket
+    pub async fn create(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        key: &str,
+        content_type: Option<Mime>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as(
+            "INSERT INTO multipart_uploads (upload_id, bucket_uuid, key, content_type, initiated_at)
+            VALUES (?, ?, ?, ?, ?) RETURNING *;",
+        )
+        .bind(Uuid::new_v4())
+        .bind(bucket_uuid)
+        .bind(key)
+        .bind(content_type.as_ref().map(ToString::to_string))
+        .bind(Utc::now())
+        .fetch_one(db)
+        .await
+    }
+
+    /// Finds an in-progress upload by its ID
+    pub async fn find_by_upload_id(
+        db: &sqlx::SqlitePool,
+        upload_id: Uuid,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as("SELECT * FROM multipart_uploads WHERE upload_id = ?;")
+            .bind(upload_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Lists the in-progress uploads to a bucket, oldest first
+    pub async fn find_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as(
+            "SELECT * FROM multipart_uploads WHERE bucket_uuid = ? ORDER BY initiated_at;",
+        )
+        .bind(bucket_uuid)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Lists the in-progress uploads to a bucket whose keys start with
+    /// `prefix`, ordered by key and then upload ID. Listing starts after
+    /// `key_marker`, or after `upload_id_marker` within that key when both
+    /// are given.
+    pub async fn find_page_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        prefix: Option<&str>,
+        key_marker: Option<&str>,
+        upload_id_marker: Option<Uuid>,
+        limit: u64,
+    ) -> sqlx::Result<MultipartUploadListing> {
+        let prefix = prefix.unwrap_or_default();
+        let key_marker = key_marker.unwrap_or_default();
+
+        let mut uploads: Vec<Self> = sqlx::query_as(
+            "SELECT * FROM multipart_uploads
+            WHERE bucket_uuid = ? AND substr(key, 1, ?) = ?
+                AND (key > ? OR (key = ? AND ? IS NOT NULL AND upload_id > ?))
+            ORDER BY key, upload_id
+            LIMIT ?;",
+        )
+        .bind(bucket_uuid)
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .bind(key_marker)
+        .bind(key_marker)
+        .bind(upload_id_marker)
+        .bind(upload_id_marker)
+        .bind((limit + 1) as i64)
+        .fetch_all(db)
+        .await?;
+
+        let is_truncated = uploads.len() as u64 > limit;
+        uploads.truncate(limit as usize);
+
+        Ok(MultipartUploadListing {
+            uploads,
+            is_truncated,
+        })
+    }
+
+    /// Lists the uploads to any bucket which were initiated before `cutoff`
+    pub async fn find_initiated_before(
+        db: &sqlx::SqlitePool,
+        cutoff: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM multipart_uploads WHERE initiated_at < ?;")
+            .bind(cutoff)
+            .fetch_all(db)
+            .await
+    }
+
+    pub fn upload_id(&self) -> Uuid {
+        self.upload_id
+    }
+
+    pub fn bucket_uuid(&self) -> Uuid {
+        self.bucket_uuid
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn initiated_at(&self) -> DateTime<Utc> {
+        self.initiated_at
+    }
+
+    /// Whether the upload has been in progress for longer than `ttl`, after
+    /// which it is aborted by a background task
+    pub fn is_stale(&self, ttl: std::time::Duration) -> bool {
+        chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| self.initiated_at.checked_add_signed(ttl))
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// Joins the given parts, in order, into an object stored under this
+    /// upload's key, and removes the upload.
+    ///
+    /// The object's ETag is the MD5 of the concatenated part MD5s followed by
+    /// the number of parts, like S3.
+    pub async fn complete(
+        self,
+        db: &sqlx::SqlitePool,
+        data_directory: &Path,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        parts: &[MultipartPart],
+    ) -> Result<Object, ObjectError> {
+        let part_files = parts
+            .iter()
+            .map(|part| {
+                self.storage_path(data_directory)
+                    .join(MultipartPart::file_name(part.part_number))
+            })
+            .collect::<Vec<_>>();
+
+        let (file, hash) = concatenate_parts(staging_directory(data_directory), part_files).await?;
+        if Object::find_by_hash(db, bucket, &hash).await?.is_none() {
+            storage
+                .put_file(bucket.uuid(), &hash, &self.key, file)
+                .await?;
+        }
+
+        let mut part_md5s = Vec::with_capacity(parts.len() * 16);
+        for part in parts {
+            // Part ETags are always written by `MultipartPart::new`
+            part_md5s.extend(hex::decode(part.etag.trim_matches('"')).unwrap_or_default());
+        }
+        let etag = format!(
+            "\"{}-{}\"",
+            hex::encode(Md5::digest(&part_md5s)),
+            parts.len()
+        );
+
+        let object = Object::insert(
+            db,
+            storage,
+            bucket,
+            StoredContents {
+                path: &self.key,
+                hash: &hash,
+                etag: &etag,
+                size: parts.iter().map(|part| part.size).sum(),
+                attributes: ObjectAttributes {
+                    content_type: self.content_type.clone(),
+                    ..Default::default()
+                },
+            },
+        )
+        .await?;
+
+        self.abort(db, data_directory).await?;
+
+        Ok(object)
+    }
+
+    /// Removes this upload along with all of its uploaded parts
+    pub async fn abort(self, db: &sqlx::SqlitePool, data_directory: &Path) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM multipart_uploads WHERE upload_id = ?;")
+            .bind(self.upload_id)
+            .execute(db)
+            .await?;
+
+        self.remove_parts(data_directory).await;
+
+        Ok(())
+    }
+
+    /// Removes the directory holding the uploaded parts, logging rather than
+    /// failing if it can't be removed
+    pub async fn remove_parts(&self, data_directory: &Path) {
+        let storage_path = self.storage_path(data_directory);
+        if let Err(e) = tokio::fs::remove_dir_all(&storage_path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::error!(
+                "Failed to remove parts of multipart upload {} at {}: {}",
+                self.upload_id,
+                storage_path.display(),
+                e
+            );
+        }
+    }
+
+    /// Directory where the parts of this upload are stored until it is
+    /// completed or aborted
+    pub fn storage_path(&self, data_directory: &Path) -> PathBuf {
+        data_directory.join(self.upload_id.to_string())
+    }
+}
+
+/// A single uploaded part of a [`MultipartUpload`]
+#[derive(Debug, Clone, FromRow)]
+pub struct MultipartPart {
+    #[sqlx(try_from = "i64")]
+    part_number: u16,
+    etag: Box<str>,
+    #[sqlx(try_from = "i64")]
+    size: u64,
+    last_modified: DateTime<Utc>,
+}
+
+impl MultipartPart {
+    /// Stores `body` as the given part of an upload, replacing any part
+    /// previously uploaded with the same number.
+    ///
+    /// The ETag is the quoted MD5 of the body, as S3 clients expect.
+    pub async fn new(
+        db: &sqlx::SqlitePool,
+        data_directory: &Path,
+        upload: &MultipartUpload,
+        part_number: u16,
+        body: &[u8],
+    ) -> Result<Self, ObjectError> {
+        let etag = format!("\"{}\"", hex::encode(Md5::digest(body)));
+
+        write_atomic(
+            upload.storage_path(data_directory),
+            Self::file_name(part_number),
+            body.to_vec(),
+        )
+        .await?;
+
+        let part = sqlx::query_as(
+            "INSERT INTO multipart_parts (upload_id, part_number, etag, size, last_modified)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (upload_id, part_number) DO UPDATE SET
+                etag = excluded.etag,
+                size = excluded.size,
+                last_modified = excluded.last_modified
+            RETURNING *;",
+        )
+        .bind(upload.upload_id)
+        .bind(part_number)
+        .bind(etag)
+        .bind(body.len() as i64)
+        .bind(Utc::now())
+        .fetch_one(db)
+        .await?;
+
+        Ok(part)
+    }
+
+    /// Lists the uploaded parts of an upload, ordered by part number
+    pub async fn find_all_in_upload(
+        db: &sqlx::SqlitePool,
+        upload_id: Uuid,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as("SELECT * FROM multipart_parts WHERE upload_id = ? ORDER BY part_number;")
+            .bind(upload_id)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Lists a page of the uploaded parts of an upload, ordered by part
+    /// number and starting after `part_number_marker`
+    pub async fn find_page_in_upload(
+        db: &sqlx::SqlitePool,
+        upload_id: Uuid,
+        part_number_marker: u16,
+        limit: u64,
+    ) -> sqlx::Result<MultipartPartListing> {
+        let mut parts: Vec<Self> = sqlx::query_as(
+            "SELECT * FROM multipart_parts WHERE upload_id = ? AND part_number > ?
+            ORDER BY part_number
+            LIMIT ?;",
+        )
+        .bind(upload_id)
+        .bind(part_number_marker)
+        .bind((limit + 1) as i64)
+        .fetch_all(db)
+        .await?;
+
+        let is_truncated = parts.len() as u64 > limit;
+        parts.truncate(limit as usize);
+
+        Ok(MultipartPartListing {
+            parts,
+            is_truncated,
+        })
+    }
+
+    pub fn part_number(&self) -> u16 {
+        self.part_number
+    }
+
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        self.last_modified
+    }
+
+    /// Name of the file a part is stored in within its upload's directory
+    fn file_name(part_number: u16) -> String {
+        format!("part_{}", part_number)
+    }
+}
+
+/// Writes the part files one after another into a new temporary file in
+/// `directory`. Returns the file along with the SHA-256 of its contents.
+async fn concatenate_parts(
+    directory: PathBuf,
+    part_files: Vec<PathBuf>,
+) -> std::io::Result<(TempPath, String)> {
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&directory)?;
+
+        let mut file = tempfile::NamedTempFile::new_in(&directory)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+
+        for part_file in part_files {
+            let mut part = std::fs::File::open(part_file)?;
+
+            loop {
+                let read = part.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+
+                hasher.update(&buffer[..read]);
+                file.write_all(&buffer[..read])?;
+            }
+        }
+
+        Ok((file.into_temp_path(), hex::encode(hasher.finalize())))
+    })
+    .await?
+}
@@ -1,19 +1,1584 @@
-use std::collections::BTreeSet;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
+use base64::{Engine, prelude::BASE64_STANDARD};
 use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
 use mime::Mime;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{FromRow, Row, sqlite::SqliteRow, types::Json};
+use tempfile::TempPath;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::InspectReader;
 use uuid::Uuid;
 
-use super::CachePolicy;
+use super::{
+    CachePolicy, begin_write,
+    bucket::Bucket,
+    tag::{self, TagError},
+};
+use crate::storage::StorageBackend;
 
+/// Most tags which can be attached to a single object, matching S3
+pub const MAX_OBJECT_TAGS: usize = 10;
+
+/// Prefix of the keys given to objects recovered by [`Object::recover`]
+pub const RECOVERED_KEY_PREFIX: &str = "recovered/";
+
+/// Validates tags to attach to an object. Tags are stored as `key=value`
+/// strings, so keys must not contain `=`
+pub fn validate_object_tags(
+    tags: impl IntoIterator<Item = (String, String)>,
+) -> Result<BTreeMap<String, String>, TagError> {
+    let tags = tag::validate_tags(tags, MAX_OBJECT_TAGS)?;
+
+    if let Some(key) = tags.keys().find(|key| key.contains('=')) {
+        return Err(TagError::KeyCharacter(key.clone()));
+    }
+
+    Ok(tags)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ObjectError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Storage error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Object exceeds the maximum size of {max_size} bytes")]
+    TooLarge { max_size: u64 },
+    #[error("The body does not match the signed `x-amz-content-sha256` header")]
+    ContentSha256Mismatch,
+}
+
+/// Normalizes an object key sent by a client so that equivalent keys are
+/// stored the same way, by removing leading slashes and collapsing repeated
+/// ones. Keys which would be empty or contain `..` segments are rejected.
+pub fn normalize_key(key: &str) -> Result<String, String> {
+    let mut normalized = String::with_capacity(key.len());
+    for segment in key.split('/') {
+        if segment == ".." {
+            return Err("Object keys must not contain `..` segments".to_owned());
+        }
+
+        if !segment.is_empty() {
+            if !normalized.is_empty() {
+                normalized.push('/');
+            }
+            normalized.push_str(segment);
+        }
+    }
+
+    if normalized.is_empty() {
+        return Err("Object keys must not be empty".to_owned());
+    }
+
+    // Keys ending in a slash are kept as they are, since clients use them to
+    // represent folders
+    if key.ends_with('/') {
+        normalized.push('/');
+    }
+
+    Ok(normalized)
+}
+
+/// Most objects which can be deleted by a single batch delete, matching S3's
+/// `DeleteObjects`
+pub const MAX_BATCH_DELETE_KEYS: usize = 1000;
+
+#[derive(Debug, Clone)]
 pub struct Object {
     bucket: Uuid,
+    /// Nil for the null version, which is stored when versioning isn't enabled
+    version_id: Uuid,
+    /// Whether this is the current version of the object
+    is_latest: bool,
+    /// Whether this version marks the object as deleted, in which case it has
+    /// no contents
+    is_delete_marker: bool,
     hash: Box<str>,
+    /// Quoted entity tag sent in the `ETag` header
+    etag: Box<str>,
     path: Box<str>,
+    size: u64,
+    /// Number of object versions in the bucket sharing these stored contents
+    ref_count: u64,
     expires_at: Option<DateTime<Utc>>,
     content_type: Option<Mime>,
-    cache_policy: CachePolicy,
+    /// Encoding the contents were uploaded with, such as `gzip`, which is
+    /// served as-is rather than decoded
+    content_encoding: Option<Box<str>>,
+    cache_policy: Option<CachePolicy>,
     tags: BTreeSet<Box<str>>,
+    /// Custom metadata sent as `x-amz-meta-*` headers, keyed by lowercase
+    /// name without the prefix
+    metadata: BTreeMap<Box<str>, Box<str>>,
+    public_read: bool,
+    checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Base64 encoded checksum the contents were verified against on upload
+    checksum_value: Option<Box<str>>,
+    created_at: DateTime<Utc>,
+    /// When the object was soft deleted, after which it is hidden until it is
+    /// either restored or purged
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A page of objects in a bucket, with keys sharing a prefix up to the
+/// delimiter rolled up into `common_prefixes`
+#[derive(Debug, Clone, Default)]
+pub struct ObjectListing {
+    pub objects: Vec<Object>,
+    pub common_prefixes: Vec<String>,
+    /// Whether there are more entries after this page
+    pub is_truncated: bool,
+}
+
+/// Outcome of recovering stored contents with [`Object::recover`]
+#[derive(Debug)]
+pub enum Recovery {
+    Recovered(Object),
+    /// An object already stores the contents
+    AlreadyIndexed(Object),
+    /// The contents no longer match the hash they are stored under
+    Corrupt {
+        actual_hash: String,
+    },
+}
+
+/// Attributes of an object which are chosen by the uploader and sent back
+/// with its contents
+#[derive(Debug, Clone, Default)]
+pub struct ObjectAttributes {
+    pub content_type: Option<Mime>,
+    pub content_encoding: Option<Box<str>>,
+    pub metadata: BTreeMap<Box<str>, Box<str>>,
+    /// Whether the object can be read without an access token, set with the
+    /// `public-read` canned ACL
+    pub public_read: bool,
+    /// Algorithm of the checksum the contents were verified against
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Base64 encoded checksum of the contents
+    pub checksum_value: Option<Box<str>>,
+}
+
+/// Algorithms a client can send an `x-amz-checksum-*` header for when
+/// uploading an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, Serialize, Deserialize, sqlx::Type)]
+#[strum(serialize_all = "UPPERCASE")]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(rename_all = "UPPERCASE")]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Sha1,
+    Sha256,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [Self; 4] = [Self::Crc32, Self::Sha1, Self::Sha256, Self::Md5];
+
+    /// Header the checksum is sent and returned in
+    pub fn header_name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "x-amz-checksum-crc32",
+            Self::Sha1 => "x-amz-checksum-sha1",
+            Self::Sha256 => "x-amz-checksum-sha256",
+            Self::Md5 => "x-amz-checksum-md5",
+        }
+    }
+}
+
+/// Contents streamed into a temporary file in the staging directory, which are
+/// handed over to the storage backend once an object is created for them. The
+/// temporary file is removed if the upload is dropped.
+#[derive(Debug)]
+pub struct Upload {
+    file: TempPath,
+    /// Hex encoded SHA-256 of the contents
+    sha256: String,
+    md5: [u8; 16],
+    size: u64,
+}
+
+impl Upload {
+    /// Streams `contents` into a file in `staging_directory`, hashing them as
+    /// they are written. Fails with [`ObjectError::TooLarge`] as soon as more
+    /// than `max_size` bytes have been read.
+    pub async fn receive(
+        staging_directory: &Path,
+        contents: impl AsyncRead + Unpin,
+        max_size: Option<u64>,
+    ) -> Result<Self, ObjectError> {
+        tokio::fs::create_dir_all(staging_directory).await?;
+
+        let (file, path) = tempfile::NamedTempFile::new_in(staging_directory)?.into_parts();
+        let mut file = tokio::fs::File::from_std(file);
+
+        let mut sha256 = Sha256::new();
+        let mut md5 = Md5::new();
+
+        // Reading one byte past the limit is enough to know it was exceeded
+        let limit = max_size.map_or(u64::MAX, |max_size| max_size.saturating_add(1));
+        let mut reader = InspectReader::new(contents.take(limit), |chunk: &[u8]| {
+            sha256.update(chunk);
+            md5.update(chunk);
+        });
+
+        let size = tokio::io::copy(&mut reader, &mut file).await?;
+        file.flush().await?;
+        drop(reader);
+
+        if let Some(max_size) = max_size
+            && size > max_size
+        {
+            return Err(ObjectError::TooLarge { max_size });
+        }
+
+        Ok(Self {
+            file: path,
+            sha256: hex::encode(sha256.finalize()),
+            md5: md5.finalize().into(),
+            size,
+        })
+    }
+
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    pub fn md5(&self) -> &[u8; 16] {
+        &self.md5
+    }
+
+    /// Base64 encoded checksum of the contents. The SHA-256 and MD5 digests
+    /// are computed while receiving, while the others read the contents back.
+    pub async fn checksum(&self, algorithm: ChecksumAlgorithm) -> std::io::Result<String> {
+        let digest = match algorithm {
+            ChecksumAlgorithm::Sha256 => {
+                hex::decode(&self.sha256).expect("SHA-256 digests are hex encoded")
+            }
+            ChecksumAlgorithm::Md5 => self.md5.to_vec(),
+            ChecksumAlgorithm::Sha1 => {
+                let mut sha1 = sha1::Sha1::new();
+                self.read_contents(|chunk| sha1.update(chunk)).await?;
+                sha1.finalize().to_vec()
+            }
+            ChecksumAlgorithm::Crc32 => {
+                let mut crc32 = crc32fast::Hasher::new();
+                self.read_contents(|chunk| crc32.update(chunk)).await?;
+                crc32.finalize().to_be_bytes().to_vec()
+            }
+        };
+
+        Ok(BASE64_STANDARD.encode(digest))
+    }
+
+    async fn read_contents(&self, mut f: impl FnMut(&[u8])) -> std::io::Result<()> {
+        let mut file = tokio::fs::File::open(&self.file).await?;
+        let mut buffer = vec![0; 64 * 1024];
+
+        loop {
+            match file.read(&mut buffer).await? {
+                0 => return Ok(()),
+                n => f(&buffer[..n]),
+            }
+        }
+    }
+}
+
+/// Contents already written to a bucket's storage which an object is being
+/// created for
+#[derive(Debug)]
+pub(super) struct StoredContents<'a> {
+    pub path: &'a str,
+    /// SHA-256 of the contents, which is also the name of the stored file
+    pub hash: &'a str,
+    pub etag: &'a str,
+    pub size: u64,
+    pub attributes: ObjectAttributes,
+}
+
+impl ObjectListing {
+    /// The key or common prefix of the last entry in this page, which the next
+    /// page starts after
+    pub fn last_entry(&self) -> Option<&str> {
+        let last_object = self.objects.last().map(Object::path);
+        let last_prefix = self.common_prefixes.last().map(String::as_str);
+
+        last_object.max(last_prefix)
+    }
+}
+
+impl FromRow<'_, SqliteRow> for Object {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        let content_type = row
+            .try_get::<Option<String>, _>("content_type")?
+            .map(|content_type| content_type.parse::<Mime>())
+            .transpose()
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "content_type".into(),
+                source: Box::new(e),
+            })?;
+
+        Ok(Self {
+            bucket: row.try_get("bucket")?,
+            version_id: row.try_get("version_id")?,
+            is_latest: row.try_get("is_latest")?,
+            is_delete_marker: row.try_get("is_delete_marker")?,
+            hash: row.try_get("hash")?,
+            etag: row.try_get("etag")?,
+            path: row.try_get("path")?,
+            size: row.try_get::<i64, _>("size")? as u64,
+            ref_count: row.try_get::<i64, _>("ref_count")? as u64,
+            expires_at: row.try_get("expires_at")?,
+            content_type,
+            content_encoding: row.try_get("content_encoding")?,
+            cache_policy: row.try_get("cache_policy")?,
+            tags: row.try_get::<Json<_>, _>("tags")?.0,
+            metadata: row.try_get::<Json<_>, _>("metadata")?.0,
+            public_read: row.try_get("public_read")?,
+            checksum_algorithm: row.try_get("checksum_algorithm")?,
+            checksum_value: row.try_get("checksum_value")?,
+            created_at: row.try_get("created_at")?,
+            deleted_at: row.try_get("deleted_at")?,
+        })
+    }
+}
+
+impl Object {
+    /// Stores uploaded contents in the bucket under `path`. With versioning
+    /// enabled this adds a new version of the object, otherwise it replaces
+    /// the null version.
+    ///
+    /// Contents are stored under their SHA-256 hash, and are only handed to
+    /// the storage backend if no other object in the bucket already stores
+    /// them. The ETag is the quoted MD5 of the contents, as S3 clients expect.
+    pub async fn new(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        path: &str,
+        attributes: ObjectAttributes,
+        upload: Upload,
+    ) -> Result<Self, ObjectError> {
+        let hash = upload.sha256;
+        let etag = format!("\"{}\"", hex::encode(upload.md5));
+
+        if Self::find_by_hash(db, bucket, &hash).await?.is_none() {
+            storage
+                .put_file(bucket.uuid(), &hash, path, upload.file)
+                .await?;
+        }
+
+        Self::insert(
+            db,
+            storage,
+            bucket,
+            StoredContents {
+                path,
+                hash: &hash,
+                etag: &etag,
+                size: upload.size,
+                attributes,
+            },
+        )
+        .await
+    }
+
+    /// Records contents found in the bucket's storage without an object, for
+    /// recovering from a lost database. The object is stored under the key
+    /// the storage backend named the contents after, unless another object
+    /// already has it. Otherwise the key is lost along with the database and
+    /// the object is stored under `recovered/{hash}`. Other attributes are
+    /// always lost.
+    ///
+    /// The contents are read back to check that they still match their hash,
+    /// and are left alone if any object already stores them.
+    pub async fn recover(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        hash: &str,
+    ) -> Result<Recovery, ObjectError> {
+        if let Some(object) = Self::find_by_hash(db, bucket, hash).await? {
+            return Ok(Recovery::AlreadyIndexed(object));
+        }
+
+        let mut contents = storage.get(bucket.uuid(), hash).await?;
+        let mut sha256 = Sha256::new();
+        let mut md5 = Md5::new();
+        let mut size = 0;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match contents.read(&mut buffer).await? {
+                0 => break,
+                n => {
+                    sha256.update(&buffer[..n]);
+                    md5.update(&buffer[..n]);
+                    size += n as u64;
+                }
+            }
+        }
+
+        let actual_hash = hex::encode(sha256.finalize());
+        if actual_hash != hash {
+            return Ok(Recovery::Corrupt { actual_hash });
+        }
+
+        let path = match storage.stored_key(bucket.uuid(), hash).await? {
+            Some(key) if !Self::path_exists(db, bucket, &key).await? => key,
+            _ => format!("{}{}", RECOVERED_KEY_PREFIX, hash),
+        };
+
+        let object = Self::insert(
+            db,
+            storage,
+            bucket,
+            StoredContents {
+                path: &path,
+                hash,
+                etag: &format!("\"{}\"", hex::encode(md5.finalize())),
+                size,
+                attributes: ObjectAttributes::default(),
+            },
+        )
+        .await?;
+
+        Ok(Recovery::Recovered(object))
+    }
+
+    /// Records contents which have already been written to the bucket's
+    /// storage as the latest version of the object at their path
+    pub(super) async fn insert(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        contents: StoredContents<'_>,
+    ) -> Result<Self, ObjectError> {
+        let mut tx = begin_write(db).await?;
+
+        let (object, unreferenced) = Self::insert_in(&mut tx, bucket, contents).await?;
+
+        tx.commit().await?;
+
+        if let Some(hash) = unreferenced {
+            remove_unreferenced(db, storage, bucket.uuid(), &hash).await?;
+        }
+
+        Ok(object)
+    }
+
+    /// Records contents as the latest version of the object at their path
+    /// within a transaction, returning the object along with the hash of any
+    /// contents it replaced which are no longer referenced once the
+    /// transaction is committed
+    pub(super) async fn insert_in(
+        tx: &mut sqlx::SqliteConnection,
+        bucket: &Bucket,
+        contents: StoredContents<'_>,
+    ) -> sqlx::Result<(Self, Option<String>)> {
+        let StoredContents {
+            path,
+            hash,
+            etag,
+            size,
+            attributes:
+                ObjectAttributes {
+                    content_type,
+                    content_encoding,
+                    metadata,
+                    public_read,
+                    checksum_algorithm,
+                    checksum_value,
+                },
+        } = contents;
+
+        let table = Self::table_name(bucket.uuid());
+        let version_id = Self::next_version_id(bucket);
+
+        Self::create_table(&mut *tx, bucket.uuid()).await?;
+
+        let was_visible = Self::latest_is_visible(tx, bucket.uuid(), path).await?;
+
+        // Only the null version is ever replaced, versions with an ID are kept
+        let replaced: Option<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT hash, size FROM {} WHERE path = ? AND version_id = ? AND NOT is_delete_marker;",
+            table
+        ))
+        .bind(path)
+        .bind(version_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        Self::clear_latest(tx, bucket.uuid(), path).await?;
+
+        let object: Object = sqlx::query_as(&format!(
+            "INSERT INTO {} (path, version_id, is_latest, is_delete_marker, hash, etag, size, content_type, content_encoding, metadata, public_read, checksum_algorithm, checksum_value, created_at)
+            VALUES (?, ?, TRUE, FALSE, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (path, version_id) DO UPDATE SET
+                is_latest = TRUE,
+                is_delete_marker = FALSE,
+                hash = excluded.hash,
+                etag = excluded.etag,
+                size = excluded.size,
+                content_type = excluded.content_type,
+                content_encoding = excluded.content_encoding,
+                metadata = excluded.metadata,
+                public_read = excluded.public_read,
+                checksum_algorithm = excluded.checksum_algorithm,
+                checksum_value = excluded.checksum_value,
+                tags = excluded.tags,
+                created_at = excluded.created_at,
+                deleted_at = NULL
+            RETURNING ? AS bucket, *;",
+            table
+        ))
+        .bind(path)
+        .bind(version_id)
+        .bind(hash)
+        .bind(etag)
+        .bind(size as i64)
+        .bind(content_type.as_ref().map(ToString::to_string))
+        .bind(content_encoding)
+        .bind(Json(metadata))
+        .bind(public_read)
+        .bind(checksum_algorithm)
+        .bind(checksum_value)
+        .bind(Utc::now())
+        .bind(bucket.uuid())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let ref_count = Self::update_ref_count(tx, bucket.uuid(), hash).await?;
+        let object = Object {
+            ref_count,
+            ..object
+        };
+
+        Bucket::adjust_counters(
+            &mut *tx,
+            bucket.uuid(),
+            if was_visible { 0 } else { 1 },
+            object.size as i64 - replaced.as_ref().map_or(0, |(_, size)| *size),
+        )
+        .await?;
+
+        let unreferenced = match replaced {
+            Some((replaced_hash, _)) if replaced_hash != hash => {
+                match Self::update_ref_count(tx, bucket.uuid(), &replaced_hash).await? {
+                    0 => Some(replaced_hash),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        Ok((object, unreferenced))
+    }
+
+    /// Copies this object to `path` in `bucket` with the given attributes,
+    /// replacing any existing object with that path. Within a bucket, the
+    /// stored contents are shared with the copy rather than duplicated.
+    pub async fn copy(
+        &self,
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        path: &str,
+        attributes: ObjectAttributes,
+    ) -> Result<Self, ObjectError> {
+        if bucket.uuid() != self.bucket {
+            storage
+                .copy(self.bucket, bucket.uuid(), &self.hash, path)
+                .await?;
+        }
+
+        Self::insert(
+            db,
+            storage,
+            bucket,
+            StoredContents {
+                path,
+                hash: &self.hash,
+                etag: &self.etag,
+                size: self.size,
+                attributes,
+            },
+        )
+        .await
+    }
+
+    /// Permanently deletes this version of the object, removing its stored
+    /// contents once no other version in the bucket shares them. When this was
+    /// the latest version, the next most recent one takes its place.
+    pub async fn delete(
+        self,
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+    ) -> Result<(), ObjectError> {
+        let mut tx = begin_write(db).await?;
+
+        let table = Self::table_name(self.bucket);
+
+        let deleted: Option<(i64, bool, bool)> = sqlx::query_as(&format!(
+            "DELETE FROM {} WHERE path = ? AND version_id = ?
+            RETURNING size, is_latest, deleted_at IS NOT NULL;",
+            table
+        ))
+        .bind(&*self.path)
+        .bind(self.version_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Already deleted by a concurrent request
+        let Some((size, was_latest, was_soft_deleted)) = deleted else {
+            return Ok(());
+        };
+
+        let mut object_count = 0;
+        if was_latest && was_soft_deleted {
+            // Purging a soft deleted object mustn't bring back older versions
+            let has_versions: bool = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) > 0 FROM {} WHERE path = ?;",
+                table
+            ))
+            .bind(&*self.path)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if has_versions {
+                Self::insert_delete_marker(&mut tx, self.bucket, &self.path, self.version_id)
+                    .await?;
+            }
+        } else if was_latest {
+            if !self.is_delete_marker {
+                object_count -= 1;
+            }
+            if Self::promote_latest(&mut tx, self.bucket, &self.path).await? {
+                object_count += 1;
+            }
+        }
+
+        Bucket::adjust_counters(&mut *tx, self.bucket, object_count, -size).await?;
+
+        if self.is_delete_marker {
+            tx.commit().await?;
+
+            return Ok(());
+        }
+
+        let ref_count = Self::update_ref_count(&mut tx, self.bucket, &self.hash).await?;
+
+        tx.commit().await?;
+
+        if ref_count == 0 {
+            remove_unreferenced(db, storage, self.bucket, &self.hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the object at `path` like an S3 `DeleteObject` without a
+    /// version ID.
+    ///
+    /// The null version is removed unless versioning is enabled. Any versions
+    /// which remain are hidden behind a new delete marker, which is returned.
+    ///
+    /// When the bucket has soft deletion enabled, the latest version is instead
+    /// hidden until it is restored or purged, keeping its contents.
+    pub async fn delete_current(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        path: &str,
+    ) -> Result<Option<Self>, ObjectError> {
+        let mut tx = begin_write(db).await?;
+
+        let (delete_marker, unreferenced) = Self::delete_current_in(&mut tx, bucket, path).await?;
+
+        tx.commit().await?;
+
+        if let Some(hash) = unreferenced {
+            remove_unreferenced(db, storage, bucket.uuid(), &hash).await?;
+        }
+
+        Ok(delete_marker)
+    }
+
+    /// Deletes the objects at each of `paths` like [`Object::delete_current`],
+    /// all in a single transaction. Returns the delete marker created for each
+    /// path, in order.
+    pub async fn delete_many(
+        db: &sqlx::SqlitePool,
+        storage: &dyn StorageBackend,
+        bucket: &Bucket,
+        paths: &[&str],
+    ) -> Result<Vec<Option<Self>>, ObjectError> {
+        let mut tx = begin_write(db).await?;
+
+        let mut delete_markers = Vec::with_capacity(paths.len());
+        let mut unreferenced = BTreeSet::new();
+        for path in paths {
+            let (delete_marker, hash) = Self::delete_current_in(&mut tx, bucket, path).await?;
+
+            delete_markers.push(delete_marker);
+            unreferenced.extend(hash);
+        }
+
+        tx.commit().await?;
+
+        for hash in unreferenced {
+            remove_unreferenced(db, storage, bucket.uuid(), &hash).await?;
+        }
+
+        Ok(delete_markers)
+    }
+
+    /// Deletes the current version at `path` within a transaction, returning
+    /// the delete marker created along with the hash of any contents which
+    /// are no longer referenced once the transaction is committed
+    pub(super) async fn delete_current_in(
+        tx: &mut sqlx::SqliteConnection,
+        bucket: &Bucket,
+        path: &str,
+    ) -> sqlx::Result<(Option<Self>, Option<String>)> {
+        let table = Self::table_name(bucket.uuid());
+
+        if bucket.settings().soft_delete_enabled {
+            let soft_deleted = sqlx::query(&format!(
+                "UPDATE {} SET deleted_at = ?
+                WHERE path = ? AND is_latest AND NOT is_delete_marker AND deleted_at IS NULL;",
+                table
+            ))
+            .bind(Utc::now())
+            .bind(path)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            if soft_deleted > 0 {
+                Bucket::adjust_counters(&mut *tx, bucket.uuid(), -1, 0).await?;
+            }
+
+            return Ok((None, None));
+        }
+
+        let was_visible = Self::latest_is_visible(tx, bucket.uuid(), path).await?;
+
+        let removed: Option<(String, i64)> = match bucket.settings().versioning_status.is_enabled()
+        {
+            true => None,
+            false => {
+                sqlx::query_as(&format!(
+                    "DELETE FROM {} WHERE path = ? AND version_id = ? AND NOT is_delete_marker
+                    RETURNING hash, size;",
+                    table
+                ))
+                .bind(path)
+                .bind(Uuid::nil())
+                .fetch_optional(&mut *tx)
+                .await?
+            }
+        };
+
+        let has_versions: bool = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) > 0 FROM {} WHERE path = ?;",
+            table
+        ))
+        .bind(path)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let delete_marker = match was_visible && has_versions {
+            true => Some(
+                Self::insert_delete_marker(tx, bucket.uuid(), path, Self::next_version_id(bucket))
+                    .await?,
+            ),
+            false => None,
+        };
+
+        Bucket::adjust_counters(
+            &mut *tx,
+            bucket.uuid(),
+            if was_visible { -1 } else { 0 },
+            -removed.as_ref().map_or(0, |(_, size)| *size),
+        )
+        .await?;
+
+        let unreferenced = match removed {
+            Some((hash, _)) => match Self::update_ref_count(tx, bucket.uuid(), &hash).await? {
+                0 => Some(hash),
+                _ => None,
+            },
+            None => None,
+        };
+
+        Ok((delete_marker, unreferenced))
+    }
+
+    /// ID for a new version in the bucket, which is the null version unless
+    /// versioning is enabled
+    fn next_version_id(bucket: &Bucket) -> Uuid {
+        match bucket.settings().versioning_status.is_enabled() {
+            true => Uuid::new_v4(),
+            false => Uuid::nil(),
+        }
+    }
+
+    /// Restores the soft deleted object at `path`, returning it unless there
+    /// was no soft deleted object to restore
+    pub async fn restore(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+        path: &str,
+    ) -> sqlx::Result<Option<Self>> {
+        let mut tx = begin_write(db).await?;
+
+        let restored: Option<Object> = sqlx::query_as(&format!(
+            "UPDATE {} SET deleted_at = NULL
+            WHERE path = ? AND is_latest AND deleted_at IS NOT NULL
+            RETURNING ? AS bucket, *;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(path)
+        .bind(bucket_uuid)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if restored.is_some() {
+            Bucket::adjust_counters(&mut *tx, bucket_uuid, 1, 0).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(restored)
+    }
+
+    /// Replaces the tags on this version of the object. An empty map removes
+    /// all tags
+    pub async fn set_tags(
+        &mut self,
+        db: &sqlx::SqlitePool,
+        tags: &BTreeMap<String, String>,
+    ) -> sqlx::Result<()> {
+        let tags: BTreeSet<Box<str>> = tags
+            .iter()
+            .map(|(key, value)| format!("{key}={value}").into())
+            .collect();
+
+        sqlx::query(&format!(
+            "UPDATE {} SET tags = ? WHERE path = ? AND version_id = ?;",
+            Self::table_name(self.bucket)
+        ))
+        .bind(Json(&tags))
+        .bind(&*self.path)
+        .bind(self.version_id)
+        .execute(db)
+        .await?;
+
+        self.tags = tags;
+
+        Ok(())
+    }
+
+    /// Whether the latest version at `path` exists and isn't a delete marker
+    /// or soft deleted
+    async fn latest_is_visible(
+        tx: &mut sqlx::SqliteConnection,
+        bucket_uuid: Uuid,
+        path: &str,
+    ) -> sqlx::Result<bool> {
+        sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) > 0 FROM {}
+            WHERE path = ? AND is_latest AND NOT is_delete_marker AND deleted_at IS NULL;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(path)
+        .fetch_one(&mut *tx)
+        .await
+    }
+
+    /// Marks the latest version at `path` as no longer being the latest
+    async fn clear_latest(
+        tx: &mut sqlx::SqliteConnection,
+        bucket_uuid: Uuid,
+        path: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {} SET is_latest = FALSE WHERE path = ? AND is_latest;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(path)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Makes the most recent version at `path` the latest, returning whether
+    /// it is an object rather than a delete marker
+    async fn promote_latest(
+        tx: &mut sqlx::SqliteConnection,
+        bucket_uuid: Uuid,
+        path: &str,
+    ) -> sqlx::Result<bool> {
+        let table = Self::table_name(bucket_uuid);
+
+        let is_delete_marker: Option<bool> = sqlx::query_scalar(&format!(
+            "UPDATE {0} SET is_latest = TRUE WHERE rowid = (
+                SELECT rowid FROM {0} WHERE path = ? ORDER BY created_at DESC, rowid DESC LIMIT 1
+            ) RETURNING is_delete_marker;",
+            table
+        ))
+        .bind(path)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        Ok(is_delete_marker == Some(false))
+    }
+
+    /// Adds a delete marker as the latest version at `path`
+    async fn insert_delete_marker(
+        tx: &mut sqlx::SqliteConnection,
+        bucket_uuid: Uuid,
+        path: &str,
+        version_id: Uuid,
+    ) -> sqlx::Result<Self> {
+        Self::clear_latest(tx, bucket_uuid, path).await?;
+
+        sqlx::query_as(&format!(
+            "INSERT INTO {} (path, version_id, is_latest, is_delete_marker, hash, etag, size, ref_count, created_at)
+            VALUES (?, ?, TRUE, TRUE, '', '', 0, 0, ?)
+            ON CONFLICT (path, version_id) DO UPDATE SET
+                is_latest = TRUE,
+                is_delete_marker = TRUE,
+                created_at = excluded.created_at
+            RETURNING ? AS bucket, *;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(path)
+        .bind(version_id)
+        .bind(Utc::now())
+        .bind(bucket_uuid)
+        .fetch_one(&mut *tx)
+        .await
+    }
+
+    /// Recounts the object versions in a bucket sharing the stored contents
+    /// for `hash`, updating their `ref_count` and returning it
+    async fn update_ref_count(
+        tx: &mut sqlx::SqliteConnection,
+        bucket_uuid: Uuid,
+        hash: &str,
+    ) -> sqlx::Result<u64> {
+        let ref_count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE hash = ?;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(hash)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE {} SET ref_count = ? WHERE hash = ?;",
+            Self::table_name(bucket_uuid)
+        ))
+        .bind(ref_count)
+        .bind(hash)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(ref_count as u64)
+    }
+
+    /// Finds the latest version of the object stored under `path` in the
+    /// given bucket, unless it has been deleted
+    pub async fn find_by_path<'c, E>(
+        executor: E,
+        bucket: &Bucket,
+        path: &str,
+    ) -> sqlx::Result<Option<Self>>
+    where
+        E: sqlx::SqliteExecutor<'c>,
+    {
+        sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE path = ? AND is_latest AND NOT is_delete_marker AND deleted_at IS NULL;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(path)
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Whether any version of an object is stored under `path`, including
+    /// delete markers and deleted objects
+    async fn path_exists(db: &sqlx::SqlitePool, bucket: &Bucket, path: &str) -> sqlx::Result<bool> {
+        sqlx::query_scalar(&format!(
+            "SELECT EXISTS(SELECT 1 FROM {} WHERE path = ?);",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(path)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Finds a specific version of the object under `path`, which may be a
+    /// delete marker
+    pub async fn find_version(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        path: &str,
+        version_id: Uuid,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {} WHERE path = ? AND version_id = ?;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(path)
+        .bind(version_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Finds any object in the given bucket whose contents have the SHA-256
+    /// `hash`, meaning they are already stored
+    pub async fn find_by_hash(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        hash: &str,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {} WHERE hash = ? AND NOT is_delete_marker LIMIT 1;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(hash)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Lists the objects in a bucket whose keys start with `prefix`, ordered
+    /// by key.
+    ///
+    /// When a `delimiter` is given, keys containing it after the prefix are
+    /// grouped into a single common prefix, like directories. Objects and
+    /// common prefixes are paginated together in key order, starting with the
+    /// first entry after `start_after` (a key or common prefix).
+    pub async fn find_all_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        prefix: Option<&str>,
+        delimiter: Option<char>,
+        start_after: Option<&str>,
+        offset: u64,
+        limit: u64,
+    ) -> sqlx::Result<ObjectListing> {
+        let prefix = prefix.unwrap_or_default();
+
+        let objects: Vec<Object> = sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE substr(path, 1, ?) = ? AND path > ? AND is_latest AND NOT is_delete_marker
+                AND deleted_at IS NULL
+            ORDER BY path;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .bind(start_after.unwrap_or_default())
+        .fetch_all(db)
+        .await?;
+
+        let mut listing = ObjectListing::default();
+        let mut last_prefix = None;
+        let mut position = 0;
+
+        for object in objects {
+            let common_prefix = delimiter.and_then(|delimiter| {
+                let rest = &object.path[prefix.len()..];
+
+                rest.find(delimiter)
+                    .map(|i| format!("{}{}", prefix, &rest[..i + delimiter.len_utf8()]))
+            });
+
+            // Keys under the common prefix a previous page ended on were
+            // already covered by it
+            if let (Some(common_prefix), Some(start_after)) = (&common_prefix, start_after)
+                && common_prefix.as_str() <= start_after
+            {
+                continue;
+            }
+
+            // All objects under a common prefix make up a single entry
+            if common_prefix.is_some() {
+                if common_prefix == last_prefix {
+                    continue;
+                }
+                last_prefix.clone_from(&common_prefix);
+            }
+
+            position += 1;
+            if position <= offset {
+                continue;
+            }
+            if position > offset + limit {
+                listing.is_truncated = true;
+                break;
+            }
+
+            match common_prefix {
+                Some(common_prefix) => listing.common_prefixes.push(common_prefix),
+                None => listing.objects.push(object),
+            }
+        }
+
+        Ok(listing)
+    }
+
+    /// Lists every version of the objects in a bucket whose keys start with
+    /// `prefix`, including delete markers, ordered by key and then from newest
+    /// to oldest.
+    ///
+    /// Pages start after the version `version_id_marker` of `key_marker`, or
+    /// after all versions of `key_marker` when no version is given.
+    pub async fn find_versions_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        prefix: Option<&str>,
+        key_marker: Option<&str>,
+        version_id_marker: Option<Uuid>,
+        limit: u64,
+    ) -> sqlx::Result<ObjectListing> {
+        let prefix = prefix.unwrap_or_default();
+        let key_marker = key_marker.unwrap_or_default();
+
+        let versions: Vec<Object> = sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE substr(path, 1, ?) = ? AND path >= ?
+            ORDER BY path, created_at DESC, rowid DESC;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .bind(key_marker)
+        .fetch_all(db)
+        .await?;
+
+        let mut listing = ObjectListing::default();
+        let mut before_version_marker = true;
+
+        for version in versions {
+            if !key_marker.is_empty() && *version.path == *key_marker {
+                match version_id_marker {
+                    None => continue,
+                    Some(version_id_marker) if before_version_marker => {
+                        before_version_marker = version.version_id != version_id_marker;
+                        continue;
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if listing.objects.len() as u64 == limit {
+                listing.is_truncated = true;
+                break;
+            }
+
+            listing.objects.push(version);
+        }
+
+        Ok(listing)
+    }
+
+    /// Lists every version of the object at `path`, including delete markers,
+    /// from newest to oldest.
+    ///
+    /// Pages start after the version `before_version_id`, and `None` is
+    /// returned when that isn't a version of the object.
+    pub async fn find_versions_of_path(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        path: &str,
+        before_version_id: Option<Uuid>,
+        limit: u64,
+    ) -> sqlx::Result<Option<ObjectListing>> {
+        let table = Self::table_name(bucket.uuid());
+
+        // Versions are ordered by when they were created, and then by when
+        // their rows were inserted for versions created at the same time
+        let cursor: Option<(String, i64)> = match before_version_id {
+            Some(before_version_id) => {
+                let cursor = sqlx::query_as(&format!(
+                    "SELECT created_at, rowid FROM {} WHERE path = ? AND version_id = ?;",
+                    table
+                ))
+                .bind(path)
+                .bind(before_version_id)
+                .fetch_optional(db)
+                .await?;
+
+                match cursor {
+                    Some(cursor) => Some(cursor),
+                    None => return Ok(None),
+                }
+            }
+            None => None,
+        };
+        let (created_at, rowid) = cursor.unzip();
+
+        // One more version than requested is fetched to tell whether there are
+        // more pages
+        let mut objects: Vec<Object> = sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE path = ? AND (? IS NULL OR (created_at, rowid) < (?, ?))
+            ORDER BY created_at DESC, rowid DESC LIMIT ?;",
+            table
+        ))
+        .bind(bucket.uuid())
+        .bind(path)
+        .bind(&created_at)
+        .bind(&created_at)
+        .bind(rowid)
+        .bind(i64::try_from(limit.saturating_add(1)).unwrap_or(i64::MAX))
+        .fetch_all(db)
+        .await?;
+
+        let is_truncated = objects.len() as u64 > limit;
+        objects.truncate(limit as usize);
+
+        Ok(Some(ObjectListing {
+            objects,
+            common_prefixes: Vec::new(),
+            is_truncated,
+        }))
+    }
+
+    /// Lists the soft deleted objects in a bucket whose keys start with
+    /// `prefix`, ordered by key
+    pub async fn find_deleted_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        prefix: Option<&str>,
+        offset: u64,
+        limit: u64,
+    ) -> sqlx::Result<Vec<Self>> {
+        let prefix = prefix.unwrap_or_default();
+
+        sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE substr(path, 1, ?) = ? AND is_latest AND deleted_at IS NOT NULL
+            ORDER BY path LIMIT ? OFFSET ?;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .bind(limit as i64)
+        .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+        .fetch_all(db)
+        .await
+    }
+
+    /// Finds the objects in a bucket which were soft deleted before `cutoff`
+    pub async fn find_deleted_before(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        cutoff: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Self>> {
+        let deleted: Vec<Object> = sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {} WHERE deleted_at IS NOT NULL;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .fetch_all(db)
+        .await?;
+
+        Ok(deleted
+            .into_iter()
+            .filter(|object| {
+                object
+                    .deleted_at
+                    .is_some_and(|deleted_at| deleted_at < cutoff)
+            })
+            .collect())
+    }
+
+    /// Finds every version of the objects in a bucket whose keys start with
+    /// `prefix` which was created before `cutoff`, not including delete
+    /// markers
+    pub async fn find_created_before(
+        db: &sqlx::SqlitePool,
+        bucket: &Bucket,
+        prefix: &str,
+        cutoff: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Self>> {
+        let versions: Vec<Object> = sqlx::query_as(&format!(
+            "SELECT ? AS bucket, * FROM {}
+            WHERE substr(path, 1, ?) = ? AND NOT is_delete_marker
+            ORDER BY path, created_at;",
+            Self::table_name(bucket.uuid())
+        ))
+        .bind(bucket.uuid())
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .fetch_all(db)
+        .await?;
+
+        Ok(versions
+            .into_iter()
+            .filter(|version| version.created_at < cutoff)
+            .collect())
+    }
+
+    pub fn bucket(&self) -> Uuid {
+        self.bucket
+    }
+
+    pub fn version_id(&self) -> Uuid {
+        self.version_id
+    }
+
+    pub fn is_latest(&self) -> bool {
+        self.is_latest
+    }
+
+    pub fn is_delete_marker(&self) -> bool {
+        self.is_delete_marker
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Number of object versions in the bucket sharing this object's stored
+    /// contents, including itself
+    pub fn ref_count(&self) -> u64 {
+        self.ref_count
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at
+    }
+
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    pub fn content_encoding(&self) -> Option<&str> {
+        self.content_encoding.as_deref()
+    }
+
+    /// The attributes this object was uploaded with, as kept by a copy
+    pub fn attributes(&self) -> ObjectAttributes {
+        ObjectAttributes {
+            content_type: self.content_type.clone(),
+            content_encoding: self.content_encoding.clone(),
+            metadata: self.metadata.clone(),
+            public_read: self.public_read,
+            checksum_algorithm: self.checksum_algorithm,
+            checksum_value: self.checksum_value.clone(),
+        }
+    }
+
+    pub fn cache_policy(&self) -> Option<CachePolicy> {
+        self.cache_policy
+    }
+
+    pub fn tags(&self) -> &BTreeSet<Box<str>> {
+        &self.tags
+    }
+
+    /// The object's tags split into keys and values
+    pub fn tag_pairs(&self) -> BTreeMap<&str, &str> {
+        self.tags
+            .iter()
+            .map(|tag| tag.split_once('=').unwrap_or((tag, "")))
+            .collect()
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<Box<str>, Box<str>> {
+        &self.metadata
+    }
+
+    pub fn public_read(&self) -> bool {
+        self.public_read
+    }
+
+    pub fn checksum_algorithm(&self) -> Option<ChecksumAlgorithm> {
+        self.checksum_algorithm
+    }
+
+    pub fn checksum_value(&self) -> Option<&str> {
+        self.checksum_value.as_deref()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    /// Whether this object has an expiry time which has already passed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// Name of the objects table for the given bucket
+    pub fn table_name(bucket_uuid: Uuid) -> String {
+        format!("objects_{}", bucket_uuid.simple())
+    }
+
+    /// Creates the objects table for the given bucket if it doesn't already
+    /// exist. Each row is a version of an object, and at most one version of
+    /// each path is the latest.
+    pub async fn create_table<'c, E>(executor: E, bucket_uuid: Uuid) -> sqlx::Result<()>
+    where
+        E: sqlx::SqliteExecutor<'c>,
+    {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {0} (
+                path TEXT NOT NULL,
+                version_id TEXT NOT NULL,
+                is_latest BOOLEAN NOT NULL DEFAULT TRUE,
+                is_delete_marker BOOLEAN NOT NULL DEFAULT FALSE,
+                hash TEXT NOT NULL,
+                etag TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                ref_count INTEGER NOT NULL DEFAULT 1,
+                expires_at DATETIME,
+                content_type TEXT,
+                content_encoding TEXT,
+                cache_policy TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                metadata TEXT NOT NULL DEFAULT '{{}}',
+                public_read BOOLEAN NOT NULL DEFAULT FALSE,
+                checksum_algorithm TEXT,
+                checksum_value TEXT,
+                created_at DATETIME NOT NULL,
+                deleted_at DATETIME,
+
+                PRIMARY KEY (path, version_id)
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS {0}_latest ON {0} (path) WHERE is_latest;",
+            Self::table_name(bucket_uuid)
+        ))
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drops the objects table for the given bucket
+    pub async fn drop_table<'c, E>(executor: E, bucket_uuid: Uuid) -> sqlx::Result<()>
+    where
+        E: sqlx::SqliteExecutor<'c>,
+    {
+        sqlx::query(&format!(
+            "DROP TABLE IF EXISTS {};",
+            Self::table_name(bucket_uuid)
+        ))
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Number of object versions in a bucket, including delete markers
+    pub async fn count_in_bucket<'c, E>(executor: E, bucket_uuid: Uuid) -> sqlx::Result<u64>
+    where
+        E: sqlx::SqliteExecutor<'c>,
+    {
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {};",
+            Self::table_name(bucket_uuid)
+        ))
+        .fetch_one(executor)
+        .await?;
+
+        Ok(count as u64)
+    }
+
+    /// Total size in bytes of every object version in a bucket, computed from
+    /// the objects table rather than the bucket's counters
+    pub async fn total_size_in_bucket(
+        db: &sqlx::SqlitePool,
+        bucket_uuid: Uuid,
+    ) -> sqlx::Result<u64> {
+        let size: i64 = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(SUM(size), 0) FROM {};",
+            Self::table_name(bucket_uuid)
+        ))
+        .fetch_one(db)
+        .await?;
+
+        Ok(size as u64)
+    }
+}
+
+/// Writes `contents` to `directory/file_name` by writing to a temporary file
+/// in the same directory and renaming it into place, so readers never observe
+/// a partially written file
+pub(super) async fn write_atomic(
+    directory: PathBuf,
+    file_name: String,
+    contents: Vec<u8>,
+) -> std::io::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&directory)?;
+
+        let mut file = tempfile::NamedTempFile::new_in(&directory)?;
+        file.write_all(&contents)?;
+        file.persist(directory.join(file_name))?;
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Removes the stored contents for `hash` if no object in the bucket refers to
+/// it anymore
+pub(super) async fn remove_unreferenced(
+    db: &sqlx::SqlitePool,
+    storage: &dyn StorageBackend,
+    bucket_uuid: Uuid,
+    hash: &str,
+) -> Result<(), ObjectError> {
+    let references: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM {} WHERE hash = ?;",
+        Object::table_name(bucket_uuid)
+    ))
+    .bind(hash)
+    .fetch_one(db)
+    .await?;
+
+    if references == 0 {
+        storage.delete(bucket_uuid, hash).await?;
+    }
+
+    Ok(())
 }
 
 // objection.buckets
@@ -0,0 +1,58 @@
+//! Key-value tags which can be attached to objects and buckets
+
+use std::collections::BTreeMap;
+
+/// Longest tag key accepted, matching S3
+pub const MAX_TAG_KEY_LENGTH: usize = 128;
+
+/// Longest tag value accepted, matching S3
+pub const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+/// Reasons a set of tags is rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TagError {
+    #[error("At most {max} tags are allowed, got {count}")]
+    TooMany { max: usize, count: usize },
+    #[error("Tag keys must be between 1 and {MAX_TAG_KEY_LENGTH} characters long")]
+    KeyLength,
+    #[error("Tag values must not exceed {MAX_TAG_VALUE_LENGTH} characters")]
+    ValueLength,
+    #[error("Tag key `{0}` must not contain `=`")]
+    KeyCharacter(String),
+    #[error("Tag key `{0}` is used more than once")]
+    DuplicateKey(String),
+}
+
+/// Validates tags sent by a client, allowing at most `max_tags` of them, and
+/// collects them by key
+pub fn validate_tags(
+    tags: impl IntoIterator<Item = (String, String)>,
+    max_tags: usize,
+) -> Result<BTreeMap<String, String>, TagError> {
+    let mut validated = BTreeMap::new();
+
+    for (key, value) in tags {
+        if !(1..=MAX_TAG_KEY_LENGTH).contains(&key.chars().count()) {
+            return Err(TagError::KeyLength);
+        }
+
+        if value.chars().count() > MAX_TAG_VALUE_LENGTH {
+            return Err(TagError::ValueLength);
+        }
+
+        if validated.contains_key(&key) {
+            return Err(TagError::DuplicateKey(key));
+        }
+
+        validated.insert(key, value);
+    }
+
+    if validated.len() > max_tags {
+        return Err(TagError::TooMany {
+            max: max_tags,
+            count: validated.len(),
+        });
+    }
+
+    Ok(validated)
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::ApiError;
+use crate::{
+    AppState,
+    models::access_token::{AccessToken, AccessTokenPermission},
+};
+
+pub fn create_access_tokens_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_access_tokens).post(post_access_tokens))
+        .route(
+            "/{uuid}",
+            get(get_access_token)
+                .patch(patch_access_token)
+                .delete(delete_access_token),
+        )
+}
+
+/// An access token without any of its secrets
+#[derive(Debug, Serialize)]
+struct ClientAccessToken {
+    uuid: String,
+    description: String,
+    access_key_id: String,
+    permissions: BTreeSet<AccessTokenPermission>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<AccessToken> for ClientAccessToken {
+    fn from(value: AccessToken) -> Self {
+        ClientAccessToken {
+            uuid: value.uuid().to_string(),
+            description: value.description().to_string(),
+            access_key_id: value.access_key_id().to_string(),
+            permissions: value.permissions().clone(),
+            created_at: value.created_at(),
+            expires_at: value.expires_at(),
+        }
+    }
+}
+
+/// A newly created access token, which is the only time its secrets are
+/// returned
+#[derive(Debug, Serialize)]
+struct CreatedAccessToken {
+    #[serde(flatten)]
+    access_token: ClientAccessToken,
+    token: String,
+    secret_access_key: String,
+}
+
+async fn get_access_tokens(
+    State(db): State<sqlx::SqlitePool>,
+) -> Result<Json<Vec<ClientAccessToken>>, ApiError> {
+    Ok(Json(
+        AccessToken::find_all(&db)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateAccessToken {
+    #[serde(default)]
+    description: String,
+    /// Tokens have every permission unless they are scoped to fewer
+    #[serde(default = "all_permissions")]
+    permissions: BTreeSet<AccessTokenPermission>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+fn all_permissions() -> BTreeSet<AccessTokenPermission> {
+    AccessTokenPermission::ALL.into()
+}
+
+/// Rejects expiry times which have already passed
+fn validate_expiry(expires_at: Option<DateTime<Utc>>) -> Result<(), ApiError> {
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_EXPIRY",
+            "Access tokens must expire in the future",
+        ));
+    }
+
+    Ok(())
+}
+
+async fn post_access_tokens(
+    State(db): State<sqlx::SqlitePool>,
+    Json(body): Json<CreateAccessToken>,
+) -> Result<(StatusCode, Json<CreatedAccessToken>), ApiError> {
+    validate_expiry(body.expires_at)?;
+
+    if body.permissions.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_PERMISSIONS",
+            "Access tokens must have at least one permission",
+        ));
+    }
+
+    let (access_token, token) =
+        AccessToken::new(&db, &body.description, body.permissions, body.expires_at).await?;
+    let secret_access_key = access_token.secret_access_key().to_owned();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedAccessToken {
+            access_token: access_token.into(),
+            token,
+            secret_access_key,
+        }),
+    ))
+}
+
+async fn find_access_token(db: &sqlx::SqlitePool, uuid: Uuid) -> Result<AccessToken, ApiError> {
+    AccessToken::find_by_uuid(db, uuid).await?.ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "ACCESS_TOKEN_NOT_FOUND",
+            format!("The access token `{}` does not exist", uuid),
+        )
+    })
+}
+
+async fn get_access_token(
+    State(db): State<sqlx::SqlitePool>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ClientAccessToken>, ApiError> {
+    Ok(Json(find_access_token(&db, uuid).await?.into()))
+}
+
+/// A partial update to an access token, where only the fields which are
+/// present are modified
+#[derive(Debug, Deserialize)]
+struct AccessTokenPatch {
+    description: Option<String>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    expires_at: Option<Option<DateTime<Utc>>>,
+}
+
+async fn patch_access_token(
+    State(db): State<sqlx::SqlitePool>,
+    Path(uuid): Path<Uuid>,
+    Json(patch): Json<AccessTokenPatch>,
+) -> Result<Json<ClientAccessToken>, ApiError> {
+    let mut access_token = find_access_token(&db, uuid).await?;
+
+    let description = patch
+        .description
+        .unwrap_or_else(|| access_token.description().to_owned());
+    let expires_at = patch.expires_at.unwrap_or(access_token.expires_at());
+    if patch.expires_at.is_some() {
+        validate_expiry(expires_at)?;
+    }
+
+    access_token.update(&db, &description, expires_at).await?;
+
+    Ok(Json(access_token.into()))
+}
+
+async fn delete_access_token(
+    State(db): State<sqlx::SqlitePool>,
+    Path(uuid): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    find_access_token(&db, uuid).await?.delete(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,189 @@
+use std::io;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::ApiError;
+use crate::{
+    AppState,
+    events::BucketEvent,
+    models::{
+        bucket::{Bucket, BucketError},
+        object::{Object, ObjectError, Recovery},
+    },
+};
+
+pub fn create_admin_router() -> Router<AppState> {
+    Router::new().route("/reindex", post(post_reindex))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReindexQuery {
+    /// Only reindexes this bucket, rather than every bucket with stored
+    /// contents
+    bucket: Option<String>,
+}
+
+/// A line of the reindexing report, about something found in a bucket
+#[derive(Debug, Serialize)]
+struct ReindexLine {
+    bucket: String,
+    #[serde(flatten)]
+    entry: ReindexEntry,
+}
+
+/// What happened to a bucket or one of the stored contents found while
+/// reindexing
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum ReindexEntry {
+    /// The bucket's contents were stored without a bucket, which was
+    /// recreated for them
+    BucketRecovered {
+        uuid: Uuid,
+    },
+    Recovered {
+        hash: String,
+        key: String,
+        size: u64,
+    },
+    /// An object already stores the contents
+    Skipped {
+        hash: String,
+        key: String,
+    },
+    /// The contents no longer match their hash, so they weren't recovered
+    Corrupt {
+        hash: String,
+        actual_hash: String,
+    },
+    Failed {
+        hash: String,
+        message: String,
+    },
+}
+
+/// Rebuilds the objects of a bucket from the contents in its storage, for
+/// recovering from a lost or corrupted database. Without a `bucket`, every
+/// bucket with stored contents is rebuilt, and buckets which are missing are
+/// recreated as `recovered-{uuid}` (see [`Bucket::recreate`]). Each bucket
+/// and stored contents is reported on a line of newline delimited JSON as it
+/// is processed.
+///
+/// Recovered objects keep their keys when the storage backend names contents
+/// after them, and are stored under `recovered/{hash}` otherwise (see
+/// [`Object::recover`]).
+async fn post_reindex(
+    State(AppState {
+        db,
+        storage,
+        events,
+        ..
+    }): State<AppState>,
+    Query(query): Query<ReindexQuery>,
+) -> Result<Response, ApiError> {
+    let unsupported = |e: io::Error| match e.kind() {
+        io::ErrorKind::Unsupported => ApiError::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "REINDEX_NOT_SUPPORTED",
+            format!(
+                "The `{}` storage backend can't list what it stores",
+                storage.name()
+            ),
+        ),
+        _ => ObjectError::Io(e).into(),
+    };
+
+    let mut lines = Vec::new();
+    let mut buckets = Vec::new();
+    match query.bucket {
+        Some(name) => buckets.push(Bucket::find_by_name(&db, &name).await?),
+        None => {
+            for uuid in storage.list_buckets().await.map_err(unsupported)? {
+                let bucket = match Bucket::find_by_uuid(&db, uuid).await {
+                    Ok(bucket) => bucket,
+                    Err(BucketError::NotFound(_)) => {
+                        let bucket = Bucket::recreate(&db, uuid).await?;
+                        lines.push(ReindexLine {
+                            bucket: bucket.name().to_owned(),
+                            entry: ReindexEntry::BucketRecovered { uuid },
+                        });
+
+                        bucket
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                buckets.push(bucket);
+            }
+            buckets.sort_by(|a, b| a.name().cmp(b.name()));
+        }
+    }
+
+    let mut contents = Vec::new();
+    for bucket in buckets {
+        let mut hashes = storage.list(bucket.uuid()).await.map_err(unsupported)?;
+        hashes.sort();
+        contents.extend(hashes.into_iter().map(|hash| (bucket.clone(), hash)));
+    }
+
+    let recovered_buckets = futures::stream::iter(lines);
+    let entries = futures::stream::iter(contents).then(move |(bucket, hash)| {
+        let (db, storage, events) = (db.clone(), storage.clone(), events.clone());
+
+        async move {
+            let entry = match Object::recover(&db, &*storage, &bucket, &hash).await {
+                Ok(Recovery::Recovered(object)) => {
+                    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+                    ReindexEntry::Recovered {
+                        hash,
+                        key: object.path().to_owned(),
+                        size: object.size(),
+                    }
+                }
+                Ok(Recovery::AlreadyIndexed(object)) => ReindexEntry::Skipped {
+                    hash,
+                    key: object.path().to_owned(),
+                },
+                Ok(Recovery::Corrupt { actual_hash }) => {
+                    ReindexEntry::Corrupt { hash, actual_hash }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to reindex contents {}: {}", hash, e);
+
+                    ReindexEntry::Failed {
+                        hash,
+                        message: e.to_string(),
+                    }
+                }
+            };
+
+            ReindexLine {
+                bucket: bucket.name().to_owned(),
+                entry,
+            }
+        }
+    });
+
+    let lines = recovered_buckets.chain(entries).map(|line| {
+        let mut line = serde_json::to_vec(&line).map_err(io::Error::other)?;
+        line.push(b'\n');
+
+        Ok::<_, io::Error>(line)
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
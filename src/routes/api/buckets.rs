@@ -1,19 +1,89 @@
-use axum::{Json, Router, extract::State, routing::get};
-use serde::Serialize;
+use std::{convert::Infallible, io, sync::Arc};
 
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::DefaultBodyLimit,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use futures::{Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::io::StreamReader;
+
+use super::{ApiError, Paginated, PaginatedQuery, StorageStats, Tags, objects, presign};
 use crate::{
     AppState,
-    models::bucket::{Bucket, BucketSettings},
+    config::Config,
+    events::{BucketEvent, BucketEvents},
+    middleware::content_types::filter_content_types,
+    models::{
+        access_log::{AccessLog, AccessLogFilter},
+        bucket::{
+            Bucket, BucketBackupError, BucketError, BucketSettings, BucketSettingsPatch,
+            VersioningStatus, validate_bucket_name, validate_bucket_tags,
+        },
+        lifecycle::{LifecycleRule, NewLifecycleRule},
+        object::ObjectError,
+    },
+    routes::upload::body_error,
+    storage::Storage,
 };
 
-pub fn create_buckets_router() -> Router<AppState> {
+pub fn create_buckets_router(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/", get(get_buckets).post(post_buckets))
         .route(
             "/{name}",
             get(get_bucket).patch(patch_bucket).delete(delete_bucket),
         )
-    // .route("/:name/objects", get(get_objects).post(handler))
+        .route(
+            "/{name}/objects",
+            get(objects::list_objects).delete(objects::delete_objects),
+        )
+        .route("/{name}/presign", post(presign::presign_object))
+        .route(
+            "/{name}/access-logs",
+            get(get_access_logs).delete(delete_access_logs),
+        )
+        .route("/{name}/stats", get(get_bucket_stats))
+        .route("/{name}/events", get(get_events))
+        .route(
+            "/{name}/versioning",
+            get(get_versioning_status).patch(patch_versioning_status),
+        )
+        .route(
+            "/{name}/lifecycle",
+            get(get_lifecycle)
+                .put(put_lifecycle)
+                .delete(delete_lifecycle),
+        )
+        .route(
+            "/{name}/tags",
+            get(get_tags).put(put_tags).delete(delete_tags),
+        )
+        .route("/{name}/backup", get(get_backup).post(post_backup))
+        .route(
+            "/{name}/objects/{*key}",
+            get(objects::get_object)
+                .head(objects::head_object)
+                .put(objects::put_object)
+                .post(objects::post_object)
+                .delete(objects::delete_object)
+                .fallback(objects::extension_method)
+                .layer(DefaultBodyLimit::disable())
+                .layer(axum::middleware::from_fn_with_state(
+                    state.config.clone(),
+                    filter_content_types,
+                )),
+        )
 }
 
 #[derive(Debug, Serialize)]
@@ -21,6 +91,8 @@ struct ClientBucket {
     uuid: String,
     name: String,
     settings: BucketSettings,
+    object_count: u64,
+    total_bytes: u64,
 }
 
 impl From<Bucket> for ClientBucket {
@@ -29,33 +101,419 @@ impl From<Bucket> for ClientBucket {
             uuid: value.uuid().to_string(),
             name: value.name().to_string(),
             settings: value.settings().clone(),
+            object_count: value.object_count(),
+            total_bytes: value.total_bytes(),
         }
     }
 }
 
-async fn get_buckets(State(db): State<sqlx::SqlitePool>) -> Json<Vec<ClientBucket>> {
-    Json(
-        Bucket::find_all(&db)
-            .await
-            .unwrap()
-            .into_iter()
-            .map(Into::into)
-            .collect(),
+/// Number of buckets returned per page when no `limit` is given
+const DEFAULT_BUCKETS_LIMIT: u64 = 20;
+/// Upper bound on the `limit` a client may request when listing buckets
+const MAX_BUCKETS_LIMIT: u64 = 100;
+
+async fn get_buckets(
+    State(db): State<sqlx::SqlitePool>,
+    Query(pagination): Query<PaginatedQuery>,
+) -> Result<Json<Paginated<ClientBucket>>, ApiError> {
+    let page = pagination.page();
+    let limit = pagination.checked_limit(DEFAULT_BUCKETS_LIMIT, MAX_BUCKETS_LIMIT)?;
+
+    let buckets = Bucket::find_all(&db, pagination.offset(limit)?, limit).await?;
+    let total = Bucket::count(&db).await?;
+
+    Ok(Json(Paginated::new(
+        buckets.into_iter().map(Into::into).collect(),
+        page,
+        limit,
+        total,
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateBucket {
+    name: String,
+    #[serde(default)]
+    settings: BucketSettings,
+}
+
+async fn post_buckets(
+    State(db): State<sqlx::SqlitePool>,
+    Json(body): Json<CreateBucket>,
+) -> Result<(StatusCode, Json<ClientBucket>), ApiError> {
+    validate_bucket_name(&body.name)?;
+
+    let bucket_exists = || {
+        ApiError::new(
+            StatusCode::CONFLICT,
+            "BUCKET_ALREADY_EXISTS",
+            format!("The bucket `{}` already exists", body.name),
+        )
+    };
+
+    body.settings.validate().map_err(invalid_settings)?;
+
+    match Bucket::find_by_name(&db, &body.name).await {
+        Ok(_) => return Err(bucket_exists()),
+        Err(BucketError::NotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let bucket = match Bucket::new(&db, &body.name, body.settings.clone()).await {
+        Ok(bucket) => bucket,
+        // Lost a race with another request creating the same bucket
+        Err(BucketError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            return Err(bucket_exists());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok((StatusCode::CREATED, Json(bucket.into())))
+}
+
+fn invalid_settings(message: String) -> ApiError {
+    ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "INVALID_BUCKET_SETTINGS",
+        message,
     )
 }
 
-async fn post_buckets() {
-    todo!()
+/// Looks up a bucket by name, returning a 404 error if it doesn't exist
+async fn get_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<Json<ClientBucket>, BucketError> {
+    Ok(Json(Bucket::find_by_name(&db, &name).await?.into()))
+}
+
+/// Storage used by a bucket, computed from its objects table
+async fn get_bucket_stats(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<Json<StorageStats>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    Ok(Json(StorageStats::for_bucket(&db, &bucket).await?))
+}
+
+/// Streams changes to a bucket and its objects as server-sent events, until
+/// the client disconnects
+async fn get_events(
+    State(db): State<sqlx::SqlitePool>,
+    State(events): State<BucketEvents>,
+    Path(name): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    let receiver = events.subscribe(bucket.uuid());
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse_event = Event::default()
+                        .event(event.name())
+                        .json_data(&event)
+                        .expect("bucket events always serialize");
+
+                    return Some((Ok(sse_event), receiver));
+                }
+                // Clients which fall behind miss the oldest events rather
+                // than being disconnected
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn patch_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    State(events): State<BucketEvents>,
+    Path(name): Path<String>,
+    Json(patch): Json<BucketSettingsPatch>,
+) -> Result<Json<ClientBucket>, ApiError> {
+    let mut bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let mut settings = bucket.settings().clone();
+    patch.apply(&mut settings);
+    settings.validate().map_err(invalid_settings)?;
+
+    bucket.update_settings(&db, settings).await?;
+    events.publish(bucket.uuid(), BucketEvent::BucketSettingsChanged);
+
+    Ok(Json(bucket.into()))
 }
 
-async fn get_bucket() {
-    todo!()
+#[derive(Debug, Serialize, Deserialize)]
+struct VersioningState {
+    status: VersioningStatus,
 }
 
-async fn patch_bucket() {
-    todo!()
+async fn get_versioning_status(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<Json<VersioningState>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    Ok(Json(VersioningState {
+        status: bucket.settings().versioning_status,
+    }))
 }
 
-async fn delete_bucket() {
-    todo!()
+/// Enables or suspends versioning for a bucket. Suspending it keeps every
+/// version which already exists, and once enabled versioning can never be
+/// disabled again.
+async fn patch_versioning_status(
+    State(db): State<sqlx::SqlitePool>,
+    State(events): State<BucketEvents>,
+    Path(name): Path<String>,
+    Json(versioning): Json<VersioningState>,
+) -> Result<Json<VersioningState>, ApiError> {
+    let mut bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let from = bucket.settings().versioning_status;
+    if !from.can_transition_to(versioning.status) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "INVALID_VERSIONING_TRANSITION",
+            format!(
+                "Versioning cannot be changed from `{}` to `{}`",
+                from, versioning.status
+            ),
+        ));
+    }
+
+    let settings = BucketSettings {
+        versioning_status: versioning.status,
+        ..bucket.settings().clone()
+    };
+    bucket.update_settings(&db, settings).await?;
+    events.publish(bucket.uuid(), BucketEvent::BucketSettingsChanged);
+
+    Ok(Json(versioning))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LifecycleRules<R> {
+    rules: Vec<R>,
+}
+
+async fn get_lifecycle(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<Json<LifecycleRules<LifecycleRule>>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    Ok(Json(LifecycleRules {
+        rules: LifecycleRule::find_all_in_bucket(&db, bucket.uuid()).await?,
+    }))
+}
+
+/// Replaces all of the lifecycle rules of a bucket, which are applied
+/// periodically in the background
+async fn put_lifecycle(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Json(lifecycle): Json<LifecycleRules<NewLifecycleRule>>,
+) -> Result<Json<LifecycleRules<LifecycleRule>>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    if let Some(index) = lifecycle.rules.iter().position(|rule| {
+        rule.expiration_days.is_none() && rule.abort_incomplete_multipart_days.is_none()
+    }) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_LIFECYCLE_RULE",
+            format!(
+                "Lifecycle rule {} must set `expiration_days` or `abort_incomplete_multipart_days`",
+                index
+            ),
+        ));
+    }
+
+    Ok(Json(LifecycleRules {
+        rules: LifecycleRule::replace_all_in_bucket(&db, bucket.uuid(), lifecycle.rules).await?,
+    }))
+}
+
+/// Removes all of the lifecycle rules of a bucket. Objects which have already
+/// expired but weren't removed yet are kept, since rules are only applied in
+/// the background.
+async fn delete_lifecycle(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    LifecycleRule::delete_all_in_bucket(&db, bucket.uuid()).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_tags(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<Json<Tags>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    Ok(Json(Tags {
+        tags: bucket.get_tags(&db).await?,
+    }))
+}
+
+/// Replaces all of the tags of a bucket
+async fn put_tags(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Json(request): Json<Tags>,
+) -> Result<Json<Tags>, ApiError> {
+    let tags = validate_bucket_tags(request.tags)?;
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    bucket.set_tags(&db, &tags).await?;
+
+    Ok(Json(Tags { tags }))
+}
+
+async fn delete_tags(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    bucket.delete_tags(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Streams a backup of a bucket and the latest version of each of its
+/// objects as newline delimited JSON
+async fn get_backup(
+    State(db): State<sqlx::SqlitePool>,
+    State(storage): State<Storage>,
+    Path(name): Path<String>,
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    let backup = bucket.export_backup(&db, storage).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(backup),
+    )
+        .into_response())
+}
+
+/// Restores a backup into a bucket as it is streamed in, creating the bucket
+/// if it doesn't exist. Backups are limited by the server's maximum body size
+/// like any other request.
+async fn post_backup(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path(name): Path<String>,
+    body: Body,
+) -> Result<Json<ClientBucket>, ApiError> {
+    validate_bucket_name(&name)?;
+
+    let backup = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+    match Bucket::import_backup(&db, &*storage, &config.data_directory, &name, backup).await {
+        Err(BucketBackupError::Object(ObjectError::Io(e))) => Err(body_error(&config, e).into()),
+        result => Ok(Json(result?.into())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteBucketQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+async fn delete_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteBucketQuery>,
+) -> Result<StatusCode, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    bucket
+        .delete(&db, &config.data_directory, &*storage, query.force)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessLogQuery {
+    /// Only logs recorded at or after this time
+    from: Option<DateTime<Utc>>,
+    /// Only logs recorded before this time
+    to: Option<DateTime<Utc>>,
+    method: Option<String>,
+    status: Option<u16>,
+}
+
+/// Lists the access logs recorded for a bucket, oldest first
+async fn get_access_logs(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Query(query): Query<AccessLogQuery>,
+    Query(pagination): Query<PaginatedQuery>,
+) -> Result<Json<Paginated<AccessLog>>, ApiError> {
+    if let (Some(from), Some(to)) = (query.from, query.to)
+        && from > to
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_TIME_RANGE",
+            "`from` must not be later than `to`",
+        ));
+    }
+
+    let offset = pagination.offset(pagination.limit())?;
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let filter = AccessLogFilter {
+        from: query.from,
+        to: query.to,
+        method: query.method.map(|method| method.to_ascii_uppercase()),
+        status_code: query.status,
+    };
+
+    let logs =
+        AccessLog::find_all_in_bucket(&db, bucket.uuid(), &filter, offset, pagination.limit())
+            .await?;
+    let total = AccessLog::count_in_bucket(&db, bucket.uuid(), &filter).await?;
+
+    Ok(Json(Paginated::new(
+        logs,
+        pagination.page(),
+        pagination.limit(),
+        total,
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteAccessLogsQuery {
+    before: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeletedAccessLogs {
+    deleted: u64,
+}
+
+/// Deletes the access logs recorded for a bucket before a given time, so old
+/// logs don't accumulate forever
+async fn delete_access_logs(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteAccessLogsQuery>,
+) -> Result<Json<DeletedAccessLogs>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    Ok(Json(DeletedAccessLogs {
+        deleted: AccessLog::delete_before(&db, bucket.uuid(), query.before).await?,
+    }))
 }
@@ -0,0 +1,192 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    config::ContentTypeError,
+    models::{
+        bucket::{BucketBackupError, BucketError, BucketNameError},
+        object::ObjectError,
+        tag::TagError,
+    },
+    routes::{multipart::MultipartError, upload::ChecksumError},
+};
+
+/// Error returned from the JSON API, rendered in the same
+/// `{"error": ..., "message": ...}` shape as the fallback handler
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    error: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            error,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!("Database error: {}", value);
+
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_SERVER_ERROR",
+            "An internal database error occurred",
+        )
+    }
+}
+
+impl From<BucketError> for ApiError {
+    fn from(value: BucketError) -> Self {
+        match value {
+            BucketError::Database(e) => e.into(),
+            BucketError::NotFound(name) => Self::new(
+                StatusCode::NOT_FOUND,
+                "BUCKET_NOT_FOUND",
+                format!("The bucket `{}` does not exist", name),
+            ),
+            BucketError::NotEmpty(name) => Self::new(
+                StatusCode::CONFLICT,
+                "BUCKET_NOT_EMPTY",
+                format!(
+                    "The bucket `{}` is not empty, pass `?force=true` to delete it along with all of its objects",
+                    name
+                ),
+            ),
+            BucketError::InvalidName(e) => e.into(),
+        }
+    }
+}
+
+impl From<BucketNameError> for ApiError {
+    fn from(value: BucketNameError) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_BUCKET_NAME",
+            value.to_string(),
+        )
+    }
+}
+
+impl From<TagError> for ApiError {
+    fn from(value: TagError) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_TAGS",
+            value.to_string(),
+        )
+    }
+}
+
+impl IntoResponse for BucketError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+impl From<ChecksumError> for ApiError {
+    fn from(value: ChecksumError) -> Self {
+        match value {
+            ChecksumError::Multiple => Self::new(
+                StatusCode::BAD_REQUEST,
+                "MULTIPLE_CHECKSUMS",
+                value.to_string(),
+            ),
+            ChecksumError::Mismatch(_) => {
+                Self::new(StatusCode::BAD_REQUEST, "BAD_DIGEST", value.to_string())
+            }
+            ChecksumError::Io(e) => ObjectError::Io(e).into(),
+        }
+    }
+}
+
+impl From<ContentTypeError> for ApiError {
+    fn from(value: ContentTypeError) -> Self {
+        Self::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_CONTENT_TYPE",
+            value.to_string(),
+        )
+    }
+}
+
+impl From<ObjectError> for ApiError {
+    fn from(value: ObjectError) -> Self {
+        match value {
+            ObjectError::Database(e) => e.into(),
+            ObjectError::Io(e) => {
+                tracing::error!("Storage error: {}", e);
+
+                Self::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "INTERNAL_SERVER_ERROR",
+                    "An internal storage error occurred",
+                )
+            }
+            ObjectError::TooLarge { max_size } => Self::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "OBJECT_TOO_LARGE",
+                format!("Objects in this bucket must not exceed {} bytes", max_size),
+            ),
+            ObjectError::ContentSha256Mismatch => {
+                Self::new(StatusCode::BAD_REQUEST, "BAD_DIGEST", value.to_string())
+            }
+        }
+    }
+}
+
+impl From<MultipartError> for ApiError {
+    fn from(value: MultipartError) -> Self {
+        let (status, error) = match value {
+            MultipartError::Object(e) => return e.into(),
+            MultipartError::NoSuchUpload { .. } => (StatusCode::NOT_FOUND, "UPLOAD_NOT_FOUND"),
+            MultipartError::InvalidPartNumber => (StatusCode::BAD_REQUEST, "INVALID_PART_NUMBER"),
+            MultipartError::MalformedXml(_) => (StatusCode::BAD_REQUEST, "MALFORMED_XML"),
+            MultipartError::InvalidPartOrder => (StatusCode::BAD_REQUEST, "INVALID_PART_ORDER"),
+            MultipartError::InvalidPart(_) | MultipartError::EmptyPart(_) => {
+                (StatusCode::BAD_REQUEST, "INVALID_PART")
+            }
+        };
+
+        Self::new(status, error, value.to_string())
+    }
+}
+
+impl From<BucketBackupError> for ApiError {
+    fn from(value: BucketBackupError) -> Self {
+        match value {
+            BucketBackupError::Bucket(e) => e.into(),
+            BucketBackupError::Object(e) => e.into(),
+            BucketBackupError::UnsupportedVersion(_)
+            | BucketBackupError::InvalidObject(_)
+            | BucketBackupError::Malformed(_) => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "INVALID_BACKUP",
+                value.to_string(),
+            ),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({
+                "error": self.error,
+                "message": self.message,
+            })),
+        )
+            .into_response()
+    }
+}
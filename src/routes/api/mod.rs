@@ -1,18 +1,175 @@
-use axum::{Json, Router, extract::Request, routing::get};
+use std::collections::BTreeMap;
+
+use access_tokens::create_access_tokens_router;
+use admin::create_admin_router;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
 use buckets::create_buckets_router;
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+
+pub use error::ApiError;
 
-use crate::AppState;
+use crate::{
+    AppState,
+    models::{bucket::Bucket, object::Object},
+    storage::Storage,
+};
 
+mod access_tokens;
+mod admin;
 mod buckets;
+mod error;
+mod multipart;
+mod objects;
+mod presign;
 
 pub fn create_api_router(state: AppState) -> Router<AppState> {
-    Router::new().nest("/buckets", create_buckets_router())
+    Router::new()
+        .route("/", get(get_info))
+        .route("/stats", get(get_stats))
+        .nest("/access-tokens", create_access_tokens_router())
+        .nest("/admin", create_admin_router())
+        .nest("/buckets", create_buckets_router(state))
+}
+
+#[derive(Debug, Serialize)]
+struct ServerInfo {
+    version: &'static str,
+    storage_backend: &'static str,
+    buckets: u64,
+}
+
+/// Describes the server, for clients browsing the REST API
+async fn get_info(
+    State(db): State<sqlx::SqlitePool>,
+    State(storage): State<Storage>,
+) -> Result<Json<ServerInfo>, ApiError> {
+    Ok(Json(ServerInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        storage_backend: storage.name(),
+        buckets: Bucket::count(&db).await?,
+    }))
+}
+
+/// Storage used by a bucket, or by every bucket together
+#[derive(Debug, Default, Serialize)]
+struct StorageStats {
+    /// Number of stored object versions, including delete markers
+    total_objects: u64,
+    total_bytes: u64,
 }
 
+impl StorageStats {
+    async fn for_bucket(db: &sqlx::SqlitePool, bucket: &Bucket) -> sqlx::Result<Self> {
+        Ok(Self {
+            total_objects: Object::count_in_bucket(db, bucket.uuid()).await?,
+            total_bytes: Object::total_size_in_bucket(db, bucket.uuid()).await?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ServerStats {
+    bucket_count: u64,
+    #[serde(flatten)]
+    storage: StorageStats,
+}
+
+/// Sums up the storage used by every bucket from their objects tables
+async fn get_stats(State(db): State<sqlx::SqlitePool>) -> Result<Json<ServerStats>, ApiError> {
+    let mut storage = StorageStats::default();
+
+    for bucket in Bucket::find_all_unpaginated(&db).await? {
+        let stats = StorageStats::for_bucket(&db, &bucket).await?;
+
+        storage.total_objects += stats.total_objects;
+        storage.total_bytes += stats.total_bytes;
+    }
+
+    Ok(Json(ServerStats {
+        bucket_count: Bucket::count(&db).await?,
+        storage,
+    }))
+}
+
+/// Tags attached to a bucket or object
+#[derive(Debug, Serialize, Deserialize)]
+struct Tags {
+    tags: BTreeMap<String, String>,
+}
+
+/// Number of items returned per page when no `limit` is given
+const DEFAULT_PAGE_LIMIT: u64 = 100;
+/// Upper bound on the `limit` a client may request
+const MAX_PAGE_LIMIT: u64 = 1000;
+
+/// Pagination query parameters, where `page` starts at 1
 #[derive(Debug, Deserialize)]
 struct PaginatedQuery {
     pub page: Option<u64>,
     pub limit: Option<u64>,
 }
+
+impl PaginatedQuery {
+    pub fn limit(&self) -> u64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Number of items before the requested page when pages hold `limit`
+    /// items. Pages too far in for their offset to fit in a database query
+    /// are rejected.
+    pub fn offset(&self, limit: u64) -> Result<u64, ApiError> {
+        (self.page() - 1)
+            .checked_mul(limit)
+            .filter(|offset| i64::try_from(*offset).is_ok())
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_PAGE",
+                    format!("`page` {} is out of range", self.page()),
+                )
+            })
+    }
+
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// The requested page size, or `default` if none was given. Unlike
+    /// [`Self::limit`], sizes outside `1..=max` are rejected rather than
+    /// clamped.
+    pub fn checked_limit(&self, default: u64, max: u64) -> Result<u64, ApiError> {
+        match self.limit {
+            None => Ok(default),
+            Some(limit) if (1..=max).contains(&limit) => Ok(limit),
+            Some(limit) => Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_LIMIT",
+                format!("`limit` must be between 1 and {}, got {}", max, limit),
+            )),
+        }
+    }
+}
+
+/// A single page of results, along with what's needed to fetch the rest
+#[derive(Debug, Serialize)]
+struct Paginated<T> {
+    data: Vec<T>,
+    page: u64,
+    limit: u64,
+    total: u64,
+    has_next: bool,
+}
+
+impl<T> Paginated<T> {
+    fn new(data: Vec<T>, page: u64, limit: u64, total: u64) -> Self {
+        Self {
+            data,
+            page,
+            limit,
+            total,
+            has_next: page.saturating_mul(limit) < total,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Multipart uploads through the JSON API, which are carried out by
+//! [`crate::routes::multipart`]
+
+use axum::{
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{ApiError, objects::parse_content_type};
+use crate::{
+    AppState,
+    config::Config,
+    models::{bucket::Bucket, multipart::MultipartUpload},
+    routes::{
+        multipart::{abort_upload, complete_upload, store_part},
+        xml::{CompleteMultipartUploadResult, InitiateMultipartUploadResult, XmlResponse},
+    },
+};
+
+/// Query parameters identifying a part of a multipart upload
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct UploadPartQuery {
+    upload_id: Option<Uuid>,
+    part_number: Option<u16>,
+    /// Present (with any value) when replacing the object's tags, which shares
+    /// this route
+    pub tagging: Option<String>,
+}
+
+/// A part of a multipart upload to be stored
+#[derive(Debug, Clone, Copy)]
+pub(super) struct UploadPart {
+    upload_id: Uuid,
+    part_number: u16,
+}
+
+impl UploadPartQuery {
+    /// The part being uploaded, if both parameters were given
+    pub fn into_part(self) -> Option<UploadPart> {
+        Some(UploadPart {
+            upload_id: self.upload_id?,
+            part_number: self.part_number?,
+        })
+    }
+}
+
+pub(super) async fn create_multipart_upload(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+    let content_type = parse_content_type(headers)?;
+
+    let upload = MultipartUpload::create(db, bucket.uuid(), key, content_type).await?;
+
+    Ok(XmlResponse(InitiateMultipartUploadResult {
+        bucket: bucket.name().to_owned(),
+        key: upload.key().to_owned(),
+        upload_id: upload.upload_id().to_string(),
+    })
+    .into_response())
+}
+
+pub(super) async fn upload_part(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    name: &str,
+    key: &str,
+    part: UploadPart,
+    body: &[u8],
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+    let part = store_part(
+        db,
+        config,
+        &bucket,
+        key,
+        &part.upload_id.to_string(),
+        part.part_number,
+        body,
+    )
+    .await?;
+
+    Ok([(header::ETAG, part.etag().to_owned())].into_response())
+}
+
+pub(super) async fn complete_multipart_upload(
+    state: &AppState,
+    name: &str,
+    key: &str,
+    upload_id: Uuid,
+    body: &[u8],
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(&state.db, name).await?;
+    let object = complete_upload(state, &bucket, key, &upload_id.to_string(), body).await?;
+
+    Ok(XmlResponse(CompleteMultipartUploadResult {
+        location: format!("/api/buckets/{}/objects/{}", bucket.name(), object.path()),
+        bucket: bucket.name().to_owned(),
+        key: object.path().to_owned(),
+        etag: object.etag().to_owned(),
+    })
+    .into_response())
+}
+
+/// Aborts a multipart upload, removing any parts which were uploaded
+pub(super) async fn abort_multipart_upload(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    name: &str,
+    key: &str,
+    upload_id: Uuid,
+) -> Result<StatusCode, ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+    abort_upload(db, config, &bucket, key, &upload_id.to_string()).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,842 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{HeaderMap, Method, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use mime::Mime;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    ApiError, PaginatedQuery, Tags,
+    multipart::{self, UploadPartQuery},
+};
+use crate::{
+    AppState,
+    config::Config,
+    events::{BucketEvent, BucketEvents},
+    middleware::sigv4::ContentSha256Mismatch,
+    models::{
+        CachePolicy,
+        bucket::Bucket,
+        object::{
+            MAX_BATCH_DELETE_KEYS, Object, ObjectAttributes, ObjectError, normalize_key,
+            validate_object_tags,
+        },
+    },
+    routes::{
+        download::{DownloadError, serve_object},
+        metadata::{
+            MAX_METADATA_SIZE, content_encoding_from_headers, metadata_from_headers,
+            public_read_from_headers,
+        },
+        upload::{caused_by, receive_upload, verify_checksum},
+    },
+    storage::Storage,
+};
+
+#[derive(Debug, Serialize)]
+pub(super) struct ClientObject {
+    bucket: String,
+    key: String,
+    /// `None` for the null version
+    version_id: Option<String>,
+    is_latest: bool,
+    size: u64,
+    ref_count: u64,
+    hash: String,
+    etag: String,
+    content_type: Option<String>,
+    content_encoding: Option<String>,
+    cache_policy: Option<CachePolicy>,
+    expires_at: Option<DateTime<Utc>>,
+    tags: BTreeSet<Box<str>>,
+    metadata: BTreeMap<Box<str>, Box<str>>,
+    public_read: bool,
+    created_at: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<Object> for ClientObject {
+    fn from(value: Object) -> Self {
+        ClientObject {
+            bucket: value.bucket().to_string(),
+            key: value.path().to_string(),
+            version_id: (!value.version_id().is_nil()).then(|| value.version_id().to_string()),
+            is_latest: value.is_latest(),
+            size: value.size(),
+            ref_count: value.ref_count(),
+            hash: value.hash().to_string(),
+            etag: value.etag().to_string(),
+            content_type: value.content_type().map(ToString::to_string),
+            content_encoding: value.content_encoding().map(ToString::to_string),
+            cache_policy: value.cache_policy(),
+            expires_at: value.expires_at(),
+            tags: value.tags().clone(),
+            metadata: value.metadata().clone(),
+            public_read: value.public_read(),
+            created_at: value.created_at(),
+            deleted_at: value.deleted_at(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ListObjectsQuery {
+    prefix: Option<String>,
+    delimiter: Option<char>,
+    /// Whether to also list soft deleted objects
+    #[serde(default)]
+    include_deleted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ObjectList {
+    objects: Vec<ClientObject>,
+    common_prefixes: Vec<String>,
+    is_truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deleted_objects: Option<Vec<ClientObject>>,
+}
+
+pub(super) async fn list_objects(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Query(query): Query<ListObjectsQuery>,
+    Query(pagination): Query<PaginatedQuery>,
+) -> Result<Json<ObjectList>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+    let offset = pagination.offset(pagination.limit())?;
+
+    let listing = Object::find_all_in_bucket(
+        &db,
+        &bucket,
+        query.prefix.as_deref(),
+        query.delimiter,
+        None,
+        offset,
+        pagination.limit(),
+    )
+    .await?;
+
+    let deleted_objects = match query.include_deleted {
+        true => Some(
+            Object::find_deleted_in_bucket(
+                &db,
+                &bucket,
+                query.prefix.as_deref(),
+                offset,
+                pagination.limit(),
+            )
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        ),
+        false => None,
+    };
+
+    Ok(Json(ObjectList {
+        objects: listing.objects.into_iter().map(Into::into).collect(),
+        common_prefixes: listing.common_prefixes,
+        is_truncated: listing.is_truncated,
+        deleted_objects,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DeleteObjects {
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct DeletedObjects {
+    deleted: Vec<String>,
+}
+
+/// Deletes the latest version of each of the given objects at once, the same
+/// way deleting them one at a time would
+pub(super) async fn delete_objects(
+    State(db): State<sqlx::SqlitePool>,
+    State(storage): State<Storage>,
+    State(events): State<BucketEvents>,
+    Path(name): Path<String>,
+    Json(request): Json<DeleteObjects>,
+) -> Result<Json<DeletedObjects>, ApiError> {
+    if !(1..=MAX_BATCH_DELETE_KEYS).contains(&request.keys.len()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REQUEST",
+            format!(
+                "Between 1 and {} keys must be deleted at once, got {}",
+                MAX_BATCH_DELETE_KEYS,
+                request.keys.len()
+            ),
+        ));
+    }
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let keys = request
+        .keys
+        .iter()
+        .map(|key| parse_key(key))
+        .collect::<Result<Vec<_>, _>>()?;
+    Object::delete_many(
+        &db,
+        &*storage,
+        &bucket,
+        &keys.iter().map(String::as_str).collect::<Vec<_>>(),
+    )
+    .await?;
+    for key in &keys {
+        events.publish(
+            bucket.uuid(),
+            BucketEvent::ObjectDeleted { key: key.clone() },
+        );
+    }
+
+    Ok(Json(DeletedObjects { deleted: keys }))
+}
+
+/// Restores a soft deleted object, returning `404` if there is no soft
+/// deleted object under the key
+async fn restore_object(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+
+    let object = Object::restore(db, bucket.uuid(), key)
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "OBJECT_NOT_FOUND",
+                format!(
+                    "No deleted object `{}` exists in bucket `{}` to restore",
+                    key, name
+                ),
+            )
+        })?;
+
+    Ok(Json(ClientObject::from(object)).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PostObjectQuery {
+    /// Present (with any value) to create a new multipart upload
+    uploads: Option<String>,
+    /// Present to complete a multipart upload
+    upload_id: Option<Uuid>,
+    /// Present (with any value) to restore a soft deleted object
+    restore: Option<String>,
+}
+
+/// Creates or completes a multipart upload of an object, or restores a soft
+/// deleted object, depending on the query parameters given
+pub(super) async fn post_object(
+    State(state): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<PostObjectQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let key = parse_key(&key)?;
+    if query.uploads.is_some() {
+        return multipart::create_multipart_upload(&state.db, &name, &key, &headers).await;
+    }
+
+    if query.restore.is_some() {
+        return restore_object(&state.db, &name, &key).await;
+    }
+
+    if let Some(upload_id) = query.upload_id {
+        return multipart::complete_multipart_upload(&state, &name, &key, upload_id, &body).await;
+    }
+
+    Err(ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "INVALID_REQUEST",
+        "`POST` requests to an object must include the `uploads`, `uploadId` or `restore` query parameter",
+    ))
+}
+
+/// Normalizes an object key sent by a client, rejecting invalid keys
+pub(super) fn parse_key(key: &str) -> Result<String, ApiError> {
+    normalize_key(key)
+        .map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, "INVALID_KEY", message))
+}
+
+/// Finds an object that can be served, returning `404` if it doesn't exist or
+/// `410` if it has expired
+async fn find_servable_object(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+) -> Result<(Bucket, Object), ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+
+    let object = Object::find_by_path(db, &bucket, key)
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "OBJECT_NOT_FOUND",
+                format!("Object `{}` does not exist in bucket `{}`", key, name),
+            )
+        })?;
+
+    if object.is_expired() {
+        return Err(ApiError::new(
+            StatusCode::GONE,
+            "OBJECT_EXPIRED",
+            format!("Object `{}` has expired", key),
+        ));
+    }
+
+    Ok((bucket, object))
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct GetObjectQuery {
+    /// Present (with any value) to get the object's tags rather than its
+    /// contents
+    tagging: Option<String>,
+    /// Present (with any value) to list the object's versions rather than
+    /// get its contents
+    versions: Option<String>,
+    /// Version to list the versions older than
+    before_version_id: Option<String>,
+}
+
+pub(super) async fn get_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<GetObjectQuery>,
+    Query(pagination): Query<PaginatedQuery>,
+    request_headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let key = parse_key(&key)?;
+    if query.tagging.is_some() {
+        return get_object_tags(&db, &name, &key).await;
+    }
+    if query.versions.is_some() {
+        if pagination.page.is_some() {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_PAGE",
+                "Versions are paged with `before_version_id` rather than `page`",
+            ));
+        }
+
+        return list_object_versions(
+            &db,
+            &name,
+            &key,
+            query.before_version_id.as_deref(),
+            pagination.limit(),
+        )
+        .await;
+    }
+
+    let (bucket, object) = find_servable_object(&db, &name, &key).await?;
+
+    Ok(
+        serve_object(&config, &*storage, &bucket, &object, &request_headers, true)
+            .await
+            .unwrap_or_else(DownloadError::into_api_response),
+    )
+}
+
+pub(super) async fn head_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path((name, key)): Path<(String, String)>,
+    request_headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let key = parse_key(&key)?;
+    let (bucket, object) = find_servable_object(&db, &name, &key).await?;
+
+    Ok(serve_object(
+        &config,
+        &*storage,
+        &bucket,
+        &object,
+        &request_headers,
+        false,
+    )
+    .await
+    .unwrap_or_else(DownloadError::into_api_response))
+}
+
+async fn get_object_tags(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+) -> Result<Response, ApiError> {
+    let (_, object) = find_servable_object(db, name, key).await?;
+
+    let tags = object
+        .tag_pairs()
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+
+    Ok(Json(Tags { tags }).into_response())
+}
+
+/// A version of an object, as listed by [`list_object_versions`]
+#[derive(Debug, Serialize)]
+struct ObjectVersion {
+    /// `None` for the null version
+    version_id: Option<String>,
+    is_latest: bool,
+    is_delete_marker: bool,
+    size: u64,
+    etag: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct ObjectVersionList {
+    data: Vec<ObjectVersion>,
+    limit: u64,
+    has_next: bool,
+}
+
+/// Lists a page of the versions of a single object, newest first, starting
+/// after the version `before_version_id`
+async fn list_object_versions(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    before_version_id: Option<&str>,
+    limit: u64,
+) -> Result<Response, ApiError> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+
+    let before_version_id = before_version_id
+        .map(|version_id| match version_id {
+            "null" => Ok(Uuid::nil()),
+            version_id => version_id.parse::<Uuid>().map_err(|_| {
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_VERSION_ID",
+                    format!("`{}` is not a valid version ID", version_id),
+                )
+            }),
+        })
+        .transpose()?;
+
+    let listing = Object::find_versions_of_path(db, &bucket, key, before_version_id, limit)
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "VERSION_NOT_FOUND",
+                format!("Object `{}` has no such version", key),
+            )
+        })?;
+
+    if listing.objects.is_empty() && before_version_id.is_none() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "OBJECT_NOT_FOUND",
+            format!("Object `{}` does not exist in bucket `{}`", key, name),
+        ));
+    }
+
+    let data = listing
+        .objects
+        .into_iter()
+        .map(|version| ObjectVersion {
+            version_id: (!version.version_id().is_nil()).then(|| version.version_id().to_string()),
+            is_latest: version.is_latest(),
+            is_delete_marker: version.is_delete_marker(),
+            size: version.size(),
+            etag: version.etag().to_string(),
+            created_at: version.created_at(),
+        })
+        .collect();
+
+    Ok(Json(ObjectVersionList {
+        data,
+        limit,
+        has_next: listing.is_truncated,
+    })
+    .into_response())
+}
+
+/// Replaces all of an object's tags with those in the request body
+async fn put_object_tags(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    body: &[u8],
+) -> Result<Response, ApiError> {
+    let request: Tags = serde_json::from_slice(body).map_err(|e| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BODY",
+            format!("The request body is not a valid tags document: {}", e),
+        )
+    })?;
+    let tags = validate_object_tags(request.tags)?;
+
+    let (_, mut object) = find_servable_object(db, name, key).await?;
+    object.set_tags(db, &tags).await?;
+
+    Ok(Json(Tags { tags }).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct DeleteObjectQuery {
+    /// Present to abort a multipart upload
+    upload_id: Option<Uuid>,
+    /// Present (with any value) to remove the object's tags
+    tagging: Option<String>,
+}
+
+/// Aborts a multipart upload of an object, or removes the object's tags,
+/// depending on the query parameters given
+pub(super) async fn delete_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<DeleteObjectQuery>,
+) -> Result<StatusCode, ApiError> {
+    let key = parse_key(&key)?;
+    if query.tagging.is_some() {
+        return delete_object_tags(&db, &name, &key).await;
+    }
+
+    if let Some(upload_id) = query.upload_id {
+        return multipart::abort_multipart_upload(&db, &config, &name, &key, upload_id).await;
+    }
+
+    Err(ApiError::new(
+        StatusCode::BAD_REQUEST,
+        "INVALID_REQUEST",
+        "`DELETE` requests to an object must include the `uploadId` or `tagging` query parameter",
+    ))
+}
+
+/// Removes all of an object's tags
+async fn delete_object_tags(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+) -> Result<StatusCode, ApiError> {
+    let (_, mut object) = find_servable_object(db, name, key).await?;
+    object.set_tags(db, &BTreeMap::new()).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Parses the `Content-Type` request header, if one was sent
+pub(super) fn parse_content_type(headers: &HeaderMap) -> Result<Option<Mime>, ApiError> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<Mime>().ok())
+                .ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        "INVALID_CONTENT_TYPE",
+                        "The `Content-Type` header is not a valid MIME type",
+                    )
+                })
+        })
+        .transpose()
+}
+
+/// Reads a whole request body which isn't stored as an object
+async fn read_body(body: Body) -> Result<Bytes, ApiError> {
+    axum::body::to_bytes(body, usize::MAX).await.map_err(|e| {
+        if caused_by::<ContentSha256Mismatch>(Some(&e)) {
+            return ObjectError::ContentSha256Mismatch.into();
+        }
+
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BODY",
+            "Failed to read the request body",
+        )
+    })
+}
+
+/// Stores an object, or a single part of a multipart upload when `uploadId`
+/// and `partNumber` are given, or replaces the object's tags when `tagging` is
+/// given
+pub(super) async fn put_object(
+    State(AppState {
+        db,
+        config,
+        storage,
+        events,
+        ..
+    }): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+    Query(part): Query<UploadPartQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, ApiError> {
+    let key = parse_key(&key)?;
+    if part.tagging.is_some() {
+        return put_object_tags(&db, &name, &key, &read_body(body).await?).await;
+    }
+    if let Some(part) = part.into_part() {
+        return multipart::upload_part(&db, &config, &name, &key, part, &read_body(body).await?)
+            .await;
+    }
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let content_type = parse_content_type(&headers)?;
+
+    let metadata = metadata_from_headers(&headers).map_err(|e| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "METADATA_TOO_LARGE",
+            format!(
+                "Object metadata must not exceed {} bytes, got {}",
+                MAX_METADATA_SIZE, e.size
+            ),
+        )
+    })?;
+
+    let upload = receive_upload(&config, &bucket, body).await?;
+    let (checksum_algorithm, checksum_value) = verify_checksum(&headers, &upload).await?.unzip();
+
+    let object = Object::new(
+        &db,
+        &*storage,
+        &bucket,
+        &key,
+        ObjectAttributes {
+            content_type,
+            content_encoding: content_encoding_from_headers(&headers),
+            metadata,
+            public_read: public_read_from_headers(&headers),
+            checksum_algorithm,
+            checksum_value,
+        },
+        upload,
+    )
+    .await?;
+    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    Ok(Json(ClientObject::from(object)).into_response())
+}
+
+/// Characters escaped in the key of a `Location` header, keeping the slashes
+/// between path segments
+const KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Methods served by the object route, sent in the `Allow` header when any
+/// other method is used
+const OBJECT_METHODS: &str = "GET, HEAD, PUT, POST, DELETE, COPY, MOVE";
+
+/// Serves the WebDAV style `COPY` and `MOVE` methods. `MethodFilter` can't
+/// match extension methods, so this is the object route's fallback and
+/// rejects any other method.
+pub(super) async fn extension_method(
+    State(state): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    match method.as_str() {
+        "COPY" => copy_object(state, &name, &key, &headers).await,
+        "MOVE" => move_object(state, &name, &key, &headers).await,
+        _ => Ok((
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(header::ALLOW, OBJECT_METHODS)],
+        )
+            .into_response()),
+    }
+}
+
+/// Copies an object to the one named by the `Destination` header, of the form
+/// `{bucket}/{key}`. The object's content type must be allowed for the
+/// destination key.
+async fn copy_object(
+    AppState {
+        db,
+        config,
+        storage,
+        events,
+        ..
+    }: AppState,
+    name: &str,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let key = parse_key(key)?;
+    let (destination_name, destination_key) = parse_destination(headers)?;
+    let destination_key = parse_key(&destination_key)?;
+
+    if (name, key.as_str()) == (destination_name.as_str(), destination_key.as_str()) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "INVALID_DESTINATION",
+            "An object can't be copied to itself",
+        ));
+    }
+
+    let (_, source) = find_servable_object(&db, name, &key).await?;
+    let bucket = Bucket::find_by_name(&db, &destination_name).await?;
+
+    let attributes = source.attributes();
+    let content_type = attributes
+        .content_type
+        .clone()
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    config
+        .content_types
+        .check(&destination_key, &content_type)?;
+
+    let object = source
+        .copy(&db, &*storage, &bucket, &destination_key, attributes)
+        .await?;
+    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    let location = format!(
+        "/api/buckets/{}/objects/{}",
+        utf8_percent_encode(bucket.name(), KEY_ENCODE_SET),
+        utf8_percent_encode(object.path(), KEY_ENCODE_SET)
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(ClientObject::from(object)),
+    )
+        .into_response())
+}
+
+/// Moves an object to the same key in the bucket named by the `Destination`
+/// header, of the form `{bucket}/{key}`, in a single transaction. The
+/// object's content type must still be allowed for its key.
+///
+/// Moving out of a versioned bucket leaves a delete marker behind, keeping
+/// the moved version there (see [`Bucket::transfer_object`]).
+async fn move_object(
+    AppState {
+        db,
+        config,
+        storage,
+        events,
+        ..
+    }: AppState,
+    name: &str,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let key = parse_key(key)?;
+    let (destination_name, destination_key) = parse_destination(headers)?;
+
+    if parse_key(&destination_key)? != key {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_DESTINATION",
+            "Objects can only be moved to the same key in another bucket",
+        ));
+    }
+
+    if destination_name == name {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "INVALID_DESTINATION",
+            "An object can't be moved to the bucket it is in",
+        ));
+    }
+
+    let (source_bucket, source) = find_servable_object(&db, name, &key).await?;
+    let bucket = Bucket::find_by_name(&db, &destination_name).await?;
+
+    let content_type = source
+        .attributes()
+        .content_type
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    config.content_types.check(&key, &content_type)?;
+
+    let object = source_bucket
+        .transfer_object(&db, &*storage, &key, &bucket)
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "OBJECT_NOT_FOUND",
+                format!("Object `{}` does not exist in bucket `{}`", key, name),
+            )
+        })?;
+    events.publish(
+        source_bucket.uuid(),
+        BucketEvent::ObjectDeleted { key: key.clone() },
+    );
+    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    let location = format!(
+        "/api/buckets/{}/objects/{}",
+        utf8_percent_encode(bucket.name(), KEY_ENCODE_SET),
+        utf8_percent_encode(object.path(), KEY_ENCODE_SET)
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(ClientObject::from(object)),
+    )
+        .into_response())
+}
+
+/// Splits a percent encoded `Destination` header of the form
+/// `{bucket}/{key}` into its bucket and key, where a leading slash is
+/// optional
+fn parse_destination(headers: &HeaderMap) -> Result<(String, String), ApiError> {
+    let invalid = || {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_DESTINATION",
+            "The `Destination` header must be of the form `{bucket}/{key}`",
+        )
+    };
+
+    let destination = headers.get("destination").ok_or_else(invalid)?;
+    let destination = percent_decode(destination.as_bytes())
+        .decode_utf8()
+        .map_err(|_| invalid())?;
+
+    let destination = destination.strip_prefix('/').unwrap_or(&destination);
+    match destination.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => {
+            Ok((bucket.to_owned(), key.to_owned()))
+        }
+        _ => Err(invalid()),
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, Method, StatusCode, header},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ApiError;
+use crate::{
+    config::Config,
+    middleware::presign::{MAX_EXPIRES_SECS, presign},
+    models::{bucket::Bucket, object::normalize_key},
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub(super) enum PresignMethod {
+    Get,
+    Put,
+}
+
+impl From<PresignMethod> for Method {
+    fn from(value: PresignMethod) -> Self {
+        match value {
+            PresignMethod::Get => Method::GET,
+            PresignMethod::Put => Method::PUT,
+        }
+    }
+}
+
+fn default_expires_in_secs() -> u64 {
+    3_600
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct PresignRequest {
+    key: String,
+    method: PresignMethod,
+    #[serde(default = "default_expires_in_secs")]
+    expires_in_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct PresignedUrl {
+    url: String,
+    method: PresignMethod,
+    expires_at: DateTime<Utc>,
+}
+
+/// Creates a URL which can be used to download or upload an object without
+/// credentials until it expires
+pub(super) async fn presign_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<PresignRequest>,
+) -> Result<Json<PresignedUrl>, ApiError> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let key = normalize_key(&body.key).map_err(|message| {
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "INVALID_KEY", message)
+    })?;
+
+    if !(1..=MAX_EXPIRES_SECS).contains(&body.expires_in_secs) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_EXPIRES",
+            format!(
+                "Presigned URLs must expire within 1 to {} seconds",
+                MAX_EXPIRES_SECS
+            ),
+        ));
+    }
+
+    let secret = config.presigned_secret.as_deref().ok_or_else(|| {
+        tracing::error!("No presigned URL secret is configured");
+
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_SERVER_ERROR",
+            "Presigned URLs are not available",
+        )
+    })?;
+
+    let now = Utc::now();
+    let path_and_query = presign(
+        secret,
+        &body.method.into(),
+        bucket.name(),
+        &key,
+        now,
+        body.expires_in_secs,
+    );
+
+    // The URL is made absolute using the host the client reached us on
+    let url = match headers.get(header::HOST).and_then(|h| h.to_str().ok()) {
+        Some(host) => {
+            let scheme = if config.tls.is_some() {
+                "https"
+            } else {
+                "http"
+            };
+
+            format!("{}://{}{}", scheme, host, path_and_query)
+        }
+        None => path_and_query,
+    };
+
+    Ok(Json(PresignedUrl {
+        url,
+        method: body.method,
+        expires_at: now + TimeDelta::seconds(body.expires_in_secs as i64),
+    }))
+}
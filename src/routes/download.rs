@@ -0,0 +1,399 @@
+//! Object downloads shared by the JSON API and the S3-compatible routes
+//!
+//! Both serve the same headers, conditional requests and byte ranges, and only
+//! differ in how errors are rendered.
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use super::{
+    api::ApiError,
+    metadata::insert_metadata_headers,
+    xml::{S3Error, format_version_id},
+};
+use crate::{
+    config::Config,
+    models::{
+        bucket::Bucket,
+        object::{Object, ObjectError},
+    },
+    storage::StorageBackend,
+};
+
+/// Metadata headers sent with both `GET` and `HEAD` object responses
+pub fn object_headers(config: &Config, bucket: &Bucket, object: &Object) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    let content_type = object
+        .content_type()
+        .unwrap_or(&mime::APPLICATION_OCTET_STREAM)
+        .to_string();
+    let last_modified = object
+        .created_at()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+
+    let mut insert = |name, value: String| {
+        if let Ok(value) = HeaderValue::try_from(value) {
+            headers.insert(name, value);
+        }
+    };
+
+    insert(header::CONTENT_TYPE, content_type);
+    if let Some(content_encoding) = object.content_encoding() {
+        insert(header::CONTENT_ENCODING, content_encoding.to_owned());
+    }
+    insert(header::CONTENT_LENGTH, object.size().to_string());
+    insert(header::ACCEPT_RANGES, "bytes".to_owned());
+    insert(header::ETAG, object.etag().to_owned());
+    insert(header::LAST_MODIFIED, last_modified);
+    insert(
+        header::CACHE_CONTROL,
+        config.cache_control.header_value(
+            object
+                .cache_policy()
+                .or(bucket.settings().default_cache_policy),
+        ),
+    );
+    // Objects uploaded without versioning don't report their null version
+    if !object.version_id().is_nil() {
+        insert(
+            HeaderName::from_static("x-amz-version-id"),
+            format_version_id(object.version_id()),
+        );
+    }
+    if let Some(expires_at) = object.expires_at() {
+        insert(
+            HeaderName::from_static("x-expires-at"),
+            expires_at.to_rfc3339(),
+        );
+    }
+
+    if let (Some(algorithm), Some(checksum)) =
+        (object.checksum_algorithm(), object.checksum_value())
+    {
+        insert(
+            HeaderName::from_static(algorithm.header_name()),
+            checksum.to_owned(),
+        );
+    }
+
+    insert_metadata_headers(&mut headers, object.metadata());
+
+    headers
+}
+
+/// Whether an `If-Match` or `If-None-Match` header value matches the given
+/// ETag. Weak tags only match when `weak` comparison is allowed.
+fn etag_matches(value: &HeaderValue, etag: &str, weak: bool) -> bool {
+    let Ok(value) = value.to_str() else {
+        return false;
+    };
+
+    value.split(',').map(str::trim).any(|tag| match tag {
+        "*" => true,
+        tag if weak => tag.strip_prefix("W/").unwrap_or(tag) == etag,
+        tag => tag == etag,
+    })
+}
+
+/// Parses an HTTP date header, which is ignored if it isn't a valid date
+fn parse_http_date(value: &HeaderValue) -> Option<DateTime<Utc>> {
+    let value = value.to_str().ok()?;
+
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Whether the object was modified after an HTTP date, which only has second
+/// precision
+fn modified_since(object: &Object, date: DateTime<Utc>) -> bool {
+    object.created_at().timestamp() > date.timestamp()
+}
+
+/// Evaluates the conditional request headers in the order RFC 9110 gives.
+/// `If-Unmodified-Since` and `If-Modified-Since` are only considered when
+/// `If-Match` and `If-None-Match` respectively aren't sent. A failed
+/// `If-None-Match` or `If-Modified-Since` is answered with
+/// `304 Not Modified`, which isn't an error.
+fn check_preconditions(
+    request_headers: &HeaderMap,
+    object_headers: &HeaderMap,
+    object: &Object,
+) -> Result<Option<Response>, DownloadError> {
+    match request_headers.get(header::IF_MATCH) {
+        Some(if_match) if !etag_matches(if_match, object.etag(), false) => {
+            return Err(DownloadError::PreconditionFailed(
+                "The object's ETag does not match `If-Match`",
+            ));
+        }
+        Some(_) => {}
+        None => {
+            if let Some(date) = request_headers
+                .get(header::IF_UNMODIFIED_SINCE)
+                .and_then(parse_http_date)
+                && modified_since(object, date)
+            {
+                return Err(DownloadError::PreconditionFailed(
+                    "The object has been modified since `If-Unmodified-Since`",
+                ));
+            }
+        }
+    }
+
+    let not_modified = match request_headers.get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => etag_matches(if_none_match, object.etag(), true),
+        None => request_headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(parse_http_date)
+            .is_some_and(|date| !modified_since(object, date)),
+    };
+
+    if not_modified {
+        let mut headers = object_headers.clone();
+        headers.remove(header::CONTENT_LENGTH);
+
+        return Ok(Some((StatusCode::NOT_MODIFIED, headers).into_response()));
+    }
+
+    Ok(None)
+}
+
+/// An inclusive range of bytes requested with a `Range` header
+#[derive(Debug, Clone, Copy)]
+struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+impl ByteRange {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// Reasons a `Range` header can't be served
+#[derive(Debug)]
+pub enum RangeError {
+    Malformed,
+    MultipleRanges,
+    NotSatisfiable { size: u64 },
+}
+
+/// Parses a `Range` header for an object of the given size. Only a single
+/// `bytes` range is supported.
+fn parse_range(value: &HeaderValue, size: u64) -> Result<ByteRange, RangeError> {
+    let ranges = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+        .ok_or(RangeError::Malformed)?;
+
+    if ranges.contains(',') {
+        return Err(RangeError::MultipleRanges);
+    }
+
+    let (start, end) = ranges.trim().split_once('-').ok_or(RangeError::Malformed)?;
+    let parse = |value: &str| value.parse::<u64>().map_err(|_| RangeError::Malformed);
+
+    let range = match (start, end) {
+        // `bytes=-{n}` requests the last `n` bytes
+        ("", suffix) => {
+            let suffix = parse(suffix)?;
+
+            (suffix > 0 && size > 0).then(|| ByteRange {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            })
+        }
+        (start, "") => {
+            let start = parse(start)?;
+
+            (start < size).then(|| ByteRange {
+                start,
+                end: size - 1,
+            })
+        }
+        (start, end) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                return Err(RangeError::Malformed);
+            }
+
+            (start < size).then(|| ByteRange {
+                start,
+                end: end.min(size - 1),
+            })
+        }
+    };
+
+    range.ok_or(RangeError::NotSatisfiable { size })
+}
+
+/// Reasons an object can't be served
+#[derive(Debug)]
+pub enum DownloadError {
+    /// A conditional request header doesn't hold, with a message saying which
+    PreconditionFailed(&'static str),
+    Range(RangeError),
+    Storage(ObjectError),
+}
+
+impl From<RangeError> for DownloadError {
+    fn from(value: RangeError) -> Self {
+        Self::Range(value)
+    }
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Storage(value.into())
+    }
+}
+
+impl DownloadError {
+    /// Headers which must accompany the error response
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if let DownloadError::Range(RangeError::NotSatisfiable { size }) = self
+            && let Ok(value) = HeaderValue::try_from(format!("bytes */{}", size))
+        {
+            headers.insert(header::CONTENT_RANGE, value);
+        }
+
+        headers
+    }
+
+    /// Renders the error as a JSON API error
+    pub fn into_api_response(self) -> Response {
+        let headers = self.headers();
+
+        let error = match self {
+            DownloadError::PreconditionFailed(message) => ApiError::new(
+                StatusCode::PRECONDITION_FAILED,
+                "PRECONDITION_FAILED",
+                message,
+            ),
+            DownloadError::Range(RangeError::Malformed) => ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_RANGE",
+                "The `Range` header is not a valid byte range",
+            ),
+            DownloadError::Range(RangeError::MultipleRanges) => ApiError::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "MULTIPLE_RANGES_NOT_SUPPORTED",
+                "Only a single byte range may be requested",
+            ),
+            DownloadError::Range(RangeError::NotSatisfiable { size }) => ApiError::new(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "RANGE_NOT_SATISFIABLE",
+                format!(
+                    "The requested range is outside of the object's {} bytes",
+                    size
+                ),
+            ),
+            DownloadError::Storage(e) => e.into(),
+        };
+
+        (headers, error).into_response()
+    }
+
+    /// Renders the error as an S3 `<Error>` document
+    pub fn into_s3_response(self) -> Response {
+        let headers = self.headers();
+
+        let error = match self {
+            DownloadError::PreconditionFailed(message) => S3Error::new(
+                StatusCode::PRECONDITION_FAILED,
+                "PreconditionFailed",
+                message,
+            ),
+            // S3 ignores range headers it can't parse and serves the whole
+            // object, but a clear error is more useful to clients
+            DownloadError::Range(RangeError::Malformed) => S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "InvalidRange",
+                "The `Range` header is not a valid byte range",
+            ),
+            DownloadError::Range(RangeError::MultipleRanges) => S3Error::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "NotImplemented",
+                "Only a single byte range may be requested",
+            ),
+            DownloadError::Range(RangeError::NotSatisfiable { size }) => S3Error::new(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "InvalidRange",
+                format!(
+                    "The requested range is outside of the object's {} bytes",
+                    size
+                ),
+            ),
+            DownloadError::Storage(e) => e.into(),
+        };
+
+        (headers, error).into_response()
+    }
+}
+
+/// Builds the response for a `GET` or `HEAD` of an object, honouring
+/// conditional and `Range` request headers. The contents are only read when
+/// `include_body` is set.
+pub async fn serve_object(
+    config: &Config,
+    storage: &dyn StorageBackend,
+    bucket: &Bucket,
+    object: &Object,
+    request_headers: &HeaderMap,
+    include_body: bool,
+) -> Result<Response, DownloadError> {
+    let mut headers = object_headers(config, bucket, object);
+    if let Some(response) = check_preconditions(request_headers, &headers, object)? {
+        return Ok(response);
+    }
+
+    if !include_body {
+        return Ok(headers.into_response());
+    }
+
+    let range = request_headers
+        .get(header::RANGE)
+        .map(|value| parse_range(value, object.size()))
+        .transpose()?;
+
+    let Some(range) = range else {
+        let contents = storage.get(object.bucket(), object.hash()).await?;
+
+        return Ok((
+            headers,
+            Body::from_stream(tokio_util::io::ReaderStream::new(contents)),
+        )
+            .into_response());
+    };
+
+    let contents = storage
+        .get_range(object.bucket(), object.hash(), range.start, range.len())
+        .await?;
+
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(range.len()));
+    if let Ok(content_range) = HeaderValue::try_from(format!(
+        "bytes {}-{}/{}",
+        range.start,
+        range.end,
+        object.size()
+    )) {
+        headers.insert(header::CONTENT_RANGE, content_range);
+    }
+
+    Ok((
+        StatusCode::PARTIAL_CONTENT,
+        headers,
+        Body::from_stream(tokio_util::io::ReaderStream::new(contents)),
+    )
+        .into_response())
+}
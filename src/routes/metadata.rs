@@ -0,0 +1,84 @@
+//! Object metadata sent and received as headers, including custom
+//! `x-amz-meta-*` metadata
+
+use std::collections::BTreeMap;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+
+const METADATA_PREFIX: &str = "x-amz-meta-";
+
+/// Upper bound on the combined size of metadata keys and values, matching S3
+pub const MAX_METADATA_SIZE: usize = 2048;
+
+/// The metadata sent with a request exceeds [`MAX_METADATA_SIZE`]
+#[derive(Debug)]
+pub struct MetadataTooLarge {
+    pub size: usize,
+}
+
+/// Collects the `x-amz-meta-*` request headers, keyed by lowercase name
+/// without the prefix. Repeated headers are joined with commas.
+pub fn metadata_from_headers(
+    headers: &HeaderMap,
+) -> Result<BTreeMap<Box<str>, Box<str>>, MetadataTooLarge> {
+    let mut metadata = BTreeMap::<Box<str>, String>::new();
+
+    for (name, value) in headers {
+        // Header names are always lowercase
+        let Some(key) = name.as_str().strip_prefix(METADATA_PREFIX) else {
+            continue;
+        };
+        let value = String::from_utf8_lossy(value.as_bytes());
+
+        metadata
+            .entry(key.into())
+            .and_modify(|existing| {
+                existing.push(',');
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+
+    let size = metadata
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum();
+    if size > MAX_METADATA_SIZE {
+        return Err(MetadataTooLarge { size });
+    }
+
+    Ok(metadata
+        .into_iter()
+        .map(|(key, value)| (key, value.into()))
+        .collect())
+}
+
+/// Reads the `Content-Encoding` request header, which is stored with an
+/// object and served back unchanged
+pub fn content_encoding_from_headers(headers: &HeaderMap) -> Option<Box<str>> {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(Into::into)
+}
+
+/// Whether the `x-amz-acl` request header grants anyone read access with the
+/// `public-read` canned ACL. Other ACLs leave the object private.
+pub fn public_read_from_headers(headers: &HeaderMap) -> bool {
+    headers
+        .get("x-amz-acl")
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"public-read"))
+}
+
+/// Adds metadata to a response as `x-amz-meta-*` headers
+pub fn insert_metadata_headers(headers: &mut HeaderMap, metadata: &BTreeMap<Box<str>, Box<str>>) {
+    for (key, value) in metadata {
+        let name = HeaderName::try_from(format!("{}{}", METADATA_PREFIX, key));
+        let value = HeaderValue::try_from(&**value);
+
+        if let (Ok(name), Ok(value)) = (name, value) {
+            headers.insert(name, value);
+        }
+    }
+}
@@ -1,18 +1,116 @@
-use axum::{Router, extract::Request, routing::get};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+    routing::get,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde_json::{Value, json};
 
-use api::create_api_router;
+use api::{ApiError, create_api_router};
+use s3::create_s3_router;
+use xml::{BucketInfo, ListAllMyBucketsResult, XmlResponse};
 
-use crate::AppState;
+use crate::{AppState, MIGRATOR, metrics::OBJECTS_TOTAL, models::bucket::Bucket};
 
-mod api;
+pub(crate) mod api;
+mod download;
+mod metadata;
+mod multipart;
+mod s3;
+mod upload;
+pub(crate) mod xml;
 
 pub fn create_router(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/", get(handle_index))
         .nest("/api", create_api_router(state.clone()))
+        .merge(create_s3_router(state))
 }
 
-async fn handle_index(req: Request) {
-    eprintln!("req = {req:?}");
-    todo!()
+/// Lists all buckets, as S3 `ListBuckets`
+async fn handle_index(
+    State(db): State<sqlx::SqlitePool>,
+) -> Result<XmlResponse<ListAllMyBucketsResult>, ApiError> {
+    let buckets = Bucket::find_all_unpaginated(&db).await?;
+
+    Ok(XmlResponse(ListAllMyBucketsResult::new(
+        buckets.iter().map(BucketInfo::from).collect(),
+    )))
+}
+
+/// Routes for liveness and readiness probes, which never require
+/// authentication
+pub fn create_health_router() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(handle_health))
+        .route("/ready", get(handle_ready))
+}
+
+/// Route for Prometheus scrapes, which never requires authentication
+pub fn create_metrics_router() -> Router<AppState> {
+    Router::new().route("/metrics", get(handle_metrics))
+}
+
+/// Renders all metrics in the Prometheus text format
+async fn handle_metrics(
+    State(db): State<sqlx::SqlitePool>,
+    State(metrics): State<PrometheusHandle>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Object counts are already maintained per bucket, so they are read at
+    // scrape time rather than tracked separately
+    for bucket in Bucket::find_all_unpaginated(&db).await? {
+        metrics::gauge!(OBJECTS_TOTAL, "bucket" => bucket.name().to_owned())
+            .set(bucket.object_count() as f64);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    ))
+}
+
+/// Reports whether the server is up and can reach its database
+async fn handle_health(State(db): State<sqlx::SqlitePool>) -> (StatusCode, Json<Value>) {
+    match sqlx::query("SELECT 1;").execute(&db).await {
+        Ok(_) => (StatusCode::OK, Json(json!({ "status": "ok", "db": "ok" }))),
+        Err(e) => {
+            tracing::error!("Health check failed: {}", e);
+
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "degraded", "db": "error" })),
+            )
+        }
+    }
+}
+
+/// Reports whether every database migration has been applied
+async fn handle_ready(State(db): State<sqlx::SqlitePool>) -> (StatusCode, Json<Value>) {
+    let applied: sqlx::Result<i64> =
+        sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success = 1;")
+            .fetch_one(&db)
+            .await;
+
+    let expected = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .count() as i64;
+
+    match applied {
+        Ok(applied) if applied >= expected => (StatusCode::OK, Json(json!({ "status": "ready" }))),
+        Ok(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "not_ready" })),
+        ),
+        Err(e) => {
+            tracing::error!("Readiness check failed: {}", e);
+
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "not_ready" })),
+            )
+        }
+    }
 }
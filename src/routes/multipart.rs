@@ -0,0 +1,177 @@
+//! S3-compatible multipart uploads, where an object is uploaded as separate
+//! parts which are joined together once the upload is completed. Uploads can
+//! be made through both the JSON API and the S3 routes, which only differ in
+//! how requests are dispatched and errors are reported.
+
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    config::Config,
+    events::BucketEvent,
+    models::{
+        bucket::Bucket,
+        multipart::{MultipartPart, MultipartUpload, PART_NUMBERS},
+        object::{Object, ObjectError},
+    },
+    routes::xml::CompleteMultipartUpload,
+};
+
+/// Reasons a multipart upload request can't be carried out
+#[derive(Debug, thiserror::Error)]
+pub enum MultipartError {
+    #[error("Upload `{upload_id}` does not exist for object `{key}` in bucket `{bucket}`")]
+    NoSuchUpload {
+        upload_id: String,
+        key: String,
+        bucket: String,
+    },
+    #[error(
+        "Part numbers must be between {} and {}",
+        PART_NUMBERS.start(),
+        PART_NUMBERS.end()
+    )]
+    InvalidPartNumber,
+    #[error("{0}")]
+    MalformedXml(&'static str),
+    #[error("Parts must be listed in ascending order of part number")]
+    InvalidPartOrder,
+    #[error("Part {0} was not uploaded or its ETag does not match")]
+    InvalidPart(u16),
+    #[error("Part {0} is empty but is not the last part")]
+    EmptyPart(u16),
+    #[error(transparent)]
+    Object(#[from] ObjectError),
+}
+
+impl From<sqlx::Error> for MultipartError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Object(value.into())
+    }
+}
+
+/// Finds an in-progress upload, making sure it is for the object it was
+/// requested through. IDs which aren't valid UUIDs can't name an upload.
+pub async fn find_upload(
+    db: &sqlx::SqlitePool,
+    bucket: &Bucket,
+    key: &str,
+    upload_id: &str,
+) -> Result<MultipartUpload, MultipartError> {
+    let upload = match upload_id.parse::<Uuid>() {
+        Ok(upload_id) => MultipartUpload::find_by_upload_id(db, upload_id).await?,
+        Err(_) => None,
+    };
+
+    upload
+        .filter(|upload| upload.bucket_uuid() == bucket.uuid() && upload.key() == key)
+        .ok_or_else(|| MultipartError::NoSuchUpload {
+            upload_id: upload_id.to_owned(),
+            key: key.to_owned(),
+            bucket: bucket.name().to_owned(),
+        })
+}
+
+/// Stores `body` as a part of an upload, replacing any part previously
+/// uploaded with the same number
+pub async fn store_part(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    bucket: &Bucket,
+    key: &str,
+    upload_id: &str,
+    part_number: u16,
+    body: &[u8],
+) -> Result<MultipartPart, MultipartError> {
+    if !PART_NUMBERS.contains(&part_number) {
+        return Err(MultipartError::InvalidPartNumber);
+    }
+
+    let upload = find_upload(db, bucket, key, upload_id).await?;
+
+    Ok(MultipartPart::new(db, &config.data_directory, &upload, part_number, body).await?)
+}
+
+/// Joins the parts listed by a `CompleteMultipartUpload` document into the
+/// object, publishing its creation
+pub async fn complete_upload(
+    state: &AppState,
+    bucket: &Bucket,
+    key: &str,
+    upload_id: &str,
+    body: &[u8],
+) -> Result<Object, MultipartError> {
+    let db = &state.db;
+    let upload = find_upload(db, bucket, key, upload_id).await?;
+
+    let request: CompleteMultipartUpload = std::str::from_utf8(body)
+        .ok()
+        .and_then(|body| quick_xml::de::from_str(body).ok())
+        .ok_or(MultipartError::MalformedXml(
+            "The request body is not a valid `CompleteMultipartUpload` document",
+        ))?;
+
+    if request.parts.is_empty() {
+        return Err(MultipartError::MalformedXml(
+            "At least one part must be given to complete an upload",
+        ));
+    }
+
+    if !request
+        .parts
+        .windows(2)
+        .all(|parts| parts[0].part_number < parts[1].part_number)
+    {
+        return Err(MultipartError::InvalidPartOrder);
+    }
+
+    let uploaded = MultipartPart::find_all_in_upload(db, upload.upload_id()).await?;
+
+    let mut parts = Vec::with_capacity(request.parts.len());
+    for requested in &request.parts {
+        let part = uploaded
+            .iter()
+            .find(|part| part.part_number() == requested.part_number)
+            .filter(|part| part.etag().trim_matches('"') == requested.etag.trim_matches('"'))
+            .ok_or(MultipartError::InvalidPart(requested.part_number))?;
+
+        parts.push(part.clone());
+    }
+
+    // Only the last part may be empty
+    if let Some(part) = parts[..parts.len() - 1]
+        .iter()
+        .find(|part| part.size() == 0)
+    {
+        return Err(MultipartError::EmptyPart(part.part_number()));
+    }
+
+    let object = upload
+        .complete(
+            db,
+            &state.config.data_directory,
+            &*state.storage,
+            bucket,
+            &parts,
+        )
+        .await?;
+    state
+        .events
+        .publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    Ok(object)
+}
+
+/// Aborts a multipart upload, removing any parts which were uploaded
+pub async fn abort_upload(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    bucket: &Bucket,
+    key: &str,
+    upload_id: &str,
+) -> Result<(), MultipartError> {
+    let upload = find_upload(db, bucket, key, upload_id).await?;
+    upload.abort(db, &config.data_directory).await?;
+
+    Ok(())
+}
@@ -0,0 +1,1356 @@
+//! S3-compatible routes, mounted at the root of the server so that S3 clients
+//! using path-style requests (`/{bucket}/{key}`) can talk to Objection
+//! directly.
+//!
+//! These respond with S3 XML documents and errors, unlike the JSON API under
+//! `/api`.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    Router,
+    body::{Body, Bytes, to_bytes},
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use mime::Mime;
+use percent_encoding::percent_decode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{
+    download::{DownloadError, serve_object},
+    metadata::{
+        MAX_METADATA_SIZE, content_encoding_from_headers, metadata_from_headers,
+        public_read_from_headers,
+    },
+    multipart::{abort_upload, complete_upload, find_upload, store_part},
+    upload::{caused_by, receive_upload, verify_checksum},
+    xml::{
+        CommonPrefix, CompleteMultipartUploadResult, CopyObjectResult, CreateBucketConfiguration,
+        Delete, DeleteError, DeleteResult, DeleteResultEntry, DeletedObject,
+        InitiateMultipartUploadResult, ListBucketResult, ListMultipartUploadsResult,
+        ListPartsResult, ListVersionsResult, MultipartUploadInfo, ObjectInfo, PartInfo, S3Error,
+        Tagging, XmlResponse, format_version_id,
+    },
+};
+use crate::{
+    AppState,
+    config::Config,
+    events::{BucketEvent, BucketEvents},
+    middleware::{content_types::filter_content_types, sigv4::ContentSha256Mismatch},
+    models::{
+        bucket::{Bucket, BucketError, BucketSettings, validate_bucket_name, validate_bucket_tags},
+        multipart::{MultipartPart, MultipartUpload},
+        object::{
+            MAX_BATCH_DELETE_KEYS, Object, ObjectAttributes, ObjectError, Upload, normalize_key,
+            validate_object_tags,
+        },
+    },
+    storage::{Storage, StorageBackend},
+};
+
+/// Upper bound on `max-keys`, which is also the default
+const MAX_KEYS: u64 = 1000;
+
+/// Largest `Tagging` document accepted, far more than the most tags an object
+/// can have take up
+const MAX_TAGGING_BODY_BYTES: usize = 64 * 1024;
+
+pub fn create_s3_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/{name}",
+            get(list_objects)
+                .head(head_bucket)
+                .put(create_bucket)
+                .post(post_bucket)
+                .delete(delete_bucket),
+        )
+        .route(
+            "/{name}/{*key}",
+            get(get_object)
+                .head(head_object)
+                .put(put_object)
+                .post(post_object)
+                .delete(delete_object)
+                .layer(DefaultBodyLimit::disable())
+                .layer(axum::middleware::from_fn_with_state(
+                    state.config.clone(),
+                    filter_content_types,
+                )),
+        )
+}
+
+/// Query parameter selecting a bucket's tags rather than the bucket
+#[derive(Debug, Deserialize)]
+struct BucketTaggingQuery {
+    /// Present (with any value) for the bucket's tags
+    tagging: Option<String>,
+}
+
+/// S3 `CreateBucket`, or `PutBucketTagging` when `?tagging` is given
+async fn create_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+    Query(query): Query<BucketTaggingQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, S3Error> {
+    if query.tagging.is_some() {
+        return put_bucket_tagging(&db, &name, &body).await;
+    }
+
+    validate_bucket_name(&name)?;
+
+    // Region constraints are accepted but ignored, since every bucket lives
+    // on this server
+    if !body.is_empty() {
+        std::str::from_utf8(&body)
+            .ok()
+            .and_then(|body| quick_xml::de::from_str::<CreateBucketConfiguration>(body).ok())
+            .ok_or_else(|| {
+                S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "MalformedXML",
+                    "The request body is not a valid `CreateBucketConfiguration` document",
+                )
+            })?;
+    }
+
+    let object_lock = headers
+        .get("x-amz-bucket-object-lock-enabled")
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"true"));
+    if object_lock {
+        return Err(S3Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Object lock is not supported",
+        ));
+    }
+
+    let bucket_exists = || {
+        S3Error::new(
+            StatusCode::CONFLICT,
+            "BucketAlreadyOwnedByYou",
+            format!("The bucket `{}` already exists", name),
+        )
+    };
+
+    match Bucket::find_by_name(&db, &name).await {
+        Ok(_) => return Err(bucket_exists()),
+        Err(BucketError::NotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    match Bucket::new(&db, &name, BucketSettings::default()).await {
+        Ok(_) => {}
+        // Lost a race with another request creating the same bucket
+        Err(BucketError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            return Err(bucket_exists());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok((StatusCode::OK, [(header::LOCATION, format!("/{}", name))]).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct PostBucketQuery {
+    /// Present (with any value) for `DeleteObjects`
+    delete: Option<String>,
+}
+
+/// S3 `DeleteObjects`, the only bucket operation sent as a `POST`
+async fn post_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    State(storage): State<Storage>,
+    State(events): State<BucketEvents>,
+    Path(name): Path<String>,
+    Query(query): Query<PostBucketQuery>,
+    body: Bytes,
+) -> Result<Response, S3Error> {
+    if query.delete.is_none() {
+        return Err(S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "`POST` requests to a bucket must include the `delete` query parameter",
+        ));
+    }
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let request = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|body| quick_xml::de::from_str::<Delete>(body).ok())
+        .filter(|request| (1..=MAX_BATCH_DELETE_KEYS).contains(&request.objects.len()))
+        .ok_or_else(|| {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "MalformedXML",
+                format!(
+                    "The request body must be a `Delete` document listing between 1 and {} objects",
+                    MAX_BATCH_DELETE_KEYS
+                ),
+            )
+        })?;
+
+    let mut errors = Vec::new();
+    let mut keys = Vec::new();
+    for object in request.objects {
+        // Deleting specific versions can't share the transaction, since each
+        // is deleted on its own
+        if object.version_id.is_some() {
+            errors.push(DeleteError {
+                key: object.key,
+                code: "NotImplemented",
+                message: "Deleting specific versions in a batch is not supported".to_owned(),
+            });
+            continue;
+        }
+
+        match normalize_key(&object.key) {
+            Ok(key) => keys.push(key),
+            Err(message) => errors.push(DeleteError {
+                key: object.key,
+                code: "InvalidKey",
+                message,
+            }),
+        }
+    }
+
+    let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+    let delete_markers = Object::delete_many(&db, &*storage, &bucket, &keys).await?;
+    for key in &keys {
+        events.publish(
+            bucket.uuid(),
+            BucketEvent::ObjectDeleted {
+                key: (*key).to_owned(),
+            },
+        );
+    }
+
+    let mut entries = Vec::new();
+    if !request.quiet {
+        entries.extend(
+            keys.into_iter()
+                .zip(delete_markers)
+                .map(|(key, delete_marker)| {
+                    DeleteResultEntry::Deleted(DeletedObject {
+                        key: key.to_owned(),
+                        delete_marker: delete_marker.as_ref().map(|_| true),
+                        delete_marker_version_id: delete_marker
+                            .map(|delete_marker| format_version_id(delete_marker.version_id())),
+                    })
+                }),
+        );
+    }
+    entries.extend(errors.into_iter().map(DeleteResultEntry::Error));
+
+    Ok(XmlResponse(DeleteResult::new(entries)).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ListObjectsQuery {
+    /// `2` for `ListObjectsV2`, otherwise `ListObjects`
+    list_type: Option<String>,
+    prefix: Option<String>,
+    delimiter: Option<String>,
+    max_keys: Option<u64>,
+    /// `ListObjects` only
+    marker: Option<String>,
+    /// `ListObjectsV2` only
+    continuation_token: Option<String>,
+    /// `ListObjectsV2` only
+    start_after: Option<String>,
+    /// Present for `ListObjectVersions`
+    versions: Option<String>,
+    /// `ListObjectVersions` only
+    key_marker: Option<String>,
+    /// `ListObjectVersions` only
+    version_id_marker: Option<String>,
+    /// Present for `ListMultipartUploads`
+    uploads: Option<String>,
+    /// `ListMultipartUploads` only
+    upload_id_marker: Option<String>,
+    /// `ListMultipartUploads` only
+    max_uploads: Option<u64>,
+    /// Present for `GetBucketTagging`
+    tagging: Option<String>,
+}
+
+/// S3 `ListObjects` and `ListObjectsV2`, or `ListObjectVersions` when
+/// `?versions` is given, `ListMultipartUploads` when `?uploads` is given and
+/// `GetBucketTagging` when `?tagging` is given
+async fn list_objects(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(name): Path<String>,
+    Query(query): Query<ListObjectsQuery>,
+) -> Result<Response, S3Error> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    if query.tagging.is_some() {
+        return get_bucket_tagging(&db, &bucket).await;
+    }
+
+    if query.uploads.is_some() {
+        return list_multipart_uploads(&db, &config, &bucket, query)
+            .await
+            .map(IntoResponse::into_response);
+    }
+
+    if query.versions.is_some() {
+        return list_object_versions(&db, &bucket, query)
+            .await
+            .map(IntoResponse::into_response);
+    }
+
+    let is_v2 = query.list_type.as_deref() == Some("2");
+    let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+
+    let delimiter = match query.delimiter.as_deref().filter(|d| !d.is_empty()) {
+        Some(delimiter) => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => {
+                    return Err(S3Error::new(
+                        StatusCode::BAD_REQUEST,
+                        "InvalidArgument",
+                        "Only single character delimiters are supported",
+                    ));
+                }
+            }
+        }
+        None => None,
+    };
+
+    let start_after = match (is_v2, &query.continuation_token) {
+        (true, Some(token)) => Some(decode_continuation_token(&bucket, token)?),
+        (true, None) => query.start_after.clone(),
+        (false, _) => query.marker.clone(),
+    };
+
+    let listing = Object::find_all_in_bucket(
+        &db,
+        &bucket,
+        query.prefix.as_deref(),
+        delimiter,
+        start_after.as_deref(),
+        0,
+        max_keys,
+    )
+    .await?;
+
+    let mut result = ListBucketResult::new(
+        bucket.name().to_owned(),
+        query.prefix.clone().unwrap_or_default(),
+        delimiter.map(String::from),
+        max_keys,
+    );
+
+    let next = listing
+        .last_entry()
+        .filter(|_| listing.is_truncated)
+        .map(ToOwned::to_owned);
+
+    if is_v2 {
+        result.continuation_token = query.continuation_token;
+        result.start_after = query.start_after;
+        result.key_count = Some((listing.objects.len() + listing.common_prefixes.len()) as u64);
+        result.next_continuation_token = next.map(|next| encode_continuation_token(&bucket, &next));
+    } else {
+        result.marker = Some(query.marker.unwrap_or_default());
+        result.next_marker = next;
+    }
+
+    result.is_truncated = listing.is_truncated;
+    result.contents = listing.objects.iter().map(ObjectInfo::from).collect();
+    result.common_prefixes = listing
+        .common_prefixes
+        .into_iter()
+        .map(|prefix| CommonPrefix { prefix })
+        .collect();
+
+    Ok(XmlResponse(result).into_response())
+}
+
+/// S3 `ListObjectVersions`, which lists every version and delete marker
+async fn list_object_versions(
+    db: &sqlx::SqlitePool,
+    bucket: &Bucket,
+    query: ListObjectsQuery,
+) -> Result<XmlResponse<ListVersionsResult>, S3Error> {
+    if query.delimiter.as_deref().is_some_and(|d| !d.is_empty()) {
+        return Err(S3Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Delimiters are not supported when listing versions",
+        ));
+    }
+
+    let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+
+    // A version can only be continued from within the key it belongs to
+    let version_id_marker = match (&query.key_marker, query.version_id_marker.as_deref()) {
+        (Some(_), Some(version_id_marker)) if !version_id_marker.is_empty() => {
+            Some(parse_version_id(version_id_marker)?)
+        }
+        _ => None,
+    };
+
+    let listing = Object::find_versions_in_bucket(
+        db,
+        bucket,
+        query.prefix.as_deref(),
+        query.key_marker.as_deref(),
+        version_id_marker,
+        max_keys,
+    )
+    .await?;
+
+    let mut result = ListVersionsResult::new(
+        bucket.name().to_owned(),
+        query.prefix.unwrap_or_default(),
+        max_keys,
+    );
+
+    result.key_marker = query.key_marker.unwrap_or_default();
+    result.version_id_marker = query.version_id_marker.unwrap_or_default();
+    result.is_truncated = listing.is_truncated;
+
+    if let Some(last) = listing.objects.last().filter(|_| listing.is_truncated) {
+        result.next_key_marker = Some(last.path().to_owned());
+        result.next_version_id_marker = Some(format_version_id(last.version_id()));
+    }
+
+    result.entries = listing.objects.iter().map(Into::into).collect();
+
+    Ok(XmlResponse(result))
+}
+
+/// S3 `ListMultipartUploads`, which lists the uploads in progress. Uploads
+/// past the multipart upload TTL are still listed until they're aborted, but
+/// are marked as stale.
+async fn list_multipart_uploads(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    bucket: &Bucket,
+    query: ListObjectsQuery,
+) -> Result<XmlResponse<ListMultipartUploadsResult>, S3Error> {
+    if query.delimiter.as_deref().is_some_and(|d| !d.is_empty()) {
+        return Err(S3Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Delimiters are not supported when listing multipart uploads",
+        ));
+    }
+
+    let max_uploads = query.max_uploads.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+
+    // An upload can only be continued from within the key it belongs to
+    let upload_id_marker = match (&query.key_marker, query.upload_id_marker.as_deref()) {
+        (Some(_), Some(upload_id_marker)) if !upload_id_marker.is_empty() => {
+            Some(upload_id_marker.parse::<Uuid>().map_err(|_| {
+                S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidArgument",
+                    "The upload ID marker is not a valid upload ID",
+                )
+            })?)
+        }
+        _ => None,
+    };
+
+    let listing = MultipartUpload::find_page_in_bucket(
+        db,
+        bucket.uuid(),
+        query.prefix.as_deref(),
+        query.key_marker.as_deref(),
+        upload_id_marker,
+        max_uploads,
+    )
+    .await?;
+
+    let mut result = ListMultipartUploadsResult::new(
+        bucket.name().to_owned(),
+        query.prefix.unwrap_or_default(),
+        max_uploads,
+    );
+
+    result.key_marker = query.key_marker.unwrap_or_default();
+    result.upload_id_marker = query.upload_id_marker.unwrap_or_default();
+    result.is_truncated = listing.is_truncated;
+
+    if let Some(last) = listing.uploads.last().filter(|_| listing.is_truncated) {
+        result.next_key_marker = Some(last.key().to_owned());
+        result.next_upload_id_marker = Some(last.upload_id().to_string());
+    }
+
+    result.uploads = listing
+        .uploads
+        .iter()
+        .map(|upload| MultipartUploadInfo {
+            key: upload.key().to_owned(),
+            upload_id: upload.upload_id().to_string(),
+            initiated: upload.initiated_at(),
+            storage_class: "STANDARD",
+            stale: upload.is_stale(config.lifecycle.multipart_upload_ttl),
+        })
+        .collect();
+
+    Ok(XmlResponse(result))
+}
+
+/// Encodes the entry a listing page ended on as an opaque continuation token,
+/// tied to the bucket it was listed from
+fn encode_continuation_token(bucket: &Bucket, last_entry: &str) -> String {
+    BASE64_STANDARD.encode(format!("{}:{}", bucket.uuid(), last_entry))
+}
+
+/// Decodes a continuation token from [`encode_continuation_token`], returning
+/// the entry to start after
+fn decode_continuation_token(bucket: &Bucket, token: &str) -> Result<String, S3Error> {
+    BASE64_STANDARD
+        .decode(token)
+        .ok()
+        .and_then(|token| String::from_utf8(token).ok())
+        .and_then(|token| {
+            let (bucket_uuid, last_entry) = token.split_once(':')?;
+
+            (bucket_uuid.parse::<Uuid>().ok()? == bucket.uuid()).then(|| last_entry.to_owned())
+        })
+        .ok_or_else(|| {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "The continuation token provided is incorrect",
+            )
+        })
+}
+
+/// S3 `HeadBucket`
+async fn head_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, S3Error> {
+    Bucket::find_by_name(&db, &name).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// S3 `DeleteBucket`, which only deletes buckets without any object versions
+/// like S3 does, or `DeleteBucketTagging` when `?tagging` is given
+async fn delete_bucket(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path(name): Path<String>,
+    Query(query): Query<BucketTaggingQuery>,
+) -> Result<StatusCode, S3Error> {
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    if query.tagging.is_some() {
+        bucket.delete_tags(&db).await?;
+
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    bucket
+        .delete(&db, &config.data_directory, &*storage, false)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// S3 `GetBucketTagging`, which fails with `NoSuchTagSet` when the bucket has
+/// no tags like S3 does
+async fn get_bucket_tagging(db: &sqlx::SqlitePool, bucket: &Bucket) -> Result<Response, S3Error> {
+    let tags = bucket.get_tags(db).await?;
+    if tags.is_empty() {
+        return Err(S3Error::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchTagSet",
+            format!("The bucket `{}` has no tags", bucket.name()),
+        ));
+    }
+
+    let tags = tags
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()));
+
+    Ok(XmlResponse(Tagging::new(tags)).into_response())
+}
+
+/// S3 `PutBucketTagging`, which replaces all of the bucket's tags
+async fn put_bucket_tagging(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    body: &[u8],
+) -> Result<Response, S3Error> {
+    let tags = validate_bucket_tags(parse_tagging(body)?.into_pairs())?;
+
+    let bucket = Bucket::find_by_name(db, name).await?;
+    bucket.set_tags(db, &tags).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Parses the `Tagging` document sent to replace the tags of a bucket or
+/// object
+fn parse_tagging(body: &[u8]) -> Result<Tagging, S3Error> {
+    std::str::from_utf8(body)
+        .ok()
+        .and_then(|body| quick_xml::de::from_str::<Tagging>(body).ok())
+        .ok_or_else(|| {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "MalformedXML",
+                "The request body is not a valid `Tagging` document",
+            )
+        })
+}
+
+fn no_such_key(name: &str, key: &str) -> S3Error {
+    S3Error::new(
+        StatusCode::NOT_FOUND,
+        "NoSuchKey",
+        format!("Object `{}` does not exist in bucket `{}`", key, name),
+    )
+}
+
+/// Normalizes an object key sent by a client, rejecting invalid keys
+fn parse_key(key: &str) -> Result<String, S3Error> {
+    normalize_key(key)
+        .map_err(|message| S3Error::new(StatusCode::BAD_REQUEST, "InvalidKey", message))
+}
+
+/// Parses a `versionId` sent by a client, where `null` is the null version
+fn parse_version_id(version_id: &str) -> Result<Uuid, S3Error> {
+    match version_id {
+        "null" => Ok(Uuid::nil()),
+        version_id => version_id.parse().map_err(|_| {
+            S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "Invalid version ID specified",
+            )
+        }),
+    }
+}
+
+/// Query parameter selecting a specific version of an object
+#[derive(Debug, Deserialize)]
+struct VersionQuery {
+    #[serde(rename = "versionId")]
+    version_id: Option<String>,
+}
+
+/// Query parameters of a `PUT` of an object, which is `PutObjectTagging` when
+/// `tagging` is given and `UploadPart` when `partNumber` and `uploadId` are
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PutObjectQuery {
+    version_id: Option<String>,
+    /// Present (with any value) for the object's tags rather than the object
+    tagging: Option<String>,
+    part_number: Option<u16>,
+    upload_id: Option<String>,
+}
+
+/// Query parameters of a `POST` to an object, which is
+/// `CreateMultipartUpload` when `uploads` is given and
+/// `CompleteMultipartUpload` when `uploadId` is
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostObjectQuery {
+    uploads: Option<String>,
+    upload_id: Option<String>,
+}
+
+/// Query parameters of a `DELETE` of an object, which is
+/// `DeleteObjectTagging` when `tagging` is given and `AbortMultipartUpload`
+/// when `uploadId` is
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteObjectQuery {
+    version_id: Option<String>,
+    /// Present (with any value) for the object's tags rather than the object
+    tagging: Option<String>,
+    upload_id: Option<String>,
+}
+
+/// Query parameters of a `GET` of an object, which is `ListParts` when an
+/// `uploadId` is given
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct GetObjectQuery {
+    #[serde(rename = "versionId")]
+    version_id: Option<String>,
+    /// Present (with any value) for `GetObjectTagging`
+    tagging: Option<String>,
+    /// Present for `ListParts`
+    #[serde(rename = "uploadId")]
+    upload_id: Option<String>,
+    /// `ListParts` only
+    max_parts: Option<u64>,
+    /// `ListParts` only
+    part_number_marker: Option<u16>,
+}
+
+/// Finds the object to serve for `GetObject` and `HeadObject`, which is the
+/// latest version unless a `version_id` is given
+async fn find_object(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> Result<(Bucket, Object), S3Error> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+
+    let Some(version_id) = version_id else {
+        let object = Object::find_by_path(db, &bucket, key)
+            .await?
+            .ok_or_else(|| no_such_key(name, key))?;
+
+        return Ok((bucket, object));
+    };
+
+    let object = Object::find_version(db, &bucket, key, parse_version_id(version_id)?)
+        .await?
+        .ok_or_else(|| {
+            S3Error::new(
+                StatusCode::NOT_FOUND,
+                "NoSuchVersion",
+                format!(
+                    "Version `{}` of object `{}` does not exist in bucket `{}`",
+                    version_id, key, name
+                ),
+            )
+        })?;
+
+    if object.is_delete_marker() {
+        return Err(S3Error::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "MethodNotAllowed",
+            "The specified version is a delete marker",
+        ));
+    }
+
+    Ok((bucket, object))
+}
+
+/// Expired objects look deleted to S3 clients, rather than being gone
+fn expired_object_response(name: &str, key: &str) -> Response {
+    ([("x-amz-delete-marker", "true")], no_such_key(name, key)).into_response()
+}
+
+/// Query parameters which override headers of a `GetObject` response
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ResponseOverrides {
+    response_content_type: Option<String>,
+    response_content_language: Option<String>,
+    response_expires: Option<String>,
+    response_cache_control: Option<String>,
+    response_content_disposition: Option<String>,
+    response_content_encoding: Option<String>,
+}
+
+impl ResponseOverrides {
+    /// The headers to replace, rejecting any value which isn't a valid header
+    fn headers(self) -> Result<Vec<(HeaderName, HeaderValue)>, S3Error> {
+        [
+            (header::CONTENT_TYPE, self.response_content_type),
+            (header::CONTENT_LANGUAGE, self.response_content_language),
+            (header::EXPIRES, self.response_expires),
+            (header::CACHE_CONTROL, self.response_cache_control),
+            (
+                header::CONTENT_DISPOSITION,
+                self.response_content_disposition,
+            ),
+            (header::CONTENT_ENCODING, self.response_content_encoding),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .map(|(name, value)| {
+            HeaderValue::try_from(value)
+                .map(|value| (name.clone(), value))
+                .map_err(|_| {
+                    S3Error::new(
+                        StatusCode::BAD_REQUEST,
+                        "InvalidArgument",
+                        format!("The override for `{}` is not a valid header value", name),
+                    )
+                })
+        })
+        .collect()
+    }
+}
+
+/// S3 `GetObject`
+async fn get_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<GetObjectQuery>,
+    Query(overrides): Query<ResponseOverrides>,
+    request_headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let key = parse_key(&key)?;
+    if let Some(upload_id) = &query.upload_id {
+        return list_parts(&db, &name, &key, upload_id, &query)
+            .await
+            .map(IntoResponse::into_response);
+    }
+    if query.tagging.is_some() {
+        return get_object_tagging(&db, &name, &key, query.version_id.as_deref())
+            .await
+            .map(IntoResponse::into_response);
+    }
+
+    let overrides = overrides.headers()?;
+
+    let (bucket, object) = find_object(&db, &name, &key, query.version_id.as_deref()).await?;
+    if object.is_expired() {
+        return Ok(expired_object_response(&name, &key));
+    }
+
+    let mut response =
+        match serve_object(&config, &*storage, &bucket, &object, &request_headers, true).await {
+            Ok(response) => response,
+            Err(e) => return Ok(e.into_s3_response()),
+        };
+
+    if response.status().is_success() {
+        response.headers_mut().extend(overrides);
+    }
+
+    Ok(response)
+}
+
+/// S3 `GetObjectTagging`
+async fn get_object_tagging(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> Result<Response, S3Error> {
+    let (_, object) = find_object(db, name, key, version_id).await?;
+
+    Ok((
+        version_headers(&object),
+        XmlResponse(Tagging::new(object.tag_pairs())),
+    )
+        .into_response())
+}
+
+/// S3 `PutObjectTagging`, which replaces all of the object's tags
+async fn put_object_tagging(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    version_id: Option<&str>,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let body = to_bytes(body, MAX_TAGGING_BODY_BYTES).await.map_err(|_| {
+        S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "MalformedXML",
+            "The request body is not a valid `Tagging` document",
+        )
+    })?;
+    let tags = validate_object_tags(parse_tagging(&body)?.into_pairs())?;
+
+    let (_, mut object) = find_object(db, name, key, version_id).await?;
+    object.set_tags(db, &tags).await?;
+
+    Ok(version_headers(&object).into_response())
+}
+
+/// S3 `DeleteObjectTagging`, which removes all of the object's tags
+async fn delete_object_tagging(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> Result<Response, S3Error> {
+    let (_, mut object) = find_object(db, name, key, version_id).await?;
+    object.set_tags(db, &BTreeMap::new()).await?;
+
+    Ok((StatusCode::NO_CONTENT, version_headers(&object)).into_response())
+}
+
+/// S3 `ListParts`, which lists the parts uploaded so far for a multipart
+/// upload
+async fn list_parts(
+    db: &sqlx::SqlitePool,
+    name: &str,
+    key: &str,
+    upload_id: &str,
+    query: &GetObjectQuery,
+) -> Result<XmlResponse<ListPartsResult>, S3Error> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+    let upload = find_upload(db, &bucket, key, upload_id).await?;
+    let upload_id = upload.upload_id();
+
+    let max_parts = query.max_parts.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+    let part_number_marker = query.part_number_marker.unwrap_or(0);
+
+    let listing =
+        MultipartPart::find_page_in_upload(db, upload_id, part_number_marker, max_parts).await?;
+
+    let mut result = ListPartsResult::new(
+        bucket.name().to_owned(),
+        upload.key().to_owned(),
+        upload_id.to_string(),
+        max_parts,
+    );
+
+    result.part_number_marker = part_number_marker;
+    result.is_truncated = listing.is_truncated;
+    result.next_part_number_marker = listing
+        .parts
+        .last()
+        .filter(|_| listing.is_truncated)
+        .map(MultipartPart::part_number);
+    result.parts = listing.parts.iter().map(PartInfo::from).collect();
+
+    Ok(XmlResponse(result))
+}
+
+/// S3 `HeadObject`, which sends the same headers as `GetObject` without the
+/// contents
+async fn head_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(storage): State<Storage>,
+    Path((name, key)): Path<(String, String)>,
+    Query(version): Query<VersionQuery>,
+    request_headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let key = parse_key(&key)?;
+    let (bucket, object) = find_object(&db, &name, &key, version.version_id.as_deref()).await?;
+    if object.is_expired() {
+        return Ok(expired_object_response(&name, &key));
+    }
+
+    Ok(serve_object(
+        &config,
+        &*storage,
+        &bucket,
+        &object,
+        &request_headers,
+        false,
+    )
+    .await
+    .unwrap_or_else(DownloadError::into_s3_response))
+}
+
+/// S3 `PutObject`, `CopyObject` when `x-amz-copy-source` is sent, or
+/// `UploadPart` when `partNumber` and `uploadId` are given
+async fn put_object(
+    State(AppState {
+        db,
+        config,
+        storage,
+        events,
+        ..
+    }): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<PutObjectQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let key = parse_key(&key)?;
+    if query.tagging.is_some() {
+        return put_object_tagging(&db, &name, &key, query.version_id.as_deref(), body).await;
+    }
+    match (query.part_number, query.upload_id) {
+        (Some(part_number), Some(upload_id)) => {
+            return upload_part(&db, &config, &name, &key, &upload_id, part_number, body).await;
+        }
+        // Storing just the part as the object would silently lose the rest
+        (Some(_), None) | (None, Some(_)) => {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "InvalidRequest",
+                "Uploading a part requires both the `partNumber` and `uploadId` query parameters",
+            ));
+        }
+        (None, None) => {}
+    }
+    if headers.contains_key("x-amz-copy-source") {
+        return copy_object(&db, &config, &*storage, &events, &name, &key, &headers).await;
+    }
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    let content_type = parse_content_type(&headers)?;
+    let metadata = parse_metadata(&headers)?;
+
+    // Digests can only be checked once the whole body has been received, and
+    // the upload is discarded if they don't match
+    let upload = receive_upload(&config, &bucket, body).await?;
+    verify_payload_digests(&headers, &upload)?;
+    let (checksum_algorithm, checksum_value) = verify_checksum(&headers, &upload).await?.unzip();
+
+    let object = Object::new(
+        &db,
+        &*storage,
+        &bucket,
+        &key,
+        ObjectAttributes {
+            content_type,
+            content_encoding: content_encoding_from_headers(&headers),
+            metadata,
+            public_read: public_read_from_headers(&headers),
+            checksum_algorithm,
+            checksum_value,
+        },
+        upload,
+    )
+    .await?;
+    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    Ok((
+        version_headers(&object),
+        [(header::ETAG, object.etag().to_owned())],
+    )
+        .into_response())
+}
+
+/// S3 `UploadPart`, which stores a single part of a multipart upload
+async fn upload_part(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    name: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: u16,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let bucket = Bucket::find_by_name(db, name).await?;
+    let body = to_bytes(body, usize::MAX).await.map_err(|e| {
+        if caused_by::<ContentSha256Mismatch>(Some(&e)) {
+            return ObjectError::ContentSha256Mismatch.into();
+        }
+
+        S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "IncompleteBody",
+            "Failed to read the request body",
+        )
+    })?;
+
+    let part = store_part(db, config, &bucket, key, upload_id, part_number, &body).await?;
+
+    Ok([(header::ETAG, part.etag().to_owned())].into_response())
+}
+
+/// S3 `CreateMultipartUpload` and `CompleteMultipartUpload`, the only object
+/// operations sent as a `POST`
+async fn post_object(
+    State(state): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+    Query(query): Query<PostObjectQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, S3Error> {
+    let key = parse_key(&key)?;
+    let bucket = Bucket::find_by_name(&state.db, &name).await?;
+
+    if query.uploads.is_some() {
+        let content_type = parse_content_type(&headers)?;
+        let upload = MultipartUpload::create(&state.db, bucket.uuid(), &key, content_type).await?;
+
+        return Ok(XmlResponse(InitiateMultipartUploadResult {
+            bucket: bucket.name().to_owned(),
+            key: upload.key().to_owned(),
+            upload_id: upload.upload_id().to_string(),
+        })
+        .into_response());
+    }
+
+    if let Some(upload_id) = query.upload_id {
+        let object = complete_upload(&state, &bucket, &key, &upload_id, &body).await?;
+
+        return Ok((
+            version_headers(&object),
+            XmlResponse(CompleteMultipartUploadResult {
+                location: format!("/{}/{}", bucket.name(), object.path()),
+                bucket: bucket.name().to_owned(),
+                key: object.path().to_owned(),
+                etag: object.etag().to_owned(),
+            }),
+        )
+            .into_response());
+    }
+
+    Err(S3Error::new(
+        StatusCode::BAD_REQUEST,
+        "InvalidRequest",
+        "`POST` requests to an object must include the `uploads` or `uploadId` query parameter",
+    ))
+}
+
+/// Headers identifying the version of an object, where the null version isn't
+/// identified
+fn version_headers(object: &Object) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    if !object.version_id().is_nil()
+        && let Ok(version_id) = HeaderValue::try_from(format_version_id(object.version_id()))
+    {
+        headers.insert("x-amz-version-id", version_id);
+    }
+    if object.is_delete_marker() {
+        headers.insert("x-amz-delete-marker", HeaderValue::from_static("true"));
+    }
+
+    headers
+}
+
+/// Parses the `Content-Type` request header, if one was sent
+fn parse_content_type(headers: &HeaderMap) -> Result<Option<Mime>, S3Error> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<Mime>().ok())
+                .ok_or_else(|| {
+                    S3Error::new(
+                        StatusCode::BAD_REQUEST,
+                        "InvalidArgument",
+                        "The `Content-Type` header is not a valid MIME type",
+                    )
+                })
+        })
+        .transpose()
+}
+
+/// Collects the `x-amz-meta-*` request headers
+fn parse_metadata(headers: &HeaderMap) -> Result<BTreeMap<Box<str>, Box<str>>, S3Error> {
+    metadata_from_headers(headers).map_err(|e| {
+        S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "MetadataTooLarge",
+            format!(
+                "Object metadata must not exceed {} bytes, got {}",
+                MAX_METADATA_SIZE, e.size
+            ),
+        )
+    })
+}
+
+/// S3 `CopyObject`, copying the object named by `x-amz-copy-source` to `key`.
+/// The content type the copy ends up with must be allowed for `key`.
+async fn copy_object(
+    db: &sqlx::SqlitePool,
+    config: &Config,
+    storage: &dyn StorageBackend,
+    events: &BucketEvents,
+    name: &str,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, S3Error> {
+    let (source_name, source_key, source_version_id) =
+        parse_copy_source(&headers["x-amz-copy-source"])?;
+    let source_key = parse_key(&source_key)?;
+
+    let replace_metadata = match headers
+        .get("x-amz-metadata-directive")
+        .map(HeaderValue::as_bytes)
+    {
+        None | Some(b"COPY") => false,
+        Some(b"REPLACE") => true,
+        Some(_) => {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "InvalidArgument",
+                "`x-amz-metadata-directive` must be either `COPY` or `REPLACE`",
+            ));
+        }
+    };
+
+    if !replace_metadata
+        && source_version_id.is_none()
+        && (source_name.as_str(), source_key.as_str()) == (name, key)
+    {
+        return Err(S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "An object can only be copied to itself when its metadata is replaced",
+        ));
+    }
+
+    let (_, source) =
+        find_object(db, &source_name, &source_key, source_version_id.as_deref()).await?;
+    if source.is_expired() {
+        return Err(no_such_key(&source_name, &source_key));
+    }
+
+    let bucket = Bucket::find_by_name(db, name).await?;
+
+    let attributes = match replace_metadata {
+        true => ObjectAttributes {
+            content_type: parse_content_type(headers)?,
+            content_encoding: content_encoding_from_headers(headers),
+            metadata: parse_metadata(headers)?,
+            public_read: public_read_from_headers(headers),
+            // The contents are unchanged, so their checksum still applies
+            ..source.attributes()
+        },
+        false => source.attributes(),
+    };
+
+    // The copy may give the source's contents a key with different rules
+    let content_type = attributes
+        .content_type
+        .clone()
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    config.content_types.check(key, &content_type)?;
+
+    let object = source.copy(db, storage, &bucket, key, attributes).await?;
+    events.publish(bucket.uuid(), BucketEvent::object_created(&object));
+
+    Ok((
+        version_headers(&object),
+        XmlResponse(CopyObjectResult {
+            last_modified: object.created_at(),
+            etag: object.etag().to_owned(),
+        }),
+    )
+        .into_response())
+}
+
+/// Splits an `x-amz-copy-source` header of the form
+/// `/{bucket}/{key}?versionId={version}` into its bucket, key and version,
+/// where the leading slash and version are optional
+fn parse_copy_source(value: &HeaderValue) -> Result<(String, String, Option<String>), S3Error> {
+    let invalid = || {
+        S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidArgument",
+            "`x-amz-copy-source` must be of the form `/{bucket}/{key}`",
+        )
+    };
+
+    let value = percent_decode(value.as_bytes())
+        .decode_utf8()
+        .map_err(|_| invalid())?;
+
+    let (value, version_id) = match value.split_once("?versionId=") {
+        Some((value, version_id)) => (value, Some(version_id.to_owned())),
+        None => (&*value, None),
+    };
+
+    let value = value.strip_prefix('/').unwrap_or(value);
+    match value.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => {
+            Ok((bucket.to_owned(), key.to_owned(), version_id))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Checks the body against the `Content-MD5` and `x-amz-content-sha256`
+/// headers, when they are sent
+fn verify_payload_digests(headers: &HeaderMap, upload: &Upload) -> Result<(), S3Error> {
+    if let Some(content_md5) = headers.get("content-md5") {
+        let expected = BASE64_STANDARD
+            .decode(content_md5.as_bytes())
+            .ok()
+            .filter(|digest| digest.len() == 16)
+            .ok_or_else(|| {
+                S3Error::new(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidDigest",
+                    "The `Content-MD5` header is not a valid base64 encoded MD5 digest",
+                )
+            })?;
+
+        if upload.md5().as_slice() != expected {
+            return Err(S3Error::new(
+                StatusCode::BAD_REQUEST,
+                "BadDigest",
+                "The `Content-MD5` header does not match the body",
+            ));
+        }
+    }
+
+    let content_sha256 = headers
+        .get("x-amz-content-sha256")
+        .and_then(|value| value.to_str().ok());
+
+    match content_sha256 {
+        None | Some("UNSIGNED-PAYLOAD") => Ok(()),
+        Some(value) if value.starts_with("STREAMING-") => Err(S3Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Chunked payload signing is not supported",
+        )),
+        Some(value) if value.eq_ignore_ascii_case(upload.sha256()) => Ok(()),
+        Some(_) => Err(S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "XAmzContentSHA256Mismatch",
+            "The `x-amz-content-sha256` header does not match the body",
+        )),
+    }
+}
+
+/// S3 `DeleteObject`, which succeeds whether or not the key exists, or
+/// `AbortMultipartUpload` when an `uploadId` is given.
+///
+/// Without a `versionId` the latest version is deleted the way the bucket's
+/// versioning dictates, otherwise that version is permanently deleted.
+async fn delete_object(
+    State(db): State<sqlx::SqlitePool>,
+    State(storage): State<Storage>,
+    State(events): State<BucketEvents>,
+    State(config): State<Arc<Config>>,
+    Path((name, key)): Path<(String, String)>,
+    Query(version): Query<DeleteObjectQuery>,
+) -> Result<Response, S3Error> {
+    let key = parse_key(&key)?;
+    if version.tagging.is_some() {
+        return delete_object_tagging(&db, &name, &key, version.version_id.as_deref()).await;
+    }
+
+    let bucket = Bucket::find_by_name(&db, &name).await?;
+
+    if let Some(upload_id) = version.upload_id {
+        abort_upload(&db, &config, &bucket, &key, &upload_id).await?;
+
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let Some(version_id) = version.version_id else {
+        let headers = Object::delete_current(&db, &*storage, &bucket, &key)
+            .await?
+            .map(|delete_marker| version_headers(&delete_marker))
+            .unwrap_or_default();
+        events.publish(bucket.uuid(), BucketEvent::ObjectDeleted { key });
+
+        return Ok((StatusCode::NO_CONTENT, headers).into_response());
+    };
+
+    let version_id = parse_version_id(&version_id)?;
+
+    let mut headers = HeaderMap::new();
+    if let Some(object) = Object::find_version(&db, &bucket, &key, version_id).await? {
+        headers = version_headers(&object);
+        object.delete(&db, &*storage).await?;
+        events.publish(bucket.uuid(), BucketEvent::ObjectDeleted { key });
+    }
+
+    Ok((StatusCode::NO_CONTENT, headers).into_response())
+}
@@ -0,0 +1,114 @@
+//! Object uploads shared by the JSON API and the S3-compatible routes
+//!
+//! Request bodies are streamed to disk rather than buffered in memory, so
+//! uploads of any size use a bounded amount of memory.
+
+use std::error::Error;
+
+use axum::{body::Body, http::HeaderMap};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use futures::TryStreamExt;
+use http_body_util::LengthLimitError;
+use tokio_util::io::StreamReader;
+
+use crate::{
+    config::Config,
+    middleware::sigv4::ContentSha256Mismatch,
+    models::{
+        bucket::Bucket,
+        object::{ChecksumAlgorithm, ObjectError, Upload},
+    },
+    storage::staging_directory,
+};
+
+/// Streams a request body into the storage of `bucket`, enforcing the
+/// bucket's maximum object size and the server's maximum body size
+pub async fn receive_upload(
+    config: &Config,
+    bucket: &Bucket,
+    body: Body,
+) -> Result<Upload, ObjectError> {
+    let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+
+    match Upload::receive(
+        &staging_directory(&config.data_directory),
+        reader,
+        bucket.settings().max_object_size,
+    )
+    .await
+    {
+        Err(ObjectError::Io(e)) => Err(body_error(config, e)),
+        result => result,
+    }
+}
+
+/// Turns an error reading a request body into the reason the body was
+/// rejected, when it grew past the limit applied by `RequestBodyLimitLayer` or
+/// didn't match its signed payload hash
+pub fn body_error(config: &Config, e: std::io::Error) -> ObjectError {
+    let source = e.get_ref().map(|e| e as &(dyn Error + 'static));
+
+    if caused_by::<LengthLimitError>(source) {
+        ObjectError::TooLarge {
+            max_size: config.http.max_body_bytes.unwrap_or(u64::MAX),
+        }
+    } else if caused_by::<ContentSha256Mismatch>(source) {
+        ObjectError::ContentSha256Mismatch
+    } else {
+        ObjectError::Io(e)
+    }
+}
+
+/// Whether `E` is somewhere in the chain of errors starting at `source`
+pub fn caused_by<E: Error + 'static>(mut source: Option<&(dyn Error + 'static)>) -> bool {
+    while let Some(e) = source {
+        if e.is::<E>() {
+            return true;
+        }
+
+        source = e.source();
+    }
+
+    false
+}
+
+/// The `x-amz-checksum-*` header sent with an upload couldn't be verified
+#[derive(Debug, thiserror::Error)]
+pub enum ChecksumError {
+    #[error("Only one `x-amz-checksum-*` header may be sent")]
+    Multiple,
+    #[error("The `{}` header does not match the body", .0.header_name())]
+    Mismatch(ChecksumAlgorithm),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Checks the body against the `x-amz-checksum-*` header sent with it, if
+/// any, returning the verified algorithm and base64 encoded checksum
+pub async fn verify_checksum(
+    headers: &HeaderMap,
+    upload: &Upload,
+) -> Result<Option<(ChecksumAlgorithm, Box<str>)>, ChecksumError> {
+    let mut sent = ChecksumAlgorithm::ALL
+        .into_iter()
+        .filter_map(|algorithm| Some((algorithm, headers.get(algorithm.header_name())?)));
+
+    let Some((algorithm, expected)) = sent.next() else {
+        return Ok(None);
+    };
+    if sent.next().is_some() {
+        return Err(ChecksumError::Multiple);
+    }
+
+    let checksum = upload.checksum(algorithm).await?;
+
+    // Compared decoded so that differences in padding don't matter
+    let matches = BASE64_STANDARD
+        .decode(expected.as_bytes())
+        .is_ok_and(|expected| Ok(expected) == BASE64_STANDARD.decode(&checksum));
+    if !matches {
+        return Err(ChecksumError::Mismatch(algorithm));
+    }
+
+    Ok(Some((algorithm, checksum.into())))
+}
@@ -0,0 +1,743 @@
+//! Wire types for S3-compatible XML requests and responses
+//!
+//! These are kept separate from the database models so that either can change
+//! without breaking the other.
+
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    config::ContentTypeError,
+    models::{
+        bucket::{Bucket, BucketError, BucketNameError},
+        multipart::MultipartPart,
+        object::{Object, ObjectError},
+        tag::TagError,
+    },
+    routes::{multipart::MultipartError, upload::ChecksumError},
+};
+
+/// An XML response, serialized with `quick-xml` and sent with the
+/// `application/xml` content type required by S3 clients.
+///
+/// The root element is named after the wrapped type, so response types should
+/// be named after the S3 element they represent (e.g.
+/// `ListAllMyBucketsResult`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XmlResponse<T>(pub T);
+
+impl<T: Serialize> IntoResponse for XmlResponse<T> {
+    fn into_response(self) -> Response {
+        match quick_xml::se::to_string(&self.0) {
+            Ok(body) => (
+                [(header::CONTENT_TYPE, "application/xml; charset=UTF-8")],
+                format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, body),
+            )
+                .into_response(),
+            Err(e) => {
+                tracing::error!("Failed to serialize XML response: {}", e);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+/// An S3 error, rendered as an `<Error>` document with the given status
+#[derive(Debug)]
+pub struct S3Error {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl S3Error {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for S3Error {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!("Database error: {}", value);
+
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "InternalError",
+            "An internal database error occurred",
+        )
+    }
+}
+
+impl From<BucketError> for S3Error {
+    fn from(value: BucketError) -> Self {
+        match value {
+            BucketError::Database(e) => e.into(),
+            BucketError::NotFound(name) => Self::new(
+                StatusCode::NOT_FOUND,
+                "NoSuchBucket",
+                format!("The bucket `{}` does not exist", name),
+            ),
+            BucketError::NotEmpty(name) => Self::new(
+                StatusCode::CONFLICT,
+                "BucketNotEmpty",
+                format!("The bucket `{}` is not empty", name),
+            ),
+            BucketError::InvalidName(e) => e.into(),
+        }
+    }
+}
+
+impl From<BucketNameError> for S3Error {
+    fn from(value: BucketNameError) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidBucketName",
+            value.to_string(),
+        )
+    }
+}
+
+impl From<ChecksumError> for S3Error {
+    fn from(value: ChecksumError) -> Self {
+        match value {
+            ChecksumError::Multiple => {
+                Self::new(StatusCode::BAD_REQUEST, "InvalidRequest", value.to_string())
+            }
+            ChecksumError::Mismatch(_) => {
+                Self::new(StatusCode::BAD_REQUEST, "BadDigest", value.to_string())
+            }
+            ChecksumError::Io(e) => ObjectError::Io(e).into(),
+        }
+    }
+}
+
+impl From<TagError> for S3Error {
+    fn from(value: TagError) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidTag", value.to_string())
+    }
+}
+
+impl From<ContentTypeError> for S3Error {
+    fn from(value: ContentTypeError) -> Self {
+        Self::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UnsupportedMediaType",
+            value.to_string(),
+        )
+    }
+}
+
+impl From<ObjectError> for S3Error {
+    fn from(value: ObjectError) -> Self {
+        match value {
+            ObjectError::Database(e) => e.into(),
+            ObjectError::Io(e) => {
+                tracing::error!("Storage error: {}", e);
+
+                Self::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "InternalError",
+                    "An internal storage error occurred",
+                )
+            }
+            ObjectError::TooLarge { max_size } => Self::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "EntityTooLarge",
+                format!("Objects in this bucket must not exceed {} bytes", max_size),
+            ),
+            ObjectError::ContentSha256Mismatch => Self::new(
+                StatusCode::BAD_REQUEST,
+                "XAmzContentSHA256Mismatch",
+                value.to_string(),
+            ),
+        }
+    }
+}
+
+impl From<MultipartError> for S3Error {
+    fn from(value: MultipartError) -> Self {
+        let (status, code) = match value {
+            MultipartError::Object(e) => return e.into(),
+            MultipartError::NoSuchUpload { .. } => (StatusCode::NOT_FOUND, "NoSuchUpload"),
+            MultipartError::InvalidPartNumber => (StatusCode::BAD_REQUEST, "InvalidArgument"),
+            MultipartError::MalformedXml(_) => (StatusCode::BAD_REQUEST, "MalformedXML"),
+            MultipartError::InvalidPartOrder => (StatusCode::BAD_REQUEST, "InvalidPartOrder"),
+            MultipartError::InvalidPart(_) => (StatusCode::BAD_REQUEST, "InvalidPart"),
+            MultipartError::EmptyPart(_) => (StatusCode::BAD_REQUEST, "EntityTooSmall"),
+        };
+
+        Self::new(status, code, value.to_string())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "Error", rename_all = "PascalCase")]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+impl IntoResponse for S3Error {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            XmlResponse(ErrorBody {
+                code: self.code,
+                message: &self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+/// Namespace of all S3 XML documents
+const S3_NAMESPACE: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+/// Serializes a timestamp in the ISO 8601 format used by S3, with millisecond
+/// precision (e.g. `2025-11-03T03:16:46.000Z`)
+fn serialize_timestamp<S: serde::Serializer>(
+    timestamp: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// Formats a version ID the way S3 does, where the null version is `null`
+pub fn format_version_id(version_id: Uuid) -> String {
+    match version_id.is_nil() {
+        true => "null".to_owned(),
+        false => version_id.to_string(),
+    }
+}
+
+/// The owner of every bucket. Objection has no concept of users, so this is
+/// always the same.
+#[derive(Debug, Serialize)]
+pub struct Owner {
+    #[serde(rename = "ID")]
+    pub id: &'static str,
+    #[serde(rename = "DisplayName")]
+    pub display_name: &'static str,
+}
+
+impl Default for Owner {
+    fn default() -> Self {
+        Self {
+            id: "objection",
+            display_name: "objection",
+        }
+    }
+}
+
+/// Response to `ListBuckets`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListAllMyBucketsResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    pub owner: Owner,
+    pub buckets: Buckets,
+}
+
+impl ListAllMyBucketsResult {
+    pub fn new(buckets: Vec<BucketInfo>) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            owner: Owner::default(),
+            buckets: Buckets { bucket: buckets },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Buckets {
+    pub bucket: Vec<BucketInfo>,
+}
+
+/// A single `<Bucket>` entry in a `ListAllMyBucketsResult`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BucketInfo {
+    pub name: String,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub creation_date: DateTime<Utc>,
+}
+
+impl From<&Bucket> for BucketInfo {
+    fn from(value: &Bucket) -> Self {
+        Self {
+            name: value.name().to_owned(),
+            creation_date: value.created_at(),
+        }
+    }
+}
+
+/// Response to `CreateMultipartUpload`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InitiateMultipartUploadResult {
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+}
+
+/// Response to `ListMultipartUploads`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListMultipartUploadsResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    pub bucket: String,
+    pub key_marker: String,
+    pub upload_id_marker: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_key_marker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_upload_id_marker: Option<String>,
+    pub prefix: String,
+    pub max_uploads: u64,
+    pub is_truncated: bool,
+    #[serde(rename = "Upload")]
+    pub uploads: Vec<MultipartUploadInfo>,
+}
+
+impl ListMultipartUploadsResult {
+    pub fn new(bucket: String, prefix: String, max_uploads: u64) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            bucket,
+            key_marker: String::new(),
+            upload_id_marker: String::new(),
+            next_key_marker: None,
+            next_upload_id_marker: None,
+            prefix,
+            max_uploads,
+            is_truncated: false,
+            uploads: Vec::new(),
+        }
+    }
+}
+
+/// A single `<Upload>` entry in a `ListMultipartUploadsResult`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultipartUploadInfo {
+    pub key: String,
+    pub upload_id: String,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub initiated: DateTime<Utc>,
+    pub storage_class: &'static str,
+    /// Not part of S3, marks uploads which are past the multipart upload TTL
+    /// and will be aborted
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// Response to `ListParts`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListPartsResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub part_number_marker: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_part_number_marker: Option<u16>,
+    pub max_parts: u64,
+    pub is_truncated: bool,
+    pub storage_class: &'static str,
+    #[serde(rename = "Part")]
+    pub parts: Vec<PartInfo>,
+}
+
+impl ListPartsResult {
+    pub fn new(bucket: String, key: String, upload_id: String, max_parts: u64) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            bucket,
+            key,
+            upload_id,
+            part_number_marker: 0,
+            next_part_number_marker: None,
+            max_parts,
+            is_truncated: false,
+            storage_class: "STANDARD",
+            parts: Vec::new(),
+        }
+    }
+}
+
+/// A single `<Part>` entry in a `ListPartsResult`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PartInfo {
+    pub part_number: u16,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+}
+
+impl From<&MultipartPart> for PartInfo {
+    fn from(value: &MultipartPart) -> Self {
+        Self {
+            part_number: value.part_number(),
+            last_modified: value.last_modified(),
+            etag: value.etag().to_owned(),
+            size: value.size(),
+        }
+    }
+}
+
+/// Request body of `CompleteMultipartUpload`, listing the parts to join in
+/// order
+#[derive(Debug, Deserialize)]
+pub struct CompleteMultipartUpload {
+    #[serde(rename = "Part", default)]
+    pub parts: Vec<CompletedPart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CompletedPart {
+    pub part_number: u16,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// Response to `CompleteMultipartUpload`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CompleteMultipartUploadResult {
+    pub location: String,
+    pub bucket: String,
+    pub key: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// Response to `CopyObject`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CopyObjectResult {
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// Optional request body of `CreateBucket`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateBucketConfiguration {
+    /// Region to create the bucket in, which is ignored
+    #[allow(dead_code)]
+    pub location_constraint: Option<String>,
+}
+
+/// Response to both `ListObjects` and `ListObjectsV2`, which share a root
+/// element but differ in how pagination is reported
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListBucketResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    pub max_keys: u64,
+    /// `ListObjects` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marker: Option<String>,
+    /// `ListObjects` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_marker: Option<String>,
+    /// `ListObjectsV2` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+    /// `ListObjectsV2` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_continuation_token: Option<String>,
+    /// `ListObjectsV2` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_after: Option<String>,
+    /// `ListObjectsV2` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_count: Option<u64>,
+    pub is_truncated: bool,
+    pub contents: Vec<ObjectInfo>,
+    pub common_prefixes: Vec<CommonPrefix>,
+}
+
+impl ListBucketResult {
+    pub fn new(name: String, prefix: String, delimiter: Option<String>, max_keys: u64) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            name,
+            prefix,
+            delimiter,
+            max_keys,
+            marker: None,
+            next_marker: None,
+            continuation_token: None,
+            next_continuation_token: None,
+            start_after: None,
+            key_count: None,
+            is_truncated: false,
+            contents: Vec::new(),
+            common_prefixes: Vec::new(),
+        }
+    }
+}
+
+/// A single `<Contents>` entry in a `ListBucketResult`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectInfo {
+    pub key: String,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+    pub storage_class: &'static str,
+}
+
+impl From<&Object> for ObjectInfo {
+    fn from(value: &Object) -> Self {
+        Self {
+            key: value.path().to_owned(),
+            last_modified: value.created_at(),
+            etag: value.etag().to_owned(),
+            size: value.size(),
+            storage_class: "STANDARD",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CommonPrefix {
+    pub prefix: String,
+}
+
+/// Response to `ListObjectVersions`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListVersionsResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    pub name: String,
+    pub prefix: String,
+    pub key_marker: String,
+    pub version_id_marker: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_key_marker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_version_id_marker: Option<String>,
+    pub max_keys: u64,
+    pub is_truncated: bool,
+    /// Versions and delete markers, interleaved in listing order
+    #[serde(rename = "$value")]
+    pub entries: Vec<VersionEntry>,
+}
+
+impl ListVersionsResult {
+    pub fn new(name: String, prefix: String, max_keys: u64) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            name,
+            prefix,
+            key_marker: String::new(),
+            version_id_marker: String::new(),
+            next_key_marker: None,
+            next_version_id_marker: None,
+            max_keys,
+            is_truncated: false,
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// A single entry in a `ListVersionsResult`, named after its element
+#[derive(Debug, Serialize)]
+pub enum VersionEntry {
+    Version(VersionInfo),
+    DeleteMarker(DeleteMarkerInfo),
+}
+
+impl From<&Object> for VersionEntry {
+    fn from(value: &Object) -> Self {
+        let key = value.path().to_owned();
+        let version_id = format_version_id(value.version_id());
+
+        match value.is_delete_marker() {
+            true => VersionEntry::DeleteMarker(DeleteMarkerInfo {
+                key,
+                version_id,
+                is_latest: value.is_latest(),
+                last_modified: value.created_at(),
+            }),
+            false => VersionEntry::Version(VersionInfo {
+                key,
+                version_id,
+                is_latest: value.is_latest(),
+                last_modified: value.created_at(),
+                etag: value.etag().to_owned(),
+                size: value.size(),
+                storage_class: "STANDARD",
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VersionInfo {
+    pub key: String,
+    pub version_id: String,
+    pub is_latest: bool,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+    pub storage_class: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteMarkerInfo {
+    pub key: String,
+    pub version_id: String,
+    pub is_latest: bool,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Tags of an object or bucket, both the request body of `PutObjectTagging`
+/// and `PutBucketTagging` and the response to their `Get` counterparts
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tagging {
+    #[serde(rename = "@xmlns", skip_deserializing)]
+    xmlns: &'static str,
+    pub tag_set: TagSet,
+}
+
+impl Tagging {
+    pub fn new<'a>(tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            tag_set: TagSet {
+                tags: tags
+                    .into_iter()
+                    .map(|(key, value)| Tag {
+                        key: key.to_owned(),
+                        value: value.to_owned(),
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// The tags as key-value pairs, in the order they were sent
+    pub fn into_pairs(self) -> impl Iterator<Item = (String, String)> {
+        self.tag_set
+            .tags
+            .into_iter()
+            .map(|tag| (tag.key, tag.value))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagSet {
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tag {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Request body of `DeleteObjects`, listing the objects to delete
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Delete {
+    /// Only errors are reported when set
+    #[serde(default)]
+    pub quiet: bool,
+    #[serde(rename = "Object", default)]
+    pub objects: Vec<ObjectIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectIdentifier {
+    pub key: String,
+    pub version_id: Option<String>,
+}
+
+/// Response to `DeleteObjects`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteResult {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    /// Deleted objects and errors, named after their elements
+    #[serde(rename = "$value")]
+    pub entries: Vec<DeleteResultEntry>,
+}
+
+impl DeleteResult {
+    pub fn new(entries: Vec<DeleteResultEntry>) -> Self {
+        Self {
+            xmlns: S3_NAMESPACE,
+            entries,
+        }
+    }
+}
+
+/// A single entry in a `DeleteResult`, named after its element
+#[derive(Debug, Serialize)]
+pub enum DeleteResultEntry {
+    Deleted(DeletedObject),
+    Error(DeleteError),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedObject {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_marker: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_marker_version_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteError {
+    pub key: String,
+    pub code: &'static str,
+    pub message: String,
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use tempfile::TempPath;
+use tokio::sync::{Notify, broadcast};
+use tokio_util::io::{ReaderStream, StreamReader};
+use uuid::Uuid;
+
+use super::{Storage, StorageBackend, StorageReader};
+
+/// Chunks of contents a shared read can get ahead of its slowest reader
+const SHARED_READ_CAPACITY: usize = 64;
+
+/// How long a shared read waits for its slowest reader to make room before
+/// leaving it behind. Readers which are left behind finish reading the
+/// contents by themselves.
+const SLOW_READER_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+enum Chunk {
+    Data(Bytes),
+    /// Sent after the last chunk, so readers can tell the end of the contents
+    /// apart from a read which failed part way
+    End,
+}
+
+/// A read of some contents which is shared by every reader that asks for them
+/// before it starts
+#[derive(Debug, Clone)]
+struct SharedRead {
+    chunks: broadcast::Sender<Chunk>,
+    /// Notified whenever a reader receives a chunk, so the shared read can
+    /// wait for its slowest reader
+    progress: Arc<Notify>,
+}
+
+/// Wraps another backend so that reads of the same contents which arrive
+/// within `window` of each other share a single read of the backend, whose
+/// chunks are broadcast to all of them.
+///
+/// Reads are delayed by up to `window` while other readers can join. Readers
+/// which fall too far behind the others finish reading by themselves, so
+/// a stalled client can't hold up everyone else. Everything other than whole
+/// reads is passed through to the wrapped backend.
+#[derive(Clone)]
+pub struct CoalescingBackend {
+    inner: Storage,
+    window: Duration,
+    reads: Arc<Mutex<HashMap<(Uuid, String), SharedRead>>>,
+}
+
+impl CoalescingBackend {
+    pub fn new(inner: Storage, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            reads: Arc::default(),
+        }
+    }
+
+    fn reads(&self) -> MutexGuard<'_, HashMap<(Uuid, String), SharedRead>> {
+        // The map is never left partially modified, so a poisoned lock is
+        // still safe to use
+        self.reads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Reads the chunks broadcast by a shared read, switching to reading the
+    /// rest of the contents directly if the shared read is lost
+    fn reader(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        receiver: broadcast::Receiver<Chunk>,
+        progress: Arc<Notify>,
+    ) -> StorageReader {
+        enum Piece {
+            Data(Bytes),
+            /// The shared read was lost after this many bytes
+            Resume(u64),
+        }
+
+        let pieces = futures::stream::unfold(Some((receiver, 0u64)), move |state| {
+            let progress = progress.clone();
+            async move {
+                let (mut receiver, offset) = state?;
+                let result = receiver.recv().await;
+                progress.notify_one();
+
+                match result {
+                    Ok(Chunk::Data(data)) => {
+                        let offset = offset + data.len() as u64;
+                        Some((Piece::Data(data), Some((receiver, offset))))
+                    }
+                    Ok(Chunk::End) => None,
+                    Err(_) => Some((Piece::Resume(offset), None)),
+                }
+            }
+        });
+
+        let inner = self.inner.clone();
+        let hash = hash.to_owned();
+        let contents = pieces.flat_map(move |piece| match piece {
+            Piece::Data(data) => futures::stream::once(async { Ok(data) }).left_stream(),
+            Piece::Resume(offset) => {
+                let inner = inner.clone();
+                let hash = hash.clone();
+                futures::stream::once(async move {
+                    inner.get_range(bucket_id, &hash, offset, u64::MAX).await
+                })
+                .map_ok(ReaderStream::new)
+                .try_flatten()
+                .right_stream()
+            }
+        });
+
+        Box::pin(StreamReader::new(contents))
+    }
+
+    /// Broadcasts `contents` once the window for joining the read has passed
+    async fn share(&self, key: (Uuid, String), read: SharedRead, contents: StorageReader) {
+        tokio::time::sleep(self.window).await;
+
+        // Later readers would miss the chunks already sent
+        {
+            let mut reads = self.reads();
+            if reads
+                .get(&key)
+                .is_some_and(|current| current.chunks.same_channel(&read.chunks))
+            {
+                reads.remove(&key);
+            }
+        }
+
+        let mut contents = ReaderStream::new(contents);
+        while let Some(data) = contents.next().await {
+            // Dropping the sender without sending the end makes readers
+            // resume by themselves, and see the error
+            let Ok(data) = data else {
+                return;
+            };
+
+            // Every reader has gone away
+            if read.send(Chunk::Data(data)).await.is_err() {
+                return;
+            }
+        }
+
+        let _ = read.send(Chunk::End).await;
+    }
+}
+
+impl SharedRead {
+    /// Sends a chunk to every reader once the slowest one has made room for
+    /// it, or has been left behind
+    async fn send(&self, chunk: Chunk) -> Result<usize, broadcast::error::SendError<Chunk>> {
+        while self.chunks.len() >= SHARED_READ_CAPACITY {
+            let progress = self.progress.notified();
+            if tokio::time::timeout(SLOW_READER_TIMEOUT, progress)
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        self.chunks.send(chunk)
+    }
+}
+
+impl std::fmt::Debug for CoalescingBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoalescingBackend")
+            .field("inner", &self.inner)
+            .field("window", &self.window)
+            .field("shared_reads", &self.reads().len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for CoalescingBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn put(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        data: StorageReader,
+    ) -> io::Result<u64> {
+        self.inner.put(bucket_id, hash, key, data).await
+    }
+
+    async fn get(&self, bucket_id: Uuid, hash: &str) -> io::Result<StorageReader> {
+        let key = (bucket_id, hash.to_owned());
+        if let Some(read) = self.reads().get(&key) {
+            return Ok(self.reader(
+                bucket_id,
+                hash,
+                read.chunks.subscribe(),
+                read.progress.clone(),
+            ));
+        }
+
+        // The contents are opened before anyone can join, so errors like
+        // missing contents are returned straight away
+        let contents = self.inner.get(bucket_id, hash).await?;
+
+        let (chunks, receiver) = broadcast::channel(SHARED_READ_CAPACITY);
+        let read = SharedRead {
+            chunks,
+            progress: Arc::new(Notify::new()),
+        };
+        let reader = self.reader(bucket_id, hash, receiver, read.progress.clone());
+
+        // Another read of the same contents may have started in the meantime,
+        // in which case both go ahead separately
+        self.reads().entry(key.clone()).or_insert(read.clone());
+
+        let backend = self.clone();
+        tokio::spawn(async move { backend.share(key, read, contents).await });
+
+        Ok(reader)
+    }
+
+    async fn delete(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        self.inner.delete(bucket_id, hash).await
+    }
+
+    async fn exists(&self, bucket_id: Uuid, hash: &str) -> io::Result<bool> {
+        self.inner.exists(bucket_id, hash).await
+    }
+
+    async fn delete_bucket(&self, bucket_id: Uuid) -> io::Result<()> {
+        self.inner.delete_bucket(bucket_id).await
+    }
+
+    async fn put_file(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        file: TempPath,
+    ) -> io::Result<u64> {
+        self.inner.put_file(bucket_id, hash, key, file).await
+    }
+
+    async fn get_range(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<StorageReader> {
+        self.inner.get_range(bucket_id, hash, offset, len).await
+    }
+
+    async fn stored_key(&self, bucket_id: Uuid, hash: &str) -> io::Result<Option<String>> {
+        self.inner.stored_key(bucket_id, hash).await
+    }
+
+    async fn copy(
+        &self,
+        from_bucket_id: Uuid,
+        to_bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+    ) -> io::Result<()> {
+        self.inner
+            .copy(from_bucket_id, to_bucket_id, hash, key)
+            .await
+    }
+
+    async fn list(&self, bucket_id: Uuid) -> io::Result<Vec<String>> {
+        self.inner.list(bucket_id).await
+    }
+
+    async fn list_buckets(&self) -> io::Result<Vec<Uuid>> {
+        self.inner.list_buckets().await
+    }
+}
@@ -0,0 +1,696 @@
+use std::{
+    io::{self, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use tempfile::{PathPersistError, TempPath};
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::{Mutex, MutexGuard},
+};
+use uuid::Uuid;
+
+use super::{StorageBackend, StorageReader};
+
+/// File in the root recording the [`PathStrategy`] it is laid out with
+const PATH_STRATEGY_FILE: &str = "path-strategy";
+
+/// Characters escaped in the file names of [`PathStrategy::Flat`]. `~` is
+/// escaped so that it can separate a name from a hash.
+const NAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.');
+
+/// Longest file name most filesystems allow, less the `~` marking the
+/// directory of contents waiting for a name
+const MAX_NAME_LEN: usize = 254;
+
+/// How [`LocalFsBackend`] lays out contents on disk. The strategy is recorded
+/// in the root the first time it is used, and the backend refuses to open a
+/// root laid out with another strategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PathStrategy {
+    /// `{root}/{bucket uuid}/{encoded key}`, named after the key the contents
+    /// were stored for with `/` and other special characters percent encoded.
+    ///
+    /// Contents stored for a key whose name is still taken by other contents,
+    /// like those of an older version, wait in
+    /// `{root}/{bucket uuid}/{encoded key}~/{hash}` and the newest of them
+    /// takes the name once it is freed. Keys too long for a file name are
+    /// stored as `~{hash}`. The name of the contents for each hash is recorded
+    /// in `{root}/hashes/{bucket uuid}/{hash}`.
+    #[default]
+    Flat,
+    /// `{root}/{bucket uuid}/{hash[0..2]}/{hash[2..4]}/{hash}`, which keeps
+    /// directories small in buckets with many objects
+    Sharded,
+    /// `{root}/contents/{hash}`, storing contents shared by several buckets
+    /// only once. Each bucket storing them has an empty file at both
+    /// `{root}/references/{bucket uuid}/{hash}` and
+    /// `{root}/referrers/{hash}/{bucket uuid}`, and the contents are removed
+    /// along with the last of these.
+    ContentAddressed,
+}
+
+/// Stores contents on local disk under `root`, laid out according to its
+/// [`PathStrategy`]
+#[derive(Debug, Clone)]
+pub struct LocalFsBackend {
+    root: PathBuf,
+    path_strategy: PathStrategy,
+    /// Held while contents are named or shared between buckets, so that names
+    /// aren't given out twice and contents aren't removed as another bucket
+    /// starts using them
+    names: Arc<Mutex<()>>,
+}
+
+impl LocalFsBackend {
+    /// Opens the contents stored under `root`, recording the path strategy in
+    /// it if it hasn't been used before. Fails with
+    /// [`io::ErrorKind::InvalidData`] if it was laid out with another path
+    /// strategy, whose contents couldn't be read.
+    pub fn open(root: impl Into<PathBuf>, path_strategy: PathStrategy) -> io::Result<Self> {
+        let root = root.into();
+        let marker = root.join(PATH_STRATEGY_FILE);
+
+        match std::fs::read_to_string(&marker) {
+            Ok(recorded) if recorded.trim() == path_strategy.to_string() => {}
+            Ok(recorded) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} is laid out with the `{}` path strategy, not `{}`",
+                        root.display(),
+                        recorded.trim(),
+                        path_strategy
+                    ),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                std::fs::create_dir_all(&root)?;
+                std::fs::write(&marker, path_strategy.to_string())?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(Self {
+            root,
+            path_strategy,
+            names: Arc::default(),
+        })
+    }
+
+    fn bucket_path(&self, bucket_id: Uuid) -> PathBuf {
+        self.root.join(bucket_id.to_string())
+    }
+
+    fn hashes_path(&self, bucket_id: Uuid) -> PathBuf {
+        self.root.join("hashes").join(bucket_id.to_string())
+    }
+
+    fn reference_path(&self, bucket_id: Uuid) -> PathBuf {
+        self.root.join("references").join(bucket_id.to_string())
+    }
+
+    fn referrers_path(&self, hash: &str) -> PathBuf {
+        self.root.join("referrers").join(hash)
+    }
+
+    fn shared_path(&self, hash: &str) -> PathBuf {
+        self.root.join("contents").join(hash)
+    }
+
+    fn sharded_path(&self, bucket_id: Uuid, hash: &str) -> PathBuf {
+        let shard = |range| hash.get(range).unwrap_or_default();
+
+        self.bucket_path(bucket_id)
+            .join(shard(0..2))
+            .join(shard(2..4))
+            .join(hash)
+    }
+
+    /// Name of the file storing the contents for `hash` in flat storage,
+    /// relative to the bucket's directory
+    async fn flat_name(&self, bucket_id: Uuid, hash: &str) -> io::Result<String> {
+        tokio::fs::read_to_string(self.hashes_path(bucket_id).join(hash)).await
+    }
+
+    /// Records the name of the contents for `hash` in flat storage. The record
+    /// is replaced in one go, so it is never read partially written.
+    async fn set_flat_name(&self, bucket_id: Uuid, hash: &str, name: &str) -> io::Result<()> {
+        let directory = self.hashes_path(bucket_id);
+        tokio::fs::create_dir_all(&directory).await?;
+
+        let mut file = tempfile::NamedTempFile::new_in(&directory)?;
+        file.write_all(name.as_bytes())?;
+        file.persist(directory.join(hash)).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    /// Chooses the name of new contents for `key` in flat storage
+    async fn free_flat_name(&self, bucket_id: Uuid, hash: &str, key: &str) -> io::Result<String> {
+        let Some(name) = encode_key(key) else {
+            return Ok(format!("~{}", hash));
+        };
+
+        match tokio::fs::try_exists(self.bucket_path(bucket_id).join(&name)).await? {
+            true => Ok(format!("{}~/{}", name, hash)),
+            false => Ok(name),
+        }
+    }
+
+    /// Where the contents for `hash` are stored, or will be stored for `key`
+    async fn path(&self, bucket_id: Uuid, hash: &str, key: &str) -> io::Result<PathBuf> {
+        match self.path_strategy {
+            PathStrategy::Flat => {
+                let name = match self.flat_name(bucket_id, hash).await {
+                    Ok(name) => name,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        self.free_flat_name(bucket_id, hash, key).await?
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                Ok(self.bucket_path(bucket_id).join(name))
+            }
+            PathStrategy::Sharded => Ok(self.sharded_path(bucket_id, hash)),
+            PathStrategy::ContentAddressed => Ok(self.shared_path(hash)),
+        }
+    }
+
+    /// Opens the contents stored for `hash`. Flat storage renames contents
+    /// when they take the name of other contents, so the name is checked again
+    /// once the file is open.
+    async fn open_contents(&self, bucket_id: Uuid, hash: &str) -> io::Result<tokio::fs::File> {
+        match self.path_strategy {
+            PathStrategy::Flat => {
+                let mut name = self.flat_name(bucket_id, hash).await?;
+                loop {
+                    let file = tokio::fs::File::open(self.bucket_path(bucket_id).join(&name)).await;
+
+                    let current = self.flat_name(bucket_id, hash).await?;
+                    if current == name {
+                        return file;
+                    }
+                    name = current;
+                }
+            }
+            PathStrategy::Sharded => {
+                tokio::fs::File::open(self.sharded_path(bucket_id, hash)).await
+            }
+            PathStrategy::ContentAddressed => tokio::fs::File::open(self.shared_path(hash)).await,
+        }
+    }
+
+    /// Moves a file into place as the contents for `hash`, stored for `key`.
+    /// The file is handed back if it can't be renamed into place.
+    async fn store(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        file: TempPath,
+    ) -> io::Result<Result<(), PathPersistError>> {
+        let _names = self.lock_names().await;
+
+        let path = self.path(bucket_id, hash, key).await?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        if let Err(e) = file.persist(&path) {
+            return Ok(Err(e));
+        }
+
+        match self.path_strategy {
+            PathStrategy::Flat => {
+                let bucket_path = self.bucket_path(bucket_id);
+                let name = path
+                    .strip_prefix(&bucket_path)
+                    .map_err(io::Error::other)?
+                    .to_string_lossy();
+
+                self.set_flat_name(bucket_id, hash, &name).await?;
+            }
+            PathStrategy::Sharded => {}
+            PathStrategy::ContentAddressed => self.add_reference(bucket_id, hash).await?,
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Removes the contents for `hash` from flat storage, handing their name
+    /// to other contents waiting for it
+    async fn delete_flat(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        let name = match self.flat_name(bucket_id, hash).await {
+            Ok(name) => name,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let bucket_path = self.bucket_path(bucket_id);
+        ignore_not_found(tokio::fs::remove_file(bucket_path.join(&name)).await)?;
+        ignore_not_found(tokio::fs::remove_file(self.hashes_path(bucket_id).join(hash)).await)?;
+
+        match name.split_once("~/") {
+            // The directory is only removed once nothing else waits in it
+            Some((name, _)) => {
+                let _ = tokio::fs::remove_dir(bucket_path.join(format!("{}~", name))).await;
+                Ok(())
+            }
+            None if name.starts_with('~') => Ok(()),
+            None => self.hand_over_name(bucket_id, &name).await,
+        }
+    }
+
+    /// Gives `name` to the newest contents waiting for it, now that it is free
+    async fn hand_over_name(&self, bucket_id: Uuid, name: &str) -> io::Result<()> {
+        let waiting = self.bucket_path(bucket_id).join(format!("{}~", name));
+        let mut entries = match tokio::fs::read_dir(&waiting).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut newest = None;
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(hash) = entry
+                .file_name()
+                .to_str()
+                .filter(|name| is_hash(name))
+                .map(ToOwned::to_owned)
+            else {
+                continue;
+            };
+
+            let modified = entry.metadata().await?.modified()?;
+            if newest
+                .as_ref()
+                .is_none_or(|(newest_modified, _)| modified > *newest_modified)
+            {
+                newest = Some((modified, hash));
+            }
+        }
+
+        if let Some((_, hash)) = newest {
+            let from = waiting.join(&hash);
+            let to = self.bucket_path(bucket_id).join(name);
+
+            // Linking keeps the contents under their old name until the new
+            // one is recorded, so they can be opened by either
+            match tokio::fs::hard_link(&from, &to).await {
+                Ok(()) => {
+                    self.set_flat_name(bucket_id, &hash, name).await?;
+                    ignore_not_found(tokio::fs::remove_file(&from).await)?;
+                }
+                // Hard links aren't supported by every filesystem
+                Err(_) => {
+                    tokio::fs::rename(&from, &to).await?;
+                    self.set_flat_name(bucket_id, &hash, name).await?;
+                }
+            }
+        }
+
+        let _ = tokio::fs::remove_dir(&waiting).await;
+
+        Ok(())
+    }
+
+    /// Whether any bucket still stores the shared contents for `hash`
+    async fn is_referenced(&self, hash: &str) -> io::Result<bool> {
+        match tokio::fs::read_dir(self.referrers_path(hash)).await {
+            Ok(mut referrers) => Ok(referrers.next_entry().await?.is_some()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Records that a bucket stores the shared contents for `hash`
+    async fn add_reference(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        let references = self.reference_path(bucket_id);
+        let referrers = self.referrers_path(hash);
+        tokio::fs::create_dir_all(&references).await?;
+        tokio::fs::create_dir_all(&referrers).await?;
+
+        tokio::fs::File::create(references.join(hash)).await?;
+        tokio::fs::File::create(referrers.join(bucket_id.to_string())).await?;
+
+        Ok(())
+    }
+
+    /// Records that a bucket no longer stores the shared contents for `hash`,
+    /// removing them if no other bucket does
+    async fn remove_reference(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        ignore_not_found(tokio::fs::remove_file(self.reference_path(bucket_id).join(hash)).await)?;
+        ignore_not_found(
+            tokio::fs::remove_file(self.referrers_path(hash).join(bucket_id.to_string())).await,
+        )?;
+
+        if self.is_referenced(hash).await? {
+            return Ok(());
+        }
+
+        ignore_not_found(tokio::fs::remove_dir(self.referrers_path(hash)).await)?;
+        ignore_not_found(tokio::fs::remove_file(self.shared_path(hash)).await)
+    }
+
+    /// Holds the lock on names and shared contents when they are used
+    async fn lock_names(&self) -> Option<MutexGuard<'_, ()>> {
+        match self.path_strategy {
+            PathStrategy::Sharded => None,
+            PathStrategy::Flat | PathStrategy::ContentAddressed => Some(self.names.lock().await),
+        }
+    }
+
+    /// Directory new contents for `hash` are written to before they are
+    /// moved into place, which is on the same filesystem
+    fn temporary_directory(&self, bucket_id: Uuid, hash: &str) -> PathBuf {
+        match self.path_strategy {
+            PathStrategy::Flat => self.hashes_path(bucket_id),
+            PathStrategy::Sharded => self
+                .sharded_path(bucket_id, hash)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| self.root.clone()),
+            PathStrategy::ContentAddressed => self.root.join("contents"),
+        }
+    }
+}
+
+/// The file name contents stored for `key` are given by flat storage, if the
+/// key fits in one
+fn encode_key(key: &str) -> Option<String> {
+    let name = utf8_percent_encode(key, NAME_ENCODE_SET).to_string();
+
+    // `.` and `..` name directories rather than files
+    let name = match name.bytes().all(|c| c == b'.') {
+        true => name.replace('.', "%2E"),
+        false => name,
+    };
+
+    (name.len() <= MAX_NAME_LEN).then_some(name)
+}
+
+/// Names of the files in `directory` which could be the hash of some contents,
+/// leaving out temporary files. A missing directory holds no contents.
+async fn list_hashes(directory: &Path) -> io::Result<Vec<String>> {
+    let mut entries = match tokio::fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut hashes = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(name) = entry.file_name().to_str()
+            && is_hash(name)
+            && entry.file_type().await?.is_file()
+        {
+            hashes.push(name.to_owned());
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// Buckets named by the directories in `directory`, leaving out anything else
+/// kept alongside them. A missing directory holds no buckets.
+async fn list_bucket_directories(directory: &Path) -> io::Result<Vec<Uuid>> {
+    let mut entries = match tokio::fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut buckets = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(uuid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+            && entry.file_type().await?.is_dir()
+        {
+            buckets.push(uuid);
+        }
+    }
+
+    Ok(buckets)
+}
+
+/// Whether a file name is a hex encoded SHA-256 hash
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Treats a missing file as having been removed already
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalFsBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        mut data: StorageReader,
+    ) -> io::Result<u64> {
+        let directory = self.temporary_directory(bucket_id, hash);
+        tokio::fs::create_dir_all(&directory).await?;
+
+        // Contents are written to a temporary file and renamed into place, so
+        // readers never observe a partially written file
+        let (file, path) = tempfile::NamedTempFile::new_in(&directory)?.into_parts();
+        let mut file = tokio::fs::File::from_std(file);
+
+        let size = tokio::io::copy(&mut data, &mut file).await?;
+        file.flush().await?;
+
+        self.store(bucket_id, hash, key, path)
+            .await?
+            .map_err(|e| e.error)?;
+
+        Ok(size)
+    }
+
+    async fn get(&self, bucket_id: Uuid, hash: &str) -> io::Result<StorageReader> {
+        Ok(Box::pin(self.open_contents(bucket_id, hash).await?))
+    }
+
+    async fn delete(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        let _names = self.lock_names().await;
+
+        match self.path_strategy {
+            PathStrategy::Flat => self.delete_flat(bucket_id, hash).await,
+            PathStrategy::Sharded => {
+                ignore_not_found(tokio::fs::remove_file(self.sharded_path(bucket_id, hash)).await)
+            }
+            PathStrategy::ContentAddressed => self.remove_reference(bucket_id, hash).await,
+        }
+    }
+
+    async fn exists(&self, bucket_id: Uuid, hash: &str) -> io::Result<bool> {
+        let path = match self.path_strategy {
+            PathStrategy::Flat => self.hashes_path(bucket_id).join(hash),
+            PathStrategy::Sharded => self.sharded_path(bucket_id, hash),
+            PathStrategy::ContentAddressed => self.reference_path(bucket_id).join(hash),
+        };
+
+        tokio::fs::try_exists(path).await
+    }
+
+    async fn delete_bucket(&self, bucket_id: Uuid) -> io::Result<()> {
+        let _names = self.lock_names().await;
+
+        match self.path_strategy {
+            PathStrategy::Flat => {
+                ignore_not_found(tokio::fs::remove_dir_all(self.hashes_path(bucket_id)).await)?;
+                ignore_not_found(tokio::fs::remove_dir_all(self.bucket_path(bucket_id)).await)
+            }
+            PathStrategy::Sharded => {
+                ignore_not_found(tokio::fs::remove_dir_all(self.bucket_path(bucket_id)).await)
+            }
+            PathStrategy::ContentAddressed => {
+                for hash in list_hashes(&self.reference_path(bucket_id)).await? {
+                    self.remove_reference(bucket_id, &hash).await?;
+                }
+
+                ignore_not_found(tokio::fs::remove_dir_all(self.reference_path(bucket_id)).await)
+            }
+        }
+    }
+
+    async fn put_file(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        file: TempPath,
+    ) -> io::Result<u64> {
+        let size = tokio::fs::metadata(&file).await?.len();
+
+        // Staged files can only be renamed into place on the same filesystem
+        match self.store(bucket_id, hash, key, file).await? {
+            Ok(()) => Ok(size),
+            Err(e) => {
+                let reader = tokio::fs::File::open(&e.path).await?;
+                self.put(bucket_id, hash, key, Box::pin(reader)).await
+            }
+        }
+    }
+
+    async fn get_range(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<StorageReader> {
+        let mut file = self.open_contents(bucket_id, hash).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(Box::pin(file.take(len)))
+    }
+
+    async fn stored_key(&self, bucket_id: Uuid, hash: &str) -> io::Result<Option<String>> {
+        if self.path_strategy != PathStrategy::Flat {
+            return Ok(None);
+        }
+
+        let name = match self.flat_name(bucket_id, hash).await {
+            Ok(name) => name,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let name = name
+            .split_once("~/")
+            .map_or(name.as_str(), |(name, _)| name);
+
+        // Keys too long for a name aren't recorded
+        match name.starts_with('~') {
+            true => Ok(None),
+            false => Ok(Some(
+                percent_decode_str(name).decode_utf8_lossy().into_owned(),
+            )),
+        }
+    }
+
+    async fn copy(
+        &self,
+        from_bucket_id: Uuid,
+        to_bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+    ) -> io::Result<()> {
+        let _names = self.lock_names().await;
+
+        if self.path_strategy == PathStrategy::ContentAddressed {
+            if !tokio::fs::try_exists(self.reference_path(from_bucket_id).join(hash)).await? {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+
+            return self.add_reference(to_bucket_id, hash).await;
+        }
+
+        // The destination bucket may already store the same contents
+        if self.exists(to_bucket_id, hash).await? {
+            return Ok(());
+        }
+
+        let source = self.path(from_bucket_id, hash, key).await?;
+        if !tokio::fs::try_exists(&source).await? {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let destination = self.path(to_bucket_id, hash, key).await?;
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Hard links aren't supported by every filesystem, in which case the
+        // contents are copied to a temporary file and renamed into place
+        if tokio::fs::hard_link(&source, &destination).await.is_err() {
+            let directory = self.temporary_directory(to_bucket_id, hash);
+            tokio::fs::create_dir_all(&directory).await?;
+
+            let path = tempfile::NamedTempFile::new_in(&directory)?.into_temp_path();
+            tokio::fs::copy(&source, &path).await?;
+            path.persist(&destination).map_err(|e| e.error)?;
+        }
+
+        if self.path_strategy == PathStrategy::Flat {
+            let name = destination
+                .strip_prefix(self.bucket_path(to_bucket_id))
+                .map_err(io::Error::other)?
+                .to_string_lossy();
+
+            self.set_flat_name(to_bucket_id, hash, &name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, bucket_id: Uuid) -> io::Result<Vec<String>> {
+        match self.path_strategy {
+            PathStrategy::Flat => list_hashes(&self.hashes_path(bucket_id)).await,
+            PathStrategy::ContentAddressed => list_hashes(&self.reference_path(bucket_id)).await,
+            PathStrategy::Sharded => {
+                let mut shards = vec![self.bucket_path(bucket_id)];
+                for _ in 0..2 {
+                    let mut children = Vec::new();
+                    for shard in shards {
+                        let mut entries = match tokio::fs::read_dir(&shard).await {
+                            Ok(entries) => entries,
+                            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                            Err(e) => return Err(e),
+                        };
+
+                        while let Some(entry) = entries.next_entry().await? {
+                            if entry.file_type().await?.is_dir() {
+                                children.push(entry.path());
+                            }
+                        }
+                    }
+                    shards = children;
+                }
+
+                let mut hashes = Vec::new();
+                for shard in shards {
+                    hashes.extend(list_hashes(&shard).await?);
+                }
+
+                Ok(hashes)
+            }
+        }
+    }
+
+    async fn list_buckets(&self) -> io::Result<Vec<Uuid>> {
+        let directory = match self.path_strategy {
+            PathStrategy::Flat => self.root.join("hashes"),
+            PathStrategy::Sharded => self.root.clone(),
+            PathStrategy::ContentAddressed => self.root.join("references"),
+        };
+
+        // Sharded buckets are kept alongside other directories, such as those
+        // of multipart uploads, which don't hold any contents
+        let mut buckets = Vec::new();
+        for bucket_id in list_bucket_directories(&directory).await? {
+            if !self.list(bucket_id).await?.is_empty() {
+                buckets.push(bucket_id);
+            }
+        }
+
+        Ok(buckets)
+    }
+}
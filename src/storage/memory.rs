@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+use super::{StorageBackend, StorageReader};
+
+/// Keeps contents in memory, which is mostly useful for testing. Everything
+/// stored is lost when the backend is dropped.
+#[derive(Clone, Default)]
+pub struct InMemoryBackend {
+    contents: Arc<Mutex<HashMap<(Uuid, String), Bytes>>>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn contents(&self) -> MutexGuard<'_, HashMap<(Uuid, String), Bytes>> {
+        // The map is never left partially modified, so a poisoned lock is
+        // still safe to use
+        self.contents
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for InMemoryBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryBackend")
+            .field("stored", &self.contents().len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for InMemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn put(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        _key: &str,
+        mut data: StorageReader,
+    ) -> io::Result<u64> {
+        let mut buffer = Vec::new();
+        let size = data.read_to_end(&mut buffer).await? as u64;
+
+        self.contents()
+            .insert((bucket_id, hash.to_owned()), Bytes::from(buffer));
+
+        Ok(size)
+    }
+
+    async fn get(&self, bucket_id: Uuid, hash: &str) -> io::Result<StorageReader> {
+        let contents = self
+            .contents()
+            .get(&(bucket_id, hash.to_owned()))
+            .cloned()
+            .ok_or(io::ErrorKind::NotFound)?;
+
+        Ok(Box::pin(io::Cursor::new(contents)))
+    }
+
+    async fn delete(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        self.contents().remove(&(bucket_id, hash.to_owned()));
+
+        Ok(())
+    }
+
+    async fn exists(&self, bucket_id: Uuid, hash: &str) -> io::Result<bool> {
+        Ok(self.contents().contains_key(&(bucket_id, hash.to_owned())))
+    }
+
+    async fn delete_bucket(&self, bucket_id: Uuid) -> io::Result<()> {
+        self.contents().retain(|(id, _), _| *id != bucket_id);
+
+        Ok(())
+    }
+
+    async fn get_range(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<StorageReader> {
+        let contents = self
+            .contents()
+            .get(&(bucket_id, hash.to_owned()))
+            .cloned()
+            .ok_or(io::ErrorKind::NotFound)?;
+
+        let start = (offset as usize).min(contents.len());
+        let end = start.saturating_add(len as usize).min(contents.len());
+
+        Ok(Box::pin(io::Cursor::new(contents.slice(start..end))))
+    }
+
+    async fn list(&self, bucket_id: Uuid) -> io::Result<Vec<String>> {
+        Ok(self
+            .contents()
+            .keys()
+            .filter(|(id, _)| *id == bucket_id)
+            .map(|(_, hash)| hash.clone())
+            .collect())
+    }
+
+    async fn list_buckets(&self) -> io::Result<Vec<Uuid>> {
+        let mut buckets: Vec<Uuid> = self.contents().keys().map(|(id, _)| *id).collect();
+        buckets.sort();
+        buckets.dedup();
+
+        Ok(buckets)
+    }
+}
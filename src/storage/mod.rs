@@ -0,0 +1,144 @@
+//! Storage of object contents, which are addressed by the bucket they belong
+//! to and the SHA-256 hash of the contents
+//!
+//! Uploads are always staged in temporary files on local disk while they are
+//! hashed, and are then handed over to the backend.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use tempfile::TempPath;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
+
+pub use coalesce::CoalescingBackend;
+pub use local::{LocalFsBackend, PathStrategy};
+pub use memory::InMemoryBackend;
+
+mod coalesce;
+mod local;
+mod memory;
+
+/// Contents read from, or written to, a storage backend
+pub type StorageReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// The storage backend shared by the whole server
+pub type Storage = Arc<dyn StorageBackend + Send + Sync>;
+
+/// A place object contents can be stored in.
+///
+/// Contents are looked up by their bucket and hash. They are stored along
+/// with the key of the object they are stored for, which backends may use to
+/// name them, but contents can be shared by several keys.
+///
+/// Only [`name`](Self::name), [`put`](Self::put), [`get`](Self::get),
+/// [`delete`](Self::delete), [`exists`](Self::exists) and
+/// [`delete_bucket`](Self::delete_bucket) need to be implemented. The other methods have default implementations in terms of
+/// those, which backends may override when they can do better.
+#[async_trait]
+pub trait StorageBackend: std::fmt::Debug + Send + Sync {
+    /// Short name identifying the kind of backend, e.g. `local`
+    fn name(&self) -> &'static str;
+
+    /// Stores `data` as the contents for `hash`, which are stored for the
+    /// object `key`, replacing anything already stored for it. Returns the
+    /// number of bytes stored.
+    async fn put(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        data: StorageReader,
+    ) -> io::Result<u64>;
+
+    /// Reads the contents stored for `hash`
+    async fn get(&self, bucket_id: Uuid, hash: &str) -> io::Result<StorageReader>;
+
+    /// Removes the contents stored for `hash`, succeeding if there are none
+    async fn delete(&self, bucket_id: Uuid, hash: &str) -> io::Result<()>;
+
+    /// Whether any contents are stored for `hash`
+    async fn exists(&self, bucket_id: Uuid, hash: &str) -> io::Result<bool>;
+
+    /// Removes all contents stored for a bucket
+    async fn delete_bucket(&self, bucket_id: Uuid) -> io::Result<()>;
+
+    /// Stores a staged upload as the contents for `hash`. The staged file is
+    /// removed afterwards.
+    async fn put_file(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        file: TempPath,
+    ) -> io::Result<u64> {
+        let reader = tokio::fs::File::open(&file).await?;
+
+        self.put(bucket_id, hash, key, Box::pin(reader)).await
+    }
+
+    /// Reads `len` bytes of the contents stored for `hash`, starting at
+    /// `offset`
+    async fn get_range(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<StorageReader> {
+        let mut reader = self.get(bucket_id, hash).await?;
+        tokio::io::copy(&mut (&mut reader).take(offset), &mut tokio::io::sink()).await?;
+
+        Ok(Box::pin(reader.take(len)))
+    }
+
+    /// Lists the hashes of every contents stored for a bucket, in no
+    /// particular order. Backends which can't enumerate what they store fail
+    /// with [`io::ErrorKind::Unsupported`].
+    async fn list(&self, bucket_id: Uuid) -> io::Result<Vec<String>> {
+        let _ = bucket_id;
+
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Lists the buckets which have any contents stored, in no particular
+    /// order. Backends which can't enumerate what they store fail with
+    /// [`io::ErrorKind::Unsupported`].
+    async fn list_buckets(&self) -> io::Result<Vec<Uuid>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The key the contents for `hash` are named after, for backends which
+    /// name what they store after keys. Contents shared by several keys are
+    /// only named after one of them.
+    async fn stored_key(&self, bucket_id: Uuid, hash: &str) -> io::Result<Option<String>> {
+        let _ = (bucket_id, hash);
+
+        Ok(None)
+    }
+
+    /// Stores the contents for `hash` from one bucket in another as well, for
+    /// the object `key` in that bucket
+    async fn copy(
+        &self,
+        from_bucket_id: Uuid,
+        to_bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+    ) -> io::Result<()> {
+        let reader = self.get(from_bucket_id, hash).await?;
+        self.put(to_bucket_id, hash, key, reader).await?;
+
+        Ok(())
+    }
+}
+
+/// Directory uploads are staged in while they are being received
+pub fn staging_directory(data_directory: &Path) -> PathBuf {
+    data_directory.join("staging")
+}
@@ -0,0 +1,232 @@
+//! Periodic background tasks which run for as long as the server is running
+
+use std::{sync::Arc, time::Duration};
+
+use axum_server::tls_rustls::RustlsConfig;
+use chrono::Utc;
+use metrics_exporter_prometheus::PrometheusHandle;
+
+use crate::{
+    AppState,
+    config::Config,
+    events::{BucketEvent, BucketEvents},
+    models::{
+        bucket::Bucket, lifecycle::LifecycleRule, multipart::MultipartUpload, object::Object,
+    },
+    storage::Storage,
+    tls,
+};
+
+/// How often the materialized bucket counters are recomputed from scratch
+const COUNTER_RECONCILIATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the metrics recorder drains its histograms
+const METRICS_UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often TLS key files are checked for changes
+const TLS_KEY_FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs all background tasks. This future never completes and should be
+/// dropped to stop the tasks.
+pub async fn run(state: AppState) {
+    tokio::join!(
+        reconcile_counters(state.db.clone()),
+        run_metrics_upkeep(state.metrics),
+        apply_lifecycle_rules(
+            state.db.clone(),
+            state.config.clone(),
+            state.storage.clone(),
+            state.events.clone()
+        ),
+        purge_soft_deleted(
+            state.db.clone(),
+            state.config.clone(),
+            state.storage,
+            state.events
+        ),
+        abort_stale_uploads(state.db, state.config.clone()),
+        reload_tls_keys(state.config, state.tls)
+    );
+}
+
+async fn reconcile_counters(db: sqlx::SqlitePool) {
+    let mut interval = tokio::time::interval(COUNTER_RECONCILIATION_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = Bucket::reconcile_counters(&db).await {
+            tracing::error!("Failed to reconcile bucket counters: {}", e);
+        }
+    }
+}
+
+async fn run_metrics_upkeep(metrics: PrometheusHandle) {
+    let mut interval = tokio::time::interval(METRICS_UPKEEP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        metrics.run_upkeep();
+    }
+}
+
+async fn apply_lifecycle_rules(
+    db: sqlx::SqlitePool,
+    config: Arc<Config>,
+    storage: Storage,
+    events: BucketEvents,
+) {
+    let mut interval = tokio::time::interval(config.lifecycle.interval);
+
+    loop {
+        interval.tick().await;
+
+        let rules = match LifecycleRule::find_all(&db).await {
+            Ok(rules) => rules,
+            Err(e) => {
+                tracing::error!("Failed to load lifecycle rules: {}", e);
+                continue;
+            }
+        };
+
+        for rule in rules {
+            match rule.apply(&db, &config.data_directory, &*storage).await {
+                Ok(expired) => {
+                    for key in expired {
+                        events.publish(rule.bucket_uuid, BucketEvent::ObjectDeleted { key });
+                    }
+                }
+                Err(e) => tracing::error!("Failed to apply lifecycle rule {}: {}", rule.id, e),
+            }
+        }
+    }
+}
+
+/// Permanently removes objects which were soft deleted longer ago than the
+/// retention period, along with their contents
+async fn purge_soft_deleted(
+    db: sqlx::SqlitePool,
+    config: Arc<Config>,
+    storage: Storage,
+    events: BucketEvents,
+) {
+    let mut interval = tokio::time::interval(config.lifecycle.interval);
+    let retention = chrono::Duration::days(config.lifecycle.soft_delete_retention_days.into());
+
+    loop {
+        interval.tick().await;
+
+        let buckets = match Bucket::find_all_unpaginated(&db).await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                tracing::error!("Failed to load buckets: {}", e);
+                continue;
+            }
+        };
+
+        let cutoff = Utc::now() - retention;
+        for bucket in buckets {
+            let objects = match Object::find_deleted_before(&db, &bucket, cutoff).await {
+                Ok(objects) => objects,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to find soft deleted objects in bucket {}: {}",
+                        bucket.name(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            for object in objects {
+                let path = object.path().to_owned();
+
+                match object.delete(&db, &*storage).await {
+                    Ok(()) => {
+                        events.publish(bucket.uuid(), BucketEvent::ObjectDeleted { key: path })
+                    }
+                    Err(e) => tracing::error!(
+                        "Failed to purge soft deleted object {} from bucket {}: {}",
+                        path,
+                        bucket.name(),
+                        e
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Aborts multipart uploads which have been in progress for longer than the
+/// configured TTL
+async fn abort_stale_uploads(db: sqlx::SqlitePool, config: Arc<Config>) {
+    let mut interval = tokio::time::interval(config.lifecycle.interval);
+
+    loop {
+        interval.tick().await;
+
+        let Ok(ttl) = chrono::Duration::from_std(config.lifecycle.multipart_upload_ttl) else {
+            return;
+        };
+
+        let uploads = match MultipartUpload::find_initiated_before(&db, Utc::now() - ttl).await {
+            Ok(uploads) => uploads,
+            Err(e) => {
+                tracing::error!("Failed to find stale multipart uploads: {}", e);
+                continue;
+            }
+        };
+
+        for upload in uploads {
+            let upload_id = upload.upload_id();
+
+            if let Err(e) = upload.abort(&db, &config.data_directory).await {
+                tracing::error!(
+                    "Failed to abort stale multipart upload {}: {}",
+                    upload_id,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Reloads the TLS certificate whenever the key files it was loaded from
+/// change, so certificates can be renewed without restarting the server. New
+/// connections use the reloaded certificate.
+async fn reload_tls_keys(config: Arc<Config>, rustls: Option<RustlsConfig>) {
+    let (Some(tls), Some(rustls)) = (&config.tls, rustls) else {
+        return;
+    };
+
+    let Some(mut last_modified) = tls::key_files_modified(tls).await else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(TLS_KEY_FILE_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified = match tls::key_files_modified(tls).await {
+            Some(modified) if modified != last_modified => modified,
+            _ => continue,
+        };
+        last_modified = modified;
+
+        match tls::load_server_config(tls) {
+            Ok(server_config) => {
+                rustls.reload_from_config(Arc::new(server_config));
+
+                tracing::info!("Reloaded TLS certificate");
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to reload TLS certificate, keeping the old one: {}",
+                    e
+                );
+            }
+        }
+    }
+}
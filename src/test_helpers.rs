@@ -0,0 +1,342 @@
+//! Shared utilities for integration tests.
+//!
+//! This module is only compiled with the `test-helpers` feature, which should
+//! only ever be enabled from `[dev-dependencies]`. It is not part of the
+//! public API and may change at any time.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    config::{AccessControlConfig, Config, ConfigBuilder, HttpConfig, RateLimitingConfig},
+    create_server, database_url,
+    models::{
+        access_token::{AccessToken, AccessTokenPermission},
+        bucket::{Bucket, BucketSettings},
+        object::{Object, ObjectAttributes, Upload},
+    },
+    storage::{InMemoryBackend, LocalFsBackend, Storage, StorageBackend, staging_directory},
+};
+
+/// An ephemeral testing server which binds to a random port and uses a tmp
+/// directory for its database and staged uploads.
+///
+/// The server task is aborted and the data directory is removed when this is
+/// dropped.
+pub struct TestServer {
+    /// Address the server is listening on
+    addr: SocketAddr,
+    /// Whether the server is serving HTTPS
+    tls: bool,
+    /// Secondary connection to the server's database, used for seeding
+    db: sqlx::SqlitePool,
+    /// Temporary directory used as the data directory
+    data_directory: PathBuf,
+    /// Backend the server stores object contents in
+    storage: Storage,
+    /// Handle to this server
+    handle: JoinHandle<std::io::Result<()>>,
+}
+
+impl TestServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL of the server on the loopback interface, without a trailing
+    /// slash
+    pub fn endpoint(&self) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        let host = match self.addr.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        };
+
+        format!("{}://{}", scheme, SocketAddr::new(host, self.addr.port()))
+    }
+
+    /// Region clients should use when talking to this server, taken from
+    /// `AWS_DEFAULT_REGION` and falling back to `us-east-1`
+    pub fn effective_region(&self) -> String {
+        std::env::var("AWS_DEFAULT_REGION")
+            .ok()
+            .filter(|region| !region.is_empty())
+            .unwrap_or_else(|| "us-east-1".to_owned())
+    }
+
+    pub fn data_directory(&self) -> &std::path::Path {
+        &self.data_directory
+    }
+
+    pub fn storage(&self) -> &dyn StorageBackend {
+        &*self.storage
+    }
+
+    /// Reads the stored contents for `hash` in a bucket, or `None` if nothing
+    /// is stored for it
+    pub async fn stored_contents(&self, bucket: Uuid, hash: &str) -> Option<Vec<u8>> {
+        let mut reader = match self.storage.get(bucket, hash).await {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => panic!("Failed to read stored contents: {}", e),
+        };
+
+        let mut contents = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut contents)
+            .await
+            .expect("Failed to read stored contents");
+
+        Some(contents)
+    }
+
+    /// Creates a bucket with default settings directly in the database,
+    /// bypassing the HTTP API.
+    pub async fn create_bucket(&self, name: &str) -> Uuid {
+        Bucket::new(&self.db, name, BucketSettings::default())
+            .await
+            .expect("Failed to create bucket")
+            .uuid()
+    }
+
+    /// Stores an object directly, bypassing the HTTP API. Returns the SHA-256
+    /// hash of the contents.
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> String {
+        let bucket = Bucket::find_by_name(&self.db, bucket)
+            .await
+            .expect("Failed to find bucket");
+
+        let content_type = content_type.map(|c| c.parse().expect("Invalid content type"));
+        let upload = Upload::receive(&staging_directory(&self.data_directory), body, None)
+            .await
+            .expect("Failed to upload object");
+
+        Object::new(
+            &self.db,
+            &*self.storage,
+            &bucket,
+            key,
+            ObjectAttributes {
+                content_type,
+                ..Default::default()
+            },
+            upload,
+        )
+        .await
+        .expect("Failed to put object")
+        .hash()
+        .to_owned()
+    }
+
+    /// Creates an access token which never expires directly in the database,
+    /// bypassing the HTTP API.
+    pub async fn create_access_token(&self) -> TestAccessToken {
+        let (token, bearer_token) =
+            AccessToken::new(&self.db, "Testing", AccessTokenPermission::ALL.into(), None)
+                .await
+                .expect("Failed to create access token");
+
+        TestAccessToken {
+            bearer_token,
+            access_key_id: token.access_key_id().to_owned(),
+            secret_access_key: token.secret_access_key().to_owned(),
+        }
+    }
+
+    /// Moves an object to the same key in another bucket directly, bypassing
+    /// the HTTP API. Returns whether there was an object to move.
+    pub async fn transfer_object(&self, source: &str, key: &str, destination: &str) -> bool {
+        let source = Bucket::find_by_name(&self.db, source)
+            .await
+            .expect("Failed to find source bucket");
+        let destination = Bucket::find_by_name(&self.db, destination)
+            .await
+            .expect("Failed to find destination bucket");
+
+        source
+            .transfer_object(&self.db, &*self.storage, key, &destination)
+            .await
+            .expect("Failed to transfer object")
+            .is_some()
+    }
+
+    /// Marks an object as having expired an hour ago, bypassing the HTTP API.
+    pub async fn expire_object(&self, bucket: &str, key: &str) {
+        let bucket = Bucket::find_by_name(&self.db, bucket)
+            .await
+            .expect("Failed to find bucket");
+
+        sqlx::query(&format!(
+            "UPDATE {} SET expires_at = ? WHERE path = ? AND is_latest;",
+            Object::table_name(bucket.uuid())
+        ))
+        .bind(chrono::Utc::now() - chrono::Duration::hours(1))
+        .bind(key)
+        .execute(&self.db)
+        .await
+        .expect("Failed to expire object");
+    }
+
+    /// Deletes every record of the objects in a bucket while leaving their
+    /// contents in storage, as if the database had been lost
+    pub async fn forget_objects(&self, bucket: &str) {
+        let bucket = Bucket::find_by_name(&self.db, bucket)
+            .await
+            .expect("Failed to find bucket");
+
+        sqlx::query(&format!(
+            "DELETE FROM {};",
+            Object::table_name(bucket.uuid())
+        ))
+        .execute(&self.db)
+        .await
+        .expect("Failed to forget objects");
+    }
+
+    /// Removes a bucket and its objects from the database while leaving their
+    /// contents in storage, as if the database was lost
+    pub async fn forget_bucket(&self, bucket: &str) {
+        let bucket = Bucket::find_by_name(&self.db, bucket)
+            .await
+            .expect("Failed to find bucket");
+
+        sqlx::query(&format!(
+            "DROP TABLE {};",
+            Object::table_name(bucket.uuid())
+        ))
+        .execute(&self.db)
+        .await
+        .expect("Failed to forget objects");
+
+        sqlx::query("DELETE FROM buckets WHERE uuid = ?;")
+            .bind(bucket.uuid())
+            .execute(&self.db)
+            .await
+            .expect("Failed to forget bucket");
+    }
+
+    /// Removes the record of the most recently applied migration, so the
+    /// server appears to not have finished migrating
+    pub async fn forget_latest_migration(&self) {
+        sqlx::query(
+            "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations);",
+        )
+        .execute(&self.db)
+        .await
+        .expect("Failed to forget migration");
+    }
+}
+
+/// Plaintext credentials of an access token created for testing
+#[derive(Debug, Clone)]
+pub struct TestAccessToken {
+    pub bearer_token: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+
+        let _ = std::fs::remove_dir_all(&self.data_directory);
+    }
+}
+
+/// Config used by [`create_test_server`], which binds to a random port,
+/// allows anonymous requests and disables rate limiting
+pub fn test_config() -> ConfigBuilder {
+    Config::builder()
+        .http(HttpConfig::random_port())
+        .access_control(AccessControlConfig {
+            enable_access_tokens: false,
+            ..Default::default()
+        })
+        .rate_limiting(RateLimitingConfig {
+            enable_rate_limiting: false,
+            ..Default::default()
+        })
+}
+
+/// Options for [`create_test_server_with`]
+pub struct TestServerConfig {
+    pub config: ConfigBuilder,
+    /// Backend to store object contents in, or `None` to store them on disk in
+    /// the data directory like a real server
+    pub storage_backend: Option<Storage>,
+}
+
+impl Default for TestServerConfig {
+    /// The [`test_config`] with contents stored in memory
+    fn default() -> Self {
+        Self {
+            config: test_config(),
+            storage_backend: Some(Arc::new(InMemoryBackend::new())),
+        }
+    }
+}
+
+/// Starts a server on a random port with an otherwise default config.
+pub async fn create_test_server() -> TestServer {
+    create_test_server_with(TestServerConfig::default()).await
+}
+
+/// Starts a server with the given config, storing contents in memory
+pub async fn create_test_server_with_config(config: ConfigBuilder) -> TestServer {
+    create_test_server_with(TestServerConfig {
+        config,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Starts a server with the given options. The data directory is always
+/// replaced with a fresh temporary directory.
+pub async fn create_test_server_with(options: TestServerConfig) -> TestServer {
+    let data_directory =
+        std::env::temp_dir().join(format!("objection-testing-{}", Uuid::new_v4().simple()));
+
+    let mut config = options.config.data_directory(&data_directory).build();
+    let tls = config.tls.is_some();
+
+    // The server always ends up using the same backend as the helpers
+    let storage = config
+        .storage_backend
+        .take()
+        .or(options.storage_backend)
+        .unwrap_or_else(|| {
+            Arc::new(
+                LocalFsBackend::open(&data_directory, config.storage.path_strategy)
+                    .expect("Failed to open storage"),
+            )
+        });
+    config.storage_backend = Some(storage.clone());
+
+    let (addr, handle) = create_server(config)
+        .await
+        .expect("Failed to start test server");
+
+    let db = sqlx::SqlitePool::connect(&database_url(&data_directory))
+        .await
+        .expect("Failed to connect to test database");
+
+    TestServer {
+        addr,
+        tls,
+        db,
+        data_directory,
+        storage,
+        handle,
+    }
+}
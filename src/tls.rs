@@ -0,0 +1,85 @@
+use std::{sync::Arc, time::SystemTime};
+
+use rustls::{ServerConfig, SupportedProtocolVersion, crypto::ring};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+
+use crate::config::{TlsConfig, TlsKeyConfig, TlsVersion};
+
+/// Errors that can occur while loading the TLS configuration
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Failed to read TLS key file: {0}")]
+    KeyFile(#[from] std::io::Error),
+    #[error("Failed to parse PEM data: {0}")]
+    Pem(#[from] rustls_pki_types::pem::Error),
+    #[error("No supported TLS versions are enabled")]
+    NoVersions,
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// Builds a rustls [`ServerConfig`] from the TLS section of the config,
+/// restricted to the configured protocol versions
+pub fn load_server_config(tls: &TlsConfig) -> Result<ServerConfig, TlsError> {
+    let versions = tls
+        .tls_versions
+        .iter()
+        .filter_map(|version| match version {
+            TlsVersion::V1_1 => {
+                tracing::warn!("TLS 1.1 is deprecated and not supported, ignoring");
+                None
+            }
+            TlsVersion::V1_2 => Some(&rustls::version::TLS12),
+            TlsVersion::V1_3 => Some(&rustls::version::TLS13),
+        })
+        .collect::<Vec<&'static SupportedProtocolVersion>>();
+
+    if versions.is_empty() {
+        return Err(TlsError::NoVersions);
+    }
+
+    let (private_key, public_key) = match &tls.keys {
+        TlsKeyConfig::String {
+            private_key,
+            public_key,
+        } => (
+            private_key.as_bytes().to_vec(),
+            public_key.as_bytes().to_vec(),
+        ),
+        TlsKeyConfig::File {
+            private_key_file,
+            public_key_file,
+        } => (
+            std::fs::read(private_key_file)?,
+            std::fs::read(public_key_file)?,
+        ),
+    };
+
+    let certs = CertificateDer::pem_slice_iter(&public_key).collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_slice(&private_key)?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_protocol_versions(&versions)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+/// When the key files were last modified, or `None` if the keys aren't loaded
+/// from files or the files can't be read
+pub async fn key_files_modified(tls: &TlsConfig) -> Option<(SystemTime, SystemTime)> {
+    let TlsKeyConfig::File {
+        private_key_file,
+        public_key_file,
+    } = &tls.keys
+    else {
+        return None;
+    };
+
+    let private_key = tokio::fs::metadata(private_key_file).await.ok()?;
+    let public_key = tokio::fs::metadata(public_key_file).await.ok()?;
+
+    Some((private_key.modified().ok()?, public_key.modified().ok()?))
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use objection::test_helpers::{TestServer, create_test_server};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// Fetches the access logs of a bucket, waiting for up to a second for at
+/// least `count` entries since they are written in the background
+async fn access_logs(server: &TestServer, bucket: &str, count: usize) -> Vec<Value> {
+    let url = format!("{}/api/buckets/{}/access-logs", server.endpoint(), bucket);
+
+    for _ in 0..20 {
+        let logs = reqwest::get(&url)
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap()["data"]
+            .as_array()
+            .unwrap()
+            .clone();
+        if logs.len() >= count {
+            return logs;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    panic!("expected at least {} access log entries", count);
+}
+
+#[tokio::test]
+pub async fn access_logging() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "photos", "settings": { "access_logging": true } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .put(format!("{}/photos/cat.txt", server.endpoint()))
+        .header("x-request-id", "upload-cat")
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Give the upload's entry time to be written so the entries are ordered
+    access_logs(&server, "photos", 1).await;
+
+    let response = client
+        .get(format!(
+            "{}/api/buckets/photos/objects/cat.txt",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let logs = access_logs(&server, "photos", 2).await;
+    assert_eq!(logs.len(), 2);
+
+    assert_eq!(logs[0]["object_key"], "cat.txt");
+    assert_eq!(logs[0]["method"], "PUT");
+    assert_eq!(logs[0]["status_code"], 200);
+    assert_eq!(logs[0]["requester_ip"], "127.0.0.1");
+    assert_eq!(logs[0]["bytes_transferred"], 4);
+    assert_eq!(logs[0]["request_id"], "upload-cat");
+
+    assert_eq!(logs[1]["object_key"], "cat.txt");
+    assert_eq!(logs[1]["method"], "GET");
+    assert_eq!(logs[1]["bytes_transferred"], 4);
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/photos/access-logs?limit=1&page=2",
+        server.endpoint()
+    ))
+    .await
+    .unwrap()
+    .json::<Value>()
+    .await
+    .unwrap();
+    assert_eq!(response["data"].as_array().unwrap().len(), 1);
+    assert_eq!(response["data"][0]["method"], "GET");
+    assert_eq!(response["total"], 2);
+    assert_eq!(response["has_next"], false);
+}
+
+#[tokio::test]
+pub async fn access_logging_disabled() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server.put_object("photos", "cat.txt", None, b"meow").await;
+
+    let response = reqwest::get(format!("{}/photos/cat.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let logs = reqwest::get(format!(
+        "{}/api/buckets/photos/access-logs",
+        server.endpoint()
+    ))
+    .await
+    .unwrap()
+    .json::<Value>()
+    .await
+    .unwrap();
+    assert_eq!(logs["data"], json!([]));
+    assert_eq!(logs["total"], 0);
+}
+
+#[tokio::test]
+pub async fn access_logs_filtered() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "photos", "settings": { "access_logging": true } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    for (index, key) in ["cat.txt", "dog.txt"].into_iter().enumerate() {
+        let response = client
+            .put(format!("{}/photos/{}", server.endpoint(), key))
+            .body("meow")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        access_logs(&server, "photos", index + 1).await;
+    }
+
+    let response = client
+        .get(format!("{}/photos/fish.txt", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let logs = access_logs(&server, "photos", 3).await;
+    let url = format!("{}/api/buckets/photos/access-logs", server.endpoint());
+    let query = |query: &[(&str, &str)]| {
+        let request = client.get(&url).query(query);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response.json::<Value>().await.unwrap()
+        }
+    };
+    let keys = |listing: &Value| {
+        listing["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|log| log["object_key"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let listing = query(&[("method", "put")]).await;
+    assert_eq!(keys(&listing), ["cat.txt", "dog.txt"]);
+    assert_eq!(listing["total"], 2);
+
+    let listing = query(&[("status", "404")]).await;
+    assert_eq!(keys(&listing), ["fish.txt"]);
+
+    let listing = query(&[("method", "PUT"), ("limit", "1")]).await;
+    assert_eq!(keys(&listing), ["cat.txt"]);
+    assert_eq!(listing["has_next"], true);
+
+    // `from` is inclusive and `to` is exclusive
+    let second = logs[1]["timestamp"].as_str().unwrap();
+    let listing = query(&[("from", second)]).await;
+    assert_eq!(keys(&listing), ["dog.txt", "fish.txt"]);
+
+    let listing = query(&[("to", second)]).await;
+    assert_eq!(keys(&listing), ["cat.txt"]);
+
+    let third = logs[2]["timestamp"].as_str().unwrap();
+    let listing = query(&[("from", second), ("to", third), ("status", "200")]).await;
+    assert_eq!(keys(&listing), ["dog.txt"]);
+
+    let response = client
+        .get(&url)
+        .query(&[("from", third), ("to", second)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "INVALID_TIME_RANGE"
+    );
+
+    let response = client
+        .delete(&url)
+        .query(&[("before", third)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({"deleted": 2})
+    );
+
+    let listing = query(&[]).await;
+    assert_eq!(keys(&listing), ["fish.txt"]);
+}
@@ -0,0 +1,198 @@
+use objection::{
+    config::{PathStrategy, StorageConfig},
+    test_helpers::{
+        TestServer, TestServerConfig, create_test_server, create_test_server_with, test_config,
+    },
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+async fn reindex(server: &TestServer, bucket: Option<&str>) -> Vec<Value> {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/admin/reindex", server.endpoint()))
+        .query(&[("bucket", bucket)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+
+    response
+        .text()
+        .await
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+pub async fn reindex_recovers_objects() {
+    let server = create_test_server_with(TestServerConfig {
+        config: test_config(),
+        storage_backend: None,
+    })
+    .await;
+    let uuid = server.create_bucket("photos").await;
+
+    let cat = server.put_object("photos", "cat.jpg", None, b"meow").await;
+    let dog = server.put_object("photos", "dog.jpg", None, b"woof").await;
+    let bird = server
+        .put_object("photos", "bird.jpg", None, b"tweet")
+        .await;
+    server.forget_objects("photos").await;
+
+    // Indexed contents are left alone
+    let fish = server.put_object("photos", "fish.jpg", None, b"blub").await;
+
+    // Contents whose key was given to another object can't keep it
+    let chirp = server
+        .put_object("photos", "bird.jpg", None, b"chirp")
+        .await;
+
+    // Contents which were changed after being stored aren't recovered
+    std::fs::write(
+        server
+            .data_directory()
+            .join(uuid.to_string())
+            .join("dog.jpg"),
+        "meow",
+    )
+    .unwrap();
+
+    let mut entries = reindex(&server, Some("photos")).await;
+    entries.sort_by_key(|entry| entry["hash"].as_str().unwrap().to_owned());
+
+    let mut expected = vec![
+        serde_json::json!({
+            "bucket": "photos",
+            "status": "recovered",
+            "hash": cat,
+            "key": "cat.jpg",
+            "size": 4,
+        }),
+        serde_json::json!({
+            "bucket": "photos",
+            "status": "corrupt",
+            "hash": dog,
+            "actual_hash": cat,
+        }),
+        serde_json::json!({
+            "bucket": "photos",
+            "status": "recovered",
+            "hash": bird,
+            "key": format!("recovered/{}", bird),
+            "size": 5,
+        }),
+        serde_json::json!({
+            "bucket": "photos",
+            "status": "skipped",
+            "hash": fish,
+            "key": "fish.jpg",
+        }),
+        serde_json::json!({
+            "bucket": "photos",
+            "status": "skipped",
+            "hash": chirp,
+            "key": "bird.jpg",
+        }),
+    ];
+    expected.sort_by_key(|entry| entry["hash"].as_str().unwrap().to_owned());
+    assert_eq!(entries, expected);
+
+    for (key, contents) in [
+        ("cat.jpg".to_owned(), "meow"),
+        (format!("recovered/{}", bird), "tweet"),
+        ("bird.jpg".to_owned(), "chirp"),
+    ] {
+        let response = reqwest::get(format!(
+            "{}/api/buckets/photos/objects/{}",
+            server.endpoint(),
+            key
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), contents);
+    }
+
+    // Reindexing again finds nothing new
+    let entries = reindex(&server, Some("photos")).await;
+    assert!(entries.iter().all(|entry| entry["status"] != "recovered"));
+}
+
+#[tokio::test]
+pub async fn reindex_recreates_buckets() {
+    // Sharded storage doesn't name contents after their keys
+    let server = create_test_server_with(TestServerConfig {
+        config: test_config().storage(StorageConfig {
+            path_strategy: PathStrategy::Sharded,
+        }),
+        storage_backend: None,
+    })
+    .await;
+    let uuid = server.create_bucket("photos").await;
+    server.create_bucket("videos").await;
+
+    let cat = server.put_object("photos", "cat.jpg", None, b"meow").await;
+    let clip = server.put_object("videos", "clip.mp4", None, b"roll").await;
+    server.forget_bucket("photos").await;
+
+    let bucket = format!("recovered-{}", uuid);
+    let entries = reindex(&server, None).await;
+    assert_eq!(
+        entries,
+        [
+            serde_json::json!({
+                "bucket": bucket,
+                "status": "bucket_recovered",
+                "uuid": uuid,
+            }),
+            serde_json::json!({
+                "bucket": bucket,
+                "status": "recovered",
+                "hash": cat,
+                "key": format!("recovered/{}", cat),
+                "size": 4,
+            }),
+            serde_json::json!({
+                "bucket": "videos",
+                "status": "skipped",
+                "hash": clip,
+                "key": "clip.mp4",
+            }),
+        ]
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/{}/objects/recovered/{}",
+        server.endpoint(),
+        bucket,
+        cat
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "meow");
+}
+
+#[tokio::test]
+pub async fn reindex_unknown_bucket() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/api/admin/reindex?bucket=missing",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = response.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "BUCKET_NOT_FOUND");
+}
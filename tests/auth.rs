@@ -0,0 +1,598 @@
+use hmac::{Hmac, Mac};
+use objection::{
+    config::AccessControlConfig,
+    test_helpers::{TestServer, create_test_server_with_config, test_config},
+};
+use reqwest::{StatusCode, header::HeaderMap};
+use s3::signing;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use time::OffsetDateTime;
+use url::Url;
+
+async fn create_authenticated_server() -> TestServer {
+    create_test_server_with_config(test_config().access_control(AccessControlConfig::default()))
+        .await
+}
+
+/// Sends a `GET` request signed with the given credentials, using the `s3`
+/// crate's signing implementation as a reference
+async fn signed_get(
+    server: &TestServer,
+    path: &str,
+    access_key: &str,
+    secret_key: &str,
+) -> reqwest::Response {
+    let payload_hash = sha256::digest(b"");
+
+    signed_request(
+        server,
+        reqwest::Method::GET,
+        path,
+        (access_key, secret_key),
+        &payload_hash,
+        Vec::new(),
+    )
+    .await
+}
+
+/// Sends a request signed with the given access key and secret, where
+/// `payload_hash` is sent as `x-amz-content-sha256` whether or not it is the
+/// hash of `body`
+async fn signed_request(
+    server: &TestServer,
+    method: reqwest::Method,
+    path: &str,
+    (access_key, secret_key): (&str, &str),
+    payload_hash: &str,
+    body: Vec<u8>,
+) -> reqwest::Response {
+    let url = Url::parse(&format!("{}{}", server.endpoint(), path)).unwrap();
+    let region = s3::Region::Custom {
+        region: server.effective_region(),
+        endpoint: server.endpoint(),
+    };
+
+    let now = chrono::Utc::now();
+    let datetime = OffsetDateTime::from_unix_timestamp(now.timestamp()).unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "host",
+        format!("{}:{}", url.host_str().unwrap(), url.port().unwrap())
+            .parse()
+            .unwrap(),
+    );
+    headers.insert("x-amz-content-sha256", payload_hash.parse().unwrap());
+    headers.insert(
+        "x-amz-date",
+        now.format("%Y%m%dT%H%M%SZ").to_string().parse().unwrap(),
+    );
+
+    let canonical_request =
+        signing::canonical_request(method.as_str(), &url, &headers, payload_hash).unwrap();
+    let string_to_sign = signing::string_to_sign(&datetime, &region, &canonical_request).unwrap();
+    let key = signing::signing_key(&datetime, secret_key, &region, "s3").unwrap();
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).unwrap();
+    mac.update(string_to_sign.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    let authorization = signing::authorization_header(
+        access_key,
+        &datetime,
+        &region,
+        &signing::signed_header_string(&headers),
+        &signature,
+    )
+    .unwrap();
+    headers.insert("authorization", authorization.parse().unwrap());
+
+    reqwest::Client::new()
+        .request(method, url)
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn signed_request_accepted() {
+    let server = create_authenticated_server().await;
+    let token = server.create_access_token().await;
+
+    let response = signed_get(
+        &server,
+        "/api/buckets",
+        &token.access_key_id,
+        &token.secret_access_key,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The signature covers the path as sent, before trailing slashes are
+    // trimmed
+    let response = signed_get(
+        &server,
+        "/api/buckets/",
+        &token.access_key_id,
+        &token.secret_access_key,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn signed_payload_hash_checked() {
+    let server = create_authenticated_server().await;
+    let token = server.create_access_token().await;
+    let credentials = (
+        token.access_key_id.as_str(),
+        token.secret_access_key.as_str(),
+    );
+    server.create_bucket("photos").await;
+
+    let response = signed_request(
+        &server,
+        reqwest::Method::PUT,
+        "/api/buckets/photos/objects/cat.txt",
+        credentials,
+        &sha256::digest(b"woof"),
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "BAD_DIGEST"
+    );
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/api/buckets/photos/objects/cat.txt",
+            server.endpoint()
+        ))
+        .bearer_auth(&token.bearer_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = signed_request(
+        &server,
+        reqwest::Method::PUT,
+        "/api/buckets/photos/objects/cat.txt",
+        credentials,
+        &sha256::digest(b"meow"),
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Parts of multipart uploads are checked too
+    let response = reqwest::Client::new()
+        .post(format!("{}/photos/cat.mp4?uploads", server.endpoint()))
+        .bearer_auth(&token.bearer_token)
+        .send()
+        .await
+        .unwrap();
+    let body = response.text().await.unwrap();
+    let upload_id = &body
+        [body.find("<UploadId>").unwrap() + "<UploadId>".len()..body.find("</UploadId>").unwrap()];
+
+    let response = signed_request(
+        &server,
+        reqwest::Method::PUT,
+        &format!("/photos/cat.mp4?partNumber=1&uploadId={}", upload_id),
+        credentials,
+        &sha256::digest(b"woof"),
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>XAmzContentSHA256Mismatch</Code>")
+    );
+
+    // Payloads which aren't signed aren't checked
+    let response = signed_request(
+        &server,
+        reqwest::Method::PUT,
+        &format!("/photos/cat.mp4?partNumber=1&uploadId={}", upload_id),
+        credentials,
+        "UNSIGNED-PAYLOAD",
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn anonymous_request_rejected() {
+    let server = create_authenticated_server().await;
+
+    // The JSON API responds with JSON errors
+    let response = reqwest::get(format!("{}/api/buckets", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "UNAUTHORIZED"
+    );
+
+    // Everything else is S3, which responds with XML errors
+    let response = reqwest::get(format!("{}/photos", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>AccessDenied</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn bearer_token_accepted() {
+    let server = create_authenticated_server().await;
+    let token = server.create_access_token().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/buckets", server.endpoint()))
+        .bearer_auth(&token.bearer_token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn invalid_bearer_token_rejected() {
+    let server = create_authenticated_server().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/buckets", server.endpoint()))
+        .bearer_auth("not-a-token")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+pub async fn local_host_auth_bypass() {
+    let server =
+        create_test_server_with_config(test_config().access_control(AccessControlConfig {
+            enable_access_tokens: true,
+            enable_local_host_auth_bypass: true,
+        }))
+        .await;
+
+    let response = reqwest::get(format!("{}/api/buckets", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn access_token_lifecycle() {
+    let server = create_authenticated_server().await;
+    let admin = server.create_access_token().await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/access-tokens", server.endpoint());
+
+    let response = client
+        .post(&url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "description": "CI uploads" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let created = response.json::<Value>().await.unwrap();
+    let uuid = created["uuid"].as_str().unwrap();
+    let token = created["token"].as_str().unwrap();
+    assert_eq!(created["description"], "CI uploads");
+    assert!(created["secret_access_key"].is_string());
+
+    // The new token works, and secrets are never returned again
+    let response = client.get(&url).bearer_auth(token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tokens = response.json::<Value>().await.unwrap();
+    assert_eq!(tokens.as_array().unwrap().len(), 2);
+    assert!(tokens[1].get("token").is_none());
+    assert!(tokens[1].get("secret_access_key").is_none());
+
+    let response = client
+        .delete(format!("{}/{}", url, uuid))
+        .bearer_auth(&admin.bearer_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&url).bearer_auth(token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+pub async fn wrong_secret_rejected() {
+    let server = create_authenticated_server().await;
+    let token = server.create_access_token().await;
+
+    let response = signed_get(
+        &server,
+        "/api/buckets",
+        &token.access_key_id,
+        "not-the-secret",
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/xml; charset=UTF-8"
+    );
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>InvalidSignature</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn unknown_access_key_rejected() {
+    let server = create_authenticated_server().await;
+
+    let response = signed_get(&server, "/api/buckets", "OBJUNKNOWNKEY", "secret").await;
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>InvalidAccessKeyId</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn public_read_objects() {
+    let server = create_authenticated_server().await;
+    let token = server.create_access_token().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "private.png", None, b"secret")
+        .await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!("{}/photos/public.png", server.endpoint()))
+        .bearer_auth(&token.bearer_token)
+        .header("x-amz-acl", "public-read")
+        .body("shared")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::get(format!("{}/photos/private.png", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = reqwest::get(format!("{}/photos/public.png", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "shared");
+
+    // Public objects can only be read anonymously, not overwritten
+    let response = client
+        .put(format!("{}/photos/public.png", server.endpoint()))
+        .body("replaced")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client
+        .patch(format!("{}/api/buckets/photos", server.endpoint()))
+        .bearer_auth(&token.bearer_token)
+        .json(&json!({ "public_read": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .head(format!(
+            "{}/api/buckets/photos/objects/private.png",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Listing a public bucket still requires an access token
+    let response = reqwest::get(format!("{}/api/buckets/photos/objects", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+pub async fn scoped_access_tokens() {
+    let server = create_authenticated_server().await;
+    let admin = server.create_access_token().await;
+    server.create_bucket("photos").await;
+    server.put_object("photos", "cat.png", None, b"meow").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/access-tokens", server.endpoint());
+
+    let response = client
+        .post(&url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "description": "Gallery", "permissions": ["read"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = response.json::<Value>().await.unwrap();
+    assert_eq!(created["permissions"], json!(["read"]));
+    let read_only = created["token"].as_str().unwrap();
+
+    let response = client
+        .get(format!("{}/photos/cat.png", server.endpoint()))
+        .bearer_auth(read_only)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!("{}/photos/dog.png", server.endpoint()))
+        .bearer_auth(read_only)
+        .body("woof")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>AccessDenied</Code>")
+    );
+
+    // Managing access tokens needs the admin permission
+    let response = client
+        .get(&url)
+        .bearer_auth(read_only)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "FORBIDDEN"
+    );
+
+    // The write permission includes reading
+    let response = client
+        .post(&url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "permissions": ["write"] }))
+        .send()
+        .await
+        .unwrap();
+    let writer = response.json::<Value>().await.unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/dog.png",
+            server.endpoint()
+        ))
+        .bearer_auth(&writer)
+        .body("woof")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .delete(format!("{}/api/buckets/photos", server.endpoint()))
+        .bearer_auth(&writer)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client
+        .post(&url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "permissions": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+pub async fn update_access_token() {
+    let server = create_authenticated_server().await;
+    let admin = server.create_access_token().await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/access-tokens", server.endpoint());
+
+    let created = client
+        .post(&url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "description": "CI", "expires_at": "2999-01-01T00:00:00Z" }))
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    let token_url = format!("{}/{}", url, created["uuid"].as_str().unwrap());
+
+    let response = client
+        .patch(&token_url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "description": "CI uploads" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = response.json::<Value>().await.unwrap();
+    assert_eq!(updated["description"], "CI uploads");
+    assert_eq!(updated["expires_at"], "2999-01-01T00:00:00Z");
+
+    let response = client
+        .patch(&token_url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "expires_at": null }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = response.json::<Value>().await.unwrap();
+    assert_eq!(updated["description"], "CI uploads");
+    assert_eq!(updated["expires_at"], Value::Null);
+
+    let response = client
+        .patch(&token_url)
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "expires_at": "2000-01-01T00:00:00Z" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,755 @@
+use objection::{
+    config::HttpConfig,
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+#[tokio::test]
+pub async fn get_bucket_by_name() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("photos").await;
+
+    let response = reqwest::get(format!("{}/api/buckets/photos", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["name"], "photos");
+    assert_eq!(bucket["uuid"], uuid.to_string());
+}
+
+#[tokio::test]
+pub async fn get_missing_bucket() {
+    let server = create_test_server().await;
+
+    let response = reqwest::get(format!("{}/api/buckets/missing", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "BUCKET_NOT_FOUND");
+}
+
+#[tokio::test]
+pub async fn delete_bucket() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("photos").await;
+
+    server
+        .storage()
+        .put(uuid, "orphaned", "orphaned.txt", Box::pin(&b"meow"[..]))
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos", server.endpoint());
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!server.storage().exists(uuid, "orphaned").await.unwrap());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn patch_missing_bucket() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .patch(format!("{}/api/buckets/missing", server.endpoint()))
+        .json(&json!({ "access_logging": true }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn create_bucket() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "photos", "settings": { "access_logging": true } }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["name"], "photos");
+    assert_eq!(bucket["settings"]["access_logging"], true);
+
+    let buckets = client
+        .get(format!("{}/api/buckets", server.endpoint()))
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+
+    assert_eq!(buckets["data"].as_array().unwrap().len(), 1);
+    assert_eq!(buckets["data"][0]["name"], "photos");
+}
+
+#[tokio::test]
+pub async fn create_duplicate_bucket() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "photos" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+pub async fn create_bucket_invalid_names() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    for name in [
+        "ab",
+        "Photos",
+        "my_bucket",
+        "-photos",
+        "photos-",
+        "my--photos",
+        "192.168.1.1",
+        &"a".repeat(64),
+    ] {
+        let response = client
+            .post(format!("{}/api/buckets", server.endpoint()))
+            .json(&json!({ "name": name }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "bucket name `{}` should be rejected",
+            name
+        );
+
+        let error = response.json::<Value>().await.unwrap();
+        assert_eq!(error["error"], "INVALID_BUCKET_NAME");
+    }
+}
+
+#[tokio::test]
+pub async fn create_bucket_invalid_name_messages() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    for (name, message) in [
+        ("ab", "between 3 and 63 characters long, got 2"),
+        ("Photos", "found `P`"),
+        ("my_bucket", "found `_`"),
+        ("-photos", "begin and end with a letter or number"),
+        ("photos-", "begin and end with a letter or number"),
+        ("my--photos", "consecutive hyphens"),
+    ] {
+        let error = client
+            .post(format!("{}/api/buckets", server.endpoint()))
+            .json(&json!({ "name": name }))
+            .send()
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        assert_eq!(error["error"], "INVALID_BUCKET_NAME");
+        assert!(
+            error["message"].as_str().unwrap().contains(message),
+            "{}: {}",
+            name,
+            error["message"]
+        );
+    }
+}
+
+#[tokio::test]
+pub async fn create_bucket_valid_names() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    for name in ["abc", "photos", "my-photos-2024", "123", &"a".repeat(63)] {
+        let response = client
+            .post(format!("{}/api/buckets", server.endpoint()))
+            .json(&json!({ "name": name }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::CREATED,
+            "bucket name `{}` should be accepted",
+            name
+        );
+    }
+}
+
+#[tokio::test]
+pub async fn patch_bucket_cache_policy() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos", server.endpoint());
+
+    client
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "photos", "settings": { "access_logging": true } }))
+        .send()
+        .await
+        .unwrap();
+
+    let bucket = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["settings"]["default_cache_policy"], Value::Null);
+
+    let response = client
+        .patch(&url)
+        .json(&json!({ "default_cache_policy": "cache" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["settings"]["default_cache_policy"], "cache");
+
+    // Lookup the bucket again to make sure the change persisted, and that
+    // fields missing from the patch were left untouched
+    let bucket = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["settings"]["default_cache_policy"], "cache");
+    assert_eq!(bucket["settings"]["access_logging"], true);
+
+    let bucket = client
+        .patch(&url)
+        .json(&json!({ "default_cache_policy": null }))
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["settings"]["default_cache_policy"], Value::Null);
+}
+
+#[tokio::test]
+pub async fn delete_non_empty_bucket() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("photos").await;
+    let hash = server
+        .put_object("photos", "cat.jpg", Some("image/jpeg"), b"meow")
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos", server.endpoint());
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "BUCKET_NOT_EMPTY");
+
+    assert!(server.storage().exists(uuid, &hash).await.unwrap());
+
+    let response = client
+        .delete(format!("{}?force=true", url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!server.storage().exists(uuid, &hash).await.unwrap());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn bucket_counters_track_uploads() {
+    let server = create_test_server().await;
+    server.create_bucket("counted").await;
+
+    server.put_object("counted", "a.txt", None, b"hello").await;
+    server.put_object("counted", "b.txt", None, b"abc").await;
+    server
+        .put_object("counted", "a.txt", None, b"hello, world")
+        .await;
+
+    let bucket = reqwest::get(format!("{}/api/buckets/counted", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+
+    assert_eq!(bucket["object_count"], 2);
+    assert_eq!(bucket["total_bytes"], 15);
+}
+
+#[tokio::test]
+pub async fn transfer_object() {
+    let server = create_test_server().await;
+    let inbox = server.create_bucket("inbox").await;
+    let archive = server.create_bucket("archive").await;
+
+    let hash = server
+        .put_object("inbox", "report.txt", Some("text/plain"), b"quarterly")
+        .await;
+    let replaced = server
+        .put_object("archive", "report.txt", None, b"old report")
+        .await;
+
+    assert!(
+        server
+            .transfer_object("inbox", "report.txt", "archive")
+            .await
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/archive/objects/report.txt",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.text().await.unwrap(), "quarterly");
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/inbox/objects/report.txt",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Contents are only kept where they are still referenced
+    assert_eq!(server.stored_contents(inbox, &hash).await, None);
+    assert_eq!(
+        server.stored_contents(archive, &hash).await.as_deref(),
+        Some(&b"quarterly"[..])
+    );
+    assert_eq!(server.stored_contents(archive, &replaced).await, None);
+
+    for (name, object_count, total_bytes) in [("inbox", 0, 0), ("archive", 1, 9)] {
+        let bucket = reqwest::get(format!("{}/api/buckets/{}", server.endpoint(), name))
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+
+        assert_eq!(bucket["object_count"], object_count);
+        assert_eq!(bucket["total_bytes"], total_bytes);
+    }
+
+    assert!(
+        !server
+            .transfer_object("inbox", "report.txt", "archive")
+            .await
+    );
+}
+
+#[tokio::test]
+pub async fn list_buckets_paginated() {
+    let server = create_test_server().await;
+    for i in 0..25 {
+        server.create_bucket(&format!("bucket-{:02}", i)).await;
+    }
+
+    let url = format!("{}/api/buckets", server.endpoint());
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page = response.json::<Value>().await.unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 20);
+    assert_eq!(page["data"][0]["name"], "bucket-00");
+    assert_eq!(page["page"], 1);
+    assert_eq!(page["limit"], 20);
+    assert_eq!(page["total"], 25);
+    assert_eq!(page["has_next"], true);
+
+    let response = reqwest::get(format!("{}?page=2", url)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page = response.json::<Value>().await.unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 5);
+    assert_eq!(page["data"][0]["name"], "bucket-20");
+    assert_eq!(page["page"], 2);
+    assert_eq!(page["has_next"], false);
+}
+
+#[tokio::test]
+pub async fn list_buckets_limit_too_large() {
+    let server = create_test_server().await;
+
+    let response = reqwest::get(format!("{}/api/buckets?limit=101", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "INVALID_LIMIT");
+}
+
+#[tokio::test]
+pub async fn list_buckets_page_out_of_range() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets?page={}",
+        server.endpoint(),
+        u64::MAX
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "INVALID_PAGE");
+}
+
+/// Parses each line of a newline delimited JSON backup
+fn backup_lines(backup: &str) -> Vec<Value> {
+    backup
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+async fn post_backup(
+    server: &TestServer,
+    bucket: &str,
+    backup: impl Into<reqwest::Body>,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!(
+            "{}/api/buckets/{}/backup",
+            server.endpoint(),
+            bucket
+        ))
+        .body(backup)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn bucket_backup_round_trip() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cat.png", Some("image/png"), b"meow")
+        .await;
+    server
+        .put_object("photos", "dogs/dog.txt", None, b"woof")
+        .await;
+
+    let response = reqwest::get(format!("{}/api/buckets/photos/backup", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+
+    let backup = response.text().await.unwrap();
+    let lines = backup_lines(&backup);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0]["version"], 2);
+    assert_eq!(lines[0]["bucket"]["name"], "photos");
+    assert_eq!(lines[1]["object"]["key"], "cat.png");
+    assert_eq!(lines[1]["object"]["content_type"], "image/png");
+    assert_eq!(lines[1]["object"]["size"], 4);
+    assert_eq!(lines[2]["chunk"], "bWVvdw==");
+    assert_eq!(lines[3]["object"]["key"], "dogs/dog.txt");
+
+    let response = post_backup(&server, "restored", backup.clone()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["name"], "restored");
+    assert_eq!(bucket["object_count"], 2);
+    assert_eq!(bucket["total_bytes"], 8);
+
+    let response = reqwest::get(format!("{}/restored/cat.png", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.text().await.unwrap(), "meow");
+
+    // Restoring again skips the objects which are unchanged
+    let response = post_backup(&server, "restored", backup).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["object_count"], 2);
+    assert_eq!(bucket["total_bytes"], 8);
+}
+
+#[tokio::test]
+pub async fn bucket_backup_large_object() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+
+    let blob = (0..512 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    server.put_object("videos", "clip.bin", None, &blob).await;
+
+    let backup = reqwest::get(format!("{}/api/buckets/videos/backup", server.endpoint()))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // The contents are split over several chunks rather than a single line
+    let lines = backup_lines(&backup);
+    assert!(lines.len() > 3);
+    assert!(lines[2..].iter().all(|line| line["chunk"].is_string()));
+
+    let response = post_backup(&server, "restored", backup).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::get(format!("{}/restored/clip.bin", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.bytes().await.unwrap(), blob);
+}
+
+#[tokio::test]
+pub async fn bucket_backup_invalid() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let header = reqwest::get(format!("{}/api/buckets/photos/backup", server.endpoint()))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let object = json!({ "object": { "key": "cat.png", "content_type": null, "content_encoding": null, "size": 4 } });
+
+    let invalid_backups = [
+        // Contents which aren't base64
+        format!(
+            "{}{}\n{}\n",
+            header,
+            object,
+            json!({ "chunk": "not base64!" })
+        ),
+        // Contents shorter than the object's size
+        format!("{}{}\n", header, object),
+        // Contents longer than the object's size
+        format!(
+            "{}{}\n{}\n",
+            header,
+            object,
+            json!({ "chunk": "bWVvdyBtZW93" })
+        ),
+    ];
+    for backup in invalid_backups {
+        let response = post_backup(&server, "restored", backup).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error = response.json::<Value>().await.unwrap();
+        assert_eq!(error["error"], "INVALID_BACKUP");
+    }
+
+    let malformed_backups = [
+        String::new(),
+        "not json\n".to_owned(),
+        format!("{}{}\n", header, json!({ "chunk": "bWVvdw==" })),
+    ];
+    for backup in malformed_backups {
+        let response = post_backup(&server, "restored", backup).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let mut future = backup_lines(&header).remove(0);
+    future["version"] = json!(3);
+    let response = post_backup(&server, "restored", format!("{}\n", future)).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = reqwest::get(format!("{}/api/buckets/missing/backup", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn bucket_backup_body_limit() {
+    let server = create_test_server_with_config(test_config().http(HttpConfig {
+        max_body_bytes: Some(64 * 1024),
+        ..HttpConfig::random_port()
+    }))
+    .await;
+    server.create_bucket("videos").await;
+
+    let blob = vec![7; 128 * 1024];
+    server.put_object("videos", "clip.bin", None, &blob).await;
+
+    let backup = reqwest::get(format!("{}/api/buckets/videos/backup", server.endpoint()))
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+
+    // Streamed without a declared length, so the limit is only hit part way
+    // through importing
+    let chunks = backup
+        .chunks(16 * 1024)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    let response = post_backup(
+        &server,
+        "restored",
+        reqwest::Body::wrap_stream(futures::stream::iter(chunks)),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+pub async fn storage_stats() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server.create_bucket("videos").await;
+    server.create_bucket("empty").await;
+    server
+        .put_object("photos", "cat.png", None, &[0; 100])
+        .await;
+    server
+        .put_object("photos", "dog.png", None, &[1; 250])
+        .await;
+    server
+        .put_object("videos", "cat.mp4", None, &[2; 1000])
+        .await;
+
+    let stats = reqwest::get(format!("{}/api/stats", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(
+        stats,
+        json!({ "bucket_count": 3, "total_objects": 3, "total_bytes": 1350 })
+    );
+
+    let stats = reqwest::get(format!("{}/api/buckets/photos/stats", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(stats, json!({ "total_objects": 2, "total_bytes": 350 }));
+
+    let response = reqwest::get(format!("{}/api/buckets/missing/stats", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn bucket_tags() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let url = format!("{}/api/buckets/photos/tags", server.endpoint());
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({"tags": {}}));
+
+    let response = client
+        .put(&url)
+        .json(&json!({"tags": {"env": "prod", "team": "infra"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({"tags": {"env": "prod", "team": "infra"}})
+    );
+
+    // Tags are replaced rather than merged
+    let response = client
+        .put(&url)
+        .json(&json!({"tags": {"env": "staging"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({"tags": {"env": "staging"}})
+    );
+
+    let too_many = (0..51)
+        .map(|i| (format!("key{}", i), json!("value")))
+        .collect::<serde_json::Map<_, _>>();
+    let response = client
+        .put(&url)
+        .json(&json!({ "tags": too_many }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "INVALID_TAGS"
+    );
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({"tags": {}}));
+
+    let response = client
+        .get(format!("{}/api/buckets/videos/tags", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,108 @@
+use std::io::{Read, Write};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use objection::test_helpers::create_test_server;
+use reqwest::StatusCode;
+
+fn text() -> Vec<u8> {
+    "All work and no play makes Jack a dull boy.\n"
+        .repeat(100)
+        .into_bytes()
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn gunzip(body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(body).read_to_end(&mut decoded).unwrap();
+    decoded
+}
+
+#[tokio::test]
+pub async fn get_object_gzip() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+    server
+        .put_object("docs", "jack.txt", Some("text/plain"), &text())
+        .await;
+
+    let client = reqwest::Client::new();
+    for url in [
+        format!("{}/api/buckets/docs/objects/jack.txt", server.endpoint()),
+        format!("{}/docs/jack.txt", server.endpoint()),
+    ] {
+        let response = client
+            .get(&url)
+            .header("Accept-Encoding", "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+
+        let body = response.bytes().await.unwrap();
+        assert!(body.len() < text().len());
+        assert_eq!(gunzip(&body), text());
+
+        // Clients which don't accept compression get the contents as-is
+        let response = client.get(&url).send().await.unwrap();
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.bytes().await.unwrap(), text());
+    }
+}
+
+#[tokio::test]
+pub async fn get_object_already_compressed() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    server
+        .put_object("videos", "clip.mp4", Some("video/mp4"), &text())
+        .await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/videos/clip.mp4", server.endpoint()))
+        .header("Accept-Encoding", "gzip, br")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.bytes().await.unwrap(), text());
+}
+
+#[tokio::test]
+pub async fn get_object_uploaded_gzipped() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+
+    let url = format!("{}/api/buckets/docs/objects/jack.txt", server.endpoint());
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "text/plain")
+        .header("Content-Encoding", "gzip")
+        .body(gzip(&text()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let object = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(object["content_encoding"], "gzip");
+
+    // The stored encoding is served without compressing the contents again
+    let response = client
+        .get(&url)
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(gunzip(&response.bytes().await.unwrap()), text());
+}
@@ -0,0 +1,362 @@
+use std::{collections::HashSet, time::Duration};
+
+use axum::http::{HeaderName, Method, StatusCode};
+use objection::config::{Config, CorsConfig, HttpConfig};
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Child,
+};
+use url::Url;
+
+#[test]
+pub fn cors_config_json_round_trip() {
+    let cors = CorsConfig {
+        allow_origins: HashSet::from([
+            Url::parse("https://cdn.example.com").unwrap().origin(),
+            Url::parse("http://localhost:8080").unwrap().origin(),
+        ]),
+        allow_methods: HashSet::from([Method::GET, Method::HEAD]),
+        allow_headers: HashSet::from([HeaderName::from_static("authorization")]),
+        allow_credentials: true,
+        expose_headers: HashSet::from([HeaderName::from_static("etag")]),
+        max_age: Some(Duration::from_secs(600)),
+    };
+
+    let json = serde_json::to_value(&cors).unwrap();
+
+    let mut origins = json["allow_origins"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| o.as_str().unwrap())
+        .collect::<Vec<_>>();
+    origins.sort();
+    assert_eq!(
+        origins,
+        ["http://localhost:8080", "https://cdn.example.com"]
+    );
+
+    let parsed: CorsConfig = serde_json::from_value(json).unwrap();
+
+    assert_eq!(parsed.allow_origins, cors.allow_origins);
+    assert_eq!(parsed.allow_methods, cors.allow_methods);
+    assert_eq!(parsed.allow_headers, cors.allow_headers);
+    assert_eq!(parsed.allow_credentials, cors.allow_credentials);
+    assert_eq!(parsed.expose_headers, cors.expose_headers);
+    assert_eq!(parsed.max_age, cors.max_age);
+}
+
+#[test]
+pub fn cors_config_rejects_invalid_origin() {
+    let json = serde_json::json!({
+        "allow_origins": ["not a url"],
+        "allow_methods": [],
+        "allow_headers": [],
+        "allow_credentials": false,
+    });
+
+    assert!(serde_json::from_value::<CorsConfig>(json).is_err());
+}
+
+/// Spawns the server binary with debug logging and waits for it to start
+/// listening, returning the process, the port it bound and the config it
+/// logged on startup
+async fn spawn_server(command: &mut tokio::process::Command) -> (Child, u16, String) {
+    let mut server = command
+        .env("RUST_LOG", "objection=debug")
+        .env("NO_COLOR", "1")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let mut lines = BufReader::new(server.stdout.take().unwrap()).lines();
+    let mut config = None::<String>;
+
+    let port = tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if let Some((_, address)) = line.split_once("Listening on: ") {
+                let (_, port) = address.rsplit_once(':').unwrap();
+                return port.parse::<u16>().unwrap();
+            }
+
+            // The config is pretty-printed over several lines, up to the next
+            // timestamped log line
+            if let Some((_, dump)) = line.split_once("using config: ") {
+                config = Some(dump.to_owned());
+            } else if let Some(config) = config
+                .as_mut()
+                .filter(|_| line.starts_with(' ') || line.starts_with('}'))
+            {
+                config.push('\n');
+                config.push_str(&line);
+            }
+        }
+
+        panic!("server exited before listening");
+    })
+    .await
+    .expect("server didn't start listening");
+
+    (server, port, config.expect("server didn't log its config"))
+}
+
+/// Runs the server binary, returning the status of its health check once it
+/// starts listening
+async fn run_server(command: &mut tokio::process::Command) -> StatusCode {
+    let (mut server, port, _) = spawn_server(command).await;
+
+    let status = reqwest::get(format!("http://127.0.0.1:{}/health", port))
+        .await
+        .unwrap()
+        .status();
+
+    server.kill().await.unwrap();
+    status
+}
+
+#[tokio::test]
+pub async fn env_overrides_http_port() {
+    let data_directory = tempfile::tempdir().unwrap();
+
+    let (mut server, port, _) = spawn_server(
+        tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+            .env("OBJECTION_HTTP_HOST", "127.0.0.1")
+            .env("OBJECTION_HTTP_PORT", "0")
+            .env("OBJECTION_DATA_DIRECTORY", data_directory.path()),
+    )
+    .await;
+
+    // Port 0 asks the OS for any free port, so the override took effect if
+    // the server isn't on the default one
+    assert_ne!(port, HttpConfig::default().port);
+
+    let response = reqwest::get(format!("http://127.0.0.1:{}/health", port))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    server.kill().await.unwrap();
+}
+
+#[tokio::test]
+pub async fn json_config_file() {
+    let data_directory = tempfile::tempdir().unwrap();
+    let config_path = data_directory.path().join("config.json");
+
+    let config = serde_json::json!({
+        "data-directory": data_directory.path(),
+        "http": { "host": "127.0.0.1", "port": 0 },
+        "cache-control": { "default-policy": "no-cache", "default-max-age": 60 },
+        "rate-limiting": { "enable-rate-limiting": false },
+        "lifecycle": { "interval": "5m" },
+    });
+    std::fs::write(&config_path, serde_json::to_vec(&config).unwrap()).unwrap();
+
+    let status =
+        run_server(tokio::process::Command::new(env!("CARGO_BIN_EXE_objection")).arg(&config_path))
+            .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // JSON isn't valid TOML, so forcing the format fails to parse it
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+        .arg(&config_path)
+        .args(["--format", "toml"])
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to parse configuration file"));
+}
+
+#[tokio::test]
+pub async fn json_config_default_round_trip() {
+    let data_directory = tempfile::tempdir().unwrap();
+    let config_path = data_directory.path().join("config.json");
+
+    let server = || {
+        let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"));
+        command
+            .env("OBJECTION_HTTP_PORT", "0")
+            .env("OBJECTION_DATA_DIRECTORY", data_directory.path());
+        command
+    };
+    let duration = |d: Duration| humantime::format_duration(d).to_string();
+
+    let (mut default_server, _, default_config) = spawn_server(&mut server()).await;
+    default_server.kill().await.unwrap();
+
+    let config = Config::default();
+    let json = serde_json::json!({
+        "data-directory": data_directory.path(),
+        "http": {
+            "host": config.http.host,
+            "port": config.http.port,
+            "max-body-bytes": config.http.max_body_bytes,
+            "virtual-host-domain": config.http.virtual_host_domain,
+            "idle-timeout-secs": config.http.idle_timeout_secs,
+            "tcp-keepalive-secs": config.http.tcp_keepalive_secs,
+        },
+        "cache-control": {
+            "default-policy": config.cache_control.default_policy,
+            "default-max-age": config.cache_control.default_max_age,
+        },
+        "access-control": {
+            "enable-access-tokens": config.access_control.enable_access_tokens,
+            "enable-local-host-auth-bypass": config.access_control.enable_local_host_auth_bypass,
+        },
+        "rate-limiting": {
+            "enable-rate-limiting": config.rate_limiting.enable_rate_limiting,
+            "default-period": duration(config.rate_limiting.default_period),
+            "default-burst-size": config.rate_limiting.default_burst_size,
+        },
+        "lifecycle": {
+            "interval": duration(config.lifecycle.interval),
+            "soft-delete-retention-days": config.lifecycle.soft_delete_retention_days,
+            "multipart-upload-ttl": duration(config.lifecycle.multipart_upload_ttl),
+        },
+        "database": {
+            "max-connections": config.database.max_connections,
+            "min-connections": config.database.min_connections,
+            "idle-timeout": config.database.idle_timeout.map(duration),
+        },
+        "storage": {
+            "path-strategy": config.storage.path_strategy.to_string(),
+        },
+        "cache": {
+            "coalesce-concurrent-reads": config.cache.coalesce_concurrent_reads,
+            "coalesce-window": duration(config.cache.coalesce_window),
+        },
+        "presigned-secret": config.presigned_secret,
+    });
+    std::fs::write(&config_path, serde_json::to_vec(&json).unwrap()).unwrap();
+
+    let (mut file_server, _, file_config) = spawn_server(server().arg(&config_path)).await;
+    file_server.kill().await.unwrap();
+
+    assert_eq!(file_config, default_config);
+}
+
+#[tokio::test]
+pub async fn env_data_directory_completes_config_file() {
+    let data_directory = tempfile::tempdir().unwrap();
+    let config_path = data_directory.path().join("config.toml");
+    std::fs::write(&config_path, "[http]\nhost = \"127.0.0.1\"\nport = 0\n").unwrap();
+
+    let status = run_server(
+        tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+            .arg(&config_path)
+            .env("OBJECTION_DATA_DIRECTORY", data_directory.path()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Without either, the server exits with an error rather than panicking
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+        .arg(&config_path)
+        .env_remove("OBJECTION_DATA_DIRECTORY")
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No data directory is configured"));
+}
+
+/// Runs the server binary with a TOML configuration file, returning the
+/// error it exits with
+async fn config_error(data_directory: &tempfile::TempDir, config: &str) -> String {
+    let config_path = data_directory.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "data-directory = {:?}\n{}",
+            data_directory.path().to_str().unwrap(),
+            config
+        ),
+    )
+    .unwrap();
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+        .arg(&config_path)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[tokio::test]
+pub async fn cors_credentials_require_origins() {
+    let data_directory = tempfile::tempdir().unwrap();
+
+    let stderr = config_error(
+        &data_directory,
+        "[cors]\nallow-methods = [\"GET\"]\nallow-credentials = true\n",
+    )
+    .await;
+    assert!(stderr.contains("`allow-credentials` requires `allow-origins`"));
+
+    let stderr = config_error(
+        &data_directory,
+        "[cors]\nallow-origins = []\nallow-credentials = true\n",
+    )
+    .await;
+    assert!(stderr.contains("`allow-credentials` requires `allow-origins`"));
+}
+
+#[tokio::test]
+pub async fn cors_rejects_wildcard_methods() {
+    let data_directory = tempfile::tempdir().unwrap();
+
+    let stderr = config_error(
+        &data_directory,
+        "[cors]\nallow-origins = [\"https://cdn.example.com\"]\nallow-methods = [\"*\"]\n",
+    )
+    .await;
+    assert!(stderr.contains("the '*' wildcard is not supported"));
+}
+
+#[tokio::test]
+pub async fn content_type_rules_config() {
+    let data_directory = tempfile::tempdir().unwrap();
+
+    let stderr = config_error(
+        &data_directory,
+        "[content-types]\nrules = [{ key-pattern = \"images/**\", content-type = \"image\" }]\n",
+    )
+    .await;
+    assert!(stderr.contains("Invalid content type 'image'"));
+
+    let stderr = config_error(
+        &data_directory,
+        "[content-types]\nrules = [{ key-pattern = \"\", content-type = \"image/*\" }]\n",
+    )
+    .await;
+    assert!(stderr.contains("'key-pattern' must not be empty"));
+
+    let stderr = config_error(
+        &data_directory,
+        "[content-types]\nrules = [{ key-pattern = \"images/a**\", content-type = \"image/*\" }]\n",
+    )
+    .await;
+    assert!(stderr.contains("Invalid key pattern 'images/a**'"));
+
+    // Rules don't need a whitelist or blacklist alongside them
+    let config_path = data_directory.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        "[http]\nport = 0\n[content-types]\nrules = [{ key-pattern = \"images/**\", content-type = \"image/*\" }]\n",
+    )
+    .unwrap();
+
+    let status = run_server(
+        tokio::process::Command::new(env!("CARGO_BIN_EXE_objection"))
+            .arg(&config_path)
+            .env("OBJECTION_DATA_DIRECTORY", data_directory.path()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use objection::{
+    config::HttpConfig,
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Opens a connection and makes a keep-alive request on it, leaving the
+/// connection open once the response has been read
+async fn open_connection(server: &TestServer) -> TcpStream {
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+
+    // Read the headers, then exactly as much body as they declare, so nothing
+    // is left to be read afterwards
+    let mut response = Vec::new();
+    let mut buf = [0; 1024];
+    let headers_end = loop {
+        if let Some(index) = response.windows(4).position(|w| w == b"\r\n\r\n") {
+            break index + 4;
+        }
+
+        let read = stream.read(&mut buf).await.unwrap();
+        assert_ne!(read, 0, "connection closed before the response was read");
+        response.extend_from_slice(&buf[..read]);
+    };
+
+    let headers = String::from_utf8_lossy(&response[..headers_end]).to_lowercase();
+    let content_length: usize = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length: "))
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let mut body = vec![0; headers_end + content_length - response.len()];
+    stream.read_exact(&mut body).await.unwrap();
+
+    assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+
+    stream
+}
+
+#[tokio::test]
+pub async fn idle_connections_are_closed() {
+    let server = create_test_server_with_config(test_config().http(HttpConfig {
+        idle_timeout_secs: Some(1),
+        tcp_keepalive_secs: Some(30),
+        ..HttpConfig::random_port()
+    }))
+    .await;
+
+    let mut stream = open_connection(&server).await;
+
+    let mut buf = [0; 1024];
+    let read = tokio::time::timeout(Duration::from_secs(3), stream.read(&mut buf))
+        .await
+        .expect("the idle connection was never closed")
+        .unwrap();
+    assert_eq!(read, 0);
+
+    // New connections are still served
+    let response = reqwest::get(format!("{}/health", server.endpoint()))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+pub async fn idle_connections_kept_without_timeout() {
+    let server = create_test_server().await;
+
+    let mut stream = open_connection(&server).await;
+
+    let mut buf = [0; 1024];
+    let result = tokio::time::timeout(Duration::from_millis(1500), stream.read(&mut buf)).await;
+    assert!(result.is_err(), "the idle connection was closed");
+}
@@ -0,0 +1,50 @@
+use std::{collections::HashSet, time::Duration};
+
+use axum::http::{HeaderName, Method};
+use objection::{
+    config::CorsConfig,
+    test_helpers::{create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use url::Url;
+
+#[tokio::test]
+pub async fn cors_expose_headers_and_max_age() {
+    let server = create_test_server_with_config(test_config().cors(CorsConfig {
+        allow_origins: HashSet::from([Url::parse("https://app.example.com").unwrap().origin()]),
+        allow_methods: HashSet::from([Method::GET, Method::PUT]),
+        allow_headers: HashSet::from([HeaderName::from_static("content-type")]),
+        allow_credentials: false,
+        expose_headers: HashSet::from([HeaderName::from_static("etag")]),
+        max_age: Some(Duration::from_secs(600)),
+    }))
+    .await;
+    server.create_bucket("assets").await;
+    server.put_object("assets", "app.js", None, b"main()").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/assets/objects/app.js", server.endpoint());
+
+    let response = client
+        .request(Method::OPTIONS, &url)
+        .header("origin", "https://app.example.com")
+        .header("access-control-request-method", "PUT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-max-age"], "600");
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+
+    let response = client
+        .get(&url)
+        .header("origin", "https://app.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-expose-headers"], "etag");
+}
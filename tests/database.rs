@@ -0,0 +1,66 @@
+use objection::{
+    config::DatabaseConfig,
+    test_helpers::{TestServer, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+async fn put_object(server: &TestServer, key: &str) -> StatusCode {
+    reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/files/objects/{}",
+            server.endpoint(),
+            key
+        ))
+        .body(key.to_owned())
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+pub async fn concurrent_writes() {
+    let server = create_test_server_with_config(test_config().database(DatabaseConfig {
+        max_connections: 10,
+        min_connections: 2,
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("files").await;
+
+    let statuses = tokio::join!(
+        put_object(&server, "0"),
+        put_object(&server, "1"),
+        put_object(&server, "2"),
+        put_object(&server, "3"),
+        put_object(&server, "4"),
+        put_object(&server, "5"),
+        put_object(&server, "6"),
+        put_object(&server, "7"),
+        put_object(&server, "8"),
+        put_object(&server, "9"),
+    );
+    let statuses: [StatusCode; 10] = statuses.into();
+    assert!(
+        statuses.iter().all(|status| *status == StatusCode::OK),
+        "{:?}",
+        statuses
+    );
+
+    let bucket = reqwest::get(format!("{}/api/buckets/files", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 10);
+
+    // The database is in WAL mode
+    assert!(
+        server
+            .data_directory()
+            .join("database.sqlite3-wal")
+            .exists()
+    );
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use objection::{
+    config::LifecycleConfig,
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// A server-sent event stream, parsed one event at a time
+struct EventStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventStream {
+    async fn connect(server: &TestServer, bucket: &str) -> Self {
+        let response = reqwest::get(format!(
+            "{}/api/buckets/{}/events",
+            server.endpoint(),
+            bucket
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        Self {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    /// Waits up to a second for the next event, returning its name and data
+    async fn next(&mut self) -> (String, Value) {
+        tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(end) = self.buffer.find("\n\n") {
+                    let event = self.buffer[..end].to_owned();
+                    self.buffer.drain(..end + 2);
+
+                    let field = |name: &str| {
+                        event
+                            .lines()
+                            .find_map(|line| line.strip_prefix(name))
+                            .map(str::to_owned)
+                    };
+
+                    // Keep-alive comments carry no event
+                    if let (Some(name), Some(data)) = (field("event: "), field("data: ")) {
+                        return (name, serde_json::from_str(&data).unwrap());
+                    }
+                    continue;
+                }
+
+                let chunk = self.response.chunk().await.unwrap().unwrap();
+                self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+        })
+        .await
+        .expect("no event arrived within a second")
+    }
+}
+
+#[tokio::test]
+pub async fn bucket_events() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    let client = reqwest::Client::new();
+
+    let mut events = EventStream::connect(&server, "photos").await;
+
+    let response = client
+        .put(format!("{}/photos/cat.txt", server.endpoint()))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+
+    assert_eq!(
+        events.next().await,
+        (
+            "ObjectCreated".to_owned(),
+            json!({"type": "ObjectCreated", "key": "cat.txt", "etag": etag, "size": 4})
+        )
+    );
+
+    let response = client
+        .delete(format!("{}/photos/cat.txt", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    assert_eq!(
+        events.next().await,
+        (
+            "ObjectDeleted".to_owned(),
+            json!({"type": "ObjectDeleted", "key": "cat.txt"})
+        )
+    );
+
+    let response = client
+        .patch(format!("{}/api/buckets/photos", server.endpoint()))
+        .json(&json!({"access_logging": true}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        events.next().await,
+        (
+            "BucketSettingsChanged".to_owned(),
+            json!({"type": "BucketSettingsChanged"})
+        )
+    );
+}
+
+#[tokio::test]
+pub async fn bucket_events_only_for_subscribed_bucket() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server.create_bucket("videos").await;
+    let client = reqwest::Client::new();
+
+    let mut events = EventStream::connect(&server, "photos").await;
+
+    let response = client
+        .put(format!("{}/videos/cat.mp4", server.endpoint()))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/cat.txt",
+            server.endpoint()
+        ))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (name, data) = events.next().await;
+    assert_eq!(name, "ObjectCreated");
+    assert_eq!(data["key"], "cat.txt");
+
+    let response = reqwest::get(format!("{}/api/buckets/music/events", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn lifecycle_expiry_events() {
+    // Lifecycle rules are applied in the background rather than while
+    // handling a request
+    let server = create_test_server_with_config(test_config().lifecycle(LifecycleConfig {
+        interval: Duration::from_millis(50),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("logs").await;
+    server
+        .put_object("logs", "app/today.log", None, b"started")
+        .await;
+
+    let mut events = EventStream::connect(&server, "logs").await;
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/api/buckets/logs/lifecycle", server.endpoint()))
+        .json(&json!({ "rules": [{ "prefix": "app/", "expiration_days": 0 }] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        events.next().await,
+        (
+            "ObjectDeleted".to_owned(),
+            json!({"type": "ObjectDeleted", "key": "app/today.log"})
+        )
+    );
+}
+
+#[tokio::test]
+pub async fn move_object_events() {
+    let server = create_test_server().await;
+    server.create_bucket("inbox").await;
+    server.create_bucket("archive").await;
+    server
+        .put_object("inbox", "report.txt", None, b"quarterly")
+        .await;
+
+    let mut inbox = EventStream::connect(&server, "inbox").await;
+    let mut archive = EventStream::connect(&server, "archive").await;
+
+    let response = reqwest::Client::new()
+        .request(
+            reqwest::Method::from_bytes(b"MOVE").unwrap(),
+            format!("{}/api/buckets/inbox/objects/report.txt", server.endpoint()),
+        )
+        .header("Destination", "archive/report.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let etag = response.json::<Value>().await.unwrap()["etag"].clone();
+
+    assert_eq!(
+        inbox.next().await,
+        (
+            "ObjectDeleted".to_owned(),
+            json!({"type": "ObjectDeleted", "key": "report.txt"})
+        )
+    );
+    assert_eq!(
+        archive.next().await,
+        (
+            "ObjectCreated".to_owned(),
+            json!({"type": "ObjectCreated", "key": "report.txt", "etag": etag, "size": 9})
+        )
+    );
+}
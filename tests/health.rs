@@ -0,0 +1,63 @@
+use objection::{
+    config::AccessControlConfig,
+    test_helpers::{create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+#[tokio::test]
+pub async fn health_check() {
+    let server = create_test_server().await;
+
+    let response = reqwest::get(format!("{}/health", server.endpoint()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let health = response.json::<Value>().await.unwrap();
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["db"], "ok");
+}
+
+#[tokio::test]
+pub async fn health_checks_skip_authentication() {
+    let server =
+        create_test_server_with_config(test_config().access_control(AccessControlConfig {
+            enable_access_tokens: true,
+            enable_local_host_auth_bypass: false,
+        }))
+        .await;
+
+    for path in ["/health", "/ready"] {
+        let response = reqwest::get(format!("{}{}", server.endpoint(), path))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status(),
+            StatusCode::OK,
+            "{} should be public",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+pub async fn ready_after_migrations() {
+    let server = create_test_server().await;
+    let url = format!("{}/ready", server.endpoint());
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().await.unwrap()["status"], "ready");
+
+    server.forget_latest_migration().await;
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["status"],
+        "not_ready"
+    );
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, Ipv6Addr},
+};
+
+use objection::{
+    config::{HttpConfig, IpFilterConfig},
+    test_helpers::{TestServer, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+fn cidrs(cidrs: &[&str]) -> BTreeSet<cidr::IpCidr> {
+    cidrs.iter().map(|c| c.parse().unwrap()).collect()
+}
+
+async fn list_buckets(server: &TestServer) -> reqwest::Response {
+    reqwest::get(format!("{}/api/buckets", server.endpoint()))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn whitelisted_address_allowed() {
+    let server = create_test_server_with_config(
+        test_config().ip_filter(IpFilterConfig::Whitelist(cidrs(&["127.0.0.1/32"]))),
+    )
+    .await;
+
+    assert_eq!(list_buckets(&server).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn address_outside_whitelist_rejected() {
+    let server = create_test_server_with_config(
+        test_config().ip_filter(IpFilterConfig::Whitelist(cidrs(&["10.0.0.0/8"]))),
+    )
+    .await;
+
+    let response = list_buckets(&server).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = response.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "FORBIDDEN");
+}
+
+#[tokio::test]
+pub async fn ipv6_address_outside_whitelist_rejected() {
+    let server = create_test_server_with_config(
+        test_config()
+            .http(HttpConfig {
+                host: IpAddr::V6(Ipv6Addr::LOCALHOST),
+                port: 0,
+                ..Default::default()
+            })
+            .ip_filter(IpFilterConfig::Whitelist(cidrs(&["127.0.0.1"]))),
+    )
+    .await;
+
+    assert_eq!(list_buckets(&server).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+pub async fn blacklisted_address_rejected() {
+    let server = create_test_server_with_config(
+        test_config().ip_filter(IpFilterConfig::Blacklist(cidrs(&["127.0.0.0/8"]))),
+    )
+    .await;
+
+    assert_eq!(list_buckets(&server).await.status(), StatusCode::FORBIDDEN);
+}
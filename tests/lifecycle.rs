@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use objection::{
+    config::LifecycleConfig,
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// Creates a test server which applies lifecycle rules almost continuously
+async fn create_lifecycle_server() -> TestServer {
+    create_test_server_with_config(test_config().lifecycle(LifecycleConfig {
+        interval: Duration::from_millis(50),
+        ..Default::default()
+    }))
+    .await
+}
+
+async fn put_lifecycle(server: &TestServer, bucket: &str, rules: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/{}/lifecycle",
+            server.endpoint(),
+            bucket
+        ))
+        .json(&json!({ "rules": rules }))
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Polls `condition` until it holds, failing the test after a few seconds
+async fn wait_until<F, Fut>(mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    for _ in 0..100 {
+        if condition().await {
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    panic!("Timed out waiting for the lifecycle rules to be applied");
+}
+
+#[tokio::test]
+pub async fn lifecycle_rules() {
+    let server = create_test_server().await;
+    server.create_bucket("logs").await;
+
+    let url = format!("{}/api/buckets/logs/lifecycle", server.endpoint());
+
+    let lifecycle = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(lifecycle["rules"], json!([]));
+
+    let response = put_lifecycle(
+        &server,
+        "logs",
+        json!([
+            { "prefix": "app/", "expiration_days": 30 },
+            { "abort_incomplete_multipart_days": 7 },
+        ]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let lifecycle = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    let rules = lifecycle["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0]["prefix"], "app/");
+    assert_eq!(rules[0]["expiration_days"], 30);
+    assert_eq!(rules[0]["abort_incomplete_multipart_days"], Value::Null);
+    assert_eq!(rules[1]["prefix"], "");
+    assert_eq!(rules[1]["abort_incomplete_multipart_days"], 7);
+
+    // Rules must do something
+    let response = put_lifecycle(&server, "logs", json!([{ "prefix": "app/" }])).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "INVALID_LIFECYCLE_RULE");
+
+    // Setting the rules replaces all of the existing ones
+    let response = put_lifecycle(&server, "logs", json!([])).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let lifecycle = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(lifecycle["rules"], json!([]));
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/missing/lifecycle",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn lifecycle_expires_objects() {
+    let server = create_lifecycle_server().await;
+    server.create_bucket("logs").await;
+    server
+        .put_object("logs", "app/today.log", None, b"started")
+        .await;
+    server
+        .put_object("logs", "audit/today.log", None, b"logged in")
+        .await;
+
+    let response = put_lifecycle(
+        &server,
+        "logs",
+        json!([{ "prefix": "app/", "expiration_days": 0 }]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = format!("{}/logs/app/today.log", server.endpoint());
+    wait_until(|| async { reqwest::get(&url).await.unwrap().status() == StatusCode::NOT_FOUND })
+        .await;
+
+    // Objects outside the prefix are kept
+    let response = reqwest::get(format!("{}/logs/audit/today.log", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "logged in");
+
+    let bucket = reqwest::get(format!("{}/api/buckets/logs", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 1);
+}
+
+#[tokio::test]
+pub async fn lifecycle_aborts_multipart_uploads() {
+    let server = create_lifecycle_server().await;
+    server.create_bucket("videos").await;
+
+    let client = reqwest::Client::new();
+    let body = client
+        .post(format!(
+            "{}/api/buckets/videos/objects/cat.mp4?uploads",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let start = body.find("<UploadId>").unwrap() + "<UploadId>".len();
+    let end = body.find("</UploadId>").unwrap();
+    let upload_id = body[start..end].to_owned();
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/videos/objects/cat.mp4?partNumber=1&uploadId={}",
+            server.endpoint(),
+            upload_id
+        ))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(server.data_directory().join(&upload_id).exists());
+
+    let response = put_lifecycle(
+        &server,
+        "videos",
+        json!([{ "abort_incomplete_multipart_days": 0 }]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let parts = server.data_directory().join(&upload_id);
+    wait_until(|| async { !parts.exists() }).await;
+
+    let response = client
+        .delete(format!(
+            "{}/api/buckets/videos/objects/cat.mp4?uploadId={}",
+            server.endpoint(),
+            upload_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn delete_lifecycle_rules() {
+    // Rules are only applied once an hour, so expired objects wait to be
+    // removed
+    let server = create_test_server().await;
+    server.create_bucket("logs").await;
+    server
+        .put_object("logs", "app/today.log", None, b"started")
+        .await;
+
+    let response = put_lifecycle(
+        &server,
+        "logs",
+        json!([{ "prefix": "app/", "expiration_days": 0 }]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = format!("{}/api/buckets/logs/lifecycle", server.endpoint());
+    let response = reqwest::Client::new().delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let lifecycle = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(lifecycle["rules"], json!([]));
+
+    // Clearing the rules doesn't remove the object which already expired
+    let response = reqwest::get(format!("{}/logs/app/today.log", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::Client::new()
+        .delete(format!(
+            "{}/api/buckets/missing/lifecycle",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn deleted_lifecycle_rules_are_not_applied() {
+    let server = create_lifecycle_server().await;
+    server.create_bucket("logs").await;
+
+    let response = put_lifecycle(
+        &server,
+        "logs",
+        json!([{ "prefix": "app/", "expiration_days": 0 }]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/api/buckets/logs/lifecycle", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    server
+        .put_object("logs", "app/today.log", None, b"started")
+        .await;
+
+    // The rules are checked several times over without expiring the object
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let response = reqwest::get(format!("{}/logs/app/today.log", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -1,41 +1,21 @@
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, Ipv6Addr},
+};
+
 use objection::{
-    config::{Config, HttpConfig},
-    create_server,
+    config::{AccessControlConfig, HttpConfig, IpFilterConfig},
+    test_helpers::{
+        TestServer, TestServerConfig, create_test_server, create_test_server_with,
+        create_test_server_with_config, test_config,
+    },
 };
 use s3::creds::Credentials;
-use tempdir::TempDir;
-use tokio_util::task::AbortOnDropHandle;
-
-/// An ephemeral testing server which binds to a random port and uses a tmp
-/// directory for object storage.
-struct TestServer {
-    /// Used to configure the S3 request region
-    region: s3::Region,
-    /// Temporary directory used for object storage
-    _data_directory: TempDir,
-    /// Handle to this server
-    _handle: AbortOnDropHandle<()>,
-}
-
-async fn create_test_server() -> TestServer {
-    let data_directory =
-        tempdir::TempDir::new("objection-testing").expect("Failed to create temporary directory");
-
-    let config = Config {
-        data_directory: data_directory.path().to_owned(),
-        http: HttpConfig::random_port(),
-        ..Default::default()
-    };
 
-    let (addr, join_handle) = create_server(config).await;
-
-    TestServer {
-        region: s3::Region::Custom {
-            region: "us-east-1".into(),
-            endpoint: format!("http://127.0.0.1:{}", addr.port()),
-        },
-        _data_directory: data_directory,
-        _handle: AbortOnDropHandle::new(join_handle),
+fn region(server: &TestServer) -> s3::Region {
+    s3::Region::Custom {
+        region: server.effective_region(),
+        endpoint: server.endpoint(),
     }
 }
 
@@ -43,9 +23,185 @@ async fn create_test_server() -> TestServer {
 pub async fn list_buckets_anonymous_empty() {
     let server = create_test_server().await;
 
-    let buckets = s3::Bucket::list_buckets(server.region, Credentials::anonymous().unwrap())
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(buckets.buckets.bucket.len(), 0);
+}
+
+#[tokio::test]
+pub async fn list_buckets_anonymous_empty_ipv6() {
+    let server = create_test_server_with_config(test_config().http(HttpConfig {
+        host: IpAddr::V6(Ipv6Addr::LOCALHOST),
+        port: 0,
+        ..Default::default()
+    }))
+    .await;
+
+    assert!(server.addr().is_ipv6());
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
         .await
         .unwrap();
 
     assert_eq!(buckets.buckets.bucket.len(), 0);
 }
+
+#[tokio::test]
+pub async fn list_buckets_after_create() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&serde_json::json!({ "name": "photos" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(buckets.buckets.bucket.len(), 1);
+    assert_eq!(buckets.buckets.bucket[0].name, "photos");
+}
+
+#[tokio::test]
+pub async fn list_buckets_signed() {
+    let server = create_test_server_with_config(
+        test_config().access_control(AccessControlConfig::default()),
+    )
+    .await;
+    let token = server.create_access_token().await;
+
+    let credentials = Credentials::new(
+        Some(&token.access_key_id),
+        Some(&token.secret_access_key),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let buckets = s3::Bucket::list_buckets(region(&server), credentials)
+        .await
+        .unwrap();
+
+    assert_eq!(buckets.buckets.bucket.len(), 0);
+}
+
+#[tokio::test]
+pub async fn list_buckets_xml() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::get(server.endpoint()).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/xml")
+    );
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        buckets.bucket_names().collect::<Vec<_>>(),
+        ["photos", "videos"]
+    );
+    assert!(chrono::DateTime::parse_from_rfc3339(&buckets.buckets.bucket[0].creation_date).is_ok());
+}
+
+#[tokio::test]
+pub async fn api_info() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::get(format!("{}/api", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/json")
+    );
+
+    let info = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["storage_backend"], "memory");
+    assert_eq!(info["buckets"], 2);
+
+    // A server storing contents on disk reports that backend instead
+    let local_server = create_test_server_with(TestServerConfig {
+        storage_backend: None,
+        ..Default::default()
+    })
+    .await;
+    let info = reqwest::get(format!("{}/api", local_server.endpoint()))
+        .await
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(info["storage_backend"], "local");
+
+    // The S3 service endpoint is unaffected
+    let response = reqwest::get(server.endpoint()).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/xml")
+    );
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<ListAllMyBucketsResult")
+    );
+}
+
+#[tokio::test]
+pub async fn list_buckets_whitelisted_localhost() {
+    let whitelist = ["127.0.0.1/32", "::1/128"]
+        .into_iter()
+        .map(|cidr| cidr.parse::<cidr::IpCidr>().unwrap())
+        .collect::<BTreeSet<_>>();
+
+    let server = create_test_server_with_config(
+        test_config().ip_filter(IpFilterConfig::Whitelist(whitelist.clone())),
+    )
+    .await;
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(buckets.buckets.bucket.len(), 0);
+
+    let server = create_test_server_with_config(
+        test_config()
+            .http(HttpConfig {
+                host: IpAddr::V6(Ipv6Addr::LOCALHOST),
+                port: 0,
+                ..Default::default()
+            })
+            .ip_filter(IpFilterConfig::Whitelist(whitelist)),
+    )
+    .await;
+    assert!(server.addr().is_ipv6());
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(buckets.buckets.bucket.len(), 0);
+}
@@ -0,0 +1,72 @@
+use objection::test_helpers::{TestServer, create_test_server};
+use reqwest::StatusCode;
+
+/// Value of the sample of `metric` whose labels include all of `labels`
+async fn sample(server: &TestServer, metric: &str, labels: &[&str]) -> Option<f64> {
+    let response = reqwest::get(format!("{}/metrics", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.text().await.unwrap();
+
+    body.lines()
+        .filter(|line| line.starts_with(&format!("{}{{", metric)))
+        .find(|line| labels.iter().all(|label| line.contains(label)))
+        .and_then(|line| line.rsplit_once(' '))
+        .map(|(_, value)| value.parse().unwrap())
+}
+
+#[tokio::test]
+pub async fn metrics() {
+    let server = create_test_server().await;
+    server.create_bucket("metrics-photos").await;
+
+    let labels = [
+        r#"method="PUT""#,
+        r#"status="200""#,
+        r#"path="/{name}/{*key}""#,
+    ];
+    let before = sample(&server, "objection_http_requests_total", &labels)
+        .await
+        .unwrap_or(0.0);
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/metrics-photos/cat.txt", server.endpoint()))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::get(format!("{}/metrics-photos/cat.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.bytes().await.unwrap(), b"meow".as_slice());
+
+    let after = sample(&server, "objection_http_requests_total", &labels).await;
+    assert_eq!(after, Some(before + 1.0));
+
+    let bucket = [r#"bucket="metrics-photos""#];
+    assert_eq!(
+        sample(&server, "objection_bytes_uploaded_total", &bucket).await,
+        Some(4.0)
+    );
+    assert_eq!(
+        sample(&server, "objection_bytes_downloaded_total", &bucket).await,
+        Some(4.0)
+    );
+    assert_eq!(
+        sample(&server, "objection_objects_total", &bucket).await,
+        Some(1.0)
+    );
+    assert!(
+        sample(
+            &server,
+            "objection_request_duration_seconds_count",
+            &[r#"method="GET""#, r#"path="/{name}/{*key}""#]
+        )
+        .await
+        .is_some()
+    );
+}
@@ -0,0 +1,630 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use md5::{Digest, Md5};
+use objection::{
+    config::{ContentTypeFilter, ContentTypesConfig, LifecycleConfig},
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+
+/// Size of each part in the multi-part tests, which is the minimum S3 allows
+/// for all but the last part
+const PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Extracts the text of the first `<{tag}>` element in an XML document
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+
+    Some(&xml[start..end])
+}
+
+async fn initiate_upload(server: &TestServer, bucket: &str, key: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!(
+            "{}/api/buckets/{}/objects/{}?uploads",
+            server.endpoint(),
+            bucket,
+            key
+        ))
+        .header("content-type", "video/mp4")
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Initiates an upload and returns its ID
+async fn initiate_upload_id(server: &TestServer, bucket: &str, key: &str) -> String {
+    let body = initiate_upload(server, bucket, key)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    xml_text(&body, "UploadId").unwrap().to_owned()
+}
+
+async fn upload_part(
+    server: &TestServer,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: u32,
+    body: Vec<u8>,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/{}/objects/{}?partNumber={}&uploadId={}",
+            server.endpoint(),
+            bucket,
+            key,
+            part_number,
+            upload_id
+        ))
+        .header("content-type", "video/mp4")
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn initiate_multipart_upload() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+
+    let response = initiate_upload(&server, "videos", "clips/cat.mp4").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/xml")
+    );
+
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<InitiateMultipartUploadResult>"));
+    assert_eq!(xml_text(&body, "Bucket"), Some("videos"));
+    assert_eq!(xml_text(&body, "Key"), Some("clips/cat.mp4"));
+    assert!(
+        xml_text(&body, "UploadId")
+            .unwrap()
+            .parse::<uuid::Uuid>()
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+pub async fn initiate_multipart_upload_missing_bucket() {
+    let server = create_test_server().await;
+
+    let response = initiate_upload(&server, "missing", "cat.mp4").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn upload_parts() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    for (part_number, byte) in [(1, b'a'), (2, b'b')] {
+        let body = vec![byte; PART_SIZE];
+        let etag = format!("\"{}\"", hex::encode(Md5::digest(&body)));
+
+        let response =
+            upload_part(&server, "videos", "cat.mp4", &upload_id, part_number, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+    }
+
+    // The object only exists once the upload is completed
+    let response = reqwest::get(format!(
+        "{}/api/buckets/videos/objects/cat.mp4",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn multipart_upload_content_type_filter() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        filter: Some(ContentTypeFilter::Whitelist(BTreeSet::from([
+            "video/*".parse().unwrap(),
+        ]))),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("videos").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/videos/objects/cat.mp4", server.endpoint());
+
+    // The object's content type is filtered when the upload is initiated
+    let response = client
+        .post(format!("{}?uploads", url))
+        .header("content-type", "text/plain")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    // Parts and the list completing the upload aren't object contents, so
+    // they aren't filtered
+    let response = client
+        .put(format!("{}?partNumber=1&uploadId={}", url, upload_id))
+        .body("the whole video")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+
+    let response = client
+        .post(format!("{}?uploadId={}", url, upload_id))
+        .header("content-type", "application/xml")
+        .body(complete_body(&[(1, &etag)]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.headers()["content-type"], "video/mp4");
+    assert_eq!(response.bytes().await.unwrap(), "the whole video");
+}
+
+#[tokio::test]
+pub async fn upload_part_validation() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    for part_number in [0, 10_001] {
+        let response = upload_part(
+            &server,
+            "videos",
+            "cat.mp4",
+            &upload_id,
+            part_number,
+            b"meow".to_vec(),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "part number {} should be rejected",
+            part_number
+        );
+    }
+
+    // Uploads are tied to the object they were created for
+    let response = upload_part(
+        &server,
+        "videos",
+        "dog.mp4",
+        &upload_id,
+        1,
+        b"woof".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &uuid::Uuid::new_v4().to_string(),
+        1,
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Builds a `CompleteMultipartUpload` document from part numbers and ETags
+fn complete_body(parts: &[(u32, &str)]) -> String {
+    let parts = parts
+        .iter()
+        .map(|(part_number, etag)| {
+            format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                part_number, etag
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+        parts
+    )
+}
+
+#[tokio::test]
+pub async fn complete_multipart_upload() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    let parts = [vec![b'a'; PART_SIZE], b"the end".to_vec()];
+    let mut etags = Vec::new();
+    for (i, body) in parts.iter().enumerate() {
+        let response = upload_part(
+            &server,
+            "videos",
+            "cat.mp4",
+            &upload_id,
+            i as u32 + 1,
+            body.clone(),
+        )
+        .await;
+
+        etags.push(response.headers()["etag"].to_str().unwrap().to_owned());
+    }
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/videos/objects/cat.mp4", server.endpoint());
+
+    let response = client
+        .post(format!("{}?uploadId={}", url, upload_id))
+        .header("content-type", "application/xml")
+        .body(complete_body(&[(1, &etags[0]), (2, &etags[1])]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut part_md5s = Vec::new();
+    for etag in &etags {
+        part_md5s.extend(hex::decode(etag.trim_matches('"')).unwrap());
+    }
+    let etag = format!("\"{}-2\"", hex::encode(Md5::digest(&part_md5s)));
+
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<CompleteMultipartUploadResult>"));
+    assert_eq!(xml_text(&body, "Key"), Some("cat.mp4"));
+    assert_eq!(xml_text(&body, "ETag"), Some(etag.as_str()));
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "video/mp4");
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    assert_eq!(response.bytes().await.unwrap(), parts.concat());
+
+    // Parts are cleaned up and the upload can't be completed again
+    assert!(!server.data_directory().join(&upload_id).exists());
+
+    let response = client
+        .post(format!("{}?uploadId={}", url, upload_id))
+        .body(complete_body(&[(1, &etags[0]), (2, &etags[1])]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn complete_multipart_upload_invalid_part() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    let response = upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &upload_id,
+        1,
+        b"meow".to_vec(),
+    )
+    .await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/api/buckets/videos/objects/cat.mp4?uploadId={}",
+        server.endpoint(),
+        upload_id
+    );
+
+    for parts in [
+        vec![(1, "\"0123456789abcdef0123456789abcdef\"")],
+        vec![(1, etag.as_str()), (2, etag.as_str())],
+    ] {
+        let response = client
+            .post(&url)
+            .body(complete_body(&parts))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let error = response.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(error["error"], "INVALID_PART");
+    }
+
+    // The upload is left untouched by a failed completion
+    let response = client
+        .post(&url)
+        .body(complete_body(&[(1, &etag)]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn abort_multipart_upload() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &upload_id,
+        1,
+        b"meow".to_vec(),
+    )
+    .await;
+    assert!(server.data_directory().join(&upload_id).exists());
+
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/api/buckets/videos/objects/cat.mp4?uploadId={}",
+        server.endpoint(),
+        upload_id
+    );
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!server.data_directory().join(&upload_id).exists());
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &upload_id,
+        2,
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn delete_bucket_removes_upload_parts() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    let response = upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &upload_id,
+        1,
+        b"meow".to_vec(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(server.data_directory().join(&upload_id).exists());
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/api/buckets/videos", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!server.data_directory().join(&upload_id).exists());
+}
+
+async fn list_uploads(server: &TestServer, bucket: &str, query: &str) -> String {
+    let response = reqwest::get(format!("{}/{}?uploads{}", server.endpoint(), bucket, query))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.text().await.unwrap()
+}
+
+#[tokio::test]
+pub async fn list_multipart_uploads() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let cat = initiate_upload_id(&server, "videos", "cats/cat.mp4").await;
+    let dog = initiate_upload_id(&server, "videos", "dogs/dog.mp4").await;
+
+    let body = list_uploads(&server, "videos", "").await;
+    assert!(body.contains("<ListMultipartUploadsResult"));
+    assert_eq!(xml_text(&body, "Bucket"), Some("videos"));
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("false"));
+    assert!(body.contains(&format!(
+        "<Key>cats/cat.mp4</Key><UploadId>{}</UploadId>",
+        cat
+    )));
+    assert!(body.contains(&format!(
+        "<Key>dogs/dog.mp4</Key><UploadId>{}</UploadId>",
+        dog
+    )));
+    assert!(!body.contains("<Stale>"));
+
+    let body = list_uploads(&server, "videos", "&prefix=dogs/").await;
+    assert!(!body.contains("cats/cat.mp4"));
+    assert!(body.contains("dogs/dog.mp4"));
+
+    let body = list_uploads(&server, "videos", "&max-uploads=1").await;
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("true"));
+    assert_eq!(xml_text(&body, "NextKeyMarker"), Some("cats/cat.mp4"));
+    assert_eq!(xml_text(&body, "NextUploadIdMarker"), Some(cat.as_str()));
+    assert!(!body.contains("dogs/dog.mp4"));
+
+    let body = list_uploads(
+        &server,
+        "videos",
+        &format!(
+            "&max-uploads=1&key-marker=cats/cat.mp4&upload-id-marker={}",
+            cat
+        ),
+    )
+    .await;
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("false"));
+    assert!(!body.contains("<Key>cats/cat.mp4</Key>"));
+    assert!(body.contains("<Key>dogs/dog.mp4</Key>"));
+
+    // Completed and aborted uploads are no longer listed
+    let response = reqwest::Client::new()
+        .delete(format!(
+            "{}/api/buckets/videos/objects/cats/cat.mp4?uploadId={}",
+            server.endpoint(),
+            cat
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let body = list_uploads(&server, "videos", "").await;
+    assert!(!body.contains("cats/cat.mp4"));
+    assert!(body.contains("dogs/dog.mp4"));
+}
+
+#[tokio::test]
+pub async fn stale_multipart_uploads() {
+    let server = create_test_server_with_config(test_config().lifecycle(LifecycleConfig {
+        multipart_upload_ttl: Duration::from_millis(1),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("videos").await;
+    initiate_upload_id(&server, "videos", "cat.mp4").await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Stale uploads are listed until the background task aborts them
+    let body = list_uploads(&server, "videos", "").await;
+    assert!(body.contains("<Key>cat.mp4</Key>"));
+    assert_eq!(xml_text(&body, "Stale"), Some("true"));
+}
+
+#[tokio::test]
+pub async fn stale_multipart_uploads_are_aborted() {
+    let server = create_test_server_with_config(test_config().lifecycle(LifecycleConfig {
+        interval: Duration::from_millis(50),
+        multipart_upload_ttl: Duration::from_millis(1),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+    upload_part(
+        &server,
+        "videos",
+        "cat.mp4",
+        &upload_id,
+        1,
+        b"meow".to_vec(),
+    )
+    .await;
+
+    for _ in 0..100 {
+        if !server.data_directory().join(&upload_id).exists() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(!server.data_directory().join(&upload_id).exists());
+
+    let body = list_uploads(&server, "videos", "").await;
+    assert!(!body.contains("cat.mp4"));
+}
+
+#[tokio::test]
+pub async fn list_parts() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let upload_id = initiate_upload_id(&server, "videos", "cat.mp4").await;
+
+    for (part_number, body) in [(1, b"meow".to_vec()), (3, b"purr".to_vec())] {
+        let response =
+            upload_part(&server, "videos", "cat.mp4", &upload_id, part_number, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let url = format!(
+        "{}/videos/cat.mp4?uploadId={}",
+        server.endpoint(),
+        upload_id
+    );
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<ListPartsResult"));
+    assert_eq!(xml_text(&body, "UploadId"), Some(upload_id.as_str()));
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("false"));
+    assert_eq!(body.matches("<Part>").count(), 2);
+    assert!(!body.contains("<PartNumber>2</PartNumber>"));
+
+    for (part_number, contents) in [(1, b"meow"), (3, b"purr")] {
+        let etag = format!("\"{}\"", hex::encode(Md5::digest(contents)));
+        let part = format!("<Part><PartNumber>{}</PartNumber>", part_number);
+        let part = &body[body.find(&part).unwrap()..];
+        let part = &part[..part.find("</Part>").unwrap()];
+
+        assert_eq!(xml_text(part, "ETag"), Some(etag.as_str()));
+        assert_eq!(xml_text(part, "Size"), Some("4"));
+        assert!(xml_text(part, "LastModified").is_some());
+    }
+
+    let body = reqwest::get(format!("{}&max-parts=1", url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("true"));
+    assert_eq!(xml_text(&body, "NextPartNumberMarker"), Some("1"));
+    assert_eq!(body.matches("<Part>").count(), 1);
+
+    let body = reqwest::get(format!("{}&max-parts=1&part-number-marker=1", url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(xml_text(&body, "IsTruncated"), Some("false"));
+    assert!(body.contains("<PartNumber>3</PartNumber>"));
+
+    for url in [
+        format!(
+            "{}/videos/cat.mp4?uploadId={}",
+            server.endpoint(),
+            uuid::Uuid::new_v4()
+        ),
+        format!(
+            "{}/videos/dog.mp4?uploadId={}",
+            server.endpoint(),
+            upload_id
+        ),
+        format!("{}/videos/cat.mp4?uploadId=invalid", server.endpoint()),
+    ] {
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(
+            response
+                .text()
+                .await
+                .unwrap()
+                .contains("<Code>NoSuchUpload</Code>")
+        );
+    }
+}
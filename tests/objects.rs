@@ -0,0 +1,1278 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, TimeDelta};
+use md5::{Digest, Md5};
+use objection::{
+    config::{
+        CacheControlConfig, CachePolicy, ContentTypeFilter, ContentTypeRule, ContentTypesConfig,
+    },
+    test_helpers::{
+        TestServer, TestServerConfig, create_test_server, create_test_server_with,
+        create_test_server_with_config, test_config,
+    },
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// A 1x1 transparent PNG
+const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00, 0x01, 0x00, 0x00,
+    0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+    0x42, 0x60, 0x82,
+];
+
+#[tokio::test]
+pub async fn put_object_png() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("photos").await;
+
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/photos/objects/2024/pixel.png",
+            server.endpoint()
+        ))
+        .header("Content-Type", "image/png")
+        .body(PNG)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let object = response.json::<Value>().await.unwrap();
+    let hash = sha256::digest(PNG);
+
+    assert_eq!(object["key"], "2024/pixel.png");
+    assert_eq!(object["size"], PNG.len());
+    assert_eq!(object["content_type"], "image/png");
+    assert_eq!(object["hash"], hash);
+
+    let stored = server.stored_contents(uuid, &hash).await.unwrap();
+    assert_eq!(stored, PNG);
+}
+
+#[tokio::test]
+pub async fn put_object_replaces_existing() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("docs").await;
+
+    let first = server
+        .put_object("docs", "readme.md", Some("text/markdown"), b"# Hello")
+        .await;
+    assert!(server.storage().exists(uuid, &first).await.unwrap());
+
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/docs/objects/readme.md",
+            server.endpoint()
+        ))
+        .header("Content-Type", "text/markdown")
+        .body("# Goodbye")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let object = response.json::<Value>().await.unwrap();
+    let second = object["hash"].as_str().unwrap();
+
+    assert_ne!(first, second);
+    assert!(!server.storage().exists(uuid, &first).await.unwrap());
+    assert!(server.storage().exists(uuid, second).await.unwrap());
+}
+
+#[tokio::test]
+pub async fn put_object_deduplicated() {
+    // Stored on disk, so the number of stored files can be counted
+    let server = create_test_server_with(TestServerConfig {
+        storage_backend: None,
+        ..Default::default()
+    })
+    .await;
+    let uuid = server.create_bucket("files").await;
+    let storage_path = server.data_directory().join(uuid.to_string());
+
+    let blob = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+    let client = reqwest::Client::new();
+
+    for (key, ref_count) in [("first.bin", 1), ("second.bin", 2)] {
+        let response = client
+            .put(format!(
+                "{}/api/buckets/files/objects/{}",
+                server.endpoint(),
+                key
+            ))
+            .body(blob.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let object = response.json::<Value>().await.unwrap();
+        assert_eq!(object["ref_count"], ref_count);
+    }
+
+    let files = std::fs::read_dir(&storage_path)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_file())
+        .count();
+    assert_eq!(files, 1);
+
+    let listing = list_objects(&server, "").await;
+    assert_eq!(listing["objects"].as_array().unwrap().len(), 2);
+
+    // The contents are named after the first key they were stored for, and
+    // only removed once neither object refers to them
+    for key in ["first.bin", "second.bin"] {
+        assert!(storage_path.join("first.bin").exists());
+
+        let response = client
+            .delete(format!("{}/files/{}", server.endpoint(), key))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+    assert!(!storage_path.join("first.bin").exists());
+}
+
+#[tokio::test]
+pub async fn put_object_missing_bucket() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/missing/objects/file.txt",
+            server.endpoint()
+        ))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn put_object_content_type_not_allowed() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        filter: Some(ContentTypeFilter::Whitelist(BTreeSet::from([
+            "image/*".parse().unwrap(),
+        ]))),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos/objects/file", server.endpoint());
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "image/png")
+        .body(PNG)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "image/jpeg")
+        .body("not really a jpeg")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // JSON API requests aren't object uploads, so aren't filtered
+    let response = client
+        .post(format!("{}/api/buckets", server.endpoint()))
+        .json(&json!({ "name": "documents" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "text/plain")
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "UNSUPPORTED_CONTENT_TYPE");
+}
+
+#[tokio::test]
+pub async fn put_object_tags_content_type_not_filtered() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        filter: Some(ContentTypeFilter::Whitelist(BTreeSet::from([
+            "image/*".parse().unwrap(),
+        ]))),
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "a.png", Some("image/png"), PNG)
+        .await;
+
+    // Tags aren't object contents, so their content type isn't filtered
+    let client = reqwest::Client::new();
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/a.png?tagging",
+            server.endpoint()
+        ))
+        .json(&json!({"tags": {"species": "cat"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!("{}/photos/a.png?tagging", server.endpoint()))
+        .header("Content-Type", "application/xml")
+        .body(
+            "<Tagging><TagSet><Tag><Key>color</Key><Value>orange</Value></Tag></TagSet></Tagging>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(format!(
+            "{}/api/buckets/photos/objects/a.png?tagging",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({"tags": {"color": "orange"}})
+    );
+}
+
+#[tokio::test]
+pub async fn put_object_content_type_rules() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        filter: Some(ContentTypeFilter::Blacklist(BTreeSet::from([
+            "image/png".parse().unwrap(),
+        ]))),
+        rules: vec![
+            ContentTypeRule {
+                key_pattern: "uploads/images/**".parse().unwrap(),
+                content_type: "image/*".parse().unwrap(),
+            },
+            ContentTypeRule {
+                key_pattern: "docs/*.txt".parse().unwrap(),
+                content_type: "text/plain".parse().unwrap(),
+            },
+        ],
+    }))
+    .await;
+    server.create_bucket("files").await;
+
+    let put = |path: &'static str, content_type: &'static str| {
+        let url = format!("{}/{}", server.endpoint(), path);
+        async move {
+            reqwest::Client::new()
+                .put(url)
+                .header("Content-Type", content_type)
+                .body("contents")
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    // A matching rule takes the place of the global filter
+    assert_eq!(
+        put(
+            "api/buckets/files/objects/uploads/images/2024/a.png",
+            "image/png"
+        )
+        .await
+        .status(),
+        StatusCode::OK
+    );
+
+    let response = put(
+        "api/buckets/files/objects/uploads/images/a.txt",
+        "text/plain",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "UNSUPPORTED_CONTENT_TYPE");
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .contains("`uploads/images/**`")
+    );
+
+    // Keys are normalized before they are matched
+    let response = put("files//uploads/images/a.txt", "text/plain").await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>UnsupportedMediaType</Code>")
+    );
+
+    // `*` doesn't match across path segments
+    assert_eq!(
+        put("files/docs/readme.txt", "text/plain").await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        put("files/docs/readme.txt", "text/html").await.status(),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    assert_eq!(
+        put("files/docs/2024/readme.txt", "text/html")
+            .await
+            .status(),
+        StatusCode::OK
+    );
+
+    // Keys without a matching rule fall back to the global filter
+    assert_eq!(
+        put("files/uploads/a.png", "image/png").await.status(),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+
+    // Copies are checked against the rules for their destination, with the
+    // content type they are copied with
+    let copy = |key: &'static str, content_type: Option<&'static str>| {
+        let url = format!("{}/files/{}", server.endpoint(), key);
+        async move {
+            let request = reqwest::Client::new()
+                .put(url)
+                .header("x-amz-copy-source", "/files/docs/readme.txt");
+            let request = match content_type {
+                Some(content_type) => request
+                    .header("x-amz-metadata-directive", "REPLACE")
+                    .header("Content-Type", content_type),
+                None => request,
+            };
+
+            request.send().await.unwrap().status()
+        }
+    };
+    assert_eq!(
+        copy("uploads/images/readme.txt", None).await,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    assert_eq!(
+        copy("uploads/images/readme.png", Some("image/png")).await,
+        StatusCode::OK
+    );
+    assert_eq!(copy("docs/copy.txt", None).await, StatusCode::OK);
+    assert_eq!(
+        copy("docs/copy.txt", Some("text/html")).await,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+}
+
+#[tokio::test]
+pub async fn copy_object_content_type_rules() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        rules: vec![ContentTypeRule {
+            key_pattern: "docs/**".parse().unwrap(),
+            content_type: "text/plain".parse().unwrap(),
+        }],
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("files").await;
+    server
+        .put_object("files", "pixel.png", Some("image/png"), PNG)
+        .await;
+
+    let copy = |destination: &'static str| {
+        reqwest::Client::new()
+            .request(
+                reqwest::Method::from_bytes(b"COPY").unwrap(),
+                format!("{}/api/buckets/files/objects/pixel.png", server.endpoint()),
+            )
+            .header("Destination", destination)
+            .send()
+    };
+
+    // The destination's rules apply to the copied content type
+    let response = copy("files/docs/pixel.png").await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "UNSUPPORTED_CONTENT_TYPE"
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/files/objects/docs/pixel.png",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = copy("files/images/pixel.png").await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+/// Sends a WebDAV style `MOVE` for an object through the API
+async fn move_object(
+    server: &TestServer,
+    bucket: &str,
+    key: &str,
+    destination: &str,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .request(
+            reqwest::Method::from_bytes(b"MOVE").unwrap(),
+            format!(
+                "{}/api/buckets/{}/objects/{}",
+                server.endpoint(),
+                bucket,
+                key
+            ),
+        )
+        .header("Destination", destination)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn move_object_between_buckets() {
+    let server = create_test_server().await;
+    server.create_bucket("inbox").await;
+    server.create_bucket("archive").await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .patch(format!(
+            "{}/api/buckets/inbox/versioning",
+            server.endpoint()
+        ))
+        .json(&json!({ "status": "enabled" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!("{}/inbox/report.txt", server.endpoint()))
+        .header("Content-Type", "text/plain")
+        .body("quarterly")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let version_id = response.headers()["x-amz-version-id"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let response = move_object(&server, "inbox", "report.txt", "archive/report.txt").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        response.headers()["location"],
+        "/api/buckets/archive/objects/report.txt"
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/archive/objects/report.txt",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.text().await.unwrap(), "quarterly");
+
+    // The versioned source is left with a delete marker over the moved version
+    let response = reqwest::get(format!("{}/inbox/report.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let listing = reqwest::get(format!("{}/inbox?versions", server.endpoint()))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(listing.matches("<Version>").count(), 1);
+    assert_eq!(listing.matches("<DeleteMarker>").count(), 1);
+
+    let response = reqwest::get(format!(
+        "{}/inbox/report.txt?versionId={}",
+        server.endpoint(),
+        version_id
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "quarterly");
+
+    let response = move_object(&server, "inbox", "report.txt", "archive/report.txt").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Objects keep their key and have to change bucket
+    let response = move_object(&server, "archive", "report.txt", "inbox/renamed.txt").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "INVALID_DESTINATION"
+    );
+
+    let response = move_object(&server, "archive", "report.txt", "archive/report.txt").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+pub async fn move_object_content_type_rules() {
+    let server = create_test_server_with_config(test_config().content_types(ContentTypesConfig {
+        rules: vec![ContentTypeRule {
+            key_pattern: "docs/**".parse().unwrap(),
+            content_type: "text/plain".parse().unwrap(),
+        }],
+        ..Default::default()
+    }))
+    .await;
+    server.create_bucket("files").await;
+    server.create_bucket("archive").await;
+
+    // Stored before the rules applied to it
+    server
+        .put_object("files", "docs/pixel.png", Some("image/png"), PNG)
+        .await;
+
+    let response = move_object(&server, "files", "docs/pixel.png", "archive/docs/pixel.png").await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "UNSUPPORTED_CONTENT_TYPE"
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/files/objects/docs/pixel.png",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn get_object_roundtrip() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let url = format!(
+        "{}/api/buckets/photos/objects/2024/pixel.png",
+        server.endpoint()
+    );
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "image/png")
+        .body(PNG)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+}
+
+#[tokio::test]
+pub async fn get_missing_object() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/photos/objects/missing.png",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = response.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "OBJECT_NOT_FOUND");
+}
+
+#[tokio::test]
+pub async fn get_expired_object() {
+    let server = create_test_server().await;
+    server.create_bucket("tmp").await;
+    server.put_object("tmp", "old.txt", None, b"stale").await;
+    server.expire_object("tmp", "old.txt").await;
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/tmp/objects/old.txt",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::GONE);
+}
+
+async fn list_objects(server: &TestServer, query: &str) -> Value {
+    let response = reqwest::get(format!(
+        "{}/api/buckets/files/objects?{}",
+        server.endpoint(),
+        query
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json::<Value>().await.unwrap()
+}
+
+fn keys(listing: &Value) -> Vec<&str> {
+    listing["objects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| o["key"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+pub async fn list_objects_with_delimiter() {
+    let server = create_test_server().await;
+    server.create_bucket("files").await;
+    for key in [
+        "photos/2024/a.jpg",
+        "photos/2024/b.jpg",
+        "docs/readme.md",
+        "index.html",
+    ] {
+        server.put_object("files", key, None, key.as_bytes()).await;
+    }
+
+    let listing = list_objects(&server, "delimiter=/").await;
+    assert_eq!(listing["common_prefixes"], json!(["docs/", "photos/"]));
+    assert_eq!(keys(&listing), ["index.html"]);
+
+    let listing = list_objects(&server, "prefix=photos/&delimiter=/").await;
+    assert_eq!(listing["common_prefixes"], json!(["photos/2024/"]));
+    assert!(keys(&listing).is_empty());
+
+    let listing = list_objects(&server, "prefix=photos/").await;
+    assert_eq!(keys(&listing), ["photos/2024/a.jpg", "photos/2024/b.jpg"]);
+}
+
+#[tokio::test]
+pub async fn list_objects_paginated() {
+    let server = create_test_server().await;
+    server.create_bucket("files").await;
+    for key in ["a", "b", "c", "d", "e"] {
+        server.put_object("files", key, None, key.as_bytes()).await;
+    }
+
+    let listing = list_objects(&server, "limit=2").await;
+    assert_eq!(keys(&listing), ["a", "b"]);
+    assert_eq!(listing["is_truncated"], true);
+
+    let listing = list_objects(&server, "limit=2&page=3").await;
+    assert_eq!(keys(&listing), ["e"]);
+    assert_eq!(listing["is_truncated"], false);
+}
+
+#[tokio::test]
+pub async fn list_objects_page_out_of_range() {
+    let server = create_test_server().await;
+    server.create_bucket("files").await;
+    server.put_object("files", "a", None, b"a").await;
+
+    for path in ["objects", "access-logs"] {
+        let response = reqwest::get(format!(
+            "{}/api/buckets/files/{}?page={}",
+            server.endpoint(),
+            path,
+            u64::MAX
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        assert_eq!(
+            response.json::<Value>().await.unwrap()["error"],
+            "INVALID_PAGE"
+        );
+    }
+
+    // Pages past the end are empty rather than an error
+    let listing = list_objects(&server, "page=1000000").await;
+    assert!(keys(&listing).is_empty());
+}
+
+#[tokio::test]
+pub async fn object_keys_normalized() {
+    let server = create_test_server().await;
+    server.create_bucket("files").await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/files/objects//photos//cat.jpg",
+            server.endpoint()
+        ))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["key"],
+        "photos/cat.jpg"
+    );
+
+    // Keys with and without a leading slash are the same object
+    for path in ["photos/cat.jpg", "/photos/cat.jpg"] {
+        let response = reqwest::get(format!(
+            "{}/api/buckets/files/objects/{}",
+            server.endpoint(),
+            path
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "meow");
+    }
+
+    let listing = list_objects(&server, "").await;
+    assert_eq!(keys(&listing), ["photos/cat.jpg"]);
+
+    // Encoded slashes stop the client from resolving the `..` itself
+    let response = client
+        .put(format!(
+            "{}/api/buckets/files/objects/%2F%2Fphotos%2F..%2Fimages%2Fcat.jpg",
+            server.endpoint()
+        ))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "INVALID_KEY"
+    );
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/files/objects/photos/dog.jpg",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn delete_objects() {
+    let server = create_test_server().await;
+    server.create_bucket("files").await;
+    for key in ["a", "b", "c", "d"] {
+        server.put_object("files", key, None, key.as_bytes()).await;
+    }
+
+    let url = format!("{}/api/buckets/files/objects", server.endpoint());
+
+    let response = reqwest::Client::new()
+        .delete(&url)
+        .json(&json!({ "keys": ["a", "b", "c"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let deleted = response.json::<Value>().await.unwrap();
+    assert_eq!(deleted["deleted"], json!(["a", "b", "c"]));
+
+    let listing = list_objects(&server, "").await;
+    assert_eq!(keys(&listing), ["d"]);
+
+    let bucket = reqwest::get(format!("{}/api/buckets/files", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 1);
+
+    let response = reqwest::Client::new()
+        .delete(&url)
+        .json(&json!({ "keys": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+pub async fn head_object_metadata() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "pixel.png", Some("image/png"), PNG)
+        .await;
+
+    let response = reqwest::Client::new()
+        .head(format!(
+            "{}/api/buckets/photos/objects/pixel.png",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-length"],
+        PNG.len().to_string().as_str()
+    );
+    assert_eq!(
+        response.headers()["etag"],
+        format!("\"{}\"", hex::encode(Md5::digest(PNG))).as_str()
+    );
+    assert!(response.headers().contains_key("last-modified"));
+}
+
+#[tokio::test]
+pub async fn head_missing_and_expired_object() {
+    let server = create_test_server().await;
+    server.create_bucket("tmp").await;
+    server.put_object("tmp", "old.txt", None, b"stale").await;
+    server.expire_object("tmp", "old.txt").await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .head(format!(
+            "{}/api/buckets/tmp/objects/missing.txt",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .head(format!(
+            "{}/api/buckets/tmp/objects/old.txt",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+pub async fn get_object_cache_control() {
+    let server = create_test_server_with_config(test_config().cache_control(CacheControlConfig {
+        default_policy: CachePolicy::NoCache,
+        default_max_age: 60,
+    }))
+    .await;
+    server.create_bucket("assets").await;
+    server.put_object("assets", "app.js", None, b"main()").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/assets/objects/app.js", server.endpoint());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.headers()["cache-control"], "no-cache");
+
+    // The bucket's default policy overrides the global default
+    let response = client
+        .patch(format!("{}/api/buckets/assets", server.endpoint()))
+        .json(&json!({ "default_cache_policy": "cache" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.head(&url).send().await.unwrap();
+    assert_eq!(response.headers()["cache-control"], "max-age=60");
+    let response = client
+        .patch(format!("{}/api/buckets/assets", server.endpoint()))
+        .json(&json!({ "default_cache_policy": "immutable" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["settings"]["default_cache_policy"], "immutable");
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(
+        response.headers()["cache-control"],
+        "immutable, max-age=31536000"
+    );
+}
+
+#[tokio::test]
+pub async fn get_object_conditional() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "pixel.png", Some("image/png"), PNG)
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos/objects/pixel.png", server.endpoint());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    let response = client
+        .get(&url)
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    let response = client
+        .get(&url)
+        .header("if-none-match", "\"something-else\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(&url)
+        .header("if-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(&url)
+        .header("if-match", "\"something-else\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+pub async fn get_object_conditional_dates() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "pixel.png", Some("image/png"), PNG)
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/photos/objects/pixel.png", server.endpoint());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let last_modified = response.headers()["last-modified"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let earlier = (DateTime::parse_from_rfc2822(&last_modified).unwrap() - TimeDelta::days(1))
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+
+    let get = |name: &'static str, value: &str| client.get(&url).header(name, value).send();
+
+    let response = get("if-modified-since", &last_modified).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["last-modified"], last_modified.as_str());
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    let response = get("if-modified-since", &earlier).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    // Invalid dates are ignored
+    let response = get("if-modified-since", "yesterday").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get("if-unmodified-since", &last_modified).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get("if-unmodified-since", &earlier).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    // `If-None-Match` takes precedence over `If-Modified-Since`
+    let response = client
+        .get(&url)
+        .header("if-none-match", "\"something-else\"")
+        .header("if-modified-since", &last_modified)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .head(format!("{}/photos/pixel.png", server.endpoint()))
+        .header("if-modified-since", &last_modified)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+pub async fn get_object_ranges() {
+    let server = create_test_server().await;
+    server.create_bucket("data").await;
+
+    let body = (0..1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    server.put_object("data", "blob.bin", None, &body).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets/data/objects/blob.bin", server.endpoint());
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["accept-ranges"], "bytes");
+
+    let response = client
+        .get(&url)
+        .header("range", "bytes=0-15")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()["content-range"], "bytes 0-15/1024");
+    assert_eq!(response.headers()["content-length"], "16");
+    assert_eq!(response.bytes().await.unwrap(), &body[..16]);
+
+    let response = client
+        .get(&url)
+        .header("range", "bytes=-16")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()["content-range"], "bytes 1008-1023/1024");
+    assert_eq!(response.bytes().await.unwrap(), &body[1008..]);
+
+    let response = client
+        .get(&url)
+        .header("range", "bytes=1000-")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.bytes().await.unwrap(), &body[1000..]);
+
+    for (range, status) in [
+        ("bytes=abc", StatusCode::BAD_REQUEST),
+        ("items=0-15", StatusCode::BAD_REQUEST),
+        ("bytes=2048-4096", StatusCode::RANGE_NOT_SATISFIABLE),
+        ("bytes=0-100,200-300", StatusCode::NOT_IMPLEMENTED),
+    ] {
+        let response = client
+            .get(&url)
+            .header("range", range)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "range `{}`", range);
+    }
+}
+
+#[tokio::test]
+pub async fn object_tags() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let url = format!("{}/api/buckets/photos/objects/cat.png", server.endpoint());
+    let tags_url = format!("{}?tagging", url);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(&url)
+        .header("Content-Type", "image/png")
+        .body(PNG)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&tags_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({"tags": {}}));
+
+    let response = client
+        .put(&tags_url)
+        .json(&json!({"tags": {"species": "cat", "color": "orange"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&tags_url).send().await.unwrap();
+    assert_eq!(
+        response.json::<Value>().await.unwrap(),
+        json!({"tags": {"species": "cat", "color": "orange"}})
+    );
+
+    // Tagging doesn't touch the object's contents
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    let response = client.delete(&tags_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&tags_url).send().await.unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({"tags": {}}));
+
+    let too_many = (0..11)
+        .map(|i| (format!("key{}", i), json!("value")))
+        .collect::<serde_json::Map<_, _>>();
+    for tags in [
+        json!(too_many),
+        json!({"a".repeat(129): "value"}),
+        json!({"key": "a".repeat(257)}),
+        json!({"": "value"}),
+        json!({"a=b": "value"}),
+    ] {
+        let response = client
+            .put(&tags_url)
+            .json(&json!({ "tags": tags }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.json::<Value>().await.unwrap()["error"],
+            "INVALID_TAGS"
+        );
+    }
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/dog.png?tagging",
+            server.endpoint()
+        ))
+        .json(&json!({"tags": {"species": "dog"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn copy_object_method() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server.create_bucket("archive").await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/2024/pixel.png",
+            server.endpoint()
+        ))
+        .header("Content-Type", "image/png")
+        .body(PNG)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let copy = |destination: &'static str| {
+        client
+            .request(
+                reqwest::Method::from_bytes(b"COPY").unwrap(),
+                format!(
+                    "{}/api/buckets/photos/objects/2024/pixel.png",
+                    server.endpoint()
+                ),
+            )
+            .header("Destination", destination)
+            .send()
+    };
+
+    let response = copy("/archive/old%20photos/pixel.png").await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        response.headers()["location"],
+        "/api/buckets/archive/objects/old%20photos/pixel.png"
+    );
+    let object = response.json::<Value>().await.unwrap();
+    assert_eq!(object["key"], "old photos/pixel.png");
+    assert_eq!(object["content_type"], "image/png");
+
+    let response = client
+        .get(format!(
+            "{}/api/buckets/archive/objects/old%20photos/pixel.png",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    let response = copy("archive").await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>().await.unwrap()["error"],
+        "INVALID_DESTINATION"
+    );
+
+    let response = copy("photos/2024/pixel.png").await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = copy("missing/pixel.png").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Other unsupported methods are still rejected
+    let response = client
+        .request(
+            reqwest::Method::PATCH,
+            format!(
+                "{}/api/buckets/photos/objects/2024/pixel.png",
+                server.endpoint()
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert!(
+        response.headers()["allow"]
+            .to_str()
+            .unwrap()
+            .contains("COPY")
+    );
+}
@@ -0,0 +1,241 @@
+use objection::{
+    config::AccessControlConfig,
+    test_helpers::{TestServer, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+async fn create_authenticated_server() -> TestServer {
+    create_test_server_with_config(test_config().access_control(AccessControlConfig::default()))
+        .await
+}
+
+async fn presign(server: &TestServer, bucket: &str, body: Value) -> reqwest::Response {
+    let token = server.create_access_token().await;
+
+    reqwest::Client::new()
+        .post(format!(
+            "{}/api/buckets/{}/presign",
+            server.endpoint(),
+            bucket
+        ))
+        .bearer_auth(token.bearer_token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn presigned_url(server: &TestServer, bucket: &str, body: Value) -> String {
+    let response = presign(server, bucket, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.json::<Value>().await.unwrap();
+    body["url"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+pub async fn presigned_get() {
+    let server = create_authenticated_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cats/tabby.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let url = presigned_url(
+        &server,
+        "photos",
+        json!({ "key": "cats/tabby.txt", "method": "GET" }),
+    )
+    .await;
+    assert!(url.starts_with(&format!("{}/photos/cats/tabby.txt?", server.endpoint())));
+    assert!(url.contains("X-Amz-Expires=3600"));
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), b"meow".as_slice());
+
+    let response = client.head(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The signature covers the method and the path
+    let response = client.put(&url).body("woof").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>SignatureDoesNotMatch</Code>")
+    );
+
+    let response = client
+        .get(url.replace("tabby", "ginger"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+pub async fn presigned_put() {
+    let server = create_authenticated_server().await;
+    server.create_bucket("photos").await;
+
+    let url = presigned_url(
+        &server,
+        "photos",
+        json!({ "key": "cat.txt", "method": "PUT", "expires_in_secs": 60 }),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+
+    let response = client.put(&url).body("meow").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = server.create_access_token().await;
+    let response = client
+        .get(format!(
+            "{}/api/buckets/photos/objects/cat.txt",
+            server.endpoint()
+        ))
+        .bearer_auth(token.bearer_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), b"meow".as_slice());
+}
+
+#[tokio::test]
+pub async fn presigned_expired() {
+    let server = create_authenticated_server().await;
+    server.create_bucket("photos").await;
+    server.put_object("photos", "cat.txt", None, b"meow").await;
+
+    let url = presigned_url(
+        &server,
+        "photos",
+        json!({ "key": "cat.txt", "method": "GET", "expires_in_secs": 1 }),
+    )
+    .await;
+
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>RequestExpired</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn presign_invalid() {
+    let server = create_authenticated_server().await;
+    server.create_bucket("photos").await;
+
+    let response = presign(
+        &server,
+        "photos",
+        json!({ "key": "cat.txt", "method": "GET", "expires_in_secs": 604_801 }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = presign(
+        &server,
+        "photos",
+        json!({ "key": "cat.txt", "method": "DELETE" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = presign(
+        &server,
+        "missing",
+        json!({ "key": "cat.txt", "method": "GET" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn presigned_put_only_writes_contents() {
+    let server = create_authenticated_server().await;
+    server.create_bucket("photos").await;
+    server.create_bucket("private").await;
+    server
+        .put_object("private", "secret.txt", None, b"hunter2")
+        .await;
+
+    let url = presigned_url(
+        &server,
+        "photos",
+        json!({ "key": "cat.txt", "method": "PUT" }),
+    )
+    .await;
+    let client = reqwest::Client::new();
+
+    // Copies would let the URL read any object into the signed key
+    let response = client
+        .put(&url)
+        .header("x-amz-copy-source", "/private/secret.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>AccessDenied</Code>")
+    );
+
+    for sub_resource in ["tagging", "partNumber=1&uploadId=x", "versionId=x"] {
+        let response = client
+            .put(format!("{}&{}", url, sub_resource))
+            .body("<Tagging><TagSet></TagSet></Tagging>")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", sub_resource);
+    }
+
+    // The expiry is covered by the signature
+    let response = client
+        .put(url.replace("X-Amz-Expires=3600", "X-Amz-Expires=7200"))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>SignatureDoesNotMatch</Code>")
+    );
+
+    let response = client.put(&url).body("meow").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn presigned_secret_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = create_authenticated_server().await;
+
+    let metadata = std::fs::metadata(server.data_directory().join("presigned_secret")).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+}
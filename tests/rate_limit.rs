@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use objection::{
+    config::RateLimitingConfig,
+    test_helpers::{create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+const BURST_SIZE: u32 = 5;
+
+#[tokio::test]
+pub async fn burst_exceeded() {
+    let server = create_test_server_with_config(test_config().rate_limiting(RateLimitingConfig {
+        enable_rate_limiting: true,
+        default_period: Duration::from_secs(60),
+        default_burst_size: BURST_SIZE,
+    }))
+    .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets", server.endpoint());
+
+    for _ in 0..BURST_SIZE {
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let retry_after = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse::<u64>()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+}
+
+#[tokio::test]
+pub async fn tokens_replenished() {
+    let server = create_test_server_with_config(test_config().rate_limiting(RateLimitingConfig {
+        enable_rate_limiting: true,
+        default_period: Duration::from_millis(100),
+        default_burst_size: 1,
+    }))
+    .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/buckets", server.endpoint());
+
+    assert_eq!(
+        client.get(&url).send().await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        client.get(&url).send().await.unwrap().status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    assert_eq!(
+        client.get(&url).send().await.unwrap().status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+pub async fn bucket_overrides_rate_limit() {
+    let server = create_test_server_with_config(test_config().rate_limiting(RateLimitingConfig {
+        enable_rate_limiting: true,
+        default_period: Duration::from_secs(60),
+        default_burst_size: 100,
+    }))
+    .await;
+    server.create_bucket("strict").await;
+    server.create_bucket("relaxed").await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .patch(format!("{}/api/buckets/strict", server.endpoint()))
+        .json(&json!({ "rate_limit_burst_size": 1 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["settings"]["rate_limit_burst_size"], 1);
+
+    let strict = format!("{}/api/buckets/strict/objects", server.endpoint());
+    assert_eq!(
+        client.get(&strict).send().await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        client.get(&strict).send().await.unwrap().status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Other buckets keep using the global limit
+    let relaxed = format!("{}/api/buckets/relaxed/objects", server.endpoint());
+    for _ in 0..3 {
+        assert_eq!(
+            client.get(&relaxed).send().await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+
+    let response = client
+        .patch(format!("{}/api/buckets/relaxed", server.endpoint()))
+        .json(&json!({ "rate_limit_burst_size": 0 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,45 @@
+use objection::test_helpers::create_test_server;
+use reqwest::StatusCode;
+use uuid::Uuid;
+
+#[tokio::test]
+pub async fn request_id_generated() {
+    let server = create_test_server().await;
+
+    let first = reqwest::get(format!("{}/health", server.endpoint()))
+        .await
+        .unwrap();
+    let second = reqwest::get(format!("{}/api/buckets/missing", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(second.status(), StatusCode::NOT_FOUND);
+
+    let first = first.headers()["x-request-id"].to_str().unwrap();
+    let second = second.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(first).is_ok());
+    assert!(Uuid::parse_str(second).is_ok());
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+pub async fn request_id_echoed() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/health", server.endpoint()))
+        .header("x-request-id", "trace-1234")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "trace-1234");
+
+    // Overly long IDs are replaced rather than echoed
+    let response = reqwest::Client::new()
+        .get(format!("{}/health", server.endpoint()))
+        .header("x-request-id", "a".repeat(129))
+        .send()
+        .await
+        .unwrap();
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(Uuid::parse_str(request_id).is_ok());
+}
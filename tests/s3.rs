@@ -0,0 +1,1257 @@
+use base64::{Engine, prelude::BASE64_STANDARD};
+use md5::{Digest, Md5};
+use objection::test_helpers::{TestServer, create_test_server};
+use reqwest::StatusCode;
+use s3::{BucketConfiguration, creds::Credentials, error::S3Error};
+
+fn region(server: &TestServer) -> s3::Region {
+    s3::Region::Custom {
+        region: server.effective_region(),
+        endpoint: server.endpoint(),
+    }
+}
+
+/// Client for an existing bucket, using path-style requests
+fn bucket(server: &TestServer, name: &str) -> Box<s3::Bucket> {
+    s3::Bucket::new(name, region(server), Credentials::anonymous().unwrap())
+        .unwrap()
+        .with_path_style()
+}
+
+async fn create_bucket(
+    server: &TestServer,
+    name: &str,
+) -> Result<s3::bucket_ops::CreateBucketResponse, S3Error> {
+    s3::Bucket::create_with_path_style(
+        name,
+        region(server),
+        Credentials::anonymous().unwrap(),
+        BucketConfiguration::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+pub async fn create_bucket_s3() {
+    let server = create_test_server().await;
+
+    let response = create_bucket(&server, "photos").await.unwrap();
+    assert_eq!(response.response_code, 200);
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(buckets.bucket_names().collect::<Vec<_>>(), ["photos"]);
+
+    match create_bucket(&server, "photos").await {
+        Err(S3Error::HttpFailWithBody(409, body)) => {
+            assert!(body.contains("<Code>BucketAlreadyOwnedByYou</Code>"))
+        }
+        Err(e) => panic!("expected a 409 response, got {:?}", e),
+        Ok(response) => panic!("expected a 409 response, got {}", response.response_code),
+    }
+}
+
+#[tokio::test]
+pub async fn create_bucket_s3_location() {
+    let server = create_test_server().await;
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/photos", server.endpoint()))
+        .body(
+            "<CreateBucketConfiguration>\
+                <LocationConstraint>eu-west-1</LocationConstraint>\
+            </CreateBucketConfiguration>",
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["location"], "/photos");
+}
+
+#[tokio::test]
+pub async fn create_bucket_s3_invalid() {
+    let server = create_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!("{}/Photos", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>InvalidBucketName</Code>")
+    );
+
+    let response = client
+        .put(format!("{}/photos", server.endpoint()))
+        .body("<CreateBucketConfiguration>")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>MalformedXML</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn delete_bucket_s3() {
+    let server = create_test_server().await;
+    create_bucket(&server, "photos").await.unwrap();
+    create_bucket(&server, "videos").await.unwrap();
+
+    bucket(&server, "videos")
+        .put_object("/intro.mp4", b"video")
+        .await
+        .unwrap();
+
+    assert_eq!(bucket(&server, "photos").delete().await.unwrap(), 204);
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(buckets.bucket_names().collect::<Vec<_>>(), ["videos"]);
+
+    match bucket(&server, "photos").delete().await {
+        Err(S3Error::HttpFailWithBody(404, body)) => {
+            assert!(body.contains("<Code>NoSuchBucket</Code>"))
+        }
+        other => panic!("expected a 404 response, got {:?}", other),
+    }
+
+    match bucket(&server, "videos").delete().await {
+        Err(S3Error::HttpFailWithBody(409, body)) => {
+            assert!(body.contains("<Code>BucketNotEmpty</Code>"))
+        }
+        other => panic!("expected a 409 response, got {:?}", other),
+    }
+
+    // Buckets can be deleted once their objects have been removed
+    bucket(&server, "videos")
+        .delete_object("/intro.mp4")
+        .await
+        .unwrap();
+    assert_eq!(bucket(&server, "videos").delete().await.unwrap(), 204);
+
+    let buckets = s3::Bucket::list_buckets(region(&server), Credentials::anonymous().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(buckets.bucket_names().count(), 0);
+
+    // The name can be reused once the bucket is gone
+    create_bucket(&server, "photos").await.unwrap();
+}
+
+/// Lists every page of a bucket with `max-keys=2`, returning the keys of each
+/// page
+async fn list_pages(bucket: &s3::Bucket) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut token = None;
+
+    loop {
+        let (page, status) = bucket
+            .list_page(String::new(), None, token, None, Some(2))
+            .await
+            .unwrap();
+        assert_eq!(status, 200);
+
+        pages.push(page.contents.into_iter().map(|object| object.key).collect());
+
+        match page.next_continuation_token {
+            Some(next) if page.is_truncated => token = Some(next),
+            _ => break,
+        }
+    }
+
+    pages
+}
+
+#[tokio::test]
+pub async fn list_objects_v2_paginated() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    for key in ["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"] {
+        server.put_object("photos", key, None, b"meow").await;
+    }
+
+    let pages = list_pages(&bucket(&server, "photos")).await;
+    assert_eq!(
+        pages,
+        [
+            vec!["a.jpg", "b.jpg"],
+            vec!["c.jpg", "d.jpg"],
+            vec!["e.jpg"]
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn list_objects_v1_paginated() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    for key in ["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"] {
+        server.put_object("photos", key, None, b"meow").await;
+    }
+
+    let pages = list_pages(&bucket(&server, "photos").with_listobjects_v1()).await;
+    assert_eq!(
+        pages,
+        [
+            vec!["a.jpg", "b.jpg"],
+            vec!["c.jpg", "d.jpg"],
+            vec!["e.jpg"]
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn list_objects_v2_delimiter() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    for key in ["2024/a.jpg", "2024/b.jpg", "2025/c.jpg", "cover.jpg"] {
+        server.put_object("photos", key, None, b"meow").await;
+    }
+
+    let bucket = bucket(&server, "photos");
+
+    let (page, _) = bucket
+        .list_page(String::new(), Some("/".into()), None, None, Some(1))
+        .await
+        .unwrap();
+    let prefixes = page.common_prefixes.unwrap_or_default();
+    assert!(page.contents.is_empty());
+    assert_eq!(prefixes.len(), 1);
+    assert_eq!(prefixes[0].prefix, "2024/");
+
+    // The next page starts after everything under `2024/`
+    let (page, _) = bucket
+        .list_page(
+            String::new(),
+            Some("/".into()),
+            page.next_continuation_token,
+            None,
+            Some(5),
+        )
+        .await
+        .unwrap();
+    let prefixes = page.common_prefixes.unwrap_or_default();
+    assert_eq!(prefixes.len(), 1);
+    assert_eq!(prefixes[0].prefix, "2025/");
+    assert_eq!(page.contents.len(), 1);
+    assert_eq!(page.contents[0].key, "cover.jpg");
+    assert!(!page.is_truncated);
+}
+
+#[tokio::test]
+pub async fn list_objects_invalid_continuation_token() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let response = reqwest::get(format!(
+        "{}/photos?list-type=2&continuation-token=bm9wZQ==",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = reqwest::get(format!("{}/missing?list-type=2", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>NoSuchBucket</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn put_object_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let response = bucket(&server, "photos")
+        .put_object_with_content_type("cats/tabby.txt", b"meow", "text/plain")
+        .await
+        .unwrap();
+
+    let etag = format!("\"{}\"", hex::encode(Md5::digest(b"meow")));
+    assert_eq!(response.status_code(), 200);
+    assert_eq!(response.headers()["etag"], etag);
+
+    let response = reqwest::get(format!(
+        "{}/api/buckets/photos/objects/cats/tabby.txt",
+        server.endpoint()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    assert_eq!(response.bytes().await.unwrap(), b"meow".as_slice());
+}
+
+#[tokio::test]
+pub async fn put_object_s3_digests() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+
+    let response = client
+        .put(&url)
+        .header("content-md5", BASE64_STANDARD.encode(Md5::digest(b"meow")))
+        .header("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    for (name, value, code) in [
+        (
+            "content-md5",
+            BASE64_STANDARD.encode(Md5::digest(b"woof")),
+            "BadDigest",
+        ),
+        ("content-md5", "not-base64".to_owned(), "InvalidDigest"),
+        (
+            "x-amz-content-sha256",
+            sha256::digest(b"woof"),
+            "XAmzContentSHA256Mismatch",
+        ),
+    ] {
+        let response = client
+            .put(&url)
+            .header(name, value)
+            .body("meow")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(
+            response
+                .text()
+                .await
+                .unwrap()
+                .contains(&format!("<Code>{}</Code>", code))
+        );
+    }
+
+    let response = client
+        .put(format!("{}/missing/cat.txt", server.endpoint()))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn put_object_s3_checksums() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+    let sha256 = BASE64_STANDARD.encode(hex::decode(sha256::digest(b"meow")).unwrap());
+
+    let response = client
+        .put(&url)
+        .header("x-amz-checksum-sha256", &sha256)
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for method in [reqwest::Method::GET, reqwest::Method::HEAD] {
+        let response = client.request(method, &url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-amz-checksum-sha256"], sha256);
+    }
+
+    let crc32 = BASE64_STANDARD.encode(crc32fast::hash(b"meow").to_be_bytes());
+    let response = client
+        .put(&url)
+        .header("x-amz-checksum-crc32", &crc32)
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.head(&url).send().await.unwrap();
+    assert_eq!(response.headers()["x-amz-checksum-crc32"], crc32);
+    assert!(response.headers().get("x-amz-checksum-sha256").is_none());
+
+    let response = client
+        .put(&url)
+        .header(
+            "x-amz-checksum-sha256",
+            BASE64_STANDARD.encode(hex::decode(sha256::digest(b"woof")).unwrap()),
+        )
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>BadDigest</Code>")
+    );
+
+    // The object uploaded with a matching checksum is kept
+    let response = client.head(&url).send().await.unwrap();
+    assert_eq!(response.headers()["x-amz-checksum-crc32"], crc32);
+
+    let response = client
+        .put(format!(
+            "{}/api/buckets/photos/objects/cat.txt",
+            server.endpoint()
+        ))
+        .header("x-amz-checksum-sha256", "not-base64")
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let error = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(error["error"], "BAD_DIGEST");
+}
+
+#[tokio::test]
+pub async fn get_object_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cats/tabby.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let response = bucket(&server, "photos")
+        .get_object("cats/tabby.txt")
+        .await
+        .unwrap();
+    assert_eq!(response.status_code(), 200);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.bytes().as_ref(), b"meow");
+
+    let response = bucket(&server, "photos")
+        .get_object_range("cats/tabby.txt", 1, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(response.status_code(), 206);
+    assert_eq!(response.bytes().as_ref(), b"eo");
+}
+
+#[tokio::test]
+pub async fn get_object_s3_overrides() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cat.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let mut bucket = bucket(&server, "photos");
+    bucket.add_query("response-content-type", "application/octet-stream");
+    bucket.add_query(
+        "response-content-disposition",
+        "attachment; filename=\"cat.txt\"",
+    );
+    bucket.add_query("response-cache-control", "no-store");
+    bucket.add_query("response-expires", "Thu, 01 Dec 1994 16:00:00 GMT");
+
+    let response = bucket.get_object("cat.txt").await.unwrap();
+    assert_eq!(response.status_code(), 200);
+
+    let headers = response.headers();
+    assert_eq!(headers["content-type"], "application/octet-stream");
+    assert_eq!(
+        headers["content-disposition"],
+        "attachment; filename=\"cat.txt\""
+    );
+    assert_eq!(headers["cache-control"], "no-store");
+    assert_eq!(headers["expires"], "Thu, 01 Dec 1994 16:00:00 GMT");
+    assert_eq!(response.bytes().as_ref(), b"meow");
+}
+
+#[tokio::test]
+pub async fn get_object_s3_missing() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server.put_object("photos", "cat.txt", None, b"meow").await;
+
+    match bucket(&server, "photos").get_object("dog.txt").await {
+        Err(S3Error::HttpFailWithBody(404, body)) => {
+            assert!(body.contains("<Code>NoSuchKey</Code>"))
+        }
+        Err(e) => panic!("expected a 404 response, got {:?}", e),
+        Ok(response) => panic!("expected a 404 response, got {}", response.status_code()),
+    }
+
+    match bucket(&server, "missing").get_object("cat.txt").await {
+        Err(S3Error::HttpFailWithBody(404, body)) => {
+            assert!(body.contains("<Code>NoSuchBucket</Code>"))
+        }
+        Err(e) => panic!("expected a 404 response, got {:?}", e),
+        Ok(response) => panic!("expected a 404 response, got {}", response.status_code()),
+    }
+
+    server.expire_object("photos", "cat.txt").await;
+
+    let response = reqwest::get(format!("{}/photos/cat.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["x-amz-delete-marker"], "true");
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>NoSuchKey</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn delete_object_s3() {
+    let server = create_test_server().await;
+    let bucket_uuid = server.create_bucket("photos").await;
+    let hash = server.put_object("photos", "cat.txt", None, b"meow").await;
+    server
+        .put_object("photos", "kitten.txt", None, b"meow")
+        .await;
+
+    let bucket = bucket(&server, "photos");
+
+    let response = bucket.delete_object("cat.txt").await.unwrap();
+    assert_eq!(response.status_code(), 204);
+
+    match bucket.get_object("cat.txt").await {
+        Err(S3Error::HttpFailWithBody(404, body)) => {
+            assert!(body.contains("<Code>NoSuchKey</Code>"))
+        }
+        Err(e) => panic!("expected a 404 response, got {:?}", e),
+        Ok(response) => panic!("expected a 404 response, got {}", response.status_code()),
+    }
+
+    // Contents shared with another object are kept until both are deleted
+    assert!(server.storage().exists(bucket_uuid, &hash).await.unwrap());
+
+    let response = bucket.delete_object("kitten.txt").await.unwrap();
+    assert_eq!(response.status_code(), 204);
+    assert!(!server.storage().exists(bucket_uuid, &hash).await.unwrap());
+
+    // Deleting a key which doesn't exist still succeeds
+    let response = bucket.delete_object("kitten.txt").await.unwrap();
+    assert_eq!(response.status_code(), 204);
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/photos/cat.txt?versionId=1", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = reqwest::get(format!("{}/api/buckets/photos", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(response["object_count"], 0);
+    assert_eq!(response["total_bytes"], 0);
+}
+
+#[tokio::test]
+pub async fn delete_objects_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    for key in ["cat.txt", "dog.txt", "bird.txt", "fish.txt"] {
+        server.put_object("photos", key, None, b"pet").await;
+    }
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos?delete", server.endpoint());
+
+    let response = client
+        .post(&url)
+        .body(
+            "<Delete>\
+                <Object><Key>cat.txt</Key></Object>\
+                <Object><Key>dog.txt</Key></Object>\
+                <Object><Key>bird.txt</Key></Object>\
+            </Delete>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(body.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><DeleteResult"#));
+    for key in ["cat.txt", "dog.txt", "bird.txt"] {
+        assert!(body.contains(&format!("<Deleted><Key>{}</Key></Deleted>", key)));
+
+        let response = reqwest::get(format!("{}/photos/{}", server.endpoint(), key))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // Quiet mode only reports errors
+    let response = client
+        .post(&url)
+        .body(
+            "<Delete>\
+                <Quiet>true</Quiet>\
+                <Object><Key>fish.txt</Key></Object>\
+                <Object><Key>cat.txt</Key><VersionId>null</VersionId></Object>\
+            </Delete>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains("<Deleted>"));
+    assert!(body.contains("<Error><Key>cat.txt</Key><Code>NotImplemented</Code>"));
+
+    let response = reqwest::get(format!("{}/photos/fish.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.post(&url).body("<Delete>").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>MalformedXML</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn object_keys_normalized_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!("{}/photos//images/cat.jpg", server.endpoint()))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = reqwest::get(format!("{}/photos/images/cat.jpg", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!(
+            "{}/photos/%2F%2Fphotos%2F..%2Fimages%2Fcat.jpg",
+            server.endpoint()
+        ))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>InvalidKey</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn head_bucket_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .head(format!("{}/photos", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    let response = client
+        .head(format!("{}/missing", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+pub async fn head_object_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cat.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+
+    let get = client.get(&url).send().await.unwrap();
+    let head = client.head(&url).send().await.unwrap();
+    assert_eq!(head.status(), StatusCode::OK);
+
+    for name in ["content-length", "content-type", "etag", "last-modified"] {
+        assert_eq!(head.headers()[name], get.headers()[name], "{}", name);
+    }
+    assert_eq!(head.headers()["content-length"], "4");
+    assert!(head.bytes().await.unwrap().is_empty());
+
+    let response = client
+        .head(format!("{}/photos/dog.txt", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    server.expire_object("photos", "cat.txt").await;
+
+    let response = client.head(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["x-amz-delete-marker"], "true");
+}
+
+#[tokio::test]
+pub async fn copy_object_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    let backups_uuid = server.create_bucket("backups").await;
+    let hash = server
+        .put_object("photos", "cat.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let photos = bucket(&server, "photos");
+    let status = photos
+        .copy_object_internal("cat.txt", "kitten.txt")
+        .await
+        .unwrap();
+    assert_eq!(status, 200);
+
+    let response = photos.get_object("kitten.txt").await.unwrap();
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.bytes().as_ref(), b"meow");
+
+    // Copies into another bucket share the stored contents
+    let response = reqwest::Client::new()
+        .put(format!("{}/backups/cat.txt", server.endpoint()))
+        .header("x-amz-copy-source", "/photos/cat.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let etag = format!("\"{}\"", hex::encode(Md5::digest(b"meow")));
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<CopyObjectResult>"));
+    assert!(body.contains(&format!("<ETag>{}</ETag>", etag)));
+    assert!(body.contains("<LastModified>"));
+
+    assert!(server.storage().exists(backups_uuid, &hash).await.unwrap());
+
+    let response = bucket(&server, "backups")
+        .get_object("cat.txt")
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.bytes().as_ref(), b"meow");
+}
+
+#[tokio::test]
+pub async fn copy_object_s3_replace_metadata() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+    server
+        .put_object("photos", "cat.txt", Some("text/plain"), b"meow")
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+
+    // Copying an object onto itself is only allowed when replacing metadata
+    let response = client
+        .put(&url)
+        .header("x-amz-copy-source", "/photos/cat.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .put(&url)
+        .header("x-amz-copy-source", "/photos/cat.txt")
+        .header("x-amz-metadata-directive", "REPLACE")
+        .header("content-type", "text/markdown")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.headers()["content-type"], "text/markdown");
+    assert_eq!(response.bytes().await.unwrap(), b"meow".as_slice());
+}
+
+#[tokio::test]
+pub async fn copy_object_s3_missing_source() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+
+    for (source, code) in [
+        ("/photos/dog.txt", "NoSuchKey"),
+        ("/missing/cat.txt", "NoSuchBucket"),
+    ] {
+        let response = client
+            .put(format!("{}/photos/cat.txt", server.endpoint()))
+            .header("x-amz-copy-source", source)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(
+            response
+                .text()
+                .await
+                .unwrap()
+                .contains(&format!("<Code>{}</Code>", code))
+        );
+    }
+}
+
+#[tokio::test]
+pub async fn object_metadata_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+
+    let response = client
+        .put(&url)
+        .header("x-amz-meta-author", "Alice")
+        .header("X-Amz-Meta-Camera", "Pinhole")
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let head = client.head(&url).send().await.unwrap();
+    let get = client.get(&url).send().await.unwrap();
+    for response in [&head, &get] {
+        assert_eq!(response.headers()["x-amz-meta-author"], "Alice");
+        assert_eq!(response.headers()["x-amz-meta-camera"], "Pinhole");
+    }
+
+    // Metadata is copied along with the object
+    let response = client
+        .put(format!("{}/photos/kitten.txt", server.endpoint()))
+        .header("x-amz-copy-source", "/photos/cat.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .head(format!("{}/photos/kitten.txt", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-amz-meta-author"], "Alice");
+
+    let response = client
+        .put(&url)
+        .header("x-amz-meta-notes", "a".repeat(2048))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>MetadataTooLarge</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn object_tagging_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let url = format!("{}/photos/cat.txt", server.endpoint());
+    let tagging_url = format!("{}?tagging", url);
+    let client = reqwest::Client::new();
+
+    let response = client.put(&url).body("meow").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(&tagging_url)
+        .body(
+            "<Tagging><TagSet>\
+                <Tag><Key>species</Key><Value>cat</Value></Tag>\
+                <Tag><Key>color</Key><Value>orange</Value></Tag>\
+            </TagSet></Tagging>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client.get(&tagging_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<Tag><Key>color</Key><Value>orange</Value></Tag>"));
+    assert!(body.contains("<Tag><Key>species</Key><Value>cat</Value></Tag>"));
+
+    // Tags set through S3 are visible through the JSON API
+    let response = client
+        .get(format!(
+            "{}/api/buckets/photos/objects/cat.txt?tagging",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({"tags": {"species": "cat", "color": "orange"}})
+    );
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "meow");
+
+    let response = client.delete(&tagging_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&tagging_url).send().await.unwrap();
+    let body = response.text().await.unwrap();
+    assert!(!body.contains("<Tag>"));
+
+    // The object itself is untouched by deleting its tags
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for (body, code) in [
+        (
+            "<Tagging><TagSet>\
+                <Tag><Key>species</Key><Value>cat</Value></Tag>\
+                <Tag><Key>species</Key><Value>dog</Value></Tag>\
+            </TagSet></Tagging>"
+                .to_owned(),
+            "InvalidTag",
+        ),
+        (
+            format!(
+                "<Tagging><TagSet><Tag><Key>{}</Key><Value>cat</Value></Tag></TagSet></Tagging>",
+                "a".repeat(129)
+            ),
+            "InvalidTag",
+        ),
+        (
+            format!(
+                "<Tagging><TagSet>{}</TagSet></Tagging>",
+                (0..11)
+                    .map(|i| format!("<Tag><Key>key{}</Key><Value>value</Value></Tag>", i))
+                    .collect::<String>()
+            ),
+            "InvalidTag",
+        ),
+        ("not xml".to_owned(), "MalformedXML"),
+    ] {
+        let response = client.put(&tagging_url).body(body).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(
+            response
+                .text()
+                .await
+                .unwrap()
+                .contains(&format!("<Code>{}</Code>", code))
+        );
+    }
+
+    let response = client
+        .get(format!("{}/photos/dog.txt?tagging", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn bucket_tagging_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("photos").await;
+
+    let url = format!("{}/photos?tagging", server.endpoint());
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>NoSuchTagSet</Code>")
+    );
+
+    let response = client
+        .put(&url)
+        .body(
+            "<Tagging><TagSet>\
+                <Tag><Key>env</Key><Value>prod</Value></Tag>\
+                <Tag><Key>team</Key><Value>infra</Value></Tag>\
+            </TagSet></Tagging>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<Tag><Key>env</Key><Value>prod</Value></Tag>"));
+    assert!(body.contains("<Tag><Key>team</Key><Value>infra</Value></Tag>"));
+
+    // Tags set through S3 are visible through the JSON API
+    let response = client
+        .get(format!("{}/api/buckets/photos/tags", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({"tags": {"env": "prod", "team": "infra"}})
+    );
+
+    let response = client
+        .put(&url)
+        .body(
+            "<Tagging><TagSet>\
+                <Tag><Key>env</Key><Value>prod</Value></Tag>\
+                <Tag><Key>env</Key><Value>staging</Value></Tag>\
+            </TagSet></Tagging>",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>InvalidTag</Code>")
+    );
+
+    // The rejected tags didn't replace the existing ones
+    let response = client.get(&url).send().await.unwrap();
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Value>infra</Value>")
+    );
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Only the tags are deleted, not the bucket
+    let response = client
+        .head(format!("{}/photos", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn multipart_upload_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let bucket = bucket(&server, "videos");
+
+    let upload = bucket
+        .initiate_multipart_upload("clips/intro.mp4", "video/mp4")
+        .await
+        .unwrap();
+    assert_eq!(upload.key, "clips/intro.mp4");
+
+    let first = bucket
+        .put_multipart_chunk(
+            b"first part, ".to_vec(),
+            "clips/intro.mp4",
+            1,
+            &upload.upload_id,
+            "video/mp4",
+        )
+        .await
+        .unwrap();
+    let second = bucket
+        .put_multipart_chunk(
+            b"second part".to_vec(),
+            "clips/intro.mp4",
+            2,
+            &upload.upload_id,
+            "video/mp4",
+        )
+        .await
+        .unwrap();
+
+    // Parts aren't visible as the object until the upload is completed
+    let response = reqwest::get(format!("{}/videos/clips/intro.mp4", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = bucket
+        .complete_multipart_upload("clips/intro.mp4", &upload.upload_id, vec![first, second])
+        .await
+        .unwrap();
+    assert_eq!(response.status_code(), 200);
+    let body = String::from_utf8(response.to_vec()).unwrap();
+    assert!(body.contains("<Key>clips/intro.mp4</Key>"));
+
+    let response = bucket.get_object("clips/intro.mp4").await.unwrap();
+    assert_eq!(response.status_code(), 200);
+    assert_eq!(response.headers()["content-type"], "video/mp4");
+    assert!(response.headers()["etag"].ends_with("-2\""));
+    assert_eq!(response.as_slice(), b"first part, second part");
+
+    // The upload is gone once completed
+    let response = reqwest::get(format!(
+        "{}/videos/clips/intro.mp4?uploadId={}",
+        server.endpoint(),
+        upload.upload_id
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("<Code>NoSuchUpload</Code>")
+    );
+}
+
+#[tokio::test]
+pub async fn abort_multipart_upload_s3() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    let bucket = bucket(&server, "videos");
+
+    let upload = bucket
+        .initiate_multipart_upload("intro.mp4", "video/mp4")
+        .await
+        .unwrap();
+    bucket
+        .put_multipart_chunk(
+            b"meow".to_vec(),
+            "intro.mp4",
+            1,
+            &upload.upload_id,
+            "video/mp4",
+        )
+        .await
+        .unwrap();
+
+    bucket
+        .abort_upload("intro.mp4", &upload.upload_id)
+        .await
+        .unwrap();
+
+    match bucket
+        .put_multipart_chunk(
+            b"meow".to_vec(),
+            "intro.mp4",
+            2,
+            &upload.upload_id,
+            "video/mp4",
+        )
+        .await
+    {
+        Err(S3Error::HttpFailWithBody(404, body)) => {
+            assert!(body.contains("<Code>NoSuchUpload</Code>"))
+        }
+        Err(e) => panic!("expected a 404 response, got {:?}", e),
+        Ok(_) => panic!("expected a 404 response"),
+    }
+}
+
+#[tokio::test]
+pub async fn upload_part_s3_incomplete_query() {
+    let server = create_test_server().await;
+    server.create_bucket("videos").await;
+    server
+        .put_object("videos", "intro.mp4", None, b"original")
+        .await;
+
+    // A part number without an upload must not replace the object
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/videos/intro.mp4?partNumber=1",
+            server.endpoint()
+        ))
+        .body("part")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = reqwest::get(format!("{}/videos/intro.mp4", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "original");
+}
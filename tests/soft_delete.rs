@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use objection::{
+    config::LifecycleConfig,
+    test_helpers::{TestServer, create_test_server, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+async fn enable_soft_delete(server: &TestServer, bucket: &str) {
+    let response = reqwest::Client::new()
+        .patch(format!("{}/api/buckets/{}", server.endpoint(), bucket))
+        .json(&json!({ "soft_delete_enabled": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["settings"]["soft_delete_enabled"], true);
+}
+
+#[tokio::test]
+pub async fn soft_delete_and_restore() {
+    let server = create_test_server().await;
+    let bucket = server.create_bucket("documents").await;
+    enable_soft_delete(&server, "documents").await;
+    let hash = server
+        .put_object("documents", "report.txt", None, b"quarterly")
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/documents/report.txt", server.endpoint());
+
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The contents are kept until the object is purged
+    assert_eq!(
+        server.stored_contents(bucket, &hash).await.unwrap(),
+        b"quarterly"
+    );
+
+    let listing = reqwest::get(format!(
+        "{}/api/buckets/documents/objects",
+        server.endpoint()
+    ))
+    .await
+    .unwrap()
+    .json::<Value>()
+    .await
+    .unwrap();
+    assert_eq!(listing["objects"], json!([]));
+    assert_eq!(listing["deleted_objects"], Value::Null);
+
+    let listing = reqwest::get(format!(
+        "{}/api/buckets/documents/objects?include_deleted=true",
+        server.endpoint()
+    ))
+    .await
+    .unwrap()
+    .json::<Value>()
+    .await
+    .unwrap();
+    assert_eq!(listing["objects"], json!([]));
+    let deleted = listing["deleted_objects"].as_array().unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0]["key"], "report.txt");
+    assert!(deleted[0]["deleted_at"].is_string());
+
+    let bucket = reqwest::get(format!("{}/api/buckets/documents", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 0);
+
+    let restore_url = format!(
+        "{}/api/buckets/documents/objects/report.txt?restore",
+        server.endpoint()
+    );
+    let response = client.post(&restore_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let object = response.json::<Value>().await.unwrap();
+    assert_eq!(object["key"], "report.txt");
+    assert_eq!(object["deleted_at"], Value::Null);
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "quarterly");
+
+    let bucket = reqwest::get(format!("{}/api/buckets/documents", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 1);
+
+    // Only soft deleted objects can be restored
+    let response = client.post(&restore_url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn soft_deleted_objects_are_purged() {
+    let server = create_test_server_with_config(test_config().lifecycle(LifecycleConfig {
+        interval: Duration::from_millis(50),
+        soft_delete_retention_days: 0,
+        ..Default::default()
+    }))
+    .await;
+    let bucket = server.create_bucket("documents").await;
+    enable_soft_delete(&server, "documents").await;
+    let hash = server
+        .put_object("documents", "report.txt", None, b"quarterly")
+        .await;
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/documents/report.txt", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    for _ in 0..100 {
+        if server.stored_contents(bucket, &hash).await.is_none() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(server.stored_contents(bucket, &hash).await, None);
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/api/buckets/documents/objects/report.txt?restore",
+            server.endpoint()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,356 @@
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use objection::{
+    config::{CacheConfig, PathStrategy, StorageConfig},
+    storage::{InMemoryBackend, LocalFsBackend, StorageBackend, StorageReader},
+    test_helpers::{TestServer, TestServerConfig, create_test_server_with, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn create_server(path_strategy: PathStrategy) -> TestServer {
+    create_test_server_with(TestServerConfig {
+        config: test_config().storage(StorageConfig { path_strategy }),
+        storage_backend: None,
+    })
+    .await
+}
+
+/// Uploads an object, returning the SHA-256 hash of its contents
+async fn put(server: &TestServer, bucket: &str, key: &str, body: &'static str) -> String {
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/{}/objects/{}",
+            server.endpoint(),
+            bucket,
+            key
+        ))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let object = response.json::<Value>().await.unwrap();
+    object["hash"].as_str().unwrap().to_owned()
+}
+
+async fn get(server: &TestServer, bucket: &str, key: &str) -> (StatusCode, String) {
+    let response = reqwest::get(format!(
+        "{}/api/buckets/{}/objects/{}",
+        server.endpoint(),
+        bucket,
+        key
+    ))
+    .await
+    .unwrap();
+
+    (response.status(), response.text().await.unwrap())
+}
+
+async fn delete(server: &TestServer, bucket: &str, key: &str) {
+    let response = reqwest::Client::new()
+        .delete(format!("{}/{}/{}", server.endpoint(), bucket, key))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+pub async fn flat_path_strategy() {
+    let server = create_server(PathStrategy::Flat).await;
+    let uuid = server.create_bucket("docs").await;
+
+    put(&server, "docs", "notes/readme.md", "hello").await;
+    let path = server
+        .data_directory()
+        .join(uuid.to_string())
+        .join("notes%2Freadme.md");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+
+    // Replaced contents hand their name over to the new ones
+    put(&server, "docs", "notes/readme.md", "world").await;
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "world");
+    assert_eq!(
+        get(&server, "docs", "notes/readme.md").await,
+        (StatusCode::OK, "world".to_owned())
+    );
+
+    delete(&server, "docs", "notes/readme.md").await;
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+pub async fn flat_path_strategy_names() {
+    let server = create_server(PathStrategy::Flat).await;
+    let uuid = server.create_bucket("docs").await;
+    let storage = server.storage();
+    let bucket_path = server.data_directory().join(uuid.to_string());
+
+    let (old, new) = ("1".repeat(64), "2".repeat(64));
+    storage
+        .put(uuid, &old, "a/b", Box::pin(&b"old"[..]))
+        .await
+        .unwrap();
+    storage
+        .put(uuid, &new, "a/b", Box::pin(&b"new"[..]))
+        .await
+        .unwrap();
+
+    // Contents wait for the name while older contents still have it
+    let waiting = bucket_path.join("a%2Fb~").join(&new);
+    assert_eq!(
+        std::fs::read_to_string(bucket_path.join("a%2Fb")).unwrap(),
+        "old"
+    );
+    assert_eq!(std::fs::read_to_string(&waiting).unwrap(), "new");
+    assert_eq!(
+        storage.stored_key(uuid, &new).await.unwrap().as_deref(),
+        Some("a/b")
+    );
+
+    storage.delete(uuid, &old).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(bucket_path.join("a%2Fb")).unwrap(),
+        "new"
+    );
+    assert!(!bucket_path.join("a%2Fb~").exists());
+
+    let mut contents = Vec::new();
+    tokio::io::AsyncReadExt::read_to_end(
+        &mut storage.get(uuid, &new).await.unwrap(),
+        &mut contents,
+    )
+    .await
+    .unwrap();
+    assert_eq!(contents, b"new");
+
+    // Keys too long for a file name are named after the hash instead
+    let long = "3".repeat(64);
+    storage
+        .put(uuid, &long, &"k".repeat(300), Box::pin(&b"long"[..]))
+        .await
+        .unwrap();
+    assert!(bucket_path.join(format!("~{}", long)).exists());
+    assert_eq!(storage.stored_key(uuid, &long).await.unwrap(), None);
+
+    let mut hashes = storage.list(uuid).await.unwrap();
+    hashes.sort();
+    assert_eq!(hashes, [new, long]);
+}
+
+#[tokio::test]
+pub async fn path_strategy_mismatch() {
+    let server = create_server(PathStrategy::Flat).await;
+
+    // Contents laid out with one strategy can't be read with another
+    let error = LocalFsBackend::open(server.data_directory(), PathStrategy::Sharded).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    assert!(LocalFsBackend::open(server.data_directory(), PathStrategy::Flat).is_ok());
+}
+
+#[tokio::test]
+pub async fn sharded_path_strategy() {
+    let server = create_server(PathStrategy::Sharded).await;
+    let uuid = server.create_bucket("docs").await;
+    server.create_bucket("archive").await;
+
+    let hash = put(&server, "docs", "readme.md", "hello").await;
+    let path = server
+        .data_directory()
+        .join(uuid.to_string())
+        .join(&hash[0..2])
+        .join(&hash[2..4])
+        .join(&hash);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+    assert_eq!(
+        get(&server, "docs", "readme.md").await,
+        (StatusCode::OK, "hello".to_owned())
+    );
+
+    // Copies to another bucket are stored under its own shards
+    let response = reqwest::Client::new()
+        .put(format!("{}/archive/readme.md", server.endpoint()))
+        .header("x-amz-copy-source", "/docs/readme.md")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        get(&server, "archive", "readme.md").await,
+        (StatusCode::OK, "hello".to_owned())
+    );
+
+    delete(&server, "docs", "readme.md").await;
+    assert!(!path.exists());
+    assert_eq!(
+        get(&server, "archive", "readme.md").await,
+        (StatusCode::OK, "hello".to_owned())
+    );
+}
+
+#[tokio::test]
+pub async fn content_addressed_path_strategy() {
+    let server = create_server(PathStrategy::ContentAddressed).await;
+    server.create_bucket("docs").await;
+    server.create_bucket("archive").await;
+
+    // Contents shared by several buckets are only stored once
+    let hash = put(&server, "docs", "readme.md", "hello").await;
+    assert_eq!(put(&server, "archive", "old.md", "hello").await, hash);
+
+    let contents = server.data_directory().join("contents");
+    assert_eq!(std::fs::read_dir(&contents).unwrap().count(), 1);
+    assert_eq!(
+        std::fs::read_to_string(contents.join(&hash)).unwrap(),
+        "hello"
+    );
+
+    // Each of the buckets storing them is indexed by their hash
+    let referrers = server.data_directory().join("referrers").join(&hash);
+    assert_eq!(std::fs::read_dir(&referrers).unwrap().count(), 2);
+
+    // They are kept until no bucket stores them
+    delete(&server, "docs", "readme.md").await;
+    assert!(contents.join(&hash).exists());
+    assert_eq!(
+        get(&server, "archive", "old.md").await,
+        (StatusCode::OK, "hello".to_owned())
+    );
+
+    delete(&server, "archive", "old.md").await;
+    assert!(!contents.join(&hash).exists());
+    assert!(!referrers.exists());
+}
+
+#[tokio::test]
+pub async fn content_addressed_delete_bucket() {
+    let server = create_server(PathStrategy::ContentAddressed).await;
+    server.create_bucket("docs").await;
+    server.create_bucket("archive").await;
+
+    let shared = put(&server, "docs", "readme.md", "hello").await;
+    put(&server, "archive", "readme.md", "hello").await;
+    let unshared = put(&server, "docs", "license.md", "MIT").await;
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/api/buckets/docs?force=true", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let contents = server.data_directory().join("contents");
+    assert!(contents.join(&shared).exists());
+    assert!(!contents.join(&unshared).exists());
+    assert_eq!(
+        get(&server, "archive", "readme.md").await,
+        (StatusCode::OK, "hello".to_owned())
+    );
+}
+
+/// Keeps contents in memory, counting how many times they are read
+#[derive(Debug, Default)]
+struct CountingBackend {
+    inner: InMemoryBackend,
+    reads: AtomicUsize,
+}
+
+#[async_trait]
+impl StorageBackend for CountingBackend {
+    fn name(&self) -> &'static str {
+        "counting"
+    }
+
+    async fn put(
+        &self,
+        bucket_id: Uuid,
+        hash: &str,
+        key: &str,
+        data: StorageReader,
+    ) -> io::Result<u64> {
+        self.inner.put(bucket_id, hash, key, data).await
+    }
+
+    async fn get(&self, bucket_id: Uuid, hash: &str) -> io::Result<StorageReader> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.get(bucket_id, hash).await
+    }
+
+    async fn delete(&self, bucket_id: Uuid, hash: &str) -> io::Result<()> {
+        self.inner.delete(bucket_id, hash).await
+    }
+
+    async fn exists(&self, bucket_id: Uuid, hash: &str) -> io::Result<bool> {
+        self.inner.exists(bucket_id, hash).await
+    }
+
+    async fn delete_bucket(&self, bucket_id: Uuid) -> io::Result<()> {
+        self.inner.delete_bucket(bucket_id).await
+    }
+}
+
+#[tokio::test]
+pub async fn coalesce_concurrent_reads() {
+    let storage = Arc::new(CountingBackend::default());
+    let server = create_test_server_with(TestServerConfig {
+        config: test_config().cache(CacheConfig {
+            coalesce_concurrent_reads: true,
+            coalesce_window: Duration::from_millis(200),
+        }),
+        storage_backend: Some(storage.clone()),
+    })
+    .await;
+    let uuid = server.create_bucket("videos").await;
+
+    // Large enough to be read in more chunks than a shared read buffers
+    let contents: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/videos/objects/clip.bin",
+            server.endpoint()
+        ))
+        .body(contents.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let hash = response.json::<Value>().await.unwrap()["hash"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let url = format!("{}/api/buckets/videos/objects/clip.bin", server.endpoint());
+    let downloads = (0..8).map(|_| async {
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.bytes().await.unwrap()
+    });
+
+    for body in futures::future::join_all(downloads).await {
+        assert_eq!(body, contents);
+    }
+    assert_eq!(storage.reads.load(Ordering::SeqCst), 1);
+
+    // Later downloads start a new read
+    let body = reqwest::get(&url).await.unwrap().bytes().await.unwrap();
+    assert_eq!(body, contents);
+    assert_eq!(storage.reads.load(Ordering::SeqCst), 2);
+
+    // Contents which can't be read are still reported before the response
+    // starts
+    server.storage().delete(uuid, &hash).await.unwrap();
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeSet;
+
+use objection::{
+    config::{TlsConfig, TlsKeyConfig, TlsVersion},
+    test_helpers::{create_test_server, create_test_server_with_config, test_config},
+};
+
+fn self_signed_tls_config(tls_versions: BTreeSet<TlsVersion>) -> TlsConfig {
+    let cert = rcgen::generate_simple_self_signed(["localhost".into(), "127.0.0.1".into()])
+        .expect("Failed to generate self-signed certificate");
+
+    TlsConfig::new(
+        tls_versions,
+        TlsKeyConfig::String {
+            private_key: cert.signing_key.serialize_pem(),
+            public_key: cert.cert.pem(),
+        },
+    )
+}
+
+#[tokio::test]
+pub async fn https_self_signed_string_keys() {
+    let tls = self_signed_tls_config(BTreeSet::from([TlsVersion::V1_2, TlsVersion::V1_3]));
+    let server = create_test_server_with_config(test_config().tls(tls)).await;
+
+    assert!(server.endpoint().starts_with("https://"));
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("{}/api/buckets", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn https_rejects_plain_http() {
+    let tls = self_signed_tls_config(BTreeSet::from([TlsVersion::V1_3]));
+    let server = create_test_server_with_config(test_config().tls(tls)).await;
+
+    let plain_endpoint = server.endpoint().replacen("https://", "http://", 1);
+
+    let result = reqwest::get(format!("{}/api/buckets", plain_endpoint)).await;
+
+    assert!(result.is_err() || !result.unwrap().status().is_success());
+}
+
+#[tokio::test]
+pub async fn https_sends_hsts() {
+    let tls = self_signed_tls_config(BTreeSet::from([TlsVersion::V1_3]));
+    let server = create_test_server_with_config(test_config().tls(tls)).await;
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    // Sent on every response, including errors
+    for path in ["/health", "/api/buckets/photos"] {
+        let response = client
+            .get(format!("{}{}", server.endpoint(), path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["strict-transport-security"],
+            "max-age=63072000; includeSubDomains"
+        );
+    }
+
+    let tls = TlsConfig {
+        hsts_max_age: None,
+        ..self_signed_tls_config(BTreeSet::from([TlsVersion::V1_3]))
+    };
+    let server = create_test_server_with_config(test_config().tls(tls)).await;
+
+    let response = client
+        .get(format!("{}/health", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key("strict-transport-security"));
+
+    // Plain HTTP servers never send HSTS
+    let server = create_test_server().await;
+
+    let response = reqwest::get(format!("{}/health", server.endpoint()))
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key("strict-transport-security"));
+}
+
+#[tokio::test]
+pub async fn http_redirects_to_https() {
+    // The redirect listener's port has to be known up front, so take one the
+    // OS considers free
+    let redirect_port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let tls = TlsConfig {
+        tls_redirect: true,
+        redirect_port,
+        ..self_signed_tls_config(BTreeSet::from([TlsVersion::V1_3]))
+    };
+    let server = create_test_server_with_config(test_config().tls(tls)).await;
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!(
+            "http://127.0.0.1:{}/api/buckets?page=2",
+            redirect_port
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+        response.headers()["location"],
+        format!("{}/api/buckets?page=2", server.endpoint())
+    );
+
+    // Following the redirect reaches the HTTPS listener
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("http://127.0.0.1:{}/health", redirect_port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.url().scheme(), "https");
+}
+
+/// Fetches the DER encoded certificate the server presents on a new connection
+async fn peer_certificate(endpoint: &str) -> Vec<u8> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("{}/health", endpoint))
+        .send()
+        .await
+        .unwrap();
+
+    response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .unwrap()
+        .to_vec()
+}
+
+#[tokio::test]
+pub async fn https_reloads_key_files() {
+    let keys = tempfile::tempdir().unwrap();
+    let private_key_file = keys.path().join("key.pem");
+    let public_key_file = keys.path().join("cert.pem");
+
+    let write_keys = |cert: &rcgen::CertifiedKey<rcgen::KeyPair>| {
+        std::fs::write(&private_key_file, cert.signing_key.serialize_pem()).unwrap();
+        std::fs::write(&public_key_file, cert.cert.pem()).unwrap();
+    };
+
+    let first = rcgen::generate_simple_self_signed(["localhost".into()]).unwrap();
+    write_keys(&first);
+
+    let server = create_test_server_with_config(test_config().tls(TlsConfig::new(
+        BTreeSet::from([TlsVersion::V1_3]),
+        TlsKeyConfig::File {
+            private_key_file: private_key_file.clone(),
+            public_key_file: public_key_file.clone(),
+        },
+    )))
+    .await;
+
+    assert_eq!(
+        peer_certificate(&server.endpoint()).await,
+        first.cert.der().to_vec()
+    );
+
+    // An invalid certificate is ignored and the old one is kept
+    std::fs::write(&public_key_file, "not a certificate").unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert_eq!(
+        peer_certificate(&server.endpoint()).await,
+        first.cert.der().to_vec()
+    );
+
+    let second = rcgen::generate_simple_self_signed(["localhost".into()]).unwrap();
+    write_keys(&second);
+
+    for _ in 0..50 {
+        if peer_certificate(&server.endpoint()).await == second.cert.der().to_vec() {
+            return;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    panic!("The server never reloaded its certificate");
+}
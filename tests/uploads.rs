@@ -0,0 +1,164 @@
+use axum::body::Bytes;
+use objection::{
+    config::HttpConfig,
+    test_helpers::{
+        TestServerConfig, create_test_server, create_test_server_with,
+        create_test_server_with_config, test_config,
+    },
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// Peak resident memory of the test process in KiB
+#[cfg(target_os = "linux")]
+fn peak_memory_kib() -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .unwrap()
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+        .unwrap()
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+pub async fn put_object_streamed() {
+    const CHUNK_SIZE: usize = 1024 * 1024;
+    const CHUNKS: usize = 50;
+
+    // Stored on disk, as keeping the contents in memory would defeat the
+    // point of streaming them
+    let server = create_test_server_with(TestServerConfig {
+        storage_backend: None,
+        ..Default::default()
+    })
+    .await;
+    let uuid = server.create_bucket("videos").await;
+
+    let before = peak_memory_kib();
+
+    // The same chunk is sent repeatedly so the client doesn't need to hold
+    // the whole body in memory either
+    let chunk = Bytes::from(vec![b'x'; CHUNK_SIZE]);
+    let body =
+        futures::stream::iter(std::iter::repeat_n(chunk, CHUNKS).map(Ok::<_, std::io::Error>));
+
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/buckets/videos/objects/large.bin",
+            server.endpoint()
+        ))
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let object = response.json::<Value>().await.unwrap();
+    assert_eq!(object["size"], CHUNK_SIZE * CHUNKS);
+
+    let stored = server
+        .data_directory()
+        .join(uuid.to_string())
+        .join("large.bin");
+    assert_eq!(
+        std::fs::metadata(stored).unwrap().len(),
+        (CHUNK_SIZE * CHUNKS) as u64
+    );
+
+    let growth_kib = peak_memory_kib() - before;
+    assert!(
+        growth_kib < 25 * 1024,
+        "peak memory grew by {} KiB while uploading",
+        growth_kib
+    );
+}
+
+#[tokio::test]
+pub async fn put_object_too_large() {
+    let server = create_test_server().await;
+    let uuid = server.create_bucket("photos").await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .patch(format!("{}/api/buckets/photos", server.endpoint()))
+        .json(&json!({ "max_object_size": 1024 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bucket = response.json::<Value>().await.unwrap();
+    assert_eq!(bucket["settings"]["max_object_size"], 1024);
+
+    let url = format!("{}/api/buckets/photos/objects/large.bin", server.endpoint());
+
+    let response = client.put(&url).body(vec![0; 1025]).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let error = response.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "OBJECT_TOO_LARGE");
+
+    // Nothing is left behind from the aborted upload
+    let staging_path = server.data_directory().join("staging");
+    assert_eq!(std::fs::read_dir(&staging_path).unwrap().count(), 0);
+    let hash = sha256::digest(vec![0; 1025]);
+    assert!(!server.storage().exists(uuid, &hash).await.unwrap());
+
+    let response = client.put(&url).body(vec![0; 1024]).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .put(format!("{}/photos/large.bin", server.endpoint()))
+        .body(vec![0; 2048])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(response.text().await.unwrap().contains("EntityTooLarge"));
+}
+
+#[tokio::test]
+pub async fn request_body_limit() {
+    let server = create_test_server_with_config(test_config().http(HttpConfig {
+        max_body_bytes: Some(1024),
+        ..HttpConfig::random_port()
+    }))
+    .await;
+    server.create_bucket("photos").await;
+    let client = reqwest::Client::new();
+
+    let url = format!("{}/api/buckets/photos/objects/large.bin", server.endpoint());
+
+    let response = client.put(&url).body(vec![0; 2048]).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // The declared length is rejected before the handler starts receiving
+    // the upload
+    assert!(!server.data_directory().join("staging").exists());
+
+    // Bodies without a declared length are cut off while being streamed
+    let chunk = Bytes::from(vec![0; 512]);
+    let body = futures::stream::iter(std::iter::repeat_n(chunk, 4).map(Ok::<_, std::io::Error>));
+    let response = client
+        .put(&url)
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let response = client
+        .put(format!("{}/photos/large.bin", server.endpoint()))
+        .body(vec![0; 2048])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.put(&url).body(vec![0; 1024]).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,384 @@
+use objection::test_helpers::{TestServer, create_test_server};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+async fn patch_versioning(server: &TestServer, bucket: &str, status: &str) -> (StatusCode, Value) {
+    let response = reqwest::Client::new()
+        .patch(format!(
+            "{}/api/buckets/{}/versioning",
+            server.endpoint(),
+            bucket
+        ))
+        .json(&json!({ "status": status }))
+        .send()
+        .await
+        .unwrap();
+
+    (response.status(), response.json::<Value>().await.unwrap())
+}
+
+async fn set_versioning(server: &TestServer, bucket: &str, status: &str) {
+    let (code, versioning) = patch_versioning(server, bucket, status).await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(versioning["status"], status);
+}
+
+/// Uploads an object with the S3 API, returning its `x-amz-version-id`
+async fn put(server: &TestServer, path: &str, body: &'static str) -> Option<String> {
+    let response = reqwest::Client::new()
+        .put(format!("{}/{}", server.endpoint(), path))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response
+        .headers()
+        .get("x-amz-version-id")
+        .map(|version_id| version_id.to_str().unwrap().to_owned())
+}
+
+async fn get(server: &TestServer, path: &str) -> (StatusCode, String) {
+    let response = reqwest::get(format!("{}/{}", server.endpoint(), path))
+        .await
+        .unwrap();
+
+    (response.status(), response.text().await.unwrap())
+}
+
+#[tokio::test]
+pub async fn versioning_settings() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+
+    let url = format!("{}/api/buckets/docs/versioning", server.endpoint());
+
+    let versioning = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(versioning["status"], "disabled");
+
+    set_versioning(&server, "docs", "enabled").await;
+
+    let versioning = reqwest::get(&url)
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(versioning["status"], "enabled");
+
+    let bucket = reqwest::get(format!("{}/api/buckets/docs", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["settings"]["versioning_status"], "enabled");
+}
+
+#[tokio::test]
+pub async fn versioned_objects() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+    set_versioning(&server, "docs", "enabled").await;
+
+    let first = put(&server, "docs/readme.md", "first").await.unwrap();
+    let second = put(&server, "docs/readme.md", "second").await.unwrap();
+    assert_ne!(first, second);
+
+    assert_eq!(
+        get(&server, "docs/readme.md").await,
+        (StatusCode::OK, "second".to_owned())
+    );
+    assert_eq!(
+        get(&server, &format!("docs/readme.md?versionId={}", first)).await,
+        (StatusCode::OK, "first".to_owned())
+    );
+
+    // Deleting without a version hides the object behind a delete marker
+    let response = reqwest::Client::new()
+        .delete(format!("{}/docs/readme.md", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()["x-amz-delete-marker"], "true");
+    let delete_marker = response.headers()["x-amz-version-id"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    assert_eq!(
+        get(&server, "docs/readme.md").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        get(&server, &format!("docs/readme.md?versionId={}", second)).await,
+        (StatusCode::OK, "second".to_owned())
+    );
+    assert_eq!(
+        get(
+            &server,
+            &format!("docs/readme.md?versionId={}", delete_marker)
+        )
+        .await
+        .0,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+
+    let (status, listing) = get(&server, "docs?versions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(listing.contains("<ListVersionsResult"));
+    assert_eq!(listing.matches("<Version>").count(), 2);
+    assert_eq!(listing.matches("<DeleteMarker>").count(), 1);
+    assert!(listing.contains(&format!(
+        "<DeleteMarker><Key>readme.md</Key><VersionId>{}</VersionId><IsLatest>true</IsLatest>",
+        delete_marker
+    )));
+
+    let bucket = reqwest::get(format!("{}/api/buckets/docs", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 0);
+    assert_eq!(bucket["total_bytes"], "first".len() + "second".len());
+
+    // Removing the delete marker brings back the previous version
+    let response = reqwest::Client::new()
+        .delete(format!(
+            "{}/docs/readme.md?versionId={}",
+            server.endpoint(),
+            delete_marker
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    assert_eq!(
+        get(&server, "docs/readme.md").await,
+        (StatusCode::OK, "second".to_owned())
+    );
+
+    let bucket = reqwest::get(format!("{}/api/buckets/docs", server.endpoint()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(bucket["object_count"], 1);
+}
+
+#[tokio::test]
+pub async fn suspended_versioning_keeps_versions() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+    set_versioning(&server, "docs", "enabled").await;
+
+    let first = put(&server, "docs/readme.md", "first").await.unwrap();
+
+    set_versioning(&server, "docs", "suspended").await;
+
+    // Uploads replace the null version rather than adding new versions
+    assert_eq!(put(&server, "docs/readme.md", "second").await, None);
+    assert_eq!(put(&server, "docs/readme.md", "third").await, None);
+
+    assert_eq!(
+        get(&server, "docs/readme.md").await,
+        (StatusCode::OK, "third".to_owned())
+    );
+    assert_eq!(
+        get(&server, "docs/readme.md?versionId=null").await,
+        (StatusCode::OK, "third".to_owned())
+    );
+    assert_eq!(
+        get(&server, &format!("docs/readme.md?versionId={}", first)).await,
+        (StatusCode::OK, "first".to_owned())
+    );
+
+    let (_, listing) = get(&server, "docs?versions").await;
+    assert_eq!(listing.matches("<Version>").count(), 2);
+    assert!(listing.contains("<VersionId>null</VersionId>"));
+
+    // The remaining version stays hidden once the null version is deleted
+    let response = reqwest::Client::new()
+        .delete(format!("{}/docs/readme.md", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        get(&server, "docs/readme.md").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        get(&server, &format!("docs/readme.md?versionId={}", first)).await,
+        (StatusCode::OK, "first".to_owned())
+    );
+}
+
+#[tokio::test]
+pub async fn versioning_status_transitions() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+
+    let url = format!("{}/api/buckets/docs/versioning", server.endpoint());
+    let status = || async {
+        reqwest::get(&url)
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap()["status"]
+            .clone()
+    };
+    assert_eq!(status().await, "disabled");
+
+    // Versioning which was never enabled cannot be suspended
+    let (code, error) = patch_versioning(&server, "docs", "suspended").await;
+    assert_eq!(code, StatusCode::CONFLICT);
+    assert_eq!(error["error"], "INVALID_VERSIONING_TRANSITION");
+
+    let (code, versioning) = patch_versioning(&server, "docs", "enabled").await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(versioning["status"], "enabled");
+    assert_eq!(status().await, "enabled");
+
+    let first = put(&server, "docs/readme.md", "first").await.unwrap();
+
+    // Once enabled, versioning can only be suspended
+    let (code, error) = patch_versioning(&server, "docs", "disabled").await;
+    assert_eq!(code, StatusCode::CONFLICT);
+    assert_eq!(error["error"], "INVALID_VERSIONING_TRANSITION");
+    assert_eq!(status().await, "enabled");
+
+    // Versioning is only managed through its own endpoint, not the bucket
+    // settings
+    let response = reqwest::Client::new()
+        .patch(format!("{}/api/buckets/docs", server.endpoint()))
+        .json(&json!({ "versioning_status": "disabled" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(status().await, "enabled");
+
+    let (code, _) = patch_versioning(&server, "docs", "suspended").await;
+    assert_eq!(code, StatusCode::OK);
+    assert_eq!(status().await, "suspended");
+
+    // Suspended buckets create the null version and keep existing versions
+    assert_eq!(put(&server, "docs/readme.md", "second").await, None);
+    assert_eq!(
+        get(&server, &format!("docs/readme.md?versionId={}", first)).await,
+        (StatusCode::OK, "first".to_owned())
+    );
+
+    let (code, _) = patch_versioning(&server, "docs", "enabled").await;
+    assert_eq!(code, StatusCode::OK);
+    assert!(put(&server, "docs/readme.md", "third").await.is_some());
+}
+
+async fn get_versions(server: &TestServer, query: &str) -> (StatusCode, Value) {
+    let response = reqwest::get(format!(
+        "{}/api/buckets/docs/objects/readme.md?versions&{}",
+        server.endpoint(),
+        query
+    ))
+    .await
+    .unwrap();
+
+    (response.status(), response.json::<Value>().await.unwrap())
+}
+
+#[tokio::test]
+pub async fn list_object_versions() {
+    let server = create_test_server().await;
+    server.create_bucket("docs").await;
+
+    assert_eq!(get_versions(&server, "").await.0, StatusCode::NOT_FOUND);
+
+    set_versioning(&server, "docs", "enabled").await;
+    let first = put(&server, "docs/readme.md", "first").await.unwrap();
+    let second = put(&server, "docs/readme.md", "second").await.unwrap();
+    put(&server, "docs/readme.md.bak", "backup").await.unwrap();
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/docs/readme.md", server.endpoint()))
+        .send()
+        .await
+        .unwrap();
+    let delete_marker = response.headers()["x-amz-version-id"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    // Versions of other keys sharing the prefix aren't listed
+    let (status, versions) = get_versions(&server, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(versions["has_next"], false);
+    let data = versions["data"].as_array().unwrap();
+    assert_eq!(data.len(), 3);
+    assert_eq!(data[0]["version_id"], delete_marker);
+    assert_eq!(data[0]["is_latest"], true);
+    assert_eq!(data[0]["is_delete_marker"], true);
+    assert_eq!(data[1]["version_id"], second);
+    assert_eq!(data[1]["is_latest"], false);
+    assert_eq!(data[1]["is_delete_marker"], false);
+    assert_eq!(data[1]["size"], "second".len());
+    assert!(data[1]["etag"].is_string());
+    assert!(data[1]["created_at"].is_string());
+    assert_eq!(data[2]["version_id"], first);
+
+    // Pages continue after the last version of the previous page
+    let (status, page) = get_versions(&server, "limit=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["has_next"], true);
+    assert_eq!(page["data"].as_array().unwrap().len(), 2);
+
+    let (status, page) =
+        get_versions(&server, &format!("limit=2&before_version_id={}", second)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["has_next"], false);
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert_eq!(page["data"][0]["version_id"], first);
+
+    // Walking one version at a time visits every version once
+    let mut walked = Vec::new();
+    let mut query = "limit=1".to_owned();
+    loop {
+        let (status, page) = get_versions(&server, &query).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let version_id = page["data"][0]["version_id"].as_str().unwrap().to_owned();
+        query = format!("limit=1&before_version_id={}", version_id);
+        walked.push(version_id);
+
+        if page["has_next"] == false {
+            break;
+        }
+    }
+    assert_eq!(
+        walked,
+        [delete_marker.clone(), second.clone(), first.clone()]
+    );
+
+    let (status, error) = get_versions(&server, "page=2").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["error"], "INVALID_PAGE");
+
+    let (status, error) = get_versions(&server, "before_version_id=nope").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["error"], "INVALID_VERSION_ID");
+
+    let (status, error) = get_versions(&server, "before_version_id=null").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["error"], "VERSION_NOT_FOUND");
+}
@@ -0,0 +1,190 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use objection::{
+    config::{AccessControlConfig, HttpConfig},
+    test_helpers::{TestServer, create_test_server_with_config, test_config},
+};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+const DOMAIN: &str = "objection.test";
+
+/// Client which resolves the virtual host domain and any bucket subdomains
+/// to the test server
+fn client(server: &TestServer, hosts: &[&str]) -> reqwest::Client {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, server.addr().port()));
+
+    hosts
+        .iter()
+        .fold(reqwest::Client::builder(), |builder, host| {
+            builder.resolve(host, addr)
+        })
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn virtual_hosted_style_requests() {
+    let server = create_test_server_with_config(test_config().http(HttpConfig {
+        virtual_host_domain: Some(DOMAIN.to_owned()),
+        ..HttpConfig::random_port()
+    }))
+    .await;
+    server.create_bucket("photos").await;
+
+    let port = server.addr().port();
+    let client = client(&server, &[DOMAIN, "photos.objection.test"]);
+    let virtual_hosted = format!("http://photos.{}:{}", DOMAIN, port);
+    let path_style = format!("http://{}:{}/photos", DOMAIN, port);
+
+    let response = client
+        .put(format!("{}/cat.txt", virtual_hosted))
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for path in ["/cat.txt", "?list-type=2", ""] {
+        let response = client
+            .get(format!("{}{}", virtual_hosted, path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        let virtual_hosted_body = response.text().await.unwrap();
+
+        let response = client
+            .get(format!("{}{}", path_style, path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        assert_eq!(response.text().await.unwrap(), virtual_hosted_body);
+    }
+
+    // Path-style requests still work on any other host
+    let response = reqwest::get(format!("{}/photos/cat.txt", server.endpoint()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "meow");
+
+    let response = client
+        .delete(format!("{}/cat.txt", virtual_hosted))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .get(format!("{}/cat.txt", path_style))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn virtual_hosted_style_disabled() {
+    let server = create_test_server_with_config(test_config()).await;
+    server.create_bucket("photos").await;
+    server.put_object("photos", "cat.txt", None, b"meow").await;
+
+    let client = client(&server, &["photos.objection.test"]);
+
+    // Without a virtual host domain the bucket isn't taken from the host, so
+    // this addresses a bucket named `cat.txt`
+    let response = client
+        .get(format!(
+            "http://photos.{}:{}/cat.txt",
+            DOMAIN,
+            server.addr().port()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+pub async fn virtual_hosted_style_permissions() {
+    let server = create_test_server_with_config(
+        test_config()
+            .http(HttpConfig {
+                virtual_host_domain: Some(DOMAIN.to_owned()),
+                ..HttpConfig::random_port()
+            })
+            .access_control(AccessControlConfig::default()),
+    )
+    .await;
+    server.create_bucket("photos").await;
+    let admin = server.create_access_token().await;
+
+    let port = server.addr().port();
+    let client = client(
+        &server,
+        &[DOMAIN, "photos.objection.test", "api.objection.test"],
+    );
+
+    let writer = client
+        .post(format!("http://{}:{}/api/access-tokens", DOMAIN, port))
+        .bearer_auth(&admin.bearer_token)
+        .json(&json!({ "permissions": ["write"] }))
+        .send()
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap()["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    // Objects in a virtual-hosted bucket only need the write permission
+    let response = client
+        .put(format!("http://photos.{}:{}/cat.txt", DOMAIN, port))
+        .bearer_auth(&writer)
+        .body("meow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Deleting the bucket itself still needs the admin permission
+    let response = client
+        .delete(format!("http://photos.{}:{}/", DOMAIN, port))
+        .bearer_auth(&writer)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // The API can't be reached by using it as a bucket subdomain, which
+    // would otherwise look like requests for objects in an `access-tokens`
+    // or `buckets` bucket
+    let response = client
+        .get(format!("http://api.{}:{}/access-tokens", DOMAIN, port))
+        .bearer_auth(&writer)
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(response.status(), StatusCode::OK);
+    assert!(!response.text().await.unwrap().contains("access_key_id"));
+
+    let response = client
+        .delete(format!("http://api.{}:{}/buckets/photos", DOMAIN, port))
+        .bearer_auth(&writer)
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .get(format!("http://{}:{}/api/buckets/photos", DOMAIN, port))
+        .bearer_auth(&admin.bearer_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}